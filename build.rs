@@ -3,13 +3,32 @@
 #[cfg(target_os = "freebsd")]
 extern crate bindgen;
 
-#[cfg(target_os = "freebsd")]
 use std::env;
-#[cfg(target_os = "freebsd")]
+use std::fs;
 use std::path::PathBuf;
 
+/// With `embedded-drivedb` feature, copies `drivedb.h` (or whatever `HDD_DRIVEDB` points at) into `OUT_DIR` so that `include_bytes!()` can pick it up.
+fn embed_drivedb() {
+	println!("cargo:rerun-if-env-changed=HDD_DRIVEDB");
+
+	if env::var_os("CARGO_FEATURE_EMBEDDED_DRIVEDB").is_none() {
+		return;
+	}
+
+	let src = env::var_os("HDD_DRIVEDB")
+		.map(PathBuf::from)
+		.unwrap_or_else(|| PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("drivedb.h"));
+	println!("cargo:rerun-if-changed={}", src.display());
+
+	let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+	fs::copy(&src, out_path.join("drivedb.h"))
+		.unwrap_or_else(|e| panic!("Couldn't copy {} to embed it (set HDD_DRIVEDB to the drivedb.h you want to embed): {}", src.display(), e));
+}
+
 #[cfg(target_os = "freebsd")]
 fn main() {
+	embed_drivedb();
+
 	println!("cargo:rustc-link-lib=cam");
 
 	let bindings = bindgen::Builder::default()
@@ -43,4 +62,5 @@ fn main() {
 
 #[cfg(not(target_os = "freebsd"))]
 fn main() {
+	embed_drivedb();
 }
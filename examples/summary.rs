@@ -0,0 +1,121 @@
+/*!
+Prints a short summary of an ATA device: model, drivedb family, SMART health, and a few well-known attributes.
+
+This only uses the public API of the library, and is meant to show how to embed it:
+
+```sh
+cargo run --example summary -- /dev/sda [/path/to/drivedb.h]
+```
+*/
+
+#![cfg_attr(feature = "cargo-clippy", allow(print_with_newline))]
+
+extern crate hdd;
+
+use hdd::Device;
+use hdd::ata::ATADevice;
+use hdd::ata::misc::Misc;
+use hdd::ata::data::{attr, id};
+use hdd::drivedb;
+use hdd::scsi::SCSIDevice;
+
+use std::env;
+use std::process;
+
+static DRIVEDB_DEFAULT: [&str; 3] = [
+	"/var/lib/smartmontools/drivedb/drivedb.h",
+	"/usr/local/share/smartmontools/drivedb.h",
+	"/usr/share/smartmontools/drivedb.h",
+];
+
+fn die(msg: &str) -> ! {
+	eprint!("{}\n", msg);
+	process::exit(1);
+}
+
+/*
+There's no single type that stands for "an ATA device, however it is attached", so callers have to pick the transport themselves.
+This one only tries SAT (which is what Linux always needs), and native ATA where FreeBSD reports it; see `open_device()` in the CLI tool for a more thorough approach.
+*/
+#[cfg(target_os = "freebsd")]
+fn ata(dev: Device) -> Box<Misc> {
+	match dev.get_type() {
+		Ok(hdd::device::Type::ATA) => Box::new(ATADevice::new(dev)),
+		_ => Box::new(ATADevice::new(SCSIDevice::new(dev))),
+	}
+}
+#[cfg(not(target_os = "freebsd"))]
+fn ata(dev: Device) -> Box<Misc> {
+	Box::new(ATADevice::new(SCSIDevice::new(dev)))
+}
+
+fn load_drivedb(path: Option<String>) -> Option<drivedb::DriveDB> {
+	let mut loader = drivedb::Loader::new();
+	let loaded = match path {
+		Some(path) => loader.load(&path).is_ok(),
+		None => DRIVEDB_DEFAULT.iter().any(|path| loader.load(path).is_ok()),
+	};
+	if !loaded {
+		eprint!("Cannot load drivedb, attribute names might be missing\n");
+	}
+	loader.db().ok()
+}
+
+fn main() {
+	let mut args = env::args().skip(1);
+	let path = args.next().unwrap_or_else(|| die("usage: summary <device> [drivedb.h]"));
+	let drivedb = load_drivedb(args.next());
+
+	let dev = Device::open(&path).unwrap_or_else(|err| die(&format!("Cannot open {}: {}", path, err)));
+	let dev = ata(dev);
+
+	let id = dev.get_device_id().unwrap_or_else(|err| die(&format!("Cannot identify {}: {}", path, err)));
+	print!("Model:    {}\n", id.model);
+	print!("Serial:   {}\n", id.serial);
+	print!("Firmware: {}\n", id.firmware);
+
+	// no vendor attribute overrides here; the CLI tool passes `-v` options in the second argument
+	let meta = match drivedb {
+		Some(ref db) => db.render_meta(&id, &vec![]),
+		None => drivedb::builtin_meta(&id, &[]),
+	};
+	if let Some(family) = meta.family {
+		print!("Family:   {}\n", family);
+	}
+	if let Some(warning) = meta.warning {
+		print!("Warning:  {}\n", warning);
+	}
+
+	if id.smart != id::Ternary::Enabled {
+		print!("S.M.A.R.T. is not available\n");
+		return;
+	}
+
+	match dev.get_smart_health() {
+		Ok(Some(true)) => print!("Health:   good\n"),
+		Ok(Some(false)) => print!("Health:   BAD\n"),
+		Ok(None) => print!("Health:   unknown\n"),
+		Err(err) => print!("Health:   cannot query: {}\n", err),
+	}
+
+	// `get_smart_attributes()` wants `Option<DriveMeta>`, as there might be no drivedb at all
+	let attrs = dev.get_smart_attributes(&Some(meta)).unwrap_or_else(|err| die(&format!("Cannot read attributes: {}", err)));
+
+	if let Some(hours) = attr::power_on_hours(&attrs) {
+		print!("Power-on: {} hours\n", hours);
+	}
+	if let Some(temp) = attr::temperature(&attrs) {
+		print!("Temperature: {}°C\n", temp);
+	}
+	if let Some(sectors) = attr::reallocated_sectors(&attrs) {
+		print!("Reallocated sectors: {}\n", sectors);
+	}
+
+	let failing: Vec<_> = attrs.iter()
+		.filter(|a| a.status(0) == attr::Status::FailingNow)
+		.map(|a| a.name.clone().unwrap_or_else(|| format!("attribute {}", a.id)))
+		.collect();
+	if !failing.is_empty() {
+		print!("Failing:  {}\n", failing.join(", "));
+	}
+}
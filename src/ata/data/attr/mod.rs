@@ -10,20 +10,25 @@ pub struct SmartAttribute {
 
 	pub name: Option<String>, // comes from the drivedb
 
+	// flags are the same bits smartmontools' ATTRIBUTE_FLAGS_* macros test: 0x01 pre_fail, 0x02 online, 0x04 performance, 0x08 error_rate, 0x10 event_count, 0x20 self_preserving
 	pub pre_fail: bool, // if true, failure is predicted within 24h; otherwise, attribute indicates drive's exceeded intended design life period
-	pub online: bool,
+	pub online: bool, // if true, attribute is updated during normal operation ("Always" in smartctl); otherwise, only during off-line data collection ("Offline")
 	// In SFF-8035i rev 2, bits 2-5 are defined as vendor-specific, and 6-15 are reserved;
 	// however, these days the following seems to be universally interpreted the way it was once (probably) established by IBM, Maxtor and Quantum
 	pub performance: bool,
 	pub error_rate: bool,
 	pub event_count: bool,
 	pub self_preserving: bool,
-	pub flags: u16,
+	pub flags: u16, // the rest of the flags (bits 6-15), not covered by the fields above
+	pub raw_flags: u16, // the whole flags word, all of the above included, for those who'd rather decode it themselves
 
-	// contains None if `raw` is rendered using byte that usually covers this value
-	// TODO? 0x00 | 0xfe | 0xff are invalid
+	// contains None if `raw` is rendered using byte that usually covers this value,
+	// that is, if the byte order of the attribute format (from drivedb presets or `-v`) has a 'v' in it, e.g. the default `543210wv` of raw64:
+	// such counter-only attributes have no meaningful normalized value, and smartmontools (ATTRFLAG_NO_NORMVAL) shows '---' for these, too;
+	// also None if drive does not populate it (reserved value 0x00 or 0xff, paired with threshold of 0x00)
+	// TODO? 0xfe is invalid
 	pub value: Option<u8>,
-	// contains None if `raw` is rendered using byte that usually covers this value
+	// same as `value`, with 'w' in the byte order instead (ATTRFLAG_NO_WORSTVAL)
 	pub worst: Option<u8>,
 
 	pub raw: raw::Raw,
@@ -31,31 +36,124 @@ pub struct SmartAttribute {
 	pub thresh: Option<u8>, // requested separately; TODO? 0x00 is "always passing", 0xff is "always failing", 0xfe is invalid
 }
 
+/// How the attribute is doing relative to its threshold, see [`SmartAttribute::status()`](struct.SmartAttribute.html#method.status)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+	/// Value is at or below the threshold
+	FailingNow,
+	/// Worst value has been at or below the threshold, but current value is above it
+	FailedInPast,
+	/// Value or worst value is above the threshold, but is getting close to it
+	Degrading,
+	/// Neither value nor worst value are anywhere near the threshold
+	Good,
+	/// Value, worst value, or threshold is not available, or threshold makes no sense (e.g. 0x00 "always passing", 0xfe invalid, 0xff "always failing")
+	Unknown,
+}
+
+impl SmartAttribute {
+	/// Classifies the attribute by comparing its value and worst value against the threshold.
+	///
+	/// On top of what the drive itself reports (value at or below the threshold), attribute is considered `Degrading` if either value or worst value is no more than `margin` points above the threshold: that is an early warning that the drive is nearing the failure.
+	pub fn status(&self, margin: u8) -> Status {
+		let thresh = match self.thresh {
+			None | Some(0x00) | Some(0xfe) | Some(0xff) => return Status::Unknown,
+			Some(t) => t,
+		};
+		let near = |v: u8| v as u16 <= thresh as u16 + margin as u16;
+
+		match (self.value, self.worst) {
+			(Some(v), _) if v <= thresh => Status::FailingNow,
+			(_, Some(w)) if w <= thresh => Status::FailedInPast,
+			(Some(v), _) if near(v) => Status::Degrading,
+			(_, Some(w)) if near(w) => Status::Degrading,
+			(None, None) => Status::Unknown,
+			_ => Status::Good,
+		}
+	}
+}
+
+/**
+Looks up the attribute by its id.
+
+`id` is the attribute number as the drive reports it (e.g. 5 for Reallocated Sectors Count), not the name drivedb gives it: names vary between vendors and drivedb revisions, numbers do not.
+*/
+pub fn find(attrs: &[SmartAttribute], id: u8) -> Option<&SmartAttribute> {
+	attrs.iter().find(|attr| attr.id == id)
+}
+
+/// Raw value of Reallocated Sectors Count (attribute 5).
+pub fn reallocated_sectors(attrs: &[SmartAttribute]) -> Option<u64> {
+	find(attrs, 5).and_then(|attr| attr.raw.count())
+}
+
+/// Power-On Hours (attribute 9), in hours, regardless of what the drive actually counts (given that drivedb knows about that).
+pub fn power_on_hours(attrs: &[SmartAttribute]) -> Option<u64> {
+	find(attrs, 9).and_then(|attr| attr.raw.hours())
+}
+
+/// Current temperature (attribute 194), in °C.
+pub fn temperature(attrs: &[SmartAttribute]) -> Option<f32> {
+	find(attrs, 194).and_then(|attr| attr.raw.celsius())
+}
+
+/**
+Parses replies to SMART READ DATA (`data`) and SMART READ THRESHOLDS (`raw_thresh`).
+
+`raw_thresh` can be empty if thresholds could not be read: attributes are still parsed, but all of them have `thresh: None`.
+
+If `meta` says the drive has the `samsung` firmware bug (see [`FirmwareBug`](../../../drivedb/enum.FirmwareBug.html)), bytes of the attribute flags are swapped back.
+*/
 pub fn parse_smart_values(data: &Vec<u8>, raw_thresh: &Vec<u8>, meta: &Option<drivedb::DriveMeta>) -> Vec<SmartAttribute> {
-	// TODO cover bytes 0..1 362..511 of data
+	// TODO cover bytes 362..511 of data
 	// XXX what if some drive reports the same attribute multiple times?
 	// TODO return None if data.len() < 512
 
+	// 2 bytes of revision plus 30 entries of 12 bytes
+	let has_thresh = raw_thresh.len() >= 362;
+
+	// both blocks start with their own structure revision number; layout of the attribute table does not depend on it, so a mismatch is only worth a note
+	if has_thresh {
+		let values_rev = (data[0] as u16) + ((data[1] as u16) << 8);
+		let thresh_rev = (raw_thresh[0] as u16) + ((raw_thresh[1] as u16) << 8);
+		if values_rev != thresh_rev {
+			debug!("SMART values revision {:#06x} differs from thresholds revision {:#06x}", values_rev, thresh_rev);
+		}
+	}
+
+	// thresholds are paired with values by attribute id, not by their position in the table:
+	// nothing requires devices to keep both tables in the same order
 	let mut threshs = HashMap::<u8, u8>::new();
-	for i in 0..30 {
+	for i in 0..(if has_thresh { 30 } else { 0 }) {
 		let offset = 2 + i * 12;
 		if raw_thresh[offset] == 0 { continue } // attribute table entry of id 0x0 is invalid
 		threshs.insert(raw_thresh[offset], raw_thresh[offset+1]);
 		// fields 2..11 are reserved
 	}
 
+	let samsung = meta.as_ref().map(|meta| meta.has_firmware_bug(drivedb::FirmwareBug::Samsung)).unwrap_or(false);
+
 	let mut attrs = vec![];
 	for i in 0..30 {
 		let offset = 2 + i * 12;
 		if data[offset] == 0 { continue } // attribute table entry of id 0x0 is invalid
 
 		let flags = (data[offset + 1] as u16) + ((data[offset + 2] as u16) << 8); // XXX endianness?
+		// some Samsung drives swap bytes of the flags word
+		let flags = if samsung { flags.swap_bytes() } else { flags };
 
 		let id = data[offset];
 
 		let attr = meta.as_ref().map(|meta| meta.render_attribute(id)).unwrap_or(None);
 		let is_in_raw = |c| attr.as_ref().map(|a| a.byte_order.contains(c)).unwrap_or(false);
 
+		// .get() returns Option<&T>, but threshs would not live long enough, and it's just easier to copy u8 using this map
+		let thresh = threshs.get(&id).cloned();
+
+		// some drives fill values they do not keep track of with 0x00 or 0xff; these would look like failing attributes otherwise.
+		// an actual value of 0x00 or 0xff is only ignored with the "always passing" threshold of 0x00, as any value passes it anyway
+		let not_populated = |v: u8| thresh == Some(0x00) && (v == 0x00 || v == 0xff);
+
 		attrs.push(SmartAttribute {
 			id: id,
 
@@ -71,19 +169,150 @@ pub fn parse_smart_values(data: &Vec<u8>, raw_thresh: &Vec<u8>, meta: &Option<dr
 			event_count:     flags & (1<<4) != 0,
 			self_preserving: flags & (1<<5) != 0,
 			flags:           flags & (!0b11_1111),
+			raw_flags:       flags,
 
-			value: if !is_in_raw('v') {
+			value: if !is_in_raw('v') && !not_populated(data[offset + 3]) {
 				Some(data[offset + 3])
 			} else { None },
-			worst: if !is_in_raw('w') {
+			worst: if !is_in_raw('w') && !not_populated(data[offset + 4]) {
 				Some(data[offset + 4])
 			} else { None },
 
 			raw: raw::Raw::from_raw_entry(&data[offset .. offset + 12], &attr),
 
-			// .get() returns Option<&T>, but threshs would not live long enough, and it's just easier to copy u8 using this map
-			thresh: threshs.get(&data[offset]).map(|&t| t),
+			thresh,
 		})
 	}
 	attrs
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ata::data::id;
+
+	// SMART READ DATA reply with `attrs` (id, flags, value, worst, raw bytes, least significant first) in its attribute table
+	fn values(attrs: &[(u8, u16, u8, u8, [u8; 6])]) -> Vec<u8> {
+		let mut data = vec![0; 512];
+		for (i, &(id, flags, value, worst, raw)) in attrs.iter().enumerate() {
+			let entry = &mut data[2 + i * 12 .. 2 + (i + 1) * 12];
+			entry[..5].copy_from_slice(&[id, flags as u8, (flags >> 8) as u8, value, worst]);
+			entry[5..11].copy_from_slice(&raw);
+		}
+		data
+	}
+
+	// SMART READ THRESHOLDS reply
+	fn thresholds(threshs: &[(u8, u8)]) -> Vec<u8> {
+		let mut data = vec![0; 512];
+		for (i, &(id, thresh)) in threshs.iter().enumerate() {
+			data[2 + i * 12] = id;
+			data[2 + i * 12 + 1] = thresh;
+		}
+		data
+	}
+
+	// attributes rendered with built-in names and formats only
+	fn parse(attrs: &[(u8, u16, u8, u8, [u8; 6])], threshs: &[(u8, u8)]) -> Vec<SmartAttribute> {
+		let meta = Some(drivedb::builtin_meta(&id::parse_id(&vec![0; 512]), &[]));
+		parse_smart_values(&values(attrs), &thresholds(threshs), &meta)
+	}
+
+	#[test]
+	fn accessors() {
+		let attrs = parse(&[
+			(5, 0x0033, 100, 100, [8, 0, 0, 0, 0, 0]),
+			// raw24(raw8): hours in the lower 24 bits
+			(9, 0x0032, 90, 90, [0x10, 0x27, 0, 0x01, 0, 0]),
+			// tempminmax: 35°C, 20..45°C
+			(194, 0x0022, 35, 45, [35, 0, 20, 0, 45, 0]),
+		], &[]);
+
+		assert_eq!(find(&attrs, 9).map(|attr| attr.value), Some(Some(90)));
+		assert!(find(&attrs, 1).is_none());
+
+		assert_eq!(reallocated_sectors(&attrs), Some(8));
+		assert_eq!(power_on_hours(&attrs), Some(10000));
+		assert_eq!(temperature(&attrs), Some(35.));
+	}
+
+	#[test]
+	fn accessors_missing_attributes() {
+		let attrs = parse(&[(1, 0x000f, 100, 100, [0; 6])], &[]);
+		assert_eq!(reallocated_sectors(&attrs), None);
+		assert_eq!(power_on_hours(&attrs), None);
+		assert_eq!(temperature(&attrs), None);
+	}
+
+	#[test]
+	fn thresholds_are_paired_by_id() {
+		// thresholds table is in a different order
+		let attrs = parse(&[
+			(5, 0x0033, 100, 100, [0; 6]),
+			(9, 0x0032, 90, 90, [0; 6]),
+		], &[(9, 0), (5, 36)]);
+		assert_eq!(find(&attrs, 5).unwrap().thresh, Some(36));
+		assert_eq!(find(&attrs, 9).unwrap().thresh, Some(0));
+	}
+
+	#[test]
+	fn flags() {
+		// pre-fail, online, event count, self-preserving, and vendor-specific bit 12
+		let attr = &parse(&[(5, 0x1033, 100, 100, [0; 6])], &[])[0];
+		assert_eq!(attr.raw_flags, 0x1033);
+		assert_eq!(attr.flags, 0x1000);
+		assert!(attr.pre_fail);
+		assert!(attr.online);
+		assert!(!attr.performance);
+		assert!(!attr.error_rate);
+		assert!(attr.event_count);
+		assert!(attr.self_preserving);
+	}
+
+	#[test]
+	fn not_populated() {
+		let attrs = parse(&[
+			// genuinely failing
+			(5, 0x0033, 10, 10, [0; 6]),
+			// not populated, with the "always passing" threshold
+			(9, 0x0032, 0, 0xff, [0; 6]),
+			// zero, but with a threshold that makes it count
+			(10, 0x0013, 0, 0, [0; 6]),
+			// no threshold at all
+			(12, 0x0032, 0xff, 0, [0; 6]),
+		], &[(5, 36), (9, 0), (10, 97)]);
+
+		assert_eq!((attrs[0].value, attrs[0].thresh), (Some(10), Some(36)));
+		assert_eq!((attrs[1].value, attrs[1].worst), (None, None));
+		assert_eq!((attrs[2].value, attrs[2].worst), (Some(0), Some(0)));
+		assert_eq!((attrs[3].value, attrs[3].worst), (Some(0xff), Some(0)));
+
+		assert_eq!(attrs[0].status(0), Status::FailingNow);
+		assert_eq!(attrs[1].status(0), Status::Unknown);
+		assert_eq!(attrs[2].status(0), Status::FailingNow);
+	}
+
+	#[test]
+	fn counter_only() {
+		let id = id::parse_id(&vec![0; 512]);
+		let extra = vec![
+			drivedb::vendor_attribute::parse("241,raw48,Total_LBAs_Written").unwrap(),
+			drivedb::vendor_attribute::parse("242,raw64,Total_LBAs_Read").unwrap(),
+			drivedb::vendor_attribute::parse("243,raw48:543210w,Mostly_Counter").unwrap(),
+		];
+		let meta = Some(drivedb::builtin_meta(&id, &extra));
+		let attrs = parse_smart_values(&values(&[
+			(241, 0x0032, 100, 100, [1, 0, 0, 0, 0, 0]),
+			(242, 0x0032, 100, 100, [1, 0, 0, 0, 0, 0]),
+			(243, 0x0032, 100, 100, [1, 0, 0, 0, 0, 0]),
+		]), &thresholds(&[]), &meta);
+
+		// raw48 leaves both bytes out of the raw value, so these are shown, same as smartmontools does
+		assert_eq!((attrs[0].value, attrs[0].worst), (Some(100), Some(100)));
+		// raw64 takes both of them in
+		assert_eq!((attrs[1].value, attrs[1].worst), (None, None));
+		assert_eq!(attrs[1].raw.count(), Some(0x0001_6464));
+		// the byte order is what counts, not the format name
+		assert_eq!((attrs[2].value, attrs[2].worst), (Some(100), None));
+	}
+}
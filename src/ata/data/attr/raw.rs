@@ -26,7 +26,8 @@ pub enum Raw {
 	Seconds(u64),
 	HoursMilliseconds(u32, u32),
 	Celsius(f32),
-	CelsiusMinMax { current: u8, min: u8, max: u8 },
+	/// Temperatures are signed (two's complement), like in smartmontools
+	CelsiusMinMax { current: i8, min: i8, max: i8 },
 }
 
 fn write_vec<T>(f: &mut fmt::Formatter, vec: &Vec<T>) -> fmt::Result
@@ -106,13 +107,50 @@ fn reorder(data: &[u8], byte_order: &str) -> Vec<u8> {
 		'4' => data[9],
 		'5' => data[10],
 		'r' => data[11], // reserved byte
-		// smartmontools defaults to 0 for any unrecognized character;
-		// we'll use '_' later for padding
+		// there are only six raw bytes, so positions past them, as well as 's' and 'm' placeholders, are reserved and contribute 0;
+		// `vendor_attribute::parse()` rejects anything else, but `Attribute` fields are public, so smartmontools' default of 0 still applies;
+		// we'll also use '_' later for padding
+		'6' | '7' | 's' | 'm' | '_' => 0,
 		_ => 0,
 	}).collect()
 }
 
 impl Raw {
+	/// The counter this raw value holds, if it's a plain counter (e.g. of `Raw16avg16`, this is the value, but not the average).
+	pub fn count(&self) -> Option<u64> {
+		use self::Raw::*;
+		match *self {
+			Raw64(x) => Some(x),
+			Raw16opt16(x, _) => Some(x as u64),
+			Raw16avg16 { value, .. } => Some(value as u64),
+			Raw24opt8(x, _) => Some(x as u64),
+			_ => None,
+		}
+	}
+
+	/// Duration this raw value holds, in whole hours. Plain counters are assumed to count hours already.
+	pub fn hours(&self) -> Option<u64> {
+		use self::Raw::*;
+		match *self {
+			Minutes(m) => Some(m / 60),
+			Seconds(s) => Some(s / 3600),
+			HoursMilliseconds(h, _) => Some(h as u64),
+			_ => self.count(),
+		}
+	}
+
+	/// Temperature this raw value holds, in °C.
+	pub fn celsius(&self) -> Option<f32> {
+		use self::Raw::*;
+		match *self {
+			Celsius(t) => Some(t),
+			CelsiusMinMax { current, .. } => Some(current as f32),
+			// not rendered using drivedb: the lowest byte is the current temperature for pretty much every drive out there (see the chart in `from_raw_entry()`)
+			Raw64(x) => Some((x & 0xff) as u8 as i8 as f32),
+			_ => None,
+		}
+	}
+
 	// `data`: see above
 	pub fn from_raw_entry(data: &[u8], attr: &Option<drivedb::Attribute>) -> Self {
 		let (fmt, byte_order) = attr.as_ref().map(|a|
@@ -120,8 +158,14 @@ impl Raw {
 		).unwrap_or(
 			("raw48".to_string(), "543210".to_string())
 		);
-		let raw48 = reorder(&data, &byte_order);
+		/*
+		Much like in smartmontools, byte order yields a single 64-bit value: byte orders shorter than 8 bytes are padded with zeroes, and 64-bit ones (e.g. the default `543210wv` of raw64) take value and worst bytes in.
+		Plain counters show that value as a whole, regardless of the format (`raw48` with `543210wv` is still a 64-bit number), and formats that split the raw value into parts take them from its lower 48 bits.
+		*/
 		let raw64 = reorder(&data, &format!("{:_>8}", byte_order));
+		let raw64 = raw64[raw64.len() - 8 ..].to_vec();
+		let raw48 = raw64[2..].to_vec();
+		let value = read(&raw64, 64);
 
 		use self::Raw::*;
 		match fmt.as_ref() {
@@ -129,20 +173,19 @@ impl Raw {
 			"raw16" => Raw16(
 				raw48.chunks(2).map(|i| read(i, 16) as u16).collect()
 			),
-			"raw56" | "hex56" => Raw64(read(&raw64[1..8], 56)),
-			"raw64" | "hex64" => Raw64(read(&raw64, 64)),
+			"raw56" | "hex56" | "raw64" | "hex64" => Raw64(value),
+			// `raw48` goes from the most significant byte, so the value is in the last word, as in smartmontools
 			"raw16(avg16)" => {
-				let mut raw48 = raw48.chunks(2).map(|i| read(i, 16) as u16);
+				let words: Vec<u16> = raw48.chunks(2).map(|i| read(i, 16) as u16).collect();
 				Raw16avg16 {
-					value: raw48.next().unwrap(), // the 0th element should always be here
-					average: raw48.next().unwrap(), // and so is the 1st
+					value: words[2],
+					average: words[1],
 				}
 			},
 			"raw16(raw16)" => {
-				let mut raw48 = raw48.chunks(2).map(|i| read(i, 16) as u16);
-				let x = raw48.next().unwrap(); // 0th element should always be here
-				let opt: Vec<u16> = raw48.collect();
-				Raw16opt16(x, opt.iter().filter(|&&i| i>0).max().map(|_| opt.clone()))
+				let words: Vec<u16> = raw48.chunks(2).map(|i| read(i, 16) as u16).collect();
+				let opt = words[..2].to_vec();
+				Raw16opt16(words[2], opt.iter().filter(|&&i| i>0).max().map(|_| opt.clone()))
 			},
 			"raw24(raw8)" => {
 				let x = read(&raw48[3..6], 24) as u32;
@@ -157,15 +200,16 @@ impl Raw {
 				read(&raw64[1..4], 24) as u32,
 				read(&raw64[4..8], 32) as u32,
 			),
-			"sec2hour" => Seconds(read(&raw48, 48)),
-			"min2hour" => Minutes(read(&raw48, 48)),
-			"halfmin2hour" => Seconds(read(&raw48, 48) * 30),
+			"sec2hour" => Seconds(value),
+			"min2hour" => Minutes(value),
+			"halfmin2hour" => Seconds(value * 30),
 			"msec24hour32" => HoursMilliseconds(
 				read(&raw64[4..8], 32) as u32, // hour
 				read(&raw64[1..4], 24) as u32, // msec
 			),
+			// signed, so that temperatures below zero (or negative offsets) do not turn into 6553.5°C
 			"temp10x" => Celsius(
-				read(&raw48[4..6], 16) as f32 / 10.
+				read(&raw48[4..6], 16) as u16 as i16 as f32 / 10.
 			),
 			"tempminmax" => {
 				/*
@@ -186,34 +230,104 @@ impl Raw {
 				xx HL xx LH xx TT
 				CC CC HL LH xx TT
 
-				TODO? negative temperatures (see e.g. https://www.smartmontools.org/ticket/291)
+				Temperatures are signed bytes (see e.g. https://www.smartmontools.org/ticket/291): ff is -1°C, not 255°C.
+
 				TODO? we're also skipping WDC overheating counters here
 				*/
 
-				match raw48[..] {
-					[0, 0, 0, 0, 0, t] => Celsius(t as f32),
-					[0, 0, 0, x, y, t] => CelsiusMinMax {
-						current: t,
-						min: min(x, y),
-						max: max(x, y),
-					},
-					[0, 0, x, y, 0, t] => CelsiusMinMax {
-						current: t,
-						min: min(x, y),
-						max: max(x, y),
-					},
-					[0, x, 0, y, 0, t] => CelsiusMinMax {
+				let minmax = |t: u8, x: u8, y: u8| {
+					let (t, x, y) = (t as i8, x as i8, y as i8);
+					CelsiusMinMax {
 						current: t,
 						min: min(x, y),
 						max: max(x, y),
-					},
+					}
+				};
+
+				match raw48[..] {
+					[0, 0, 0, 0, 0, t] => Celsius(t as i8 as f32),
+					// negative temperature, sign-extended into the next byte
+					[0, 0, 0, 0, 0xff, t] if t >= 0x80 => Celsius(t as i8 as f32),
+					[0, 0, 0, x, y, t] => minmax(t, x, y),
+					[0, 0, x, y, 0, t] => minmax(t, x, y),
+					[0, x, 0, y, 0, t] => minmax(t, x, y),
 					// whatever this might be, show it using default formatter
-					_ => Raw64( read(&raw48, 48) ),
+					_ => Raw64(value),
 				}
 			},
 			// {raw,hex}48 is the default
-			_ => Raw64( read(&raw48, 48) ),
+			_ => Raw64(value),
 		}
 
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::Raw::*;
+	use drivedb::vendor_attribute;
+
+	// attribute table entry with `value`, `worst`, and `raw` bytes (least significant first)
+	fn entry(value: u8, worst: u8, raw: [u8; 6]) -> Vec<u8> {
+		let mut data = vec![241, 0x32, 0, value, worst];
+		data.extend_from_slice(&raw);
+		data.push(0); // reserved
+		data
+	}
+
+	// decodes the entry the way `-v attr` says
+	fn decode(data: &[u8], attr: &str) -> Raw {
+		Raw::from_raw_entry(data, &Some(vendor_attribute::parse(attr).unwrap()))
+	}
+
+	#[test]
+	fn raw64() {
+		let data = entry(0x12, 0x34, [1, 2, 3, 4, 5, 6]);
+		// default byte order of raw64 is 543210wv
+		assert_eq!(decode(&data, "241,raw64").count(), Some(0x0605_0403_0201_3412));
+		assert_eq!(decode(&data, "241,hex64").count(), Some(0x0605_0403_0201_3412));
+		// raw48 leaves value and worst out
+		assert_eq!(decode(&data, "241,raw48").count(), Some(0x0605_0403_0201));
+		assert_eq!(Raw::from_raw_entry(&data, &None).count(), Some(0x0605_0403_0201));
+	}
+
+	#[test]
+	fn byte_order_tokens() {
+		let mut data = entry(0x11, 0x22, [0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5]);
+		data[11] = 0x33; // reserved
+		let tokens = [
+			('v', 0x11), ('w', 0x22), ('r', 0x33),
+			('0', 0xa0), ('1', 0xa1), ('2', 0xa2), ('3', 0xa3), ('4', 0xa4), ('5', 0xa5),
+			// not backed by any attribute data
+			('6', 0), ('7', 0), ('s', 0), ('m', 0),
+		];
+		assert_eq!(tokens.len(), vendor_attribute::BYTE_ORDER_TOKENS.len());
+		for &(token, byte) in tokens.iter() {
+			let attr = format!("241,raw64:{}", token);
+			assert_eq!(decode(&data, &attr).count(), Some(byte), "{}", attr);
+		}
+	}
+
+	#[test]
+	fn signed_temperature() {
+		let temp = |raw| match decode(&entry(100, 100, raw), "194,tempminmax") {
+			Celsius(t) => t,
+			raw => panic!("not a plain temperature: {:?}", raw),
+		};
+		assert_eq!(temp([25, 0, 0, 0, 0, 0]), 25.);
+		// ffh, sign-extended into the next byte
+		assert_eq!(temp([0xff, 0xff, 0, 0, 0, 0]), -1.);
+
+		match decode(&entry(100, 100, [0xff, 0, 0xf6, 0, 0x28, 0]), "194,tempminmax") {
+			CelsiusMinMax { current, min, max } => assert_eq!((current, min, max), (-1, -10, 40)),
+			raw => panic!("not a temperature range: {:?}", raw),
+		}
+
+		// tenths of °C
+		match decode(&entry(100, 100, [0xf6, 0xff, 0, 0, 0, 0]), "194,temp10x") {
+			Celsius(t) => assert_eq!(t, -1.),
+			raw => panic!("not a plain temperature: {:?}", raw),
+		}
+	}
+}
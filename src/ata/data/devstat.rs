@@ -0,0 +1,246 @@
+/*!
+Functions to parse and structs to represent Device Statistics log (General Purpose log address 04h).
+
+Page 0 lists the statistics pages the device supports. Every other page starts with an 8-byte header (revision number, page number), followed by 8-byte statistics: bits 55..0 hold the value (most are narrower than that), and the top byte holds flags, such as whether the statistic is supported at all, and whether its value is valid at the moment.
+
+Besides the generic lookup by page and offset, the most commonly used statistics of General Statistics (page 01h) and Rotating Media Statistics (page 03h) pages have accessors of their own.
+
+For more, see ACS-4, 9.5 Device Statistics log.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+/// General Statistics page
+pub const GENERAL: u8 = 0x01;
+/// Rotating Media Statistics page
+pub const ROTATING_MEDIA: u8 = 0x03;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct Statistic {
+	/// Offset of the statistic within its page, in bytes
+	pub offset: u16,
+	/// Value, if it is valid; devices might report statistics they are yet to compute, or ones that do not apply at the moment
+	pub value: Option<u64>,
+	/// Value is normalized rather than being an absolute count
+	pub normalized: bool,
+	/// Device Statistics Notification is supported for this statistic
+	pub dsn_supported: bool,
+	/// Threshold that the host set with Device Statistics Notification is reached
+	pub monitored_condition_met: bool,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct Page {
+	pub number: u8,
+	pub revision: u16,
+	/// Statistics the device supports; unsupported ones are left out
+	pub statistics: Vec<Statistic>,
+}
+
+impl Page {
+	/// Statistic at `offset` (in bytes, as in ACS-4 tables), if the device supports it
+	pub fn statistic(&self, offset: u16) -> Option<&Statistic> {
+		self.statistics.iter().find(|stat| stat.offset == offset)
+	}
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct DeviceStatistics {
+	/// Pages listed in page 0, in that order, excluding page 0 itself
+	pub supported_pages: Vec<u8>,
+	pub pages: Vec<Page>,
+}
+
+impl DeviceStatistics {
+	pub fn page(&self, number: u8) -> Option<&Page> {
+		self.pages.iter().find(|page| page.number == number)
+	}
+
+	/// Valid value of the statistic at `offset` of the page `page`
+	pub fn value(&self, page: u8, offset: u16) -> Option<u64> {
+		self.page(page)?.statistic(offset)?.value
+	}
+
+	/// Number of times the device was powered on, or reset (General Statistics page)
+	pub fn lifetime_power_on_resets(&self) -> Option<u64> { self.value(GENERAL, 0x08) }
+	/// General Statistics page
+	pub fn power_on_hours(&self) -> Option<u64> { self.value(GENERAL, 0x10) }
+	/// General Statistics page
+	pub fn logical_sectors_written(&self) -> Option<u64> { self.value(GENERAL, 0x18) }
+	/// General Statistics page
+	pub fn write_commands(&self) -> Option<u64> { self.value(GENERAL, 0x20) }
+	/// General Statistics page
+	pub fn logical_sectors_read(&self) -> Option<u64> { self.value(GENERAL, 0x28) }
+	/// General Statistics page
+	pub fn read_commands(&self) -> Option<u64> { self.value(GENERAL, 0x30) }
+
+	/// Hours the spindle motor was spinning (Rotating Media Statistics page)
+	pub fn spindle_motor_power_on_hours(&self) -> Option<u64> { self.value(ROTATING_MEDIA, 0x08) }
+	/// Hours the heads were loaded (Rotating Media Statistics page)
+	pub fn head_flying_hours(&self) -> Option<u64> { self.value(ROTATING_MEDIA, 0x10) }
+	/// Rotating Media Statistics page
+	pub fn head_load_events(&self) -> Option<u64> { self.value(ROTATING_MEDIA, 0x18) }
+	/// Rotating Media Statistics page
+	pub fn reallocated_sectors(&self) -> Option<u64> { self.value(ROTATING_MEDIA, 0x20) }
+	/// Rotating Media Statistics page
+	pub fn mechanical_start_failures(&self) -> Option<u64> { self.value(ROTATING_MEDIA, 0x30) }
+	/// Sectors that are candidates for reallocation (Rotating Media Statistics page)
+	pub fn pending_sectors(&self) -> Option<u64> { self.value(ROTATING_MEDIA, 0x38) }
+	/// Emergency unloads, e.g. on power loss, or when the device detects free fall (Rotating Media Statistics page)
+	pub fn high_priority_unload_events(&self) -> Option<u64> { self.value(ROTATING_MEDIA, 0x40) }
+}
+
+/// Pages listed in page 0 of the log. Returns `None` if `data` is not a complete 512-byte page.
+pub fn supported_pages(data: &[u8]) -> Option<Vec<u8>> {
+	if data.len() < 512 {
+		return None;
+	}
+	// bytes 0..7: header; byte 8: number of entries; bytes 9..: page numbers, starting with page 0 itself
+	let entries = data[8] as usize;
+	Some(data[9 .. ::std::cmp::min(9 + entries, 512)].iter()
+		.cloned()
+		.filter(|&page| page != 0)
+		.collect())
+}
+
+fn parse_page(data: &[u8]) -> Option<Page> {
+	// unwraps: pages are 512 bytes long
+	let revision = (&data[0..2]).read_u16::<LittleEndian>().unwrap();
+	// unsupported pages tend to be all zeroes
+	if revision == 0 {
+		return None;
+	}
+
+	let statistics = data.chunks(8).enumerate().skip(1).filter_map(|(i, stat)| {
+		let stat = (&stat[..]).read_u64::<LittleEndian>().unwrap();
+		let is_set = |bit: u8| stat & (1 << bit) != 0;
+		if !is_set(63) {
+			return None;
+		}
+		Some(Statistic {
+			offset: i as u16 * 8,
+			value: if is_set(62) { Some(stat & 0x00ff_ffff_ffff_ffff) } else { None },
+			normalized: is_set(61),
+			dsn_supported: is_set(60),
+			monitored_condition_met: is_set(59),
+		})
+	}).collect();
+
+	Some(Page {
+		number: data[2],
+		revision,
+		statistics,
+	})
+}
+
+/**
+Parses Device Statistics log pages (`data` holds page 0 followed by any number of statistics pages, in any order). Returns `None` if page 0 is truncated.
+
+Pages are identified by headers of their own, so pages that are all zeroes (as some devices reply to pages they do not support) are skipped.
+*/
+pub fn parse_device_statistics(data: &[u8]) -> Option<DeviceStatistics> {
+	let supported_pages = supported_pages(data)?;
+	let pages = data[512..].chunks(512)
+		.filter(|page| page.len() == 512)
+		.filter_map(parse_page)
+		.collect();
+
+	Some(DeviceStatistics {
+		supported_pages,
+		pages,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// page of the log with `stats` (offset, the whole 8-byte statistic) set, and the header for page `number`
+	fn page(number: u8, stats: &[(usize, u64)]) -> Vec<u8> {
+		let mut data = vec![0; 512];
+		data[..3].copy_from_slice(&[0x01, 0x00, number]);
+		for &(offset, stat) in stats {
+			for i in 0..8 {
+				data[offset + i] = (stat >> (i * 8)) as u8;
+			}
+		}
+		data
+	}
+
+	// Device Statistics log of a 3.5" HDD: pages 0..7 are listed, and General Statistics, Rotating Media Statistics, and Temperature Statistics (page 05h) are read back; the rest are all zeroes
+	fn log() -> Vec<u8> {
+		let mut data = vec![0; 512];
+		data[0] = 0x01;
+		data[8] = 8;
+		data[9..17].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+
+		data.extend(page(GENERAL, &[
+			(0x08, 0xc000_0000_0000_0142),
+			(0x10, 0xc000_0000_0000_6d7a),
+			(0x18, 0xc000_0009_1c4a_2e10),
+			(0x20, 0xc000_0000_1b3e_0c5d),
+			(0x28, 0xc000_0019_fb02_41d8),
+			(0x30, 0xc000_0000_2c8f_3a42),
+			// Date and Time TimeStamp: supported, but not valid yet
+			(0x38, 0x8000_0000_0000_0000),
+		]));
+		data.extend(vec![0; 512]);
+		data.extend(page(ROTATING_MEDIA, &[
+			(0x08, 0xc000_0000_0000_6c10),
+			(0x10, 0xc000_0000_0000_6a2f),
+			(0x18, 0xc000_0000_0000_0519),
+			(0x20, 0xc000_0000_0000_0000),
+			(0x30, 0xc000_0000_0000_0000),
+			(0x38, 0xc000_0000_0000_0008),
+			(0x40, 0xc000_0000_0000_000d),
+		]));
+		data.extend(page(0x05, &[
+			// Current Temperature, with DSN supported
+			(0x08, 0xd000_0000_0000_0024),
+		]));
+		data
+	}
+
+	#[test]
+	fn captured_log() {
+		let stats = parse_device_statistics(&log()).unwrap();
+		assert_eq!(stats.supported_pages, vec![1, 2, 3, 4, 5, 6, 7]);
+		// page 2 is all zeroes
+		assert_eq!(stats.pages.iter().map(|page| page.number).collect::<Vec<_>>(), vec![GENERAL, ROTATING_MEDIA, 0x05]);
+
+		assert_eq!(stats.lifetime_power_on_resets(), Some(0x142));
+		assert_eq!(stats.power_on_hours(), Some(0x6d7a));
+		assert_eq!(stats.logical_sectors_written(), Some(0x09_1c4a_2e10));
+		assert_eq!(stats.write_commands(), Some(0x1b3e_0c5d));
+		assert_eq!(stats.logical_sectors_read(), Some(0x19_fb02_41d8));
+		assert_eq!(stats.read_commands(), Some(0x2c8f_3a42));
+		let timestamp = stats.page(GENERAL).unwrap().statistic(0x38).unwrap();
+		assert_eq!(timestamp.value, None);
+
+		assert_eq!(stats.spindle_motor_power_on_hours(), Some(0x6c10));
+		assert_eq!(stats.head_flying_hours(), Some(0x6a2f));
+		assert_eq!(stats.head_load_events(), Some(0x519));
+		assert_eq!(stats.reallocated_sectors(), Some(0));
+		assert_eq!(stats.mechanical_start_failures(), Some(0));
+		assert_eq!(stats.pending_sectors(), Some(8));
+		assert_eq!(stats.high_priority_unload_events(), Some(0xd));
+		// Read Recovery Attempts (28h) is not supported
+		assert!(stats.page(ROTATING_MEDIA).unwrap().statistic(0x28).is_none());
+
+		let temp = stats.page(0x05).unwrap().statistic(0x08).unwrap();
+		assert_eq!(temp.value, Some(0x24));
+		assert!(temp.dsn_supported && !temp.normalized && !temp.monitored_condition_met);
+	}
+
+	#[test]
+	fn truncated_log() {
+		assert!(parse_device_statistics(&log()[..511]).is_none());
+		// partial pages are left out
+		let stats = parse_device_statistics(&log()[..1024 + 100]).unwrap();
+		assert_eq!(stats.pages.len(), 1);
+		assert_eq!(stats.power_on_hours(), Some(0x6d7a));
+	}
+}
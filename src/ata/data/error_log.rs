@@ -0,0 +1,387 @@
+/*!
+Functions to parse and structs to represent SMART error logs: Summary SMART error log (SMART log address 01h) and Extended Comprehensive SMART error log (General Purpose log address 03h).
+
+Both logs are circular buffers of error data structures, with an index pointing to the most recent one. Each structure holds the registers the device returned for the failed command, along with up to five commands that led to the error, the last of them being the failed command itself. The former log holds 5 structures with 28-bit LBAs, the latter holds 4 structures per page with 48-bit LBAs.
+
+Firmware bugs of these logs (`-F samsung2`, `-F xerrorlba`, see [`FirmwareBug`](../../../drivedb/enum.FirmwareBug.html)) are worked around if drivedb says the drive has them.
+
+For more, see ACS-3, 9.3 Extended Comprehensive SMART Error log and A.3 Summary SMART Error log.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use drivedb;
+
+/// Command, or reset, logged along with the error
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct CommandRecord {
+	pub command: u8,
+	pub features: u16,
+	pub count: u16,
+	pub lba: u64,
+	pub device: u8,
+	pub device_control: u8,
+	/// Milliseconds since power-on at the time the command was issued (wraps around after about 49.7 days)
+	pub timestamp: u32,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct ErrorRecord {
+	/// Commands that led to the error, from the last (the one that failed) to the oldest one
+	pub commands: Vec<CommandRecord>,
+	/// Error, status, and other registers returned for the failed command
+	pub error: u8,
+	pub status: u8,
+	pub count: u16,
+	pub lba: u64,
+	pub device: u8,
+	/// Device state at the time of the error (bits 3..0; see [`state_description()`](fn.state_description.html))
+	pub state: u8,
+	/// Power-on hours at the time of the error
+	pub lifetime: u16,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct ErrorLog {
+	/// Number of errors the device reported over its lifetime, including those that did not fit the log
+	pub error_count: u16,
+	/// Logged errors, ordered from the most recent one
+	pub errors: Vec<ErrorRecord>,
+}
+
+/// Describes the device state the error occurred in, e.g. 'active or idle'.
+pub fn state_description(state: u8) -> &'static str {
+	match state & 0x0f {
+		0 => "in an unknown state",
+		1 => "sleeping",
+		2 => "in standby",
+		3 => "active or idle",
+		4 => "doing SMART off-line or self-test",
+		0x0b..=0x0f => "in a vendor-specific state",
+		_ => "in a reserved state",
+	}
+}
+
+// 28-bit LBAs: bits 27..24 are in the DEVICE field
+fn lba28(low: u8, mid: u8, high: u8, device: u8) -> u64 {
+	(device as u64 & 0x0f) << 24 | (high as u64) << 16 | (mid as u64) << 8 | low as u64
+}
+
+// 48-bit LBAs: bytes go in 7:0, 31:24, 15:8, 39:32, 23:16, 47:40 order, unless the drive has the `xerrorlba` bug and puts them in little-endian order instead
+fn lba48(data: &[u8], xerrorlba: bool) -> u64 {
+	if xerrorlba {
+		// unwrap: callers pass six bytes
+		return (&data[..6]).read_uint::<LittleEndian>(6).unwrap();
+	}
+	data[0] as u64 | (data[2] as u64) << 8 | (data[4] as u64) << 16
+		| (data[1] as u64) << 24 | (data[3] as u64) << 32 | (data[5] as u64) << 40
+}
+
+fn has_bug(meta: &Option<drivedb::DriveMeta>, bug: drivedb::FirmwareBug) -> bool {
+	meta.as_ref().map(|meta| meta.has_firmware_bug(bug)).unwrap_or(false)
+}
+
+// 90-byte error data structure of the Summary SMART error log
+fn parse_summary_record(entry: &[u8]) -> Option<ErrorRecord> {
+	// unused structures are filled with zeroes
+	if entry.iter().all(|&b| b == 0) {
+		return None;
+	}
+
+	// unwraps: callers pass structures that are long enough
+	let commands = entry[..60].chunks(12).rev()
+		.filter(|cmd| cmd.iter().any(|&b| b != 0))
+		.map(|cmd| CommandRecord {
+			device_control: cmd[0],
+			features: cmd[1] as u16,
+			count: cmd[2] as u16,
+			lba: lba28(cmd[3], cmd[4], cmd[5], cmd[6]),
+			device: cmd[6],
+			command: cmd[7],
+			timestamp: (&cmd[8..12]).read_u32::<LittleEndian>().unwrap(),
+		})
+		.collect();
+
+	let err = &entry[60..90];
+	Some(ErrorRecord {
+		commands,
+		// err[0] is reserved
+		error: err[1],
+		count: err[2] as u16,
+		lba: lba28(err[3], err[4], err[5], err[6]),
+		device: err[6],
+		status: err[7],
+		// err[8..27]: extended error information (vendor-specific)
+		state: err[27],
+		lifetime: (&err[28..30]).read_u16::<LittleEndian>().unwrap(),
+	})
+}
+
+// 124-byte error data structure of the Extended Comprehensive SMART error log
+fn parse_ext_record(entry: &[u8], xerrorlba: bool) -> Option<ErrorRecord> {
+	if entry.iter().all(|&b| b == 0) {
+		return None;
+	}
+
+	// unwraps: callers pass structures that are long enough
+	let commands = entry[..90].chunks(18).rev()
+		.filter(|cmd| cmd.iter().any(|&b| b != 0))
+		.map(|cmd| CommandRecord {
+			device_control: cmd[0],
+			features: (&cmd[1..3]).read_u16::<LittleEndian>().unwrap(),
+			count: (&cmd[3..5]).read_u16::<LittleEndian>().unwrap(),
+			lba: lba48(&cmd[5..11], xerrorlba),
+			device: cmd[11],
+			command: cmd[12],
+			// cmd[13] is reserved
+			timestamp: (&cmd[14..18]).read_u32::<LittleEndian>().unwrap(),
+		})
+		.collect();
+
+	let err = &entry[90..124];
+	Some(ErrorRecord {
+		commands,
+		// err[0]: transport-specific
+		error: err[1],
+		count: (&err[2..4]).read_u16::<LittleEndian>().unwrap(),
+		lba: lba48(&err[4..10], xerrorlba),
+		device: err[10],
+		status: err[11],
+		// err[12..31]: extended error information (vendor-specific)
+		state: err[31],
+		lifetime: (&err[32..34]).read_u16::<LittleEndian>().unwrap(),
+	})
+}
+
+/**
+Parses Summary SMART error log. Returns `None` if `data` is not a complete 512-byte sector.
+
+If `meta` says the drive has the `samsung2` firmware bug, bytes of the error count are swapped back.
+*/
+pub fn parse_summary_error_log(data: &[u8], meta: &Option<drivedb::DriveMeta>) -> Option<ErrorLog> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	// unwrap: length is checked above
+	let mut error_count = (&data[452..454]).read_u16::<LittleEndian>().unwrap();
+	if has_bug(meta, drivedb::FirmwareBug::Samsung2) {
+		error_count = error_count.swap_bytes();
+	}
+
+	// byte 0: revision; index is 1-based, and 0 means the log is empty
+	let index = data[1] as usize;
+	if index == 0 || index > 5 {
+		return Some(ErrorLog { error_count, errors: vec![] });
+	}
+
+	Some(ErrorLog {
+		error_count,
+		errors: (0..5)
+			.map(|i| (index - 1 + 5 - i) % 5)
+			.filter_map(|n| parse_summary_record(&data[2 + n * 90 .. 2 + (n + 1) * 90]))
+			.collect(),
+	})
+}
+
+/**
+Parses Extended Comprehensive SMART error log pages, starting with page 0. Returns `None` if `data` does not hold a complete 512-byte page.
+
+If `meta` says the drive has the `xerrorlba` firmware bug, LBAs are read in little-endian byte order.
+*/
+pub fn parse_ext_error_log(data: &[u8], meta: &Option<drivedb::DriveMeta>) -> Option<ErrorLog> {
+	if data.len() < 512 {
+		return None;
+	}
+	let xerrorlba = has_bug(meta, drivedb::FirmwareBug::XErrorLBA);
+
+	// unwraps: length is checked above
+	let error_count = (&data[500..502]).read_u16::<LittleEndian>().unwrap();
+
+	let records = data.len() / 512 * 4;
+	// byte 0: revision; index is 1-based, and 0 means the log is empty
+	let index = (&data[2..4]).read_u16::<LittleEndian>().unwrap() as usize;
+	if index == 0 || index > records {
+		return Some(ErrorLog { error_count, errors: vec![] });
+	}
+
+	Some(ErrorLog {
+		error_count,
+		errors: (0..records)
+			.map(|i| (index - 1 + records - i) % records)
+			.filter_map(|n| {
+				let offset = n / 4 * 512 + 4 + n % 4 * 124;
+				parse_ext_record(&data[offset .. offset + 124], xerrorlba)
+			})
+			.collect(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ata::data::id;
+
+	// drive database with a single entry, for the MOCK model, with `presets` of its own
+	fn drivedb(presets: &str) -> drivedb::DriveDB {
+		drivedb::DriveDB::new(vec![drivedb::Entry {
+			family: "Mock".to_string(),
+			model: "MOCK".to_string(),
+			firmware: "".to_string(),
+			warning: "".to_string(),
+			presets: presets.to_string(),
+		}]).unwrap()
+	}
+
+	fn mock_id() -> id::Id {
+		let mut id = id::parse_id(&vec![0; 512]);
+		id.model = "MOCK".to_string();
+		id
+	}
+
+	// Extended Comprehensive SMART error log page with one error, which has LBA bytes 01h..06h in the order they are stored
+	fn ext_log() -> Vec<u8> {
+		let mut data = vec![0; 512];
+		data[0] = 1; // revision
+		data[2] = 1; // index
+		let entry = &mut data[4..128];
+		entry[5..11].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+		entry[12] = 0x60; // READ FPDMA QUEUED
+		entry[91] = 0x40; // UNC
+		entry[94..100].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+		entry[101] = 0x51;
+		data
+	}
+
+	#[test]
+	fn xerrorlba() {
+		let log = parse_ext_error_log(&ext_log(), &None).unwrap();
+		assert_eq!(log.errors[0].lba, 0x06_04_02_05_03_01);
+		assert_eq!(log.errors[0].commands[0].lba, 0x06_04_02_05_03_01);
+
+		let db = drivedb("-F xerrorlba");
+		let meta = Some(db.render_meta(&mock_id(), &vec![]));
+		let log = parse_ext_error_log(&ext_log(), &meta).unwrap();
+		assert_eq!(log.errors[0].lba, 0x06_05_04_03_02_01);
+		assert_eq!(log.errors[0].commands[0].lba, 0x06_05_04_03_02_01);
+		assert_eq!(log.errors[0].commands[0].command, 0x60);
+	}
+
+	#[test]
+	fn samsung2() {
+		let mut data = vec![0; 512];
+		data[452..454].copy_from_slice(&[0x00, 0x02]);
+
+		assert_eq!(parse_summary_error_log(&data, &None).unwrap().error_count, 0x0200);
+
+		let db = drivedb("-F samsung2");
+		let meta = Some(db.render_meta(&mock_id(), &vec![]));
+		assert_eq!(parse_summary_error_log(&data, &meta).unwrap().error_count, 2);
+
+		// other bugs of the drive do not matter
+		let db = drivedb("-F xerrorlba");
+		let meta = Some(db.render_meta(&mock_id(), &vec![]));
+		assert_eq!(parse_summary_error_log(&data, &meta).unwrap().error_count, 0x0200);
+	}
+	// Summary SMART error log with errors 1..=`count` in the structures, starting with the first one, and `index` pointing to the most recent one; error `n` has `n` as its lifetime
+	fn summary_log(count: usize, index: u8) -> Vec<u8> {
+		let mut data = vec![0; 512];
+		data[0] = 1; // revision
+		data[1] = index;
+		for n in 0..count {
+			let entry = &mut data[2 + n * 90 .. 2 + (n + 1) * 90];
+			// two commands: SMART READ DATA followed by READ DMA of LBA 0ABCDEFh, which failed
+			entry[36..48].copy_from_slice(&[0, 0xd0, 1, 0, 0x4f, 0xc2, 0xa0, 0xb0, 0x10, 0, 0, 0]);
+			entry[48..60].copy_from_slice(&[0, 0, 8, 0xef, 0xcd, 0xab, 0xe0, 0xc8, 0x20, 0, 0, 0]);
+			entry[60..68].copy_from_slice(&[0, 0x40, 8, 0xef, 0xcd, 0xab, 0xe0, 0x51]);
+			entry[87] = 3; // active or idle
+			entry[88] = n as u8 + 1;
+		}
+		data[452] = count as u8 + 10;
+		data
+	}
+
+	#[test]
+	fn summary_log_format() {
+		let log = parse_summary_error_log(&summary_log(1, 1), &None).unwrap();
+		assert_eq!(log.error_count, 11);
+		assert_eq!(log.errors.len(), 1);
+
+		let err = &log.errors[0];
+		assert_eq!((err.error, err.status, err.count, err.device), (0x40, 0x51, 8, 0xe0));
+		assert_eq!(err.lba, 0x00ab_cdef);
+		assert_eq!((err.state, err.lifetime), (3, 1));
+
+		// from the failed command back
+		assert_eq!(err.commands.iter().map(|cmd| cmd.command).collect::<Vec<_>>(), vec![0xc8, 0xb0]);
+		assert_eq!((err.commands[0].lba, err.commands[0].count, err.commands[0].timestamp), (0x00ab_cdef, 8, 0x20));
+		assert_eq!((err.commands[1].features, err.commands[1].lba), (0xd0, 0xc2_4f00));
+
+		assert!(parse_summary_error_log(&summary_log(1, 1)[..511], &None).is_none());
+	}
+
+	#[test]
+	fn summary_log_wraparound() {
+		let lifetimes = |data: &[u8]| -> Vec<u16> {
+			parse_summary_error_log(data, &None).unwrap().errors.iter().map(|err| err.lifetime).collect()
+		};
+		// most recent one first, skipping unused structures
+		assert_eq!(lifetimes(&summary_log(3, 3)), vec![3, 2, 1]);
+		// errors 6 and 7 overwrote the first two structures
+		let mut data = summary_log(5, 2);
+		data[2 + 88] = 6;
+		data[2 + 90 + 88] = 7;
+		assert_eq!(lifetimes(&data), vec![7, 6, 5, 4, 3]);
+		// index past the last structure
+		assert_eq!(lifetimes(&summary_log(5, 6)), Vec::<u16>::new());
+		assert_eq!(lifetimes(&summary_log(0, 0)), Vec::<u16>::new());
+	}
+
+	// `pages` of Extended Comprehensive SMART error log with errors 1..=`count`, and `index` pointing to the most recent one; error `n` has `n` as its lifetime
+	fn ext_log_pages(pages: usize, count: usize, index: u16) -> Vec<u8> {
+		let mut data = vec![0; pages * 512];
+		data[0] = 1; // revision
+		data[2..4].copy_from_slice(&[index as u8, (index >> 8) as u8]);
+		for n in 0..count {
+			let offset = n / 4 * 512 + 4 + n % 4 * 124;
+			let entry = &mut data[offset .. offset + 124];
+			entry[72 + 12] = 0x25; // READ DMA EXT
+			entry[91] = 0x40; // UNC
+			entry[101] = 0x51;
+			entry[122] = n as u8 + 1;
+		}
+		data[500] = count as u8;
+		data
+	}
+
+	#[test]
+	fn ext_log_format() {
+		let log = parse_ext_error_log(&ext_log(), &None).unwrap();
+		assert_eq!(log.errors.len(), 1);
+		let err = &log.errors[0];
+		assert_eq!((err.error, err.status), (0x40, 0x51));
+		assert_eq!(err.commands.len(), 1);
+		assert_eq!(err.commands[0].command, 0x60);
+
+		assert!(parse_ext_error_log(&ext_log()[..511], &None).is_none());
+	}
+
+	#[test]
+	fn ext_log_wraparound() {
+		let lifetimes = |data: &[u8]| -> Vec<u16> {
+			parse_ext_error_log(data, &None).unwrap().errors.iter().map(|err| err.lifetime).collect()
+		};
+		assert_eq!(lifetimes(&ext_log_pages(1, 3, 3)), vec![3, 2, 1]);
+		assert_eq!(lifetimes(&ext_log_pages(2, 6, 6)), vec![6, 5, 4, 3, 2, 1]);
+		// errors 9 and 10 overwrote the first two structures of the first page
+		let mut data = ext_log_pages(2, 8, 2);
+		data[4 + 122] = 9;
+		data[4 + 124 + 122] = 10;
+		assert_eq!(lifetimes(&data), vec![10, 9, 8, 7, 6, 5, 4, 3]);
+		// index past the last structure of the pages that were read
+		assert_eq!(lifetimes(&ext_log_pages(1, 4, 5)), Vec::<u16>::new());
+	}
+}
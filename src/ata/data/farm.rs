@@ -0,0 +1,244 @@
+/*!
+Functions to parse and structs to represent Seagate Field Accessible Reliability Metrics (FARM) log (General Purpose log address A6h).
+
+The log is vendor-specific, and is only available on (fairly recent) Seagate drives. It consists of pages of `page_size` bytes each (16 KiB, as of FARM versions 2 to 4): page 0 is the header, followed by drive information, workload statistics, error statistics, environment statistics and reliability statistics. Only fields that are the same across FARM versions are decoded here; per-head reliability statistics are not.
+
+Every field is a little-endian 64-bit number: bit 63 is set if the field is supported, bit 62 is set if its value is valid, and bits 55..0 hold the value itself. Fields that are unsupported or invalid are represented as `None`.
+
+For more, see Seagate's FARM log specification, or smartmontools' farmcmds.h.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+/// Log page 0 of the FARM log
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct FarmHeader {
+	pub major_version: u64,
+	pub minor_version: u64,
+	/// Number of log pages, including the header
+	pub pages: u64,
+	/// Size of a log page, in bytes
+	pub page_size: u64,
+	pub heads: Option<u64>,
+}
+
+impl FarmHeader {
+	/// Number of 512-byte log sectors that hold pages this module knows about (that is, every page up to environment statistics)
+	pub fn sectors(&self) -> u64 {
+		::std::cmp::min(self.pages, 5) * self.page_size / 512
+	}
+}
+
+/// Page 1: drive information
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct FarmDrive {
+	/// Device capacity, in logical sectors
+	pub capacity: Option<u64>,
+	pub sector_size_phy: Option<u64>,
+	pub sector_size_log: Option<u64>,
+	pub heads: Option<u64>,
+	pub rpm: Option<u64>,
+	pub power_on_hours: Option<u64>,
+	pub spindle_power_on_hours: Option<u64>,
+	pub head_flight_hours: Option<u64>,
+	pub head_load_events: Option<u64>,
+	pub power_cycles: Option<u64>,
+	pub resets: Option<u64>,
+	/// Time of the last spin-up, in milliseconds
+	pub spin_up_time: Option<u64>,
+}
+
+/// Page 2: workload statistics
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct FarmWorkload {
+	pub read_commands: Option<u64>,
+	pub write_commands: Option<u64>,
+	pub random_reads: Option<u64>,
+	pub random_writes: Option<u64>,
+	pub other_commands: Option<u64>,
+	pub sectors_written: Option<u64>,
+	pub sectors_read: Option<u64>,
+}
+
+/// Page 3: error statistics
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct FarmErrors {
+	pub unrecoverable_read_errors: Option<u64>,
+	pub unrecoverable_write_errors: Option<u64>,
+	pub reallocated_sectors: Option<u64>,
+	pub read_recovery_attempts: Option<u64>,
+	pub mechanical_start_failures: Option<u64>,
+	pub reallocation_candidates: Option<u64>,
+	pub asr_events: Option<u64>,
+	pub interface_crc_errors: Option<u64>,
+	pub spin_retries: Option<u64>,
+}
+
+/// Page 4: environment statistics; temperatures are in °C
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct FarmEnvironment {
+	pub temperature: Option<i64>,
+	pub highest_temperature: Option<i64>,
+	pub lowest_temperature: Option<i64>,
+	/// Time spent above the temperature limit, in minutes
+	pub over_temperature_time: Option<u64>,
+	/// Time spent below the temperature limit, in minutes
+	pub under_temperature_time: Option<u64>,
+	/// Temperature limits the drive is specified for
+	pub max_temperature: Option<i64>,
+	pub min_temperature: Option<i64>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct FarmLog {
+	pub header: FarmHeader,
+	// `None` if the page is not there (not supported, or not read)
+	pub drive: Option<FarmDrive>,
+	pub workload: Option<FarmWorkload>,
+	pub errors: Option<FarmErrors>,
+	pub environment: Option<FarmEnvironment>,
+}
+
+// "FARM"
+const SIGNATURE: u64 = 0x4641_524d;
+
+const SUPPORTED: u64 = 1 << 63;
+const VALID: u64 = 1 << 62;
+const VALUE: u64 = (1 << 56) - 1;
+
+// `index`th field of the page, if it's supported and valid; `page` length is checked by the caller
+fn field(page: &[u8], index: usize) -> Option<u64> {
+	// unwrap: the caller makes sure the page is long enough
+	let raw = (&page[index * 8 .. index * 8 + 8]).read_u64::<LittleEndian>().unwrap();
+	if raw & SUPPORTED != 0 && raw & VALID != 0 {
+		Some(raw & VALUE)
+	} else {
+		None
+	}
+}
+
+// same, for values that could be negative (56-bit two's complement)
+fn signed_field(page: &[u8], index: usize) -> Option<i64> {
+	field(page, index).map(|v| ((v << 8) as i64) >> 8)
+}
+
+/**
+Parses log page 0 of the FARM log.
+
+Returns `None` if the page is truncated, if its signature is not `FARM`, or if the page size it declares is not a multiple of the log sector size.
+*/
+pub fn parse_farm_header(data: &[u8]) -> Option<FarmHeader> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	// signature, unlike other fields, is not required to have its supported/valid bits set
+	// unwrap: length is checked above
+	if (&data[0..8]).read_u64::<LittleEndian>().unwrap() & VALUE != SIGNATURE {
+		return None;
+	}
+
+	let header = FarmHeader {
+		major_version: field(data, 1).unwrap_or(0),
+		minor_version: field(data, 2).unwrap_or(0),
+		pages: field(data, 3).unwrap_or(0),
+		// 4: log size
+		page_size: field(data, 5).unwrap_or(0),
+		heads: field(data, 6),
+		// 7: copies, 8: frame capture
+	};
+
+	if header.page_size == 0 || header.page_size % 512 != 0 {
+		return None;
+	}
+
+	Some(header)
+}
+
+/**
+Parses FARM log pages, starting with the header (see [`parse_farm_header()`](fn.parse_farm_header.html)).
+
+Pages that are beyond the end of `data`, or that the header does not declare, are represented as `None`.
+*/
+pub fn parse_farm_log(data: &[u8]) -> Option<FarmLog> {
+	let header = parse_farm_header(data)?;
+	let (pages, page_size) = (header.pages, header.page_size as usize);
+
+	let page = |n: u64| -> Option<&[u8]> {
+		let start = n as usize * page_size;
+		// every page starts with page number (and copy number), which is also a handy sanity check
+		if n < pages && data.len() >= start + page_size && field(&data[start..], 0) == Some(n) {
+			Some(&data[start .. start + page_size])
+		} else {
+			None
+		}
+	};
+
+	// 0: page number, 1: copy number
+	let drive = page(1).map(|p| FarmDrive {
+		// 2, 3: serial number; 4, 5: WWN; 6: interface
+		capacity: field(p, 7),
+		sector_size_phy: field(p, 8),
+		sector_size_log: field(p, 9),
+		// 10: buffer size
+		heads: field(p, 11),
+		// 12: form factor
+		rpm: field(p, 13),
+		// 14, 15: firmware revision; 16: security state; 17, 18: features supported and enabled
+		power_on_hours: field(p, 19),
+		spindle_power_on_hours: field(p, 20),
+		head_flight_hours: field(p, 21),
+		head_load_events: field(p, 22),
+		power_cycles: field(p, 23),
+		resets: field(p, 24),
+		spin_up_time: field(p, 25),
+	});
+
+	let workload = page(2).map(|p| FarmWorkload {
+		// 2: reserved
+		read_commands: field(p, 3),
+		write_commands: field(p, 4),
+		random_reads: field(p, 5),
+		random_writes: field(p, 6),
+		other_commands: field(p, 7),
+		sectors_written: field(p, 8),
+		sectors_read: field(p, 9),
+	});
+
+	let errors = page(3).map(|p| FarmErrors {
+		unrecoverable_read_errors: field(p, 2),
+		unrecoverable_write_errors: field(p, 3),
+		reallocated_sectors: field(p, 4),
+		read_recovery_attempts: field(p, 5),
+		mechanical_start_failures: field(p, 6),
+		reallocation_candidates: field(p, 7),
+		asr_events: field(p, 8),
+		interface_crc_errors: field(p, 9),
+		spin_retries: field(p, 10),
+	});
+
+	let environment = page(4).map(|p| FarmEnvironment {
+		temperature: signed_field(p, 2),
+		highest_temperature: signed_field(p, 3),
+		lowest_temperature: signed_field(p, 4),
+		// 5..10: short- and long-term averages and extremes
+		over_temperature_time: field(p, 11),
+		under_temperature_time: field(p, 12),
+		max_temperature: signed_field(p, 13),
+		min_temperature: signed_field(p, 14),
+	});
+
+	Some(FarmLog {
+		header,
+		drive,
+		workload,
+		errors,
+		environment,
+	})
+}
@@ -1,5 +1,8 @@
 use std::fmt;
 
+#[cfg(feature = "serializable")]
+use serde::{Serialize, Serializer};
+
 // TODO make sure characters are in the range of 0x20 to (and including) 0x7e
 // (this is in the standard, and also to make std::String safe again)
 fn read_string(arr: &Vec<u16>, start: usize, fin: usize) -> String {
@@ -14,7 +17,6 @@ fn read_string(arr: &Vec<u16>, start: usize, fin: usize) -> String {
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
-#[cfg_attr(feature = "serializable", derive(Serialize))]
 pub enum Ternary {
     Unsupported,
     Disabled,
@@ -32,13 +34,70 @@ impl fmt::Display for Ternary {
 }
 
 #[derive(Debug)]
-#[cfg_attr(feature = "serializable", derive(Serialize))]
 pub enum RPM {
     Unknown,
     NonRotating,
     RPM(u16),
 }
 
+// word 69, bits 1..0
+// Note that host-managed zoned devices are not reported here: these have a different device signature and are not supposed to pretend to be regular ATA devices at all (see ZAC).
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Zoned {
+    NotReported,
+    HostAware,
+    DeviceManaged,
+    Reserved,
+}
+
+impl fmt::Display for Zoned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Zoned::NotReported => write!(f, "not reported"),
+            Zoned::HostAware => write!(f, "host-aware"),
+            Zoned::DeviceManaged => write!(f, "device-managed"),
+            Zoned::Reserved => write!(f, "unknown (reserved value)"),
+        }
+    }
+}
+
+// Serialized by hand so that JSON output stays the same regardless of how variants are named:
+// Ternary and Zoned are strings, RPM is a number (0 for non-rotating media), or null if unknown.
+
+#[cfg(feature = "serializable")]
+impl Serialize for Ternary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match *self {
+            Ternary::Unsupported => "Unsupported",
+            Ternary::Disabled => "Disabled",
+            Ternary::Enabled => "Enabled",
+        })
+    }
+}
+
+#[cfg(feature = "serializable")]
+impl Serialize for Zoned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match *self {
+            Zoned::NotReported => "none",
+            Zoned::HostAware => "host-aware",
+            Zoned::DeviceManaged => "device-managed",
+            Zoned::Reserved => "reserved",
+        })
+    }
+}
+
+#[cfg(feature = "serializable")]
+impl Serialize for RPM {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            RPM::Unknown => serializer.serialize_none(),
+            RPM::NonRotating => serializer.serialize_u16(0),
+            RPM::RPM(rpm) => serializer.serialize_u16(rpm),
+        }
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serializable", derive(Serialize))]
 pub struct IdCommands {
@@ -47,6 +106,7 @@ pub struct IdCommands {
     pub read_buffer: bool,
     pub nop: bool,
     pub download_microcode: bool,
+    pub download_microcode_segmented: bool, // DOWNLOAD MICROCODE with offsets, aka mode 03h
     pub read_write_dma_queued: bool,
     pub flush_cache: bool,
     pub flush_cache_ext: bool,
@@ -56,6 +116,230 @@ pub struct IdCommands {
     pub read_write_dma_ext_gpl: bool,
 }
 
+// word 59, bits 15..10
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct Sanitize {
+    pub supported: bool,
+    pub block_erase: bool,
+    pub overwrite: bool,
+    pub crypto_scramble: bool,
+    pub antifreeze_lock: bool,
+    pub commands_allowed_as_specified: bool, // if not, commands allowed during sanitize operation are as specified by ACS-2
+}
+
+// word 128
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct SecurityState {
+    pub supported: bool,
+    pub enabled: bool, // user password is set
+    pub locked: bool, // most commands that access the media abort until SECURITY UNLOCK
+    pub frozen: bool, // SECURITY commands that change the state abort until power cycle (the BIOS usually issues SECURITY FREEZE LOCK on boot)
+    pub count_expired: bool, // too many failed SECURITY UNLOCK attempts, power cycle is required to try again
+    pub enhanced_erase_supported: bool,
+    pub master_password_maximum: bool, // if not, master password capability is High
+}
+
+/// What kind of commands are about to be issued, for [`SecurityState::check()`](struct.SecurityState.html#method.check)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecurityOperation {
+    /// Commands that access the media, e.g. READ VERIFY SECTOR(S)
+    MediaAccess,
+    /// SECURITY SET PASSWORD, SECURITY ERASE UNIT, SECURITY DISABLE PASSWORD and such
+    SecurityChange,
+}
+
+impl SecurityState {
+    /**
+    Checks whether commands of the `operation` kind stand a chance with the device in its current security state.
+
+    Devices simply abort commands they are not willing to execute in locked or frozen states, and the reason is nowhere to be found in the reply; this returns an explanation instead.
+    */
+    pub fn check(&self, operation: SecurityOperation) -> Result<(), &'static str> {
+        if !self.supported {
+            return Ok(());
+        }
+        match operation {
+            SecurityOperation::MediaAccess if self.locked =>
+                Err("drive is SECURITY LOCKED; it has to be unlocked with the user password (SECURITY UNLOCK) first"),
+            SecurityOperation::MediaAccess => Ok(()),
+            SecurityOperation::SecurityChange if self.frozen =>
+                Err("drive is SECURITY FROZEN; a power cycle without BIOS freeze is required (e.g. hot-plugging the drive, or suspending the system to RAM)"),
+            SecurityOperation::SecurityChange if self.count_expired =>
+                Err("drive has exceeded the number of SECURITY UNLOCK attempts; a power cycle is required"),
+            SecurityOperation::SecurityChange => Ok(()),
+        }
+    }
+}
+
+/// Serial ATA features that can be turned on and off with SET FEATURES (subcommands 10h and 90h), numbered as in the COUNT field of these
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub enum SataFeature {
+    NonZeroBufferOffsets = 0x01,
+    DmaSetupAutoActivation = 0x02,
+    /// Device-initiated interface power management (DIPM)
+    DeviceInitiatedPowerManagement = 0x03,
+    InOrderDataDelivery = 0x04,
+    SoftwareSettingsPreservation = 0x06,
+    DeviceAutomaticPartialToSlumber = 0x07,
+    /// DevSleep
+    DeviceSleep = 0x09,
+}
+
+impl SataFeature {
+    pub fn all() -> [SataFeature; 7] {
+        use self::SataFeature::*;
+        [
+            NonZeroBufferOffsets,
+            DmaSetupAutoActivation,
+            DeviceInitiatedPowerManagement,
+            InOrderDataDelivery,
+            SoftwareSettingsPreservation,
+            DeviceAutomaticPartialToSlumber,
+            DeviceSleep,
+        ]
+    }
+
+    /// Short name of the feature, e.g. `dipm`
+    pub fn name(&self) -> &'static str {
+        use self::SataFeature::*;
+        match *self {
+            NonZeroBufferOffsets => "non-zero-offsets",
+            DmaSetupAutoActivation => "dma-auto-activate",
+            DeviceInitiatedPowerManagement => "dipm",
+            InOrderDataDelivery => "in-order-delivery",
+            SoftwareSettingsPreservation => "ssp",
+            DeviceAutomaticPartialToSlumber => "apst",
+            DeviceSleep => "devsleep",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        use self::SataFeature::*;
+        match *self {
+            NonZeroBufferOffsets => "Non-zero buffer offsets",
+            DmaSetupAutoActivation => "DMA Setup FIS auto-activate optimization",
+            DeviceInitiatedPowerManagement => "Device-initiated interface power management",
+            InOrderDataDelivery => "Guaranteed in-order data delivery",
+            SoftwareSettingsPreservation => "Software settings preservation",
+            DeviceAutomaticPartialToSlumber => "Device automatic Partial to Slumber transitions",
+            DeviceSleep => "Device Sleep (DevSleep)",
+        }
+    }
+
+    /// Looks the feature up by either its [`name()`](#method.name) or its code (e.g. `3` or `0x03`)
+    pub fn parse(s: &str) -> Option<Self> {
+        let code = if let Some(hex) = s.strip_prefix("0x") {
+            u8::from_str_radix(hex, 16).ok()
+        } else {
+            s.parse().ok()
+        };
+        Self::all().iter()
+            .find(|f| f.name() == s || Some(**f as u8) == code)
+            .cloned()
+    }
+}
+
+// words 76, 78 and 79; all of these are `Unsupported` for devices that are not Serial ATA ones
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct SataFeatures {
+    pub non_zero_buffer_offsets: Ternary,
+    pub dma_setup_auto_activation: Ternary,
+    pub device_initiated_power_management: Ternary,
+    pub in_order_data_delivery: Ternary,
+    pub software_settings_preservation: Ternary,
+    pub device_automatic_partial_to_slumber: Ternary,
+    pub device_sleep: Ternary,
+}
+
+impl SataFeatures {
+    pub fn get(&self, feature: SataFeature) -> Ternary {
+        use self::SataFeature::*;
+        match feature {
+            NonZeroBufferOffsets => self.non_zero_buffer_offsets,
+            DmaSetupAutoActivation => self.dma_setup_auto_activation,
+            DeviceInitiatedPowerManagement => self.device_initiated_power_management,
+            InOrderDataDelivery => self.in_order_data_delivery,
+            SoftwareSettingsPreservation => self.software_settings_preservation,
+            DeviceAutomaticPartialToSlumber => self.device_automatic_partial_to_slumber,
+            DeviceSleep => self.device_sleep,
+        }
+    }
+}
+
+fn parse_sata_features(data: &Vec<u16>) -> SataFeatures {
+    // word 76 (Serial ATA capabilities) is 0000h or ffffh for devices that are not Serial ATA ones, and words 78-79 are not valid then
+    let sata = data[76] != 0x0000 && data[76] != 0xffff;
+    let feature = |word_sup: usize, bit_sup: usize, bit_enabled: usize| if sata {
+        make_ternary(data, word_sup, bit_sup, 79, bit_enabled)
+    } else {
+        Ternary::Unsupported
+    };
+
+    SataFeatures {
+        non_zero_buffer_offsets: feature(78, 1, 1),
+        dma_setup_auto_activation: feature(78, 2, 2),
+        device_initiated_power_management: feature(78, 3, 3),
+        in_order_data_delivery: feature(78, 4, 4),
+        software_settings_preservation: feature(78, 6, 6),
+        // support for this one is reported along with other capabilities, not features
+        device_automatic_partial_to_slumber: feature(76, 14, 7),
+        device_sleep: feature(78, 8, 8),
+    }
+}
+
+// words 63, 64, 88; modes are numbered as in the standard (e.g. `udma_selected: Some(5)` is UDMA/100)
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct TransferModes {
+    pub pio_max: Option<u8>, // modes 0 to 2 are always supported, but this is only known with word 64 marked as valid
+    pub mwdma_max: Option<u8>, // Multiword DMA
+    pub mwdma_selected: Option<u8>,
+    pub udma_max: Option<u8>, // Ultra DMA; `None` if not supported, or if word 88 is not valid
+    pub udma_selected: Option<u8>,
+}
+
+/// Nominal transfer rate of Ultra DMA `mode`, in MB/s (as in `UDMA/133`)
+pub fn udma_rate(mode: u8) -> Option<u16> {
+    match mode {
+        0 => Some(16),
+        1 => Some(25),
+        2 => Some(33),
+        3 => Some(44),
+        4 => Some(66),
+        5 => Some(100),
+        6 => Some(133),
+        _ => None,
+    }
+}
+
+// number of the highest bit set among `bits` lowest bits of `word`
+fn highest_mode(word: u16, bits: usize) -> Option<u8> {
+    (0..bits).rev().find(|&bit| is_set(word, bit)).map(|bit| bit as u8)
+}
+
+fn parse_transfer_modes(data: &[u16]) -> TransferModes {
+    // word 53: bit 1 means words 64–70 are valid, bit 2 means word 88 is valid
+    let pio_valid = is_set(data[53], 1);
+    let udma_valid = is_set(data[53], 2);
+
+    TransferModes {
+        pio_max: if pio_valid {
+            // bits 0, 1: PIO modes 3, 4
+            Some(highest_mode(data[64], 2).map(|mode| mode + 3).unwrap_or(2))
+        } else {
+            None
+        },
+        mwdma_max: highest_mode(data[63], 3),
+        mwdma_selected: highest_mode(data[63] >> 8, 3),
+        udma_max: if udma_valid { highest_mode(data[88], 7) } else { None },
+        udma_selected: if udma_valid { highest_mode(data[88] >> 8, 7) } else { None },
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serializable", derive(Serialize))]
 pub struct Id {
@@ -69,13 +353,21 @@ pub struct Id {
     pub capacity: u64,
     pub sector_size_phy: u32,
     pub sector_size_log: u32,
+    // offset of the first logical sector within the physical one, in logical sectors (`None` if not reported)
+    pub sector_alignment: Option<u16>,
+    pub lba48_supported: bool, // 48-bit Address feature set, required for commands like READ VERIFY SECTOR(S) EXT
 
+    /// Nominal media rotation rate; serialized as the number of RPMs, `0` for non-rotating media (SSDs), or `null` if not reported
     pub rpm: RPM,
+    pub zoned: Zoned, // zoned capabilities (SMR drives)
 
     pub trusted_computing_supported: bool,
+    pub encrypts_all_user_data: bool,
 
     pub ata_version: Option<&'static str>,
 
+    pub transfer_modes: TransferModes,
+
     pub commands_supported: IdCommands,
 
     pub power_mgmt_supported: bool,
@@ -87,10 +379,61 @@ pub struct Id {
     pub gp_logging_supported: bool, // General Purpose Logging
     pub wwn_supported: bool,        // World Wide Name
     pub security: Ternary,
+    pub security_state: SecurityState,
+    pub sata_features: SataFeatures,
 
     pub smart: Ternary,
     pub smart_error_logging_supported: bool,
     pub smart_self_test_supported: bool,
+
+    pub sct_supported: bool, // SMART Command Transport
+
+    // number of logical sectors currently set to transfer per DRQ data block on READ/WRITE MULTIPLE (`None` if not valid)
+    pub multiple_sectors: Option<u8>,
+    pub sanitize: Sanitize,
+
+    // limits of a single DOWNLOAD MICROCODE with offsets transfer, in 512-byte blocks (`None` if not reported)
+    pub download_microcode_min: Option<u16>,
+    pub download_microcode_max: Option<u16>,
+
+    // whether word 255 checksum matches (`None` if the device does not provide one)
+    pub checksum_valid: Option<bool>,
+
+    // whether sector sizes (and capacity) come from `override_sector_size()` rather than from the device
+    pub sector_size_overridden: bool,
+}
+
+fn valid_sector_size(size: u32) -> bool {
+    size.is_power_of_two() && (512..=65536).contains(&size)
+}
+
+impl Id {
+    /**
+    Replaces sector sizes reported by the device (some, especially those behind USB bridges, misreport them), recomputing `capacity` for the new logical sector size.
+
+    `None` keeps the reported size, although physical sector is never left smaller than the logical one. Sizes must be powers of two from 512 to 65536 bytes.
+    */
+    pub fn override_sector_size(&mut self, logical: Option<u32>, physical: Option<u32>) -> Result<(), &'static str> {
+        if !logical.map(valid_sector_size).unwrap_or(true) || !physical.map(valid_sector_size).unwrap_or(true) {
+            return Err("sector size must be a power of two from 512 to 65536 bytes");
+        }
+
+        let logical = logical.unwrap_or(self.sector_size_log);
+        let physical = physical.unwrap_or(::std::cmp::max(self.sector_size_phy, logical));
+        if physical < logical {
+            return Err("physical sector cannot be smaller than the logical one");
+        }
+
+        // capacity is what device reports as a number of logical sectors, multiplied by the logical sector size
+        // (bogus IDENTIFY DEVICE data might claim 0-byte logical sectors, in which case capacity is 0 anyway)
+        let sectors = self.capacity.checked_div(self.sector_size_log as u64).unwrap_or(0);
+        self.capacity = sectors * logical as u64;
+        self.sector_size_log = logical;
+        self.sector_size_phy = physical;
+        self.sector_size_overridden = true;
+
+        Ok(())
+    }
 }
 
 fn is_set(word: u16, bit: usize) -> bool {
@@ -116,6 +459,14 @@ fn make_ternary(
 
 pub fn parse_id(data: &Vec<u8>) -> Id {
     // TODO return None if data.len() < 512
+
+    // word 255: checksum in the high byte is only there if the low byte is the A5h signature
+    let checksum_valid = if data.len() >= 512 && data[510] == 0xa5 {
+        Some(super::checksum_valid(data))
+    } else {
+        None
+    };
+
     let data = ::utils::bytes_to_be_words(data);
     /*
     TODO ATA8-ACS T13/1699-D Revision 3f field description
@@ -132,7 +483,6 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
     w49:9    LBA transition is supported
     w49:8    DMA is supported
     w50:0    device has a minimum Standby timer value that is device-specific
-    w59:8    if 1, w59:7..0 reflects the number of logical sectors currently set to transfer on READ/WRITE MULTIPLE command
     w63      Multiword DMA transfer modes
     w64      PIO transfer modes
     w65      minimum Multiword DMA transfer cycle time per word
@@ -165,7 +515,6 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
     w83:8    SET MAX security extension is supported
     w86:8    SET MAX security extension enabled
 
-    w83:10   48-bit Address feature set is supported
     w86:10   48-bit Address feature set is supported
 
     w83:11   Device Configuration Overlay feature set is supported
@@ -181,7 +530,6 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
     w119:1   Write-Read-Verify feature set is supported
     w120:1   Write-Read-Verify feature set is enabled
 
-    w119:4   segmented feature for DOWNLOAD MICROCODE is supported
     w120:4   segmented feature for DOWNLOAD MICROCODE is supported
 
     w85:3    mandatory Power Management feature set is supported
@@ -207,7 +555,7 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
     w128      Security status
     w160      CFA power mode
     w176-205  Current media serial number
-    w206      SCT Command Transport
+    w206:1-15 SCT Command Transport features
     w209      Alignment of logical blocks within a physical block
     w210-211  Write-Read-Verify Sector Count Mode 3 Only
     w212-213  Verify Sector Count Mode 2 Only
@@ -237,11 +585,21 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
     > If not, information is not valid in these words.
     */
 
-    let sectors = ((data[61] as u64) << 16) + (data[60] as u64);
+    let lba48_supported = is_set(data[83], 10);
+
+    // words 60..61 are capped at 0x0fff_ffff (~137 GB with 512-byte sectors), so 48-bit drives report their actual capacity in words 100..103;
+    // on 28-bit drives, however, words 100..103 are not guaranteed to hold anything meaningful
+    let sectors_28bit = ((data[61] as u64) << 16) + (data[60] as u64);
     let sectors_48bit = ((data[103] as u64) << 48)
         + ((data[102] as u64) << 32)
         + ((data[101] as u64) << 16)
         + (data[100] as u64);
+    // some 48-bit devices still leave words 100..103 empty
+    let sectors = if lba48_supported && sectors_48bit > 0 {
+        sectors_48bit
+    } else {
+        sectors_28bit
+    };
 
     // data[106] is valid if bit 14 is 1 and bit 15 is 0
     let sector_size_valid = data[106] & ((1 << 14) + (1 << 15)) == (1 << 14);
@@ -266,12 +624,7 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
         firmware: read_string(&data, 23, 26),
         model: read_string(&data, 27, 46),
 
-        capacity: (sector_size_log as u64)
-            * if sectors_48bit > 0 {
-                sectors_48bit
-            } else {
-                sectors
-            },
+        capacity: (sector_size_log as u64) * sectors,
 
         sector_size_phy: if sector_size_valid {
             // bit 13 set to 1 indicates there's more than 1 logical sector per physical
@@ -286,6 +639,13 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
             512
         },
         sector_size_log: sector_size_log,
+        // data[209] is valid if bit 14 is 1 and bit 15 is 0
+        sector_alignment: if data[209] & ((1 << 14) + (1 << 15)) == (1 << 14) {
+            Some(data[209] & 0x3fff)
+        } else {
+            None
+        },
+        lba48_supported,
 
         rpm: match data[217] {
             // all values except 0x0000 are reserved (TODO warning?)
@@ -293,8 +653,16 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
             0x0001 => RPM::NonRotating,
             i => RPM::RPM(i),
         },
+        zoned: match data[69] & 0b11 {
+            0b00 => Zoned::NotReported,
+            0b01 => Zoned::HostAware,
+            0b10 => Zoned::DeviceManaged,
+            _ => Zoned::Reserved,
+        },
 
-        trusted_computing_supported: is_set(data[48], 0),
+        // data[48] is valid if bit 14 is 1 and bit 15 is 0
+        trusted_computing_supported: data[48] & ((1 << 14) + (1 << 15)) == (1 << 14) && is_set(data[48], 0),
+        encrypts_all_user_data: is_set(data[69], 4),
 
         // TODO word 80: major revision number compatibility bits (if not 0x0000 nor 0xffff)
         ata_version: match data[81] {
@@ -354,6 +722,8 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
             _ => None,               // reserved values
         },
 
+        transfer_modes: parse_transfer_modes(&data),
+
         commands_supported: IdCommands {
             // XXX these, according to ATA8-ACS rev 62, should be mirrored in 'feature status' words
             // e.g. w82:12 == w85:12, w119:2 == w120:2
@@ -363,6 +733,7 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
             read_buffer: is_set(data[82], 13),
             nop: is_set(data[82], 14),
             download_microcode: is_set(data[83], 0),
+            download_microcode_segmented: is_set(data[119], 4),
             read_write_dma_queued: is_set(data[83], 1),
             flush_cache: is_set(data[83], 12),
             flush_cache_ext: is_set(data[83], 13),
@@ -386,10 +757,165 @@ pub fn parse_id(data: &Vec<u8>) -> Id {
         gp_logging_supported: is_set(data[84], 5),
         wwn_supported: is_set(data[84], 8), // XXX mirrored; see commands_supported
         security: make_ternary(&data, 82, 1, 85, 1),
+        security_state: SecurityState {
+            supported: is_set(data[128], 0),
+            enabled: is_set(data[128], 1),
+            locked: is_set(data[128], 2),
+            frozen: is_set(data[128], 3),
+            count_expired: is_set(data[128], 4),
+            enhanced_erase_supported: is_set(data[128], 5),
+            master_password_maximum: is_set(data[128], 8),
+        },
+        sata_features: parse_sata_features(&data),
 
         smart: make_ternary(&data, 82, 0, 85, 0),
 
         smart_error_logging_supported: is_set(data[84], 0), // XXX mirrored; see commands_supported
         smart_self_test_supported: is_set(data[84], 1),     // XXX mirrored; see commands_supported
+
+        sct_supported: is_set(data[206], 0),
+
+        // bit 8 indicates bits 7..0 are valid
+        multiple_sectors: if is_set(data[59], 8) {
+            Some((data[59] & 0xff) as u8)
+        } else {
+            None
+        },
+        sanitize: Sanitize {
+            supported: is_set(data[59], 12),
+            block_erase: is_set(data[59], 15),
+            overwrite: is_set(data[59], 14),
+            crypto_scramble: is_set(data[59], 13),
+            antifreeze_lock: is_set(data[59], 10),
+            commands_allowed_as_specified: is_set(data[59], 11),
+        },
+
+        download_microcode_min: match data[234] {
+            0x0000 | 0xffff => None,
+            i => Some(i),
+        },
+        download_microcode_max: match data[235] {
+            0x0000 | 0xffff => None,
+            i => Some(i),
+        },
+
+        checksum_valid,
+
+        sector_size_overridden: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IDENTIFY DEVICE data with `words` set, and everything else zeroed
+    fn id(words: &[(usize, u16)]) -> Id {
+        let mut data = vec![0; 512];
+        for &(word, value) in words {
+            data[word * 2] = value as u8;
+            data[word * 2 + 1] = (value >> 8) as u8;
+        }
+        parse_id(&data)
+    }
+
+    #[test]
+    fn word_59() {
+        // 16 sectors per DRQ block; sanitize with block erase and crypto scramble, but without overwrite; antifreeze lock
+        let id = id(&[(59, 0b1011_0101_0001_0000)]);
+        assert_eq!(id.multiple_sectors, Some(16));
+        assert!(id.sanitize.supported);
+        assert!(id.sanitize.block_erase);
+        assert!(!id.sanitize.overwrite);
+        assert!(id.sanitize.crypto_scramble);
+        assert!(!id.sanitize.commands_allowed_as_specified);
+        assert!(id.sanitize.antifreeze_lock);
+    }
+
+    #[test]
+    fn word_59_multiple_sectors_invalid() {
+        // bit 8 is not set, so the low byte means nothing
+        let id = id(&[(59, 0x0010)]);
+        assert_eq!(id.multiple_sectors, None);
+        assert!(!id.sanitize.supported);
+    }
+
+    #[test]
+    fn transfer_modes() {
+        // MWDMA 0..2 supported, 2 selected; PIO 3 and 4 supported; UDMA 0..6 supported, 5 selected
+        let modes = id(&[(53, 0x0006), (63, 0x0407), (64, 0x0003), (88, 0x207f)]).transfer_modes;
+        assert_eq!(modes.mwdma_max, Some(2));
+        assert_eq!(modes.mwdma_selected, Some(2));
+        assert_eq!(modes.pio_max, Some(4));
+        assert_eq!(modes.udma_max, Some(6));
+        assert_eq!(modes.udma_selected, Some(5));
+        assert_eq!(modes.udma_selected.and_then(udma_rate), Some(100));
+    }
+
+    #[test]
+    fn transfer_modes_pio_only() {
+        // word 64 is valid, but has no advanced PIO modes in it
+        let modes = id(&[(53, 0x0002)]).transfer_modes;
+        assert_eq!(modes.pio_max, Some(2));
+        assert_eq!(modes.mwdma_max, None);
+        assert_eq!(modes.udma_max, None);
+    }
+
+    #[test]
+    fn transfer_modes_invalid() {
+        // words 64 and 88 are not marked as valid in word 53, so whatever is in them means nothing
+        let modes = id(&[(63, 0x0101), (64, 0x0003), (88, 0x407f)]).transfer_modes;
+        assert_eq!(modes.pio_max, None);
+        assert_eq!(modes.udma_max, None);
+        assert_eq!(modes.udma_selected, None);
+        // word 63 is not covered by word 53
+        assert_eq!(modes.mwdma_max, Some(0));
+        assert_eq!(modes.mwdma_selected, Some(0));
+    }
+
+    #[test]
+    fn security_check() {
+        use self::SecurityOperation::*;
+
+        // supported and enabled, neither locked nor frozen
+        let state = id(&[(128, 0b0_0011)]).security_state;
+        assert_eq!(state.check(MediaAccess), Ok(()));
+        assert_eq!(state.check(SecurityChange), Ok(()));
+
+        let frozen = id(&[(128, 0b0_1001)]).security_state;
+        assert_eq!(frozen.check(MediaAccess), Ok(()));
+        assert!(frozen.check(SecurityChange).unwrap_err().contains("FROZEN"));
+
+        let locked = id(&[(128, 0b0_0111)]).security_state;
+        assert!(locked.check(MediaAccess).unwrap_err().contains("LOCKED"));
+        assert_eq!(locked.check(SecurityChange), Ok(()));
+
+        let expired = id(&[(128, 0b1_0111)]).security_state;
+        assert!(expired.check(SecurityChange).unwrap_err().contains("SECURITY UNLOCK attempts"));
+
+        // without the feature set, bits of the word mean nothing
+        let unsupported = id(&[(128, 0b1_1110)]).security_state;
+        assert_eq!(unsupported.check(MediaAccess), Ok(()));
+        assert_eq!(unsupported.check(SecurityChange), Ok(()));
+    }
+
+    #[test]
+    fn capacity_28bit() {
+        // 1,000,000 sectors; words 100..103 hold garbage, but 48-bit addressing is not supported
+        let id = id(&[(60, 0x4240), (61, 0x000f), (100, 0xffff), (101, 0xffff)]);
+        assert!(!id.lba48_supported);
+        assert_eq!(id.capacity, 512_000_000);
+    }
+
+    #[test]
+    fn capacity_48bit() {
+        // 4 TB drive: 7,814,037,168 sectors in words 100..103, words 60..61 capped at 0x0fff_ffff
+        let large = id(&[(60, 0xffff), (61, 0x0fff), (83, 1 << 10), (100, 0xbeb0), (101, 0xd1c0), (102, 0x0001)]);
+        assert!(large.lba48_supported);
+        assert_eq!(large.capacity, 7_814_037_168 * 512);
+
+        // 48-bit drive that leaves words 100..103 empty
+        let empty = id(&[(60, 0x4240), (61, 0x000f), (83, 1 << 10)]);
+        assert_eq!(empty.capacity, 512_000_000);
     }
 }
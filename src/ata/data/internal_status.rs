@@ -0,0 +1,60 @@
+/*!
+Functions to parse and structs to represent the header of Current Device Internal Status Data log (log address 24h).
+
+The rest of the log is vendor-specific (it is meant for manufacturers' failure analysis), and is not parsed here.
+
+For more, see ACS-4, 9.11 Current Device Internal Status Data log.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+/// Log page 0 of the Current Device Internal Status Data log
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct InternalStatusHeader {
+	/// IEEE OUI of the organization that defines the format of the data
+	pub organization_id: u32,
+
+	// data areas are nested: area 2 includes area 1, area 3 includes area 2; log page 0 is this header
+	pub area1_last_page: u16,
+	pub area2_last_page: u16,
+	pub area3_last_page: u16,
+
+	/// Whether Saved Device Internal Status Data log (25h) holds anything
+	pub saved_data_available: bool,
+	pub saved_data_generation: u8,
+}
+
+impl InternalStatusHeader {
+	/// Number of log pages that make up the whole log, including the header
+	pub fn pages(&self) -> u32 {
+		self.area3_last_page as u32 + 1
+	}
+}
+
+/**
+Parses log page 0 of the Current Device Internal Status Data log.
+
+Returns `None` if the page is truncated, if it is not for log 24h, or if the sizes of data areas it declares are inconsistent.
+*/
+pub fn parse_internal_status_header(data: &[u8]) -> Option<InternalStatusHeader> {
+	if data.len() < 512 || data[0] != 0x24 {
+		return None;
+	}
+
+	// unwraps: length is checked above
+	let header = InternalStatusHeader {
+		organization_id: (&data[4..8]).read_u32::<LittleEndian>().unwrap(),
+		area1_last_page: (&data[8..10]).read_u16::<LittleEndian>().unwrap(),
+		area2_last_page: (&data[10..12]).read_u16::<LittleEndian>().unwrap(),
+		area3_last_page: (&data[12..14]).read_u16::<LittleEndian>().unwrap(),
+		saved_data_available: data[382] != 0,
+		saved_data_generation: data[383],
+	};
+
+	if header.area1_last_page > header.area2_last_page || header.area2_last_page > header.area3_last_page {
+		return None;
+	}
+
+	Some(header)
+}
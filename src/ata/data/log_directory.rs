@@ -0,0 +1,88 @@
+/*!
+Functions to parse and structs to represent log directories: General Purpose Log Directory (log address 00h, read with READ LOG EXT) and SMART Log Directory (log address 00h, read with SMART READ LOG).
+
+Both share the same layout: word 0 is the version of the directory, and word N is the number of 512-byte pages the log at address N has (0 if the log is not supported).
+
+For more, see ACS-3, 9.2 General Purpose Log Directory and A.2 SMART Log Directory.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct LogDirectory {
+	pub version: u16,
+	// indexed by log address; entry 0 (the directory itself) is always there, as it's the one we were able to read
+	pages: Vec<u16>,
+}
+
+impl LogDirectory {
+	/// Number of pages the log at `address` has; 0 means the log is not supported.
+	pub fn pages(&self, address: u8) -> u16 {
+		self.pages[address as usize]
+	}
+
+	/// Addresses of supported logs, along with their sizes in pages.
+	pub fn logs(&self) -> Vec<(u8, u16)> {
+		self.pages.iter()
+			.enumerate()
+			.filter(|&(_, &pages)| pages != 0)
+			.map(|(address, &pages)| (address as u8, pages))
+			.collect()
+	}
+}
+
+/// Parses log directory page. Returns `None` if `data` is not a complete 512-byte sector.
+pub fn parse_log_directory(data: &[u8]) -> Option<LogDirectory> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	// unwraps: length is checked above
+	let mut pages: Vec<u16> = (0..256)
+		.map(|i| (&data[i * 2 .. i * 2 + 2]).read_u16::<LittleEndian>().unwrap())
+		.collect();
+	let version = pages[0];
+	pages[0] = 1;
+
+	Some(LogDirectory {
+		version,
+		pages,
+	})
+}
+
+/// Name of the log at `address`, as per ACS-4 (Table A.2 Log address definition).
+pub fn log_name(address: u8) -> Option<&'static str> {
+	match address {
+		0x00 => Some("Log directory"),
+		0x01 => Some("Summary SMART error log"),
+		0x02 => Some("Comprehensive SMART error log"),
+		0x03 => Some("Extended Comprehensive SMART error log"),
+		0x04 => Some("Device Statistics"),
+		0x06 => Some("SMART self-test log"),
+		0x07 => Some("Extended SMART self-test log"),
+		0x08 => Some("Power Conditions"),
+		0x09 => Some("Selective self-test log"),
+		0x0a => Some("Device Statistics Notification"),
+		0x0c => Some("Pending Defects"),
+		0x0d => Some("LPS Mis-alignment"),
+		0x0e => Some("Sense Data for Successful NCQ Commands"),
+		0x10 => Some("NCQ Command Error"),
+		0x11 => Some("SATA Phy Event Counters"),
+		0x12 => Some("SATA NCQ Queue Management"),
+		0x13 => Some("SATA NCQ Send and Receive"),
+		0x19 => Some("LBA Status"),
+		0x20 => Some("Streaming performance (obsolete)"),
+		0x21 => Some("Write Stream Error"),
+		0x22 => Some("Read Stream Error"),
+		0x24 => Some("Current Device Internal Status Data"),
+		0x25 => Some("Saved Device Internal Status Data"),
+		0x2f => Some("Set Sector Configuration"),
+		0x30 => Some("IDENTIFY DEVICE data"),
+		0x80..=0x9f => Some("Host vendor specific"),
+		0xa0..=0xdf => Some("Device vendor specific"),
+		0xe0 => Some("SCT Command/Status"),
+		0xe1 => Some("SCT Data Transfer"),
+		_ => None,
+	}
+}
@@ -1,3 +1,19 @@
 pub mod attr;
 pub mod id;
 pub mod health;
+pub mod sct;
+pub mod ncq;
+pub mod internal_status;
+pub mod farm;
+pub mod log_directory;
+pub mod pending_defects;
+pub mod self_test;
+pub mod power_mode;
+pub mod smart_capabilities;
+pub mod devstat;
+pub mod error_log;
+
+/// Whether all bytes of a 512-byte data structure with a trailing checksum byte (IDENTIFY DEVICE data, SMART READ DATA reply, etc.) add up to zero, as they should.
+pub fn checksum_valid(data: &[u8]) -> bool {
+	data.len() >= 512 && data[..512].iter().fold(0u8, |sum, &x| sum.wrapping_add(x)) == 0
+}
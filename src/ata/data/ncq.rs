@@ -0,0 +1,76 @@
+/*!
+Functions to parse and structs to represent NCQ Command Error log (log address 10h).
+
+For more, see ACS-3, 9.13 NCQ Command Error log.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+/// Registers of the queued command that failed, as returned by the device
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct NcqCommandError {
+	/// Tag of the failed command, or `None` if the error was not for a queued command (bit NQ is set)
+	pub tag: Option<u8>,
+	/// Whether an IDLE IMMEDIATE with UNLOAD FEATURE was received (the error indicates no actual failure of a queued command then)
+	pub unload: bool,
+
+	pub status: u8,
+	pub error: u8,
+	pub device: u8,
+	pub lba: u64,
+	pub count: u16,
+
+	// ACS-4 and later; zeroes for older devices
+	pub sense_key: u8,
+	pub asc: u8,
+	pub ascq: u8,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub enum NcqErrorLog {
+	/// No error is logged
+	Empty,
+	Error(NcqCommandError),
+}
+
+/**
+Parses the NCQ Command Error log page.
+
+Returns `None` if `data` is not a complete 512-byte sector.
+*/
+pub fn parse_ncq_error_log(data: &[u8]) -> Option<NcqErrorLog> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	// devices are not required to clear the log, but in practice it's all zeroes if there was no NCQ error since power-on
+	if data[0..256].iter().all(|&b| b == 0) {
+		return Some(NcqErrorLog::Empty);
+	}
+
+	Some(NcqErrorLog::Error(NcqCommandError {
+		tag: if data[0] & (1 << 7) != 0 { None } else { Some(data[0] & 0x1f) },
+		unload: data[0] & (1 << 6) != 0,
+		// 1: reserved
+		status: data[2],
+		error: data[3],
+		// lba 23:0, device, then lba 47:24
+		lba: ((data[10] as u64) << 40)
+			+ ((data[9] as u64) << 32)
+			+ ((data[8] as u64) << 24)
+			+ ((data[6] as u64) << 16)
+			+ ((data[5] as u64) << 8)
+			+ (data[4] as u64),
+		device: data[7],
+		// 11: reserved
+		count: (&data[12..14]).read_u16::<LittleEndian>().unwrap(),
+		sense_key: data[14],
+		asc: data[15],
+		ascq: data[16],
+		// 17..256: reserved
+		// 256..511: vendor-specific
+		// 511: checksum
+	}))
+}
@@ -0,0 +1,49 @@
+/*!
+Functions to parse and structs to represent Pending Defects log (General Purpose log address 0Ch).
+
+The log lists LBAs that the device failed to read and has not reallocated yet (also known as pending, or reallocation candidate, sectors). Page 0 starts with a 16-byte header that holds the number of entries, followed by the entries themselves, 16 bytes each; entries continue on the following pages, if there are too many of them to fit one page.
+
+For more, see ACS-4, 9.21 Pending Defects log.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct PendingDefect {
+	pub lba: u64,
+	/// Power-on hours at the time the defect was added to the list, if the device keeps track of that
+	pub power_on_hours: Option<u32>,
+}
+
+/// Number of 512-byte pages the log takes, given the header page
+pub fn pages(data: &[u8]) -> Option<u16> {
+	if data.len() < 512 {
+		return None;
+	}
+	// unwrap: length is checked above
+	let entries = (&data[0..4]).read_u32::<LittleEndian>().unwrap() as u64;
+	Some(::std::cmp::min((16 + entries * 16 + 511) / 512, 0xffff) as u16)
+}
+
+/// Parses Pending Defects log pages, starting with page 0. Returns `None` if `data` is shorter than the number of entries in the header implies.
+pub fn parse_pending_defects(data: &[u8]) -> Option<Vec<PendingDefect>> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	// unwraps: lengths are checked above and below
+	let entries = (&data[0..4]).read_u32::<LittleEndian>().unwrap() as usize;
+	if data.len() < 16 + entries * 16 {
+		return None;
+	}
+
+	Some(data[16 .. 16 + entries * 16].chunks(16).map(|entry| {
+		let hours = (&entry[0..4]).read_u32::<LittleEndian>().unwrap();
+		PendingDefect {
+			// bytes 4..7 are reserved
+			lba: (&entry[8..16]).read_u64::<LittleEndian>().unwrap(),
+			power_on_hours: if hours == 0xffff_ffff { None } else { Some(hours) },
+		}
+	}).collect())
+}
@@ -0,0 +1,53 @@
+/*!
+Functions to parse and structs to represent the reply to CHECK POWER MODE.
+
+The mode is reported in the COUNT field. Devices in the Sleep mode do not reply at all (they need to be reset first), so there's no value for that.
+
+For more, see ACS-4, 7.3 CHECK POWER MODE, and 4.15 Power management feature set.
+*/
+
+use ata;
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub enum PowerMode {
+	/// Standby (media is not spinning, and accessing it would spin it up)
+	Standby,
+	/// One of the Idle modes (idle_a, idle_b, idle_c), or the NV cache power mode
+	Idle,
+	/// Active or Idle mode, device does not tell which
+	ActiveOrIdle,
+	/// Reserved value
+	Unknown(u8),
+}
+
+impl PowerMode {
+	/// Whether the device is in Standby, and would be woken up by commands that access the media
+	pub fn is_standby(&self) -> bool {
+		*self == PowerMode::Standby
+	}
+}
+
+impl fmt::Display for PowerMode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			PowerMode::Standby => write!(f, "standby"),
+			PowerMode::Idle => write!(f, "idle"),
+			PowerMode::ActiveOrIdle => write!(f, "active or idle"),
+			PowerMode::Unknown(x) => write!(f, "unknown (0x{:02x})", x),
+		}
+	}
+}
+
+pub fn parse_power_mode(reg: &ata::RegistersRead) -> PowerMode {
+	match reg.sector_count {
+		// 01h: Standby_y (ACS-4); 40h, 41h: NV Cache power mode, spun down and spun up respectively (obsolete)
+		0x00 | 0x01 | 0x40 => PowerMode::Standby,
+		// 80h: Idle; 81h..83h: Idle_a, Idle_b, Idle_c (ACS-4)
+		0x41 | 0x80 | 0x81 | 0x82 | 0x83 => PowerMode::Idle,
+		0xff => PowerMode::ActiveOrIdle,
+		x => PowerMode::Unknown(x),
+	}
+}
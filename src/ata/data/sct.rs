@@ -0,0 +1,134 @@
+/*!
+Functions to parse and structs to represent SMART Command Transport (SCT) replies.
+
+For more, see ACS-3, 8.3 SCT Command Transport, or `ata_sct_status_response` from smartmontools' atacmds.h.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+/// Temperature readings, in °C. Every value is `None` if device reports it as invalid (`0x80`).
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct Temperatures {
+	pub current: Option<i8>,
+	/// Minimum temperature seen during this power cycle
+	pub power_cycle_min: Option<i8>,
+	/// Maximum temperature seen during this power cycle
+	pub power_cycle_max: Option<i8>,
+	/// Minimum temperature seen during the lifetime of the device
+	pub lifetime_min: Option<i8>,
+	/// Maximum temperature seen during the lifetime of the device
+	pub lifetime_max: Option<i8>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct SctStatus {
+	pub format_version: u16,
+	pub sct_version: u16, // vendor-specific
+	pub sct_spec: u16,
+
+	/// 0: active or idle, 1: standby, 2: sleep, 3: DST executing in background, 4: SMART off-line data collection executing in background, 5: SCT command executing in background
+	pub device_state: u8,
+
+	pub temperature: Temperatures,
+	/// Number of intervals (since the last reset) during which the temperature was above the maximum operating limit
+	pub over_limit_count: u32,
+	/// Number of intervals (since the last reset) during which the temperature was below the minimum operating limit
+	pub under_limit_count: u32,
+}
+
+fn temp(t: u8) -> Option<i8> {
+	match t {
+		0x80 => None,
+		t => Some(t as i8),
+	}
+}
+
+/**
+Parses reply to the SCT status request (log address E0h).
+
+Returns `None` if `data` is not a complete 512-byte sector.
+*/
+pub fn parse_sct_status(data: &[u8]) -> Option<SctStatus> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	Some(SctStatus {
+		format_version: (&data[0..2]).read_u16::<LittleEndian>().unwrap(),
+		sct_version: (&data[2..4]).read_u16::<LittleEndian>().unwrap(),
+		sct_spec: (&data[4..6]).read_u16::<LittleEndian>().unwrap(),
+		// 6..10: status flags
+		device_state: data[10],
+		// 11..14: reserved
+		// 14..20: extended status, action and function codes of the last SCT command
+		// 20..40: reserved
+		// 40..48: LBA of the SCT command that's being executed in background
+		// 48..200: reserved
+
+		// XXX it is easy to mix these up: power-cycle min/max go first, then lifetime min/max
+		temperature: Temperatures {
+			current: temp(data[200]),
+			power_cycle_min: temp(data[201]),
+			power_cycle_max: temp(data[202]),
+			lifetime_min: temp(data[203]),
+			lifetime_max: temp(data[204]),
+		},
+		// 205: maximum operating temperature (ACS-4)
+		over_limit_count: (&data[206..210]).read_u32::<LittleEndian>().unwrap(),
+		under_limit_count: (&data[210..214]).read_u32::<LittleEndian>().unwrap(),
+		// 214..480: reserved (mostly)
+		// 480..512: vendor-specific
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// SCT status of a drive that has been running at 32°C, with both lifetime fields left invalid
+	fn status() -> Vec<u8> {
+		let mut data = vec![0; 512];
+		data[0..6].copy_from_slice(&[0x03, 0x00, 0x00, 0x01, 0x01, 0x00]);
+		data[200..206].copy_from_slice(&[32, 24, 41, 0x80, 0x80, 60]);
+		data[206..210].copy_from_slice(&[2, 0, 0, 0]);
+		data[210..214].copy_from_slice(&[0, 1, 0, 0]);
+		data
+	}
+
+	#[test]
+	fn temperatures() {
+		let status = parse_sct_status(&status()).unwrap();
+		assert_eq!(status.format_version, 3);
+		assert_eq!(status.sct_version, 0x100);
+		assert_eq!(status.sct_spec, 1);
+
+		let temp = status.temperature;
+		assert_eq!(temp.current, Some(32));
+		assert_eq!(temp.power_cycle_min, Some(24));
+		assert_eq!(temp.power_cycle_max, Some(41));
+		assert_eq!(temp.lifetime_min, None);
+		assert_eq!(temp.lifetime_max, None);
+
+		assert_eq!(status.over_limit_count, 2);
+		assert_eq!(status.under_limit_count, 256);
+	}
+
+	#[test]
+	fn negative_temperature() {
+		let mut data = status();
+		data[201] = -5i8 as u8;
+		data[203] = -10i8 as u8;
+		data[204] = 55;
+		let temp = parse_sct_status(&data).unwrap().temperature;
+		assert_eq!(temp.power_cycle_min, Some(-5));
+		assert_eq!(temp.lifetime_min, Some(-10));
+		assert_eq!(temp.lifetime_max, Some(55));
+	}
+
+	#[test]
+	fn truncated() {
+		assert!(parse_sct_status(&status()[..511]).is_none());
+	}
+}
@@ -0,0 +1,118 @@
+/*!
+Functions to parse and structs to represent self-test logs: SMART self-test log (SMART log address 06h) and Extended SMART self-test log (General Purpose log address 07h).
+
+Both logs are circular buffers of descriptors, with an index pointing to the most recent one. The former holds 21 descriptors with 28-bit LBAs, the latter holds 19 descriptors per page with 48-bit LBAs.
+
+For more, see ACS-3, 9.19 Extended SMART Self-Test log and A.4 SMART Self-Test log.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct SelfTest {
+	/// Self-test that was run (value of the LBA field of SMART EXECUTE OFF-LINE IMMEDIATE), e.g. 1 for short self-test, 2 for extended one
+	pub test: u8,
+	/// Self-test execution status (bits 7..4; 0 means the test completed without errors, 15 means it is still in progress)
+	pub status: u8,
+	/// Percent of the test remaining (bits 3..0 of the status byte, in tens of percent)
+	pub remaining: u8,
+	/// Power-on hours at the time the test completed
+	pub lifetime: u16,
+	/// LBA of the first error the test encountered, if the test failed and there's one to report
+	pub failing_lba: Option<u64>,
+}
+
+impl SelfTest {
+	/// Whether the test failed (as opposed to being completed, aborted, interrupted, or still in progress)
+	pub fn failed(&self) -> bool {
+		// 3: fatal error, 4: unknown element failed, 5: electrical element failed, 6: servo/seek element failed, 7: read element failed, 8: handling damage
+		self.status >= 3 && self.status <= 8
+	}
+}
+
+/// Describes self-test execution status (bits 7..4 of the status byte), e.g. 'completed without error'.
+pub fn status_description(status: u8) -> &'static str {
+	match status {
+		0 => "completed without error",
+		1 => "aborted by host",
+		2 => "interrupted by reset",
+		3 => "fatal error",
+		4 => "failed (unknown element)",
+		5 => "failed (electrical element)",
+		6 => "failed (servo/seek element)",
+		7 => "failed (read element)",
+		8 => "failed (handling damage)",
+		15 => "in progress",
+		_ => "reserved",
+	}
+}
+
+// `entry` points at the descriptor; `lba_len` is 4 bytes for 28-bit LBAs, or 6 for 48-bit ones
+fn parse_descriptor(entry: &[u8], lba_len: usize) -> Option<SelfTest> {
+	// unused descriptors are filled with zeroes
+	if entry.iter().all(|&b| b == 0) {
+		return None;
+	}
+
+	// unwraps: callers pass descriptors that are long enough
+	let lba = (&entry[5 .. 5 + lba_len]).read_uint::<LittleEndian>(lba_len).unwrap();
+	// all bits set means there's no LBA to report, much like with any other LBA field in ACS
+	let no_lba = if lba_len == 4 { 0x0fff_ffff } else { 0xffff_ffff_ffff };
+	let lba = if lba_len == 4 { lba & 0x0fff_ffff } else { lba };
+
+	let mut test = SelfTest {
+		test: entry[0],
+		status: entry[1] >> 4,
+		remaining: entry[1] & 0xf,
+		lifetime: (&entry[2..4]).read_u16::<LittleEndian>().unwrap(),
+		// entry[4]: self-test failure checkpoint
+		failing_lba: None,
+	};
+	if test.failed() && lba != no_lba {
+		test.failing_lba = Some(lba);
+	}
+
+	Some(test)
+}
+
+/// Parses SMART self-test log. Returns `None` if `data` is not a complete 512-byte sector. Tests are ordered from the most recent one.
+pub fn parse_self_test_log(data: &[u8]) -> Option<Vec<SelfTest>> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	// bytes 0..1: revision; index is 1-based, and 0 means the log is empty
+	let index = data[508] as usize;
+	if index == 0 || index > 21 {
+		return Some(vec![]);
+	}
+
+	Some((0..21)
+		.map(|i| (index - 1 + 21 - i) % 21)
+		.filter_map(|n| parse_descriptor(&data[2 + n * 24 .. 2 + (n + 1) * 24], 4))
+		.collect())
+}
+
+/// Parses Extended SMART self-test log pages, starting with page 0. Returns `None` if `data` does not hold a complete 512-byte page. Tests are ordered from the most recent one.
+pub fn parse_ext_self_test_log(data: &[u8]) -> Option<Vec<SelfTest>> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	let descriptors = data.len() / 512 * 19;
+	// byte 0: revision; index is 1-based, and 0 means the log is empty
+	// unwrap: length is checked above
+	let index = (&data[2..4]).read_u16::<LittleEndian>().unwrap() as usize;
+	if index == 0 || index > descriptors {
+		return Some(vec![]);
+	}
+
+	Some((0..descriptors)
+		.map(|i| (index - 1 + descriptors - i) % descriptors)
+		.filter_map(|n| {
+			let offset = n / 19 * 512 + 4 + n % 19 * 26;
+			parse_descriptor(&data[offset .. offset + 26], 6)
+		})
+		.collect())
+}
@@ -0,0 +1,191 @@
+/*!
+Functions to parse and structs to represent off-line data collection and SMART capabilities, found in bytes 362..376 of the SMART READ DATA reply (right after the attribute table).
+
+This is where the device tells whether automatic off-line data collection is enabled, which, along with attribute autosave, determines how often attribute values are updated. This is also where the progress of the running self-test is reported, along with how long self-tests take.
+
+For more, see ACS-3, Table A.18 (Device SMART data structure), and SFF-8035i rev 2.
+*/
+
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use ata::SelfTestKind;
+
+use std::fmt;
+
+/// Off-line data collection status (bits 6..0 of byte 362)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub enum OfflineCollectionStatus {
+	/// 00h
+	NeverStarted,
+	/// 02h
+	Completed,
+	/// 03h (reserved in ACS-3, but some devices report it anyway, and smartmontools knows it as such)
+	InProgress,
+	/// 04h: suspended by an interrupting command from host
+	Suspended,
+	/// 05h: aborted by an interrupting command from host
+	Aborted,
+	/// 06h: aborted by the device with a fatal error
+	Fatal,
+	/// 40h..7Fh
+	VendorSpecific(u8),
+	/// Reserved value
+	Unknown(u8),
+}
+
+impl From<u8> for OfflineCollectionStatus {
+	fn from(status: u8) -> Self {
+		use self::OfflineCollectionStatus::*;
+		match status & 0x7f {
+			0x00 => NeverStarted,
+			0x02 => Completed,
+			0x03 => InProgress,
+			0x04 => Suspended,
+			0x05 => Aborted,
+			0x06 => Fatal,
+			x @ 0x40..=0x7f => VendorSpecific(x),
+			x => Unknown(x),
+		}
+	}
+}
+
+impl fmt::Display for OfflineCollectionStatus {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use self::OfflineCollectionStatus::*;
+		match *self {
+			NeverStarted => write!(f, "never started"),
+			Completed => write!(f, "completed without error"),
+			InProgress => write!(f, "in progress"),
+			Suspended => write!(f, "suspended by an interrupting command from host"),
+			Aborted => write!(f, "aborted by an interrupting command from host"),
+			Fatal => write!(f, "aborted by the device with a fatal error"),
+			VendorSpecific(x) => write!(f, "vendor-specific (0x{:02x})", x),
+			Unknown(x) => write!(f, "unknown (0x{:02x})", x),
+		}
+	}
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct SmartCapabilities {
+	/// Off-line data collection status
+	pub offline_collection_status: OfflineCollectionStatus,
+	/// Whether automatic off-line data collection is enabled (bit 7 of byte 362)
+	pub auto_offline_enabled: bool,
+	/// Self-test execution status byte, as is (see [`self_test`](../self_test/index.html) for what its halves mean)
+	pub self_test_status: u8,
+	/// Time it takes to complete off-line data collection, in seconds
+	pub offline_collection_time: u16,
+
+	/// SMART EXECUTE OFF-LINE IMMEDIATE is supported
+	pub offline_immediate_supported: bool,
+	/// SMART ENABLE/DISABLE AUTOMATIC OFF-LINE is supported (the command is vendor-specific, but is widely implemented)
+	pub auto_offline_supported: bool,
+	/// Off-line data collection is aborted upon receipt of a new command (as opposed to being suspended)
+	pub offline_aborted_by_command: bool,
+	pub offline_read_scanning_supported: bool,
+	pub self_test_supported: bool,
+	pub conveyance_self_test_supported: bool,
+	pub selective_self_test_supported: bool,
+
+	/// Device saves attribute values before entering power saving mode
+	pub saves_before_power_saving: bool,
+	/// SMART ENABLE/DISABLE ATTRIBUTE AUTOSAVE is supported. Note that devices do not report whether autosave is currently enabled.
+	pub attribute_autosave_supported: bool,
+
+	/// Recommended polling times, i.e. how long self-tests take to complete, in minutes
+	pub short_self_test_time: u8,
+	pub extended_self_test_time: u16,
+	pub conveyance_self_test_time: u8,
+}
+
+impl SmartCapabilities {
+	/// Whether a self-test is running at the moment (self-test execution status 15)
+	pub fn self_test_in_progress(&self) -> bool {
+		self.self_test_status >> 4 == 15
+	}
+
+	/// Percent of the running self-test remaining, if one is running
+	pub fn self_test_remaining(&self) -> Option<u8> {
+		if self.self_test_in_progress() {
+			Some((self.self_test_status & 0xf) * 10)
+		} else { None }
+	}
+
+	/// How long `test` takes to complete, in minutes
+	pub fn self_test_time(&self, test: SelfTestKind) -> u16 {
+		match test {
+			SelfTestKind::Short => self.short_self_test_time as u16,
+			SelfTestKind::Extended => self.extended_self_test_time,
+			SelfTestKind::Conveyance => self.conveyance_self_test_time as u16,
+		}
+	}
+}
+
+/// Parses SMART READ DATA reply. Returns `None` if `data` is not a complete 512-byte sector.
+pub fn parse_smart_capabilities(data: &[u8]) -> Option<SmartCapabilities> {
+	if data.len() < 512 {
+		return None;
+	}
+
+	let is_set = |byte: usize, bit: u8| data[byte] & (1 << bit) != 0;
+
+	Some(SmartCapabilities {
+		offline_collection_status: OfflineCollectionStatus::from(data[362]),
+		auto_offline_enabled: is_set(362, 7),
+		self_test_status: data[363],
+		// unwrap: length is checked above
+		offline_collection_time: (&data[364..366]).read_u16::<LittleEndian>().unwrap(),
+		// byte 366 is vendor-specific
+
+		offline_immediate_supported: is_set(367, 0),
+		auto_offline_supported: is_set(367, 1),
+		offline_aborted_by_command: is_set(367, 2),
+		offline_read_scanning_supported: is_set(367, 3),
+		self_test_supported: is_set(367, 4),
+		conveyance_self_test_supported: is_set(367, 5),
+		selective_self_test_supported: is_set(367, 6),
+
+		// bytes 368..369: SMART capabilities
+		saves_before_power_saving: is_set(368, 0),
+		attribute_autosave_supported: is_set(368, 1),
+
+		// byte 370: error logging capability, byte 371 is vendor-specific
+		short_self_test_time: data[372],
+		// FFh means the time does not fit a byte, and is in bytes 375..376 instead
+		extended_self_test_time: match data[373] {
+			0xff => (&data[375..377]).read_u16::<LittleEndian>().unwrap(),
+			time => time as u16,
+		},
+		conveyance_self_test_time: data[374],
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::OfflineCollectionStatus::*;
+
+	#[test]
+	fn offline_collection_status() {
+		let statuses = [
+			(0x00, NeverStarted),
+			(0x01, Unknown(0x01)),
+			(0x02, Completed),
+			(0x03, InProgress),
+			(0x04, Suspended),
+			(0x05, Aborted),
+			(0x06, Fatal),
+			(0x07, Unknown(0x07)),
+			(0x3f, Unknown(0x3f)),
+			(0x40, VendorSpecific(0x40)),
+			(0x7f, VendorSpecific(0x7f)),
+		];
+		for &(status, expected) in statuses.iter() {
+			assert_eq!(OfflineCollectionStatus::from(status), expected, "0x{:02x}", status);
+			// bit 7 is whether automatic off-line data collection is enabled
+			assert_eq!(OfflineCollectionStatus::from(status | 0x80), expected, "0x{:02x}", status | 0x80);
+		}
+	}
+}
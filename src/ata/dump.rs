@@ -0,0 +1,353 @@
+/*!
+Saving replies to ATA commands, and replaying them later as if they come from an actual device.
+
+This is meant for reproducing bugs and for development without the hardware in question: wrap the device into a [`Recorder`](struct.Recorder.html), issue commands you're interested in through the [`Misc`](../misc/trait.Misc.html) interface, save the [`Dump`](struct.Dump.html), then [`Dump::open()`](struct.Dump.html#method.open) it elsewhere and use it as a device.
+
+## File format
+
+Everything is little-endian. File starts with 8-byte magic `HDDDUMP2`, followed by any number of records:
+
+- 13 bytes: registers the command was issued with — command, features, sector_count, sector, cyl_low, cyl_high, device, then 1 if the command is a 48-bit one (0 otherwise), and features, sector_count, sector, cyl_low, cyl_high of the 48-bit extension (zeroes if there's none),
+- 12 bytes: registers the device replied with — error, sector_count, sector, cyl_low, cyl_high, device, status, then 1 if there is a 48-bit extension (0 otherwise), and its sector_count, sector, cyl_low, cyl_high,
+- 4 bytes: length of the data, in bytes,
+- data.
+
+Replies are looked up by all the registers the command was issued with, so that e.g. different pages of the same log or different SMART READ LOG block counts don't get mixed up; first matching record wins. Replies with ERR bit of the status register set are returned as errors, same as they would be by the actual device.
+
+## Example
+
+```no_run
+# extern crate hdd;
+# use hdd::Device;
+# use hdd::ata::ATADevice;
+# use hdd::scsi::SCSIDevice;
+use hdd::ata::misc::Misc;
+use hdd::ata::dump::{Dump, Recorder};
+
+# fn main() -> Result<(), Box<::std::error::Error>> {
+# let dev = ATADevice::new(SCSIDevice::new(Device::open("/dev/sda")?));
+let recorder = Recorder::new(&dev);
+let id = recorder.get_device_id()?;
+recorder.into_dump().save("capture.bin")?;
+
+// …later, elsewhere
+let dump = Dump::open("capture.bin")?;
+assert_eq!(dump.get_device_id()?.serial, id.serial);
+# Ok(())
+# }
+```
+*/
+
+use Direction;
+use ata::{RegistersRead, RegistersReadExt, RegistersWrite};
+use ata::misc::{self, Misc};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian};
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+const MAGIC: &[u8] = b"HDDDUMP2";
+// issued registers, reply registers, data length
+const HEADER_LEN: usize = 13 + 12 + 4;
+
+quick_error! {
+	#[derive(Debug)]
+	pub enum Error {
+		IO(err: io::Error) {
+			from()
+			display("IO error: {}", err)
+			description(err.description())
+			cause(err)
+		}
+		/// File does not start with the magic, or the last record is truncated
+		Malformed {
+			display("malformed dump")
+			description("malformed dump")
+		}
+	}
+}
+
+// registers the command was issued with, as stored in the file
+fn issued(regs: &RegistersWrite) -> [u8; 13] {
+	let ext = regs.ext.map(|ext| [1, ext.features, ext.sector_count, ext.sector, ext.cyl_low, ext.cyl_high])
+		.unwrap_or([0; 6]);
+	[
+		regs.command, regs.features, regs.sector_count, regs.sector, regs.cyl_low, regs.cyl_high, regs.device,
+		ext[0], ext[1], ext[2], ext[3], ext[4], ext[5],
+	]
+}
+
+// registers the device replied with, as stored in the file
+fn replied(regs: &RegistersRead) -> [u8; 12] {
+	let ext = regs.ext.map(|ext| [1, ext.sector_count, ext.sector, ext.cyl_low, ext.cyl_high])
+		.unwrap_or([0; 5]);
+	[
+		regs.error, regs.sector_count, regs.sector, regs.cyl_low, regs.cyl_high, regs.device, regs.status,
+		ext[0], ext[1], ext[2], ext[3], ext[4],
+	]
+}
+
+fn reply(regs: &[u8; 12]) -> RegistersRead {
+	RegistersRead {
+		error: regs[0],
+		sector_count: regs[1],
+		sector: regs[2],
+		cyl_low: regs[3],
+		cyl_high: regs[4],
+		device: regs[5],
+		status: regs[6],
+		ext: if regs[7] != 0 {
+			Some(RegistersReadExt {
+				sector_count: regs[8],
+				sector: regs[9],
+				cyl_low: regs[10],
+				cyl_high: regs[11],
+			})
+		} else {
+			None
+		},
+	}
+}
+
+#[derive(Debug)]
+struct Record {
+	// what the command was issued with
+	issued: [u8; 13],
+
+	// what the device replied with
+	regs: [u8; 12],
+	data: Vec<u8>,
+}
+
+/// Saved replies to ATA commands. Implements [`Misc`](../misc/trait.Misc.html), so it can be used in place of an actual device.
+#[derive(Debug, Default)]
+pub struct Dump {
+	records: Vec<Record>,
+}
+
+impl Dump {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds reply to the command issued with `regs`.
+	pub fn record(&mut self, regs: &RegistersWrite, reply: &RegistersRead, data: &[u8]) {
+		self.records.push(Record {
+			issued: issued(regs),
+			regs: replied(reply),
+			data: data.to_vec(),
+		});
+	}
+
+	/// Parses content of the dump file (see [module documentation](index.html) for the format).
+	pub fn parse(mut data: &[u8]) -> Result<Self, Error> {
+		if !data.starts_with(MAGIC) {
+			return Err(Error::Malformed);
+		}
+		data = &data[MAGIC.len()..];
+
+		let mut records = vec![];
+		while !data.is_empty() {
+			if data.len() < HEADER_LEN {
+				return Err(Error::Malformed);
+			}
+			let len = (&data[25..29]).read_u32::<LittleEndian>().unwrap() as usize;
+			if data.len() - HEADER_LEN < len {
+				return Err(Error::Malformed);
+			}
+
+			let mut issued = [0; 13];
+			issued.copy_from_slice(&data[0..13]);
+			let mut regs = [0; 12];
+			regs.copy_from_slice(&data[13..25]);
+
+			records.push(Record {
+				issued,
+				regs,
+				data: data[HEADER_LEN..HEADER_LEN + len].to_vec(),
+			});
+
+			data = &data[HEADER_LEN + len..];
+		}
+
+		Ok(Dump { records })
+	}
+
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+		Self::parse(&fs::read(path)?)
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut out = MAGIC.to_vec();
+		for r in &self.records {
+			out.extend_from_slice(&r.issued);
+			out.extend_from_slice(&r.regs);
+			// unwrap: writing into Vec does not fail; ATA commands transfer at most 65536 blocks (32 MiB), which is well within u32
+			out.write_u32::<LittleEndian>(r.data.len() as u32).unwrap();
+			out.extend_from_slice(&r.data);
+		}
+		out
+	}
+
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+		File::create(path)?.write_all(&self.to_bytes())?;
+		Ok(())
+	}
+}
+
+// what a device that does not support the command would reply with: ERR and ABRT
+fn aborted() -> misc::Error {
+	misc::Error::Aborted(RegistersRead {
+		error: 1 << 2,
+		sector_count: 0,
+		sector: 0,
+		cyl_low: 0,
+		cyl_high: 0,
+		device: 0,
+		status: 0x51, // DRDY, DSC, ERR
+		ext: None,
+	})
+}
+
+impl Misc for Dump {
+	fn ata_do(&self, _: Direction, regs: &RegistersWrite) -> Result<(RegistersRead, Vec<u8>), misc::Error> {
+		let issued = issued(regs);
+		match self.records.iter().find(|r| r.issued == issued) {
+			// replies with ERR set are errors, same as with an actual device
+			Some(r) => Ok((misc::check_status(reply(&r.regs))?, r.data.clone())),
+			// pretend that device does not support the command we have no reply for
+			None => Err(aborted()),
+		}
+	}
+	fn ata_do_out(&self, _: &RegistersWrite, _: &[u8]) -> Result<RegistersRead, misc::Error> {
+		// there's no device to send anything to
+		Err(aborted())
+	}
+}
+
+/// Passes commands through to the device, saving every successful reply into the [`Dump`](struct.Dump.html).
+pub struct Recorder<'a, T: Misc + ?Sized + 'a> {
+	device: &'a T,
+	dump: RefCell<Dump>,
+}
+
+impl<'a, T: Misc + ?Sized> fmt::Debug for Recorder<'a, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Recorder {{ dump: {:?} }}", self.dump)
+	}
+}
+
+impl<'a, T: Misc + ?Sized> Recorder<'a, T> {
+	pub fn new(device: &'a T) -> Self {
+		Recorder {
+			device,
+			dump: RefCell::new(Dump::new()),
+		}
+	}
+
+	pub fn into_dump(self) -> Dump {
+		self.dump.into_inner()
+	}
+}
+
+impl<'a, T: Misc + ?Sized> Misc for Recorder<'a, T> {
+	fn ata_do(&self, dir: Direction, regs: &RegistersWrite) -> Result<(RegistersRead, Vec<u8>), misc::Error> {
+		let (reply, data) = self.device.ata_do(dir, regs)?;
+		self.dump.borrow_mut().record(regs, &reply, &data);
+		Ok((reply, data))
+	}
+	fn ata_do_out(&self, _: &RegistersWrite, _: &[u8]) -> Result<RegistersRead, misc::Error> {
+		// replaying these makes no sense, and it's best not to issue anything that changes device state while recording
+		Err(misc::Error::InvalidArgument("data-out commands are not recorded"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ata::RegistersWriteExt;
+
+	fn ok() -> RegistersRead {
+		RegistersRead {
+			error: 0,
+			sector_count: 0,
+			sector: 0,
+			cyl_low: 0,
+			cyl_high: 0,
+			device: 0,
+			status: 0x50,
+			ext: None,
+		}
+	}
+
+	// READ LOG EXT of the given page of log 04h
+	fn read_log(page: u16, blocks: u8) -> RegistersWrite {
+		RegistersWrite {
+			command: 0x2f,
+			features: 0,
+			sector_count: blocks,
+			sector: 0x04,
+			cyl_low: page as u8,
+			cyl_high: 0,
+			device: 0,
+			ext: Some(RegistersWriteExt {
+				features: 0,
+				sector_count: 0,
+				sector: 0,
+				cyl_low: (page >> 8) as u8,
+				cyl_high: 0,
+			}),
+		}
+	}
+
+	#[test]
+	fn replies_are_keyed_on_every_register() {
+		let mut dump = Dump::new();
+		dump.record(&read_log(1, 1), &ok(), &[1; 512]);
+		dump.record(&read_log(1, 2), &ok(), &[2; 1024]);
+		dump.record(&read_log(0x101, 1), &ok(), &[3; 512]);
+
+		assert_eq!(dump.ata_do(Direction::From, &read_log(1, 2)).unwrap().1[0], 2);
+		assert_eq!(dump.ata_do(Direction::From, &read_log(0x101, 1)).unwrap().1[0], 3);
+		assert!(dump.ata_do(Direction::From, &read_log(2, 1)).is_err());
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut dump = Dump::new();
+		let reply = RegistersRead {
+			sector: 0x10,
+			ext: Some(RegistersReadExt { sector_count: 0, sector: 0x20, cyl_low: 0, cyl_high: 0 }),
+			..ok()
+		};
+		dump.record(&read_log(1, 1), &reply, &[0x5a; 512]);
+
+		let dump = Dump::parse(&dump.to_bytes()).unwrap();
+		let (regs, data) = dump.ata_do(Direction::From, &read_log(1, 1)).unwrap();
+		assert_eq!(regs.lba(), 0x2000_0010);
+		assert_eq!(data, vec![0x5a; 512]);
+	}
+
+	#[test]
+	fn errors_are_replayed() {
+		let mut dump = Dump::new();
+		dump.record(&read_log(1, 1), &RegistersRead { error: 0x04, status: 0x51, ..ok() }, &[]);
+		match dump.ata_do(Direction::From, &read_log(1, 1)) {
+			Err(misc::Error::Aborted(_)) => (),
+			other => panic!("expected Aborted, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn malformed() {
+		let mut dump = Dump::new();
+		dump.record(&read_log(1, 1), &ok(), &[0; 512]);
+		let data = dump.to_bytes();
+		assert!(Dump::parse(&data[..data.len() - 1]).is_err());
+		assert!(Dump::parse(b"HDDDUMP1").is_err());
+	}
+}
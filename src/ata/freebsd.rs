@@ -12,10 +12,24 @@ use std::io;
 impl ATADevice<Device> {
 	ata_do!(io::Error);
 	fn ata_platform_do(&self, dir: Direction, regs: &ata::RegistersWrite) -> Result<(ata::RegistersRead, Vec<u8>), io::Error> {
-		let timeout = 10; // in seconds; TODO configurable
+		// sector_count is the number of 512-byte blocks to transfer, but non-data commands use that register for other purposes
+		let mut data = vec![0; match dir {
+			Direction::From => 512 * ::std::cmp::max(regs.blocks(), 1),
+			_ => 512,
+		}];
+
+		let regs = self.ata_platform_do_buf(dir, regs, &mut data)?;
+		Ok((regs, data))
+	}
+
+	fn ata_platform_do_out(&self, regs: &ata::RegistersWrite, data: &[u8]) -> Result<ata::RegistersRead, io::Error> {
+		// ataio.data_ptr is *mut, so we need our own copy
+		let mut data = data.to_vec();
 
-		let mut data: [u8; 512] = [0; 512];
+		self.ata_platform_do_buf(Direction::To, regs, &mut data)
+	}
 
+	fn ata_platform_do_buf(&self, dir: Direction, regs: &ata::RegistersWrite, data: &mut [u8]) -> Result<ata::RegistersRead, io::Error> {
 		let ccb = CCB::new(&self.device.dev);
 
 		unsafe {
@@ -25,31 +39,42 @@ impl ATADevice<Device> {
 				use self::Direction::*;
 				match dir {
 					From => ccb_flags_CAM_DIR_IN,
-					To => unimplemented!(), //CAM_DIR_OUT,
+					To => ccb_flags_CAM_DIR_OUT,
 					Both => unimplemented!(), //CAM_DIR_BOTH,
 					None => ccb_flags_CAM_DIR_NONE,
 				}
 			};
 			h.retry_count = 0;
-			h.timeout = timeout * 1000;
+			h.timeout = self.device.timeout_ms();
 
 			let ataio = ccb.ataio();
 			ataio.data_ptr = data.as_mut_ptr();
-			ataio.dxfer_len = 512;
+			ataio.dxfer_len = data.len() as u32;
 			ataio.ata_flags = 0;
 
+			// previous content of the registers (HOB), for 48-bit commands
+			let (features_exp, sector_count_exp, lba_low_exp, lba_mid_exp, lba_high_exp) = match regs.ext {
+				Some(ext) => (ext.features, ext.sector_count, ext.sector, ext.cyl_low, ext.cyl_high),
+				None => (0, 0, 0, 0, 0),
+			};
+
 			ataio.cmd.command	= regs.command;
+			ataio.cmd.features_exp	= features_exp;
 			ataio.cmd.features	= regs.features;
-			ataio.cmd.lba_low_exp	= 0;
+			ataio.cmd.lba_low_exp	= lba_low_exp;
 			ataio.cmd.lba_low	= regs.sector;
-			ataio.cmd.lba_mid_exp	= 0;
+			ataio.cmd.lba_mid_exp	= lba_mid_exp;
 			ataio.cmd.lba_mid	= regs.cyl_low;
-			ataio.cmd.lba_high_exp	= 0;
+			ataio.cmd.lba_high_exp	= lba_high_exp;
 			ataio.cmd.lba_high	= regs.cyl_high;
 			ataio.cmd.device	= regs.device;
+			ataio.cmd.sector_count_exp	= sector_count_exp;
 			ataio.cmd.sector_count	= regs.sector_count;
 
 			ataio.cmd.flags = (CAM_ATAIO_NEEDRESULT | CAM_ATAIO_48BIT) as u8;
+			if regs.is_dma() {
+				ataio.cmd.flags |= CAM_ATAIO_DMA as u8;
+			}
 
 			h.flags |= ccb_flags_CAM_DEV_QFRZDIS;
 		}
@@ -62,7 +87,7 @@ impl ATADevice<Device> {
 
 		let ataio = unsafe { ccb.ataio() };
 
-		Ok((ata::RegistersRead {
+		Ok(ata::RegistersRead {
 			error: ataio.res.error,
 
 			sector_count: ataio.res.sector_count,
@@ -73,6 +98,14 @@ impl ATADevice<Device> {
 			device: ataio.res.device,
 
 			status: ataio.res.status,
-		}, data.to_vec()))
+
+			ext: regs.ext.map(|_| ata::RegistersReadExt {
+				sector_count: ataio.res.sector_count_exp,
+
+				sector: ataio.res.lba_low_exp,
+				cyl_low: ataio.res.lba_mid_exp,
+				cyl_high: ataio.res.lba_high_exp,
+			}),
+		})
 	}
 }
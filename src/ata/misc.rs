@@ -3,13 +3,15 @@ Functions implementing typical ATA commands.
 
 ## Example
 
-```
+```no_run
 use hdd::Device;
 use hdd::ata::ATADevice;
 use hdd::ata::misc::Misc;
 use hdd::ata::data::id::Ternary;
+use hdd::scsi::SCSIDevice;
 
-...
+// e.g. a SATA drive behind the SCSI layer (SAT)
+let dev = ATADevice::new(SCSIDevice::new(Device::open("/dev/sda").unwrap()));
 
 // it is a good idea to get feature status with device id info before proceeding further
 // the good thing is, ATA IDENTIFY DEVICE covers a lot of features, so we only need to call this once
@@ -35,28 +37,139 @@ use Direction;
 #[cfg(not(target_os = "linux"))]
 use Device;
 
-use ata::{ATADevice, RegistersRead, RegistersWrite, Command, SMARTFeature};
+use ata::{ATADevice, RegistersRead, RegistersWrite, RegistersWriteExt, Command, SMARTFeature, SelfTestKind, DownloadMicrocodeMode, ErrorBits};
 use scsi::{self, SCSIDevice};
 
-use ata::data::{id, health, attr};
+use ata::data::{id, health, attr, sct, ncq, internal_status, farm, log_directory, pending_defects, self_test, power_mode, smart_capabilities, devstat, error_log};
 use drivedb;
+use tcg;
 
 use std::io;
 
+// ATA PASS-THROUGH without EXTEND only takes transfer length from SECTOR COUNT (T_LENGTH=01), thus 28-bit data-out commands are limited to this many blocks
+const MAX_OUT_BLOCKS: usize = 0xff;
+
 quick_error! {
 	#[derive(Debug)]
 	pub enum Error {
 		IO(err: io::Error) {
-			from()
 			//from(ATAError::IO(err): ATAError) -> (err)
 			display("IO error: {}", err)
 			description(err.description())
 			cause(err)
 		}
 		SCSI(err: scsi::ATAError) {
-			from()
 			display("{}", err)
 		}
+		/// Command did not complete within the device timeout (see [`Device::set_timeout()`](../../struct.Device.html#method.set_timeout))
+		Timeout {
+			display("command timed out")
+		}
+		/// Arguments are rejected before anything is sent to the device
+		InvalidArgument(reason: &'static str) {
+			display("invalid argument: {}", reason)
+		}
+		/// Device aborted the command (status bit ERR and error bit ABRT are set), typically because the command or its arguments are not supported; carries the registers the device returned
+		Aborted(regs: RegistersRead) {
+			display("command aborted by device, error register: {}", ErrorBits(regs.error))
+		}
+		/// Device reported some other error (status bit ERR is set); carries the registers the device returned, with the LBA of the failing sector for media access commands
+		Device(regs: RegistersRead) {
+			display("device error, error register: {}, status 0x{:02x}, LBA {}", ErrorBits(regs.error), regs.status, regs.lba())
+		}
+	}
+}
+
+// timeouts are reported separately, whichever layer they came from
+impl From<io::Error> for Error {
+	fn from(err: io::Error) -> Self {
+		match err.kind() {
+			io::ErrorKind::TimedOut => Error::Timeout,
+			_ => Error::IO(err),
+		}
+	}
+}
+
+impl From<scsi::ATAError> for Error {
+	fn from(err: scsi::ATAError) -> Self {
+		match err {
+			scsi::ATAError::SCSI(scsi::Error::Timeout) => Error::Timeout,
+			err => Error::SCSI(err),
+		}
+	}
+}
+
+/**
+Encodes standby timer period (in seconds) into the value IDLE and STANDBY commands expect.
+
+Returns `None` if the period cannot be represented exactly. Use `0` to disable the timer.
+*/
+pub fn encode_standby_timer(seconds: u32) -> Option<u8> {
+	match seconds {
+		0 => Some(0),
+		// 1..240: multiples of 5 seconds, up to 20 minutes
+		s if s <= 240 * 5 && s % 5 == 0 => Some((s / 5) as u8),
+		// 241..251: multiples of 30 minutes, up to 5.5 hours
+		s if (30 * 60 ..= 11 * 30 * 60).contains(&s) && s % (30 * 60) == 0 => Some((240 + s / (30 * 60)) as u8),
+		1260 => Some(252), // 21 minutes
+		1275 => Some(255), // 21 minutes 15 seconds
+		_ => None,
+	}
+}
+
+/// Decodes standby timer value into the period, in seconds. Returns `None` for values that are vendor-specific (253) or reserved (254).
+pub fn decode_standby_timer(value: u8) -> Option<u32> {
+	match value {
+		0 ..= 240 => Some(value as u32 * 5),
+		241 ..= 251 => Some((value as u32 - 240) * 30 * 60),
+		252 => Some(1260),
+		255 => Some(1275),
+		_ => None, // 253 is vendor-specific, 254 is reserved
+	}
+}
+
+// READ LOG EXT and READ LOG DMA EXT only differ in the command code
+fn read_log_regs(command: Command, log: u8, page: u16, count: u16) -> RegistersWrite {
+	RegistersWrite {
+		command: command as u8,
+		features: 0,
+		sector_count: (count & 0xff) as u8,
+		sector: log,
+		cyl_low: (page & 0xff) as u8,
+		cyl_high: 0,
+		device: 0,
+		ext: Some(RegistersWriteExt {
+			features: 0,
+			sector_count: (count >> 8) as u8,
+			sector: 0,
+			cyl_low: (page >> 8) as u8,
+			cyl_high: 0,
+		}),
+	}
+}
+
+// turns registers with status bit ERR set into an error, so that the callers can tell device errors apart from transport failures
+pub(crate) fn check_status(regs: RegistersRead) -> Result<RegistersRead, Error> {
+	if !regs.is_err() {
+		return Ok(regs);
+	}
+
+	let err = ErrorBits(regs.error);
+	warn!("device reported an error: status=0x{:02x} error={} lba={}", regs.status, err, regs.lba());
+	if err.abort() {
+		Err(Error::Aborted(regs))
+	} else {
+		Err(Error::Device(regs))
+	}
+}
+
+impl Error {
+	/// Registers the device returned along with the error, if it is the device that reported one (as opposed to the transport failing).
+	pub fn registers(&self) -> Option<&RegistersRead> {
+		match *self {
+			Error::Aborted(ref regs) | Error::Device(ref regs) => Some(regs),
+			_ => None,
+		}
 	}
 }
 
@@ -64,9 +177,24 @@ quick_error! {
 pub trait Misc {
 	// This one not only invokes ata_do() from `ATADevice<Whatever>`, but also converts into our own error type.
 	fn ata_do(&self, dir: Direction, regs: &RegistersWrite) -> Result<(RegistersRead, Vec<u8>), Error>;
+	// Same for `ata_do_out()`.
+	fn ata_do_out(&self, regs: &RegistersWrite, data: &[u8]) -> Result<RegistersRead, Error>;
 
 	/// Issues IDENTIFY DEVICE command, returning a wide range of data, from model name to status of various features.
 	fn get_device_id(&self) -> Result<id::Id, Error> {
+		match self.get_device_id_raw() {
+			Ok(data) => Ok(id::parse_id(&data)),
+			// ATAPI devices (optical drives and such) abort IDENTIFY DEVICE and expect IDENTIFY PACKET DEVICE instead
+			Err(Error::Aborted(_)) => {
+				info!("IDENTIFY DEVICE aborted, assuming ATAPI device");
+				Ok(id::parse_id(&self.get_packet_device_id_raw()?))
+			},
+			Err(err) => Err(err),
+		}
+	}
+
+	/// Same as [`get_device_id()`](#method.get_device_id), but returns unparsed reply (256 little-endian words).
+	fn get_device_id_raw(&self) -> Result<Vec<u8>, Error> {
 		info!("reading device identification packet");
 
 		let (_, data) = self.ata_do(Direction::From, &RegistersWrite {
@@ -77,62 +205,723 @@ pub trait Misc {
 			cyl_high: 0,
 			cyl_low: 0,
 			device: 0,
+			ext: None,
 		})?;
 
-		Ok(id::parse_id(&data))
+		Ok(data)
+	}
+
+	/// Issues IDENTIFY PACKET DEVICE command, returning unparsed reply (256 little-endian words).
+	///
+	/// Serial, firmware revision and model are where [`id::parse_id()`](../data/id/fn.parse_id.html) expects them to be, and word 0 tells [`Id.is_ata`](../data/id/struct.Id.html#structfield.is_ata) apart, but plenty of other words are only meaningful for one kind of device or the other.
+	fn get_packet_device_id_raw(&self) -> Result<Vec<u8>, Error> {
+		info!("reading packet device identification packet");
+
+		let (_, data) = self.ata_do(Direction::From, &RegistersWrite {
+			command: Command::IdentifyPacket as u8,
+			sector: 1,
+			features: 0,
+			sector_count: 1,
+			cyl_high: 0,
+			cyl_low: 0,
+			device: 0,
+			ext: None,
+		})?;
+
+		Ok(data)
 	}
 
 	/// Issues SMART RETURN STATUS command, returns `Some(false)` if device can no longer be considered reliable.
 	fn get_smart_health(&self) -> Result<Option<bool>, Error> {
 		info!("reading SMART status");
 
+		let (regs, _) = self.ata_do(Direction::None, &RegistersWrite::smart(SMARTFeature::ReturnStatus, 0, 0))?;
+		Ok(health::parse_smart_status(&regs))
+	}
+
+	/// Issues SMART READ DATA and SMART READ THRESHOLDS commands, then renders their answers using optional [drivedb](../../drivedb/index.html) entry.
+	///
+	/// Fails if either of the commands fails; to show values even if thresholds are not available, use [`get_smart_values_raw()`](#method.get_smart_values_raw), [`get_smart_thresholds_raw()`](#method.get_smart_thresholds_raw) and [`attr::parse_smart_values()`](../data/attr/fn.parse_smart_values.html) instead.
+	fn get_smart_attributes(&self, meta: &Option<drivedb::DriveMeta>) -> Result<Vec<attr::SmartAttribute>, Error> {
+		let data = self.get_smart_values_raw()?;
+		let thresh = self.get_smart_thresholds_raw()?;
+
+		Ok(attr::parse_smart_values(&data, &thresh, &meta))
+	}
+
+	/// Issues SMART READ DATA command, returning unparsed reply.
+	fn get_smart_values_raw(&self) -> Result<Vec<u8>, Error> {
+		info!("reading SMART attributes");
+
+		let (_, data) = self.ata_do(Direction::From, &RegistersWrite::smart(SMARTFeature::ReadValues, 0, 1))?;
+
+		Ok(data)
+	}
+
+	/// Issues SMART READ DATA command, and parses off-line data collection and SMART capabilities that follow the attribute table. Returns `None` if the reply is truncated.
+	fn get_smart_capabilities(&self) -> Result<Option<smart_capabilities::SmartCapabilities>, Error> {
+		let data = self.get_smart_values_raw()?;
+
+		Ok(smart_capabilities::parse_smart_capabilities(&data))
+	}
+
+	/// Issues SMART ENABLE/DISABLE ATTRIBUTE AUTOSAVE. Check `attribute_autosave_supported` from [`get_smart_capabilities()`](#method.get_smart_capabilities) first.
+	fn set_attribute_autosave(&self, enable: bool) -> Result<(), Error> {
+		info!("issuing SMART ENABLE/DISABLE ATTRIBUTE AUTOSAVE: enable={:?}", enable);
+
+		self.ata_do(Direction::None, &RegistersWrite::smart(SMARTFeature::AttributeAutosave, 0, if enable { 0xf1 } else { 0 }))?;
+
+		Ok(())
+	}
+
+	/// Issues SMART ENABLE/DISABLE AUTOMATIC OFF-LINE, which makes the device collect off-line data (and update off-line attributes) every four hours or so. Check `auto_offline_supported` from [`get_smart_capabilities()`](#method.get_smart_capabilities) first.
+	fn set_auto_offline(&self, enable: bool) -> Result<(), Error> {
+		info!("issuing SMART ENABLE/DISABLE AUTOMATIC OFF-LINE: enable={:?}", enable);
+
+		self.ata_do(Direction::None, &RegistersWrite::smart(SMARTFeature::AutoOffline, 0, if enable { 0xf8 } else { 0 }))?;
+
+		Ok(())
+	}
+
+	/// Issues SMART EXECUTE OFF-LINE IMMEDIATE to start the self-test `test` in off-line mode. Check `self_test_supported` (and `conveyance_self_test_supported` for conveyance tests) from [`get_smart_capabilities()`](#method.get_smart_capabilities) first.
+	///
+	/// This returns right away; the progress is then reported in `self_test_status` of [`get_smart_capabilities()`](#method.get_smart_capabilities), and the result ends up in the self-test log.
+	fn start_self_test(&self, test: SelfTestKind) -> Result<(), Error> {
+		info!("issuing SMART EXECUTE OFF-LINE IMMEDIATE: test={:?}", test);
+
+		self.ata_do(Direction::None, &RegistersWrite::smart(SMARTFeature::ExecuteOfflineImmediate, test as u8, 0))?;
+
+		Ok(())
+	}
+
+	/// Aborts the self-test that runs in off-line mode, if any (SMART EXECUTE OFF-LINE IMMEDIATE with subcommand 7Fh).
+	fn abort_self_test(&self) -> Result<(), Error> {
+		info!("issuing SMART EXECUTE OFF-LINE IMMEDIATE: abort");
+
+		self.ata_do(Direction::None, &RegistersWrite::smart(SMARTFeature::ExecuteOfflineImmediate, 0x7f, 0))?;
+
+		Ok(())
+	}
+
+	/// Issues SMART READ THRESHOLDS command, returning unparsed reply.
+	fn get_smart_thresholds_raw(&self) -> Result<Vec<u8>, Error> {
+		info!("reading SMART thresholds");
+
+		let (_, thresh) = self.ata_do(Direction::From, &RegistersWrite::smart(SMARTFeature::ReadThresholds, 0, 1))?;
+
+		Ok(thresh)
+	}
+
+	/// Issues READ LOG EXT (a 48-bit command), reading `count` 512-byte pages of the General Purpose log `log`, starting at page `page`.
+	///
+	/// Check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first. Use log directory (log address 00h) to find out which logs are supported and how many pages they have.
+	fn read_log_ext(&self, log: u8, page: u16, count: u16) -> Result<Vec<u8>, Error> {
+		info!("issuing READ LOG EXT: log={:?} page={:?} count={:?}", log, page, count);
+
+		let (_, data) = self.ata_do(Direction::From, &read_log_regs(Command::ReadLogExt, log, page, count))?;
+
+		Ok(data)
+	}
+
+	/// Same as [`read_log_ext()`](#method.read_log_ext), but issues READ LOG DMA EXT, which transfers data using DMA. That is faster for large logs, and some devices are only reliable with this one.
+	///
+	/// Check `commands_supported.read_write_dma_ext_gpl` from [`Id`](../data/id/struct.Id.html) first, or use [`read_log_pages()`](#method.read_log_pages).
+	fn read_log_dma_ext(&self, log: u8, page: u16, count: u16) -> Result<Vec<u8>, Error> {
+		info!("issuing READ LOG DMA EXT: log={:?} page={:?} count={:?}", log, page, count);
+
+		let (_, data) = self.ata_do(Direction::From, &read_log_regs(Command::ReadLogDmaExt, log, page, count))?;
+
+		Ok(data)
+	}
+
+	/// Reads General Purpose log pages using READ LOG DMA EXT if `dma` is set, falling back to READ LOG EXT if that fails (e.g. if the SATL does not support DMA pass-through); `dma` is then cleared, so that subsequent reads of the same log go straight to READ LOG EXT.
+	///
+	/// Set `dma` from `commands_supported.read_write_dma_ext_gpl` of [`Id`](../data/id/struct.Id.html).
+	fn read_log_pages(&self, dma: &mut bool, log: u8, page: u16, count: u16) -> Result<Vec<u8>, Error> {
+		if *dma {
+			match self.read_log_dma_ext(log, page, count) {
+				// no point in repeating the command that won't complete in time anyway
+				Err(Error::Timeout) => return Err(Error::Timeout),
+				Err(err) => {
+					warn!("READ LOG DMA EXT failed, falling back to READ LOG EXT: {}", err);
+					*dma = false;
+				},
+				ok => return ok,
+			}
+		}
+		self.read_log_ext(log, page, count)
+	}
+
+	/// Issues SMART READ LOG, reading `count` 512-byte pages of the SMART log `log`. Check `smart` from [`Id`](../data/id/struct.Id.html) first.
+	fn smart_read_log(&self, log: u8, count: u8) -> Result<Vec<u8>, Error> {
+		info!("issuing SMART READ LOG: log={:?} count={:?}", log, count);
+
+		let (_, data) = self.ata_do(Direction::From, &RegistersWrite::smart(SMARTFeature::ReadLog, log, count))?;
+
+		Ok(data)
+	}
+
+	/// Reads General Purpose Log Directory (log address 00h) using READ LOG EXT. Returns `None` if the reply is truncated; check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first.
+	fn get_gp_log_directory(&self) -> Result<Option<log_directory::LogDirectory>, Error> {
+		info!("reading General Purpose Log Directory");
+
+		let data = self.read_log_ext(0x00, 0, 1)?;
+
+		Ok(log_directory::parse_log_directory(&data))
+	}
+
+	/// Reads SMART Log Directory (log address 00h) using SMART READ LOG. Returns `None` if the reply is truncated; check `smart` from [`Id`](../data/id/struct.Id.html) first.
+	fn get_smart_log_directory(&self) -> Result<Option<log_directory::LogDirectory>, Error> {
+		info!("reading SMART Log Directory");
+
+		let data = self.smart_read_log(0x00, 1)?;
+
+		Ok(log_directory::parse_log_directory(&data))
+	}
+
+	/// Reads NCQ Command Error log (log address 10h) using READ LOG EXT. Returns `None` if the reply is truncated; check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first.
+	fn get_ncq_error_log(&self) -> Result<Option<ncq::NcqErrorLog>, Error> {
+		info!("reading NCQ Command Error log");
+
+		let data = self.read_log_ext(0x10, 0, 1)?;
+
+		Ok(ncq::parse_ncq_error_log(&data))
+	}
+
+	/// Reads the whole Current Device Internal Status Data log (log address 24h) using READ LOG EXT: the header page first (the rest is read with READ LOG DMA EXT if the device supports it), then as many pages as the header declares. Returns parsed header along with raw content of the log (header page included), or `None` if the header is malformed or any reply is truncated.
+	///
+	/// If `capture` is set, device is asked to collect fresh data first; otherwise it returns whatever it collected before (if anything). Content is vendor-specific and is not parsed. Check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first, and set `dma` from its `commands_supported.read_write_dma_ext_gpl`.
+	fn get_internal_status_log(&self, capture: bool, mut dma: bool) -> Result<Option<(internal_status::InternalStatusHeader, Vec<u8>)>, Error> {
+		info!("reading Current Device Internal Status Data log");
+
+		// this is READ LOG EXT, but with LOG SPECIFIC field (features register) that read_log_ext() does not allow to set
+		let (_, mut data) = self.ata_do(Direction::From, &RegistersWrite {
+			command: Command::ReadLogExt as u8,
+			features: if capture { 1 } else { 0 },
+			sector_count: 1,
+			sector: 0x24,
+			cyl_low: 0,
+			cyl_high: 0,
+			device: 0,
+			ext: Some(RegistersWriteExt {
+				features: 0,
+				sector_count: 0,
+				sector: 0,
+				cyl_low: 0,
+				cyl_high: 0,
+			}),
+		})?;
+
+		let header = match internal_status::parse_internal_status_header(&data) {
+			Some(header) => header,
+			None => return Ok(None),
+		};
+		data.truncate(512);
+
+		// the log can be quite large, so it is read in chunks to keep transfers reasonably small
+		let pages = header.pages();
+		let mut page = 1;
+		while page < pages {
+			let count = ::std::cmp::min(pages - page, 128);
+			let chunk = self.read_log_pages(&mut dma, 0x24, page as u16, count as u16)?;
+			if chunk.len() < count as usize * 512 {
+				return Ok(None);
+			}
+			data.extend_from_slice(&chunk[..count as usize * 512]);
+			page += count;
+		}
+
+		Ok(Some((header, data)))
+	}
+
+	/// Reads Seagate FARM log (log address A6h) using READ LOG EXT: the header first (the rest is read with READ LOG DMA EXT if the device supports it), then every page that [`farm`](../data/farm/index.html) knows how to decode. Returns `None` if the header is malformed (e.g. if this is not a Seagate drive, and log A6h means something else there).
+	///
+	/// Check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first, and set `dma` from its `commands_supported.read_write_dma_ext_gpl`.
+	fn get_farm_log(&self, mut dma: bool) -> Result<Option<farm::FarmLog>, Error> {
+		info!("reading FARM log");
+
+		let mut data = self.read_log_ext(0xa6, 0, 1)?;
+		let header = match farm::parse_farm_header(&data) {
+			Some(header) => header,
+			None => return Ok(None),
+		};
+		data.truncate(512);
+
+		// read in chunks to keep transfers reasonably small
+		let sectors = header.sectors();
+		let mut sector = 1;
+		while sector < sectors {
+			let count = ::std::cmp::min(sectors - sector, 128);
+			let chunk = self.read_log_pages(&mut dma, 0xa6, sector as u16, count as u16)?;
+			if chunk.len() < count as usize * 512 {
+				// whatever was read already is still useful; missing pages are reported as such
+				break;
+			}
+			data.extend_from_slice(&chunk[..count as usize * 512]);
+			sector += count;
+		}
+
+		Ok(farm::parse_farm_log(&data))
+	}
+
+	/// Reads the whole Pending Defects log (log address 0Ch) using READ LOG EXT (or READ LOG DMA EXT for the pages that follow the first one, if the device supports it). Returns `None` if any reply is truncated; check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first, and set `dma` from its `commands_supported.read_write_dma_ext_gpl`.
+	fn get_pending_defects(&self, mut dma: bool) -> Result<Option<Vec<pending_defects::PendingDefect>>, Error> {
+		info!("reading Pending Defects log");
+
+		let mut data = self.read_log_ext(0x0c, 0, 1)?;
+		let pages = match pending_defects::pages(&data) {
+			Some(pages) => pages,
+			None => return Ok(None),
+		};
+		data.truncate(512);
+
+		// read in chunks to keep transfers reasonably small
+		let mut page = 1;
+		while page < pages {
+			let count = ::std::cmp::min(pages - page, 128);
+			let chunk = self.read_log_pages(&mut dma, 0x0c, page, count)?;
+			if chunk.len() < count as usize * 512 {
+				return Ok(None);
+			}
+			data.extend_from_slice(&chunk[..count as usize * 512]);
+			page += count;
+		}
+
+		Ok(pending_defects::parse_pending_defects(&data))
+	}
+
+	/// Reads Device Statistics log (log address 04h) using READ LOG EXT: the list of supported pages first, and then every page on that list. Returns `None` if the list is truncated; truncated statistics pages, and pages the device fails to return, are left out.
+	///
+	/// Check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first.
+	fn get_device_statistics(&self) -> Result<Option<devstat::DeviceStatistics>, Error> {
+		info!("reading Device Statistics log");
+
+		let mut data = self.read_log_ext(0x04, 0, 1)?;
+		let pages = match devstat::supported_pages(&data) {
+			Some(pages) => pages,
+			None => return Ok(None),
+		};
+		data.truncate(512);
+
+		for page in pages {
+			let chunk = match self.read_log_ext(0x04, page as u16, 1) {
+				Ok(chunk) => chunk,
+				// e.g. vendor-specific page FFh, which some devices list but abort reading; other pages are still worth showing
+				Err(ref err) if err.registers().is_some() => {
+					warn!("cannot read Device Statistics page 0x{:02x}: {}", page, err);
+					continue;
+				},
+				Err(err) => return Err(err),
+			};
+			if chunk.len() >= 512 {
+				data.extend_from_slice(&chunk[..512]);
+			}
+		}
+
+		Ok(devstat::parse_device_statistics(&data))
+	}
+
+	/// Reads Summary SMART error log (log address 01h) using SMART READ LOG. Returns `None` if the reply is truncated; check `smart_error_logging_supported` from [`Id`](../data/id/struct.Id.html) first. `meta` is for firmware bug workarounds.
+	fn get_summary_error_log(&self, meta: &Option<drivedb::DriveMeta>) -> Result<Option<error_log::ErrorLog>, Error> {
+		info!("reading Summary SMART error log");
+
+		let data = self.smart_read_log(0x01, 1)?;
+
+		Ok(error_log::parse_summary_error_log(&data, meta))
+	}
+
+	/// Reads `pages` pages of Extended Comprehensive SMART error log (log address 03h) using READ LOG EXT (or READ LOG DMA EXT, if the device supports it); use General Purpose Log Directory to find out how many pages there are. Returns `None` if the reply is truncated.
+	///
+	/// Unlike Summary SMART error log, this one reports 48-bit LBAs, and usually holds more errors. Check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first, and set `dma` from its `commands_supported.read_write_dma_ext_gpl`; `meta` is for firmware bug workarounds.
+	fn get_ext_error_log(&self, pages: u16, mut dma: bool, meta: &Option<drivedb::DriveMeta>) -> Result<Option<error_log::ErrorLog>, Error> {
+		info!("reading Extended Comprehensive SMART error log");
+
+		let data = self.read_log_pages(&mut dma, 0x03, 0, pages)?;
+		if data.len() < pages as usize * 512 {
+			return Ok(None);
+		}
+
+		Ok(error_log::parse_ext_error_log(&data[..pages as usize * 512], meta))
+	}
+
+	/// Reads SMART self-test log (log address 06h) using SMART READ LOG. Returns `None` if the reply is truncated; check `smart` from [`Id`](../data/id/struct.Id.html) first.
+	fn get_self_test_log(&self) -> Result<Option<Vec<self_test::SelfTest>>, Error> {
+		info!("reading SMART self-test log");
+
+		let data = self.smart_read_log(0x06, 1)?;
+
+		Ok(self_test::parse_self_test_log(&data))
+	}
+
+	/// Reads `pages` pages of Extended SMART self-test log (log address 07h) using READ LOG EXT; use General Purpose Log Directory to find out how many pages there are. Returns `None` if the reply is truncated.
+	///
+	/// Unlike SMART self-test log, this one reports 48-bit LBAs. Check `gp_logging_supported` from [`Id`](../data/id/struct.Id.html) first.
+	fn get_ext_self_test_log(&self, pages: u16) -> Result<Option<Vec<self_test::SelfTest>>, Error> {
+		info!("reading Extended SMART self-test log");
+
+		let data = self.read_log_ext(0x07, 0, pages)?;
+		if data.len() < pages as usize * 512 {
+			return Ok(None);
+		}
+
+		Ok(self_test::parse_ext_self_test_log(&data[..pages as usize * 512]))
+	}
+
+	/// Issues IDLE, setting the standby timer to `timer` (see [`encode_standby_timer()`](fn.encode_standby_timer.html)), and putting device into the Idle mode.
+	///
+	/// Note that the timer is volatile (it is reset on power cycle or device reset), and that devices might be putting themselves into standby on their own if APM is enabled.
+	fn set_standby_timer(&self, timer: u8) -> Result<RegistersRead, Error> {
+		info!("issuing IDLE: timer={:?}", timer);
+
 		let (regs, _) = self.ata_do(Direction::None, &RegistersWrite {
-			command: Command::SMART as u8,
-			features: SMARTFeature::ReturnStatus as u8,
+			command: Command::Idle as u8,
+			features: 0,
+			sector_count: timer,
+			sector: 0,
+			cyl_low: 0,
+			cyl_high: 0,
+			device: 0,
+			ext: None,
+		})?;
+
+		Ok(regs)
+	}
+
+	/// Issues SET FEATURES to enable (subcommand 10h) or disable (subcommand 90h) Serial ATA `feature`.
+	///
+	/// Check `sata_features` from [`Id`](../data/id/struct.Id.html) first: devices abort the command for features they do not support. Some of the features are reset on power cycle, unless Software Settings Preservation is enabled.
+	fn set_sata_feature(&self, feature: id::SataFeature, enable: bool) -> Result<(), Error> {
+		info!("issuing SET FEATURES: sata feature={:?} enable={:?}", feature, enable);
+
+		self.ata_do(Direction::None, &RegistersWrite {
+			command: Command::SetFeatures as u8,
+			features: if enable { 0x10 } else { 0x90 },
+			sector_count: feature as u8,
+			sector: 0,
+			cyl_low: 0,
+			cyl_high: 0,
+			device: 0,
+			ext: None,
+		})?;
+
+		Ok(())
+	}
+
+	/// Issues CHECK POWER MODE, which tells whether the device is spun down, without waking it up.
+	///
+	/// Use it before issuing commands that access the media (e.g. SMART READ DATA, or reading logs) to leave devices in Standby alone.
+	fn get_power_mode(&self) -> Result<power_mode::PowerMode, Error> {
+		info!("issuing CHECK POWER MODE");
+
+		let (regs, _) = self.ata_do(Direction::None, &RegistersWrite {
+			command: Command::CheckPowerMode as u8,
+			features: 0,
 			sector_count: 0,
 			sector: 0,
-			cyl_low: 0x4f,
-			cyl_high: 0xc2,
+			cyl_low: 0,
+			cyl_high: 0,
 			device: 0,
+			ext: None,
 		})?;
-		Ok(health::parse_smart_status(&regs))
+
+		Ok(power_mode::parse_power_mode(&regs))
 	}
 
-	/// Issues SMART READ DATA and SMART READ THRESHOLDS commands, then renders their answers using optional [drivedb](../../drivedb/index.html) entry.
-	fn get_smart_attributes(&self, meta: &Option<drivedb::DriveMeta>) -> Result<Vec<attr::SmartAttribute>, Error> {
-		info!("reading SMART attributes and thresholds");
+	/// Issues READ VERIFY SECTOR(S) (a 28-bit command), making the device read `count` sectors starting at `lba` without transferring anything to the host. `count` of 0 means 256 sectors.
+	///
+	/// Unreadable sectors are reported as [`Error::Device`](enum.Error.html) with UNC (or IDNF, for addresses out of range) set.
+	fn read_verify_sectors(&self, lba: u32, count: u8) -> Result<(), Error> {
+		debug!("issuing READ VERIFY SECTOR(S): lba={:?} count={:?}", lba, count);
+
+		self.ata_do(Direction::None, &RegistersWrite {
+			command: Command::ReadVerifySectors as u8,
+			features: 0,
+			sector_count: count,
+			sector: lba as u8,
+			cyl_low: (lba >> 8) as u8,
+			cyl_high: (lba >> 16) as u8,
+			// bit 6: LBA addressing; bits 3:0 are bits 27:24 of the LBA
+			device: 0x40 | ((lba >> 24) as u8 & 0x0f),
+			ext: None,
+		})?;
+
+		Ok(())
+	}
+
+	/// Same as [`read_verify_sectors()`](#method.read_verify_sectors), but issues READ VERIFY SECTOR(S) EXT, a 48-bit command (check `lba48_supported` from [`Id`](../data/id/struct.Id.html) first). `count` of 0 means 65536 sectors.
+	fn read_verify_sectors_ext(&self, lba: u64, count: u16) -> Result<(), Error> {
+		debug!("issuing READ VERIFY SECTOR(S) EXT: lba={:?} count={:?}", lba, count);
+
+		self.ata_do(Direction::None, &RegistersWrite {
+			command: Command::ReadVerifySectorsExt as u8,
+			features: 0,
+			sector_count: count as u8,
+			sector: lba as u8,
+			cyl_low: (lba >> 8) as u8,
+			cyl_high: (lba >> 16) as u8,
+			device: 0x40, // LBA addressing
+			ext: Some(RegistersWriteExt {
+				features: 0,
+				sector_count: (count >> 8) as u8,
+				sector: (lba >> 24) as u8,
+				cyl_low: (lba >> 32) as u8,
+				cyl_high: (lba >> 40) as u8,
+			}),
+		})?;
+
+		Ok(())
+	}
+
+	/// Reads SCT status (SMART READ LOG, log address E0h), which includes current and min/max temperatures. Check `sct_supported` from [`Id`](../data/id/struct.Id.html) first.
+	fn get_sct_status(&self) -> Result<Option<sct::SctStatus>, Error> {
+		info!("reading SCT status");
+
+		let (_, data) = self.ata_do(Direction::From, &RegistersWrite::smart(SMARTFeature::ReadLog, 0xe0, 1))?;
+
+		Ok(sct::parse_sct_status(&data))
+	}
+
+	/// Issues TRUSTED RECEIVE (aka IF-RECV, or SECURITY PROTOCOL IN), receiving `blocks` 512-byte blocks of data.
+	///
+	/// `protocol` and `sp_specific` are what the security protocol in question (e.g. TCG, see [`tcg` module](../../tcg/index.html)) make of them. Check `trusted_computing_supported` from [`Id`](../data/id/struct.Id.html) first.
+	fn security_protocol_in(&self, protocol: u8, sp_specific: u16, blocks: u8) -> Result<Vec<u8>, Error> {
+		info!("issuing TRUSTED RECEIVE: protocol={:?} sp_specific={:?} blocks={:?}", protocol, sp_specific, blocks);
 
 		let (_, data) = self.ata_do(Direction::From, &RegistersWrite {
-			command: Command::SMART as u8,
+			command: Command::TrustedReceive as u8,
+			features: protocol,
+			// transfer length, in 512-byte blocks (bits 7:0 and 15:8, respectively)
+			sector_count: blocks,
 			sector: 0,
-			features: SMARTFeature::ReadValues as u8,
-			sector_count: 1,
-			cyl_low: 0x4f,
-			cyl_high: 0xc2,
+			cyl_low: (sp_specific & 0xff) as u8,
+			cyl_high: (sp_specific >> 8) as u8,
 			device: 0,
+			ext: None,
 		})?;
-		let (_, thresh) = self.ata_do(Direction::From, &RegistersWrite {
-			command: Command::SMART as u8,
+
+		Ok(data)
+	}
+
+	/// Issues TRUSTED SEND (aka IF-SEND, or SECURITY PROTOCOL OUT). See also [`security_protocol_in()`](#method.security_protocol_in).
+	///
+	/// `data` is padded with zeroes to the 512-byte block boundary, and must not exceed 255 blocks.
+	fn security_protocol_out(&self, protocol: u8, sp_specific: u16, data: &[u8]) -> Result<(), Error> {
+		info!("issuing TRUSTED SEND: protocol={:?} sp_specific={:?} len={:?}", protocol, sp_specific, data.len());
+
+		let blocks = (data.len() + 511) / 512;
+		if blocks > MAX_OUT_BLOCKS {
+			return Err(Error::InvalidArgument("TRUSTED SEND payload is too long"));
+		}
+		let mut data = data.to_vec();
+		data.resize(blocks * 512, 0);
+
+		self.ata_do_out(&RegistersWrite {
+			command: Command::TrustedSend as u8,
+			features: protocol,
+			// transfer length, in 512-byte blocks (bits 7:0 and 15:8, respectively)
+			sector_count: blocks as u8,
 			sector: 0,
-			features: SMARTFeature::ReadThresholds as u8,
-			sector_count: 1,
-			cyl_low: 0x4f,
-			cyl_high: 0xc2,
+			cyl_low: (sp_specific & 0xff) as u8,
+			cyl_high: (sp_specific >> 8) as u8,
 			device: 0,
-		})?;
+			ext: None,
+		}, &data)?;
 
-		Ok(attr::parse_smart_values(&data, &thresh, &meta))
+		Ok(())
+	}
+
+	/// Issues DOWNLOAD MICROCODE, sending `data` to the device at `offset` (in 512-byte blocks; only meaningful for segmented modes). `data` should be empty for `DownloadMicrocodeMode::Activate`.
+	///
+	/// This is obviously dangerous: interrupted or otherwise botched firmware download might easily render the device unusable.
+	///
+	/// `data` must be a multiple of 512 bytes long, and at most 255 blocks, which is all ATA PASS-THROUGH can carry at once. Larger images should be sent in segments.
+	///
+	/// Errors reported by the device (ERR bit of the status register) come back as `Error::Device`. For segmented modes, sector count of the returned registers indicates whether the device expects more segments (01h) or had applied new microcode (02h, 03h).
+	fn download_microcode(&self, mode: DownloadMicrocodeMode, offset: u16, data: &[u8]) -> Result<RegistersRead, Error> {
+		info!("issuing DOWNLOAD MICROCODE: mode={:?} offset={:?} len={:?}", mode, offset, data.len());
+
+		if data.len() % 512 != 0 {
+			return Err(Error::InvalidArgument("microcode length must be a multiple of 512 bytes"));
+		}
+		let blocks = data.len() / 512;
+		if blocks > MAX_OUT_BLOCKS {
+			return Err(Error::InvalidArgument("microcode segment is too long"));
+		}
+
+		let regs = RegistersWrite {
+			command: Command::DownloadMicrocode as u8,
+			features: mode as u8,
+			// block count (bits 7:0 and 15:8, respectively)
+			sector_count: blocks as u8,
+			sector: 0,
+			// buffer offset
+			cyl_low: (offset & 0xff) as u8,
+			cyl_high: (offset >> 8) as u8,
+			device: 0,
+			ext: None,
+		};
+
+		if data.is_empty() {
+			let (regs, _) = self.ata_do(Direction::None, &regs)?;
+			Ok(regs)
+		} else {
+			self.ata_do_out(&regs, data)
+		}
+	}
+
+	/// Reads TCG Level 0 Discovery. Check `trusted_computing_supported` from [`Id`](../data/id/struct.Id.html) first.
+	fn get_tcg_discovery(&self) -> Result<Option<tcg::Discovery>, Error> {
+		info!("reading TCG Level 0 Discovery");
+
+		let data = self.security_protocol_in(tcg::PROTOCOL, tcg::DISCOVERY_COMID, 1)?;
+
+		Ok(tcg::parse_discovery(&data))
 	}
 }
 
 #[cfg(not(target_os = "linux"))]
 impl Misc for ATADevice<Device> {
 	fn ata_do(&self, dir: Direction, regs: &RegistersWrite) -> Result<(RegistersRead, Vec<u8>), Error> {
-		Ok(Self::ata_do(self, dir, regs)?)
+		let (regs, data) = Self::ata_do(self, dir, regs)?;
+		Ok((check_status(regs)?, data))
+	}
+	fn ata_do_out(&self, regs: &RegistersWrite, data: &[u8]) -> Result<RegistersRead, Error> {
+		check_status(Self::ata_do_out(self, regs, data)?)
 	}
 }
 impl Misc for ATADevice<SCSIDevice> {
 	fn ata_do(&self, dir: Direction, regs: &RegistersWrite) -> Result<(RegistersRead, Vec<u8>), Error> {
-		Ok(Self::ata_do(self, dir, regs)?)
+		let (regs, data) = Self::ata_do(self, dir, regs)?;
+		Ok((check_status(regs)?, data))
+	}
+	fn ata_do_out(&self, regs: &RegistersWrite, data: &[u8]) -> Result<RegistersRead, Error> {
+		check_status(Self::ata_do_out(self, regs, data)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn regs(status: u8, error: u8) -> RegistersRead {
+		RegistersRead {
+			error,
+			sector_count: 0,
+			sector: 0x10,
+			cyl_low: 0x20,
+			cyl_high: 0x30,
+			device: 0x40,
+			status,
+			ext: None,
+		}
+	}
+
+	#[test]
+	fn check_status_passes_success() {
+		// error register is meaningless without ERR
+		match check_status(regs(0x50, 0x04)) {
+			Ok(regs) => assert_eq!(regs.lba(), 0x302010),
+			Err(err) => panic!("unexpected error: {:?}", err),
+		}
+	}
+
+	#[test]
+	fn check_status_abort() {
+		match check_status(regs(0x51, 0x04)) {
+			Err(Error::Aborted(regs)) => assert_eq!(regs.error, 0x04),
+			other => panic!("expected Aborted, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn check_status_device_error() {
+		// UNC, IDNF
+		for &error in &[0x40, 0x10] {
+			match check_status(regs(0x51, error)) {
+				Err(Error::Device(regs)) => assert_eq!(regs.lba(), 0x302010),
+				other => panic!("expected Device, got {:?}", other),
+			}
+		}
+	}
+
+	#[test]
+	fn error_registers() {
+		assert!(Error::Aborted(regs(0x51, 0x04)).registers().is_some());
+		assert!(Error::Timeout.registers().is_none());
+	}
+
+	#[test]
+	fn read_log_pages_falls_back_to_pio() {
+		let page = vec![0xaa; 512];
+		let mut dump = ::ata::dump::Dump::new();
+		// READ LOG DMA EXT has no reply, and is aborted
+		dump.record(&read_log_regs(Command::ReadLogExt, 0x03, 0, 1), &regs(0x50, 0), &page);
+
+		let mut dma = true;
+		assert_eq!(dump.read_log_pages(&mut dma, 0x03, 0, 1).unwrap(), page);
+		assert!(!dma);
+	}
+
+	#[test]
+	fn read_log_pages_protocol() {
+		let page = vec![0xaa; 512];
+		let mut dump = ::ata::dump::Dump::new();
+		dump.record(&read_log_regs(Command::ReadLogDmaExt, 0x03, 0, 1), &regs(0x50, 0), &page);
+
+		let mut dma = true;
+		assert_eq!(dump.read_log_pages(&mut dma, 0x03, 0, 1).unwrap(), page);
+		assert!(dma);
+		// devices that do not support READ LOG DMA EXT never get it
+		let mut dma = false;
+		assert!(dump.read_log_pages(&mut dma, 0x03, 0, 1).is_err());
+	}
+	#[test]
+	fn device_statistics_skips_failing_pages() {
+		let mut list = vec![0; 512];
+		list[0] = 0x01;
+		list[8] = 3;
+		list[9..12].copy_from_slice(&[0x00, 0x01, 0xff]);
+		let mut general = vec![0; 512];
+		general[..3].copy_from_slice(&[0x01, 0x00, 0x01]);
+		// Power-On Hours: supported, valid
+		general[0x10..0x18].copy_from_slice(&[0x10, 0x27, 0, 0, 0, 0, 0, 0xc0]);
+
+		let mut dump = ::ata::dump::Dump::new();
+		dump.record(&read_log_regs(Command::ReadLogExt, 0x04, 0, 1), &regs(0x50, 0), &list);
+		dump.record(&read_log_regs(Command::ReadLogExt, 0x04, 1, 1), &regs(0x50, 0), &general);
+		// vendor-specific page FFh is listed, but READ LOG EXT of it is aborted
+
+		let stats = dump.get_device_statistics().unwrap().unwrap();
+		assert_eq!(stats.supported_pages, vec![0x01, 0xff]);
+		assert_eq!(stats.pages.len(), 1);
+		assert_eq!(stats.power_on_hours(), Some(10000));
+	}
+
+	#[test]
+	fn security_protocol_out_length_limit() {
+		let dump = ::ata::dump::Dump::new();
+		// dump aborts every data-out command, so getting that far means the length was accepted
+		match dump.security_protocol_out(0x01, 0, &[0; 255 * 512]) {
+			Err(Error::Aborted(_)) => (),
+			other => panic!("expected Aborted, got {:?}", other),
+		}
+		// padded to 256 blocks
+		match dump.security_protocol_out(0x01, 0, &[0; 255 * 512 + 1]) {
+			Err(Error::InvalidArgument(_)) => (),
+			other => panic!("expected InvalidArgument, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn download_microcode_segment_limit() {
+		let dump = ::ata::dump::Dump::new();
+		match dump.download_microcode(DownloadMicrocodeMode::Offsets, 0, &[0; 255 * 512]) {
+			Err(Error::Aborted(_)) => (),
+			other => panic!("expected Aborted, got {:?}", other),
+		}
+		match dump.download_microcode(DownloadMicrocodeMode::Offsets, 0, &[0; 256 * 512]) {
+			Err(Error::InvalidArgument(_)) => (),
+			other => panic!("expected InvalidArgument, got {:?}", other),
+		}
 	}
 }
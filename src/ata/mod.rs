@@ -8,24 +8,85 @@ All things ATA.
 
 pub mod data;
 pub mod misc;
+pub mod dump;
 
 use Direction;
 use scsi::{self, SCSIDevice, SCSICommon};
 
+use std::fmt;
+
 #[derive(Debug, Clone, Copy)]
 pub enum Command {
 	Identify = 0xec,
+	IdentifyPacket = 0xa1,
 	SMART = 0xb0,
+	ReadLogExt = 0x2f,
+	ReadLogDmaExt = 0x47,
+	TrustedReceive = 0x5c,
+	TrustedSend = 0x5e,
+	DownloadMicrocode = 0x92,
+	Idle = 0xe3,
+	CheckPowerMode = 0xe5,
+	SetFeatures = 0xef,
+	ReadVerifySectors = 0x40,
+	ReadVerifySectorsExt = 0x42,
 }
+// commands that transfer data with DMA protocol (see ACS-4, 7 Command descriptions), whether this crate issues them or not: raw commands from users are sent through the same path
+const DMA_COMMANDS: [u8; 16] = [
+	0x06, // DATA SET MANAGEMENT
+	0x25, // READ DMA EXT
+	0x2a, // READ STREAM DMA EXT
+	0x35, // WRITE DMA EXT
+	0x3a, // WRITE STREAM DMA EXT
+	0x3d, // WRITE DMA FUA EXT
+	Command::ReadLogDmaExt as u8,
+	0x57, // WRITE LOG DMA EXT
+	0x5d, // TRUSTED RECEIVE DMA
+	0x5f, // TRUSTED SEND DMA
+	0x93, // DOWNLOAD MICROCODE DMA
+	0xc8, // READ DMA
+	0xca, // WRITE DMA
+	0xe9, // READ BUFFER DMA
+	0xeb, // WRITE BUFFER DMA
+	0xee, // IDENTIFY DEVICE DMA (obsolete)
+];
+
 #[derive(Debug, Clone, Copy)]
 pub enum SMARTFeature {
 	ReadValues = 0xd0, // in ATA8-ACS it's called 'SMART READ DATA', which is a bit unclear to people not familiar with ATA… or sometimes even to some who knows ATA well
 	ReadThresholds = 0xd1,
+	AttributeAutosave = 0xd2,
+	ExecuteOfflineImmediate = 0xd4,
+	ReadLog = 0xd5,
 	ReturnStatus = 0xda,
+	AutoOffline = 0xdb, // vendor-specific in ATA-4 and later, yet widely supported
+}
+
+/// Self-tests SMART EXECUTE OFF-LINE IMMEDIATE can start; these run in off-line mode, i.e. the device keeps executing other commands in the meantime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelfTestKind {
+	Short = 0x01,
+	Extended = 0x02,
+	/// Checks for damage the device might have incurred during transportation
+	Conveyance = 0x03,
+}
+
+/// Subcommands of DOWNLOAD MICROCODE
+#[derive(Debug, Clone, Copy)]
+pub enum DownloadMicrocodeMode {
+	/// Download microcode in segments (see `download_microcode_{min,max}` from [`Id`](data/id/struct.Id.html)), activate it and save for future use.
+	Offsets = 0x03,
+	/// Download microcode in one go, activate it and save for future use.
+	Full = 0x07,
+	/// Download microcode in segments for activation with `Activate`.
+	OffsetsDeferred = 0x0e,
+	/// Activate microcode previously downloaded with `OffsetsDeferred`.
+	Activate = 0x0f,
 }
 
 // data port is omitted for obvious reasons
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
 pub struct RegistersRead {
 	pub error: u8,
 
@@ -37,6 +98,9 @@ pub struct RegistersRead {
 	pub device: u8, // lba (most significant bits); aka drive/head, device/head, select
 
 	pub status: u8,
+
+	/// Previous content of the registers, only returned for 48-bit commands (that is, if `RegistersWrite.ext` was `Some()`)
+	pub ext: Option<RegistersReadExt>,
 }
 #[derive(Debug)]
 pub struct RegistersWrite {
@@ -50,16 +114,144 @@ pub struct RegistersWrite {
 	pub device: u8,
 
 	pub command: u8,
+
+	/// Previous content of the registers (aka HOB, high order bytes), for 48-bit commands like READ LOG EXT; `None` for 28-bit commands
+	pub ext: Option<RegistersWriteExt>,
+}
+
+/// Bits 15:8 of the count, and bits 47:24 of the LBA
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct RegistersReadExt {
+	pub sector_count: u8,
+
+	pub sector: u8, // lba 31:24
+	pub cyl_low: u8, // lba 39:32
+	pub cyl_high: u8, // lba 47:40
+}
+/// Bits 15:8 of the features and the count, and bits 47:24 of the LBA
+#[derive(Debug, Clone, Copy)]
+pub struct RegistersWriteExt {
+	pub features: u8,
+
+	pub sector_count: u8,
+
+	pub sector: u8, // lba 31:24
+	pub cyl_low: u8, // lba 39:32
+	pub cyl_high: u8, // lba 47:40
+}
+
+impl RegistersRead {
+	/// Whether the device reports that the command ended with an error (status bit ERR), in which case `error` register describes what went wrong.
+	pub fn is_err(&self) -> bool {
+		self.status & 0x01 != 0
+	}
+
+	/// COUNT field, including bits 15:8 for 48-bit commands.
+	pub fn count(&self) -> u16 {
+		match self.ext {
+			Some(ext) => ((ext.sector_count as u16) << 8) + self.sector_count as u16,
+			None => self.sector_count as u16,
+		}
+	}
+
+	/// LBA field: 48 bits for 48-bit commands, or 28 bits (with bits 27:24 taken from the DEVICE field) otherwise.
+	///
+	/// For commands that fail while accessing the media, this is typically the address of the first sector that caused the error.
+	pub fn lba(&self) -> u64 {
+		let low = (self.cyl_high as u64) << 16 | (self.cyl_low as u64) << 8 | self.sector as u64;
+		match self.ext {
+			Some(ext) => (ext.cyl_high as u64) << 40 | (ext.cyl_low as u64) << 32 | (ext.sector as u64) << 24 | low,
+			None => (self.device as u64 & 0x0f) << 24 | low,
+		}
+	}
+}
+
+/// Bits of the Error register
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorBits(pub u8);
+
+// see ACS-3, 6.3 ERROR field; bits that are obsolete now are still named here for the sake of older devices
+const ERROR_BITS: [(u8, &str); 8] = [
+	(1 << 7, "ICRC"), // interface CRC error
+	(1 << 6, "UNC"), // uncorrectable data
+	(1 << 5, "MC"), // media changed (obsolete)
+	(1 << 4, "IDNF"), // ID not found
+	(1 << 3, "MCR"), // media change request (obsolete)
+	(1 << 2, "ABRT"), // command aborted
+	(1 << 1, "NM"), // no media (obsolete) or end of media
+	(1 << 0, "AMNF"), // address mark not found (obsolete)
+];
+
+impl ErrorBits {
+	/// Names of the bits that are set, e.g. `["ABRT"]`
+	pub fn names(&self) -> Vec<&'static str> {
+		ERROR_BITS.iter()
+			.filter(|&&(bit, _)| self.0 & bit != 0)
+			.map(|&(_, name)| name)
+			.collect()
+	}
+
+	/// ABRT: command was aborted, typically because the device does not support it or rejects its arguments
+	pub fn abort(&self) -> bool { self.0 & (1 << 2) != 0 }
+	/// IDNF: requested address is out of range, or cannot be found
+	pub fn id_not_found(&self) -> bool { self.0 & (1 << 4) != 0 }
+	/// UNC: data is uncorrectable
+	pub fn uncorrectable(&self) -> bool { self.0 & (1 << 6) != 0 }
+	/// ICRC: transfer failed due to interface CRC error
+	pub fn interface_crc(&self) -> bool { self.0 & (1 << 7) != 0 }
+}
+
+impl fmt::Display for ErrorBits {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let names = self.names();
+		if names.is_empty() {
+			write!(f, "0x{:02x}", self.0)
+		} else {
+			write!(f, "0x{:02x} ({})", self.0, names.join(", "))
+		}
+	}
+}
+
+impl RegistersWrite {
+	/// Registers for the SMART command with the given `feature` (subcommand). LBA mid and LBA high are set to 4Fh and C2h, the signature every SMART command expects.
+	///
+	/// `sector` and `sector_count` mean whatever `feature` makes of them: e.g. log address and the number of pages for `ReadLog`, or F1h (enable) and 00h (disable) for `AttributeAutosave`; `ReadValues` and `ReadThresholds` transfer a single block.
+	pub fn smart(feature: SMARTFeature, sector: u8, sector_count: u8) -> Self {
+		RegistersWrite {
+			command: Command::SMART as u8,
+			features: feature as u8,
+			sector_count,
+			sector,
+			cyl_low: 0x4f,
+			cyl_high: 0xc2,
+			device: 0,
+			ext: None,
+		}
+	}
+
+	// whether the command transfers data using DMA rather than PIO, which is something transports need to know beforehand
+	pub(crate) fn is_dma(&self) -> bool {
+		DMA_COMMANDS.contains(&self.command)
+	}
+
+	// number of 512-byte blocks data-in and data-out commands are expected to transfer
+	pub(crate) fn blocks(&self) -> usize {
+		let ext = self.ext.map(|ext| ext.sector_count).unwrap_or(0);
+		((ext as usize) << 8) + self.sector_count as usize
+	}
 }
 
 #[derive(Debug)]
 pub struct ATADevice<T> {
 	device: T,
+	// only makes sense for `ATADevice<SCSIDevice>`: whether to use ATA PASS-THROUGH (12) instead of (16)
+	cdb12: bool,
 }
 
 impl<T> ATADevice<T> {
 	pub fn new(device: T) -> Self {
-		Self { device }
+		Self { device, cdb12: false }
 	}
 }
 
@@ -84,6 +276,23 @@ macro_rules! ata_do { ($Err:ty) => {
 		}
 		ret
 	}
+
+	/// Same as `ata_do()`, but sends `data` to the device.
+	pub fn ata_do_out(&self, regs: &::ata::RegistersWrite, data: &[u8]) -> Result<::ata::RegistersRead, $Err> {
+		info!("issuing cmd: dir={:?} regs={:?}", Direction::To, regs);
+		debug!("cmd data: {}", ::utils::hexdump_8(data));
+
+		let ret = Self::ata_platform_do_out(self, regs, data);
+		match &ret {
+			Ok(regs) => {
+				debug!("cmd reply: regs={:?}", regs);
+			},
+			err => {
+				debug!("cmd error: {:?}", err);
+			},
+		}
+		ret
+	}
 } }
 
 /*
@@ -100,9 +309,25 @@ mod freebsd;
 pub use self::freebsd::*;
 
 impl ATADevice<SCSIDevice> {
+	/// Same as `new()`, but issues ATA commands using ATA PASS-THROUGH (12) instead of (16). Some SAT implementations (e.g. USB bridges) only support the former.
+	pub fn new_12(device: SCSIDevice) -> Self {
+		Self { device, cdb12: true }
+	}
+
 	ata_do!(scsi::ATAError);
 	fn ata_platform_do(&self, dir: Direction, regs: &RegistersWrite) -> Result<(RegistersRead, Vec<u8>), scsi::ATAError> {
-		self.device.ata_pass_through_16(dir, regs)
+		if self.cdb12 {
+			self.device.ata_pass_through_12(dir, regs)
+		} else {
+			self.device.ata_pass_through_16(dir, regs)
+		}
+	}
+	fn ata_platform_do_out(&self, regs: &RegistersWrite, data: &[u8]) -> Result<RegistersRead, scsi::ATAError> {
+		if self.cdb12 {
+			self.device.ata_pass_through_12_out(regs, data)
+		} else {
+			self.device.ata_pass_through_16_out(regs, data)
+		}
 	}
 
 	/// Return the wrapped device. Useful in cases when ATA PASS-THROUGH is used to determine whether this is an ATA device or not.
@@ -110,3 +335,30 @@ impl ATADevice<SCSIDevice> {
 		self.device
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn smart_registers() {
+		let regs = RegistersWrite::smart(SMARTFeature::ReadLog, 0x06, 2);
+		assert_eq!(regs.command, 0xb0);
+		assert_eq!(regs.features, 0xd5);
+		// log address, number of pages
+		assert_eq!((regs.sector, regs.sector_count), (0x06, 2));
+		// signature
+		assert_eq!((regs.cyl_low, regs.cyl_high), (0x4f, 0xc2));
+		assert_eq!(regs.device, 0);
+		assert!(regs.ext.is_none());
+		assert_eq!(regs.blocks(), 2);
+	}
+
+	#[test]
+	fn smart_registers_without_data() {
+		let regs = RegistersWrite::smart(SMARTFeature::ExecuteOfflineImmediate, SelfTestKind::Short as u8, 0);
+		assert_eq!((regs.features, regs.sector, regs.sector_count), (0xd4, 0x01, 0));
+		assert_eq!((regs.cyl_low, regs.cyl_high), (0x4f, 0xc2));
+		assert_eq!(regs.blocks(), 0);
+	}
+}
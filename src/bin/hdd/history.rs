@@ -0,0 +1,230 @@
+/*!
+Attribute history that persists across runs (`attrs --state-file`), for alerting on how fast error counters grow rather than on their absolute values.
+
+The state file is a JSON object that maps drive serial numbers to raw values of their attributes, each kept as a list of `[timestamp, raw]` samples (timestamps are seconds since the Unix epoch):
+
+```json
+{"drives": {"Z1D2ABCD": {"updated": 1500000000, "attributes": {"5": [[1499990000, 8], [1500000000, 16]]}}}}
+```
+
+Every run appends current values, and then drops whatever the retention policy says should go:
+- samples older than [`MAX_AGE`](constant.MAX_AGE.html),
+- drives that were not seen for as long (e.g. replaced ones),
+- and, if an attribute still has more than [`MAX_SAMPLES`](constant.MAX_SAMPLES.html) samples (say, the tool runs every minute), every other sample from the older half of its history, so that long-term rates could still be computed.
+
+Only counters that are not supposed to grow at all on a healthy drive (reallocated and pending sectors, uncorrectable errors and such) are evaluated, but every attribute with a plain counter for its raw value is recorded.
+*/
+
+use hdd::ata::data::attr::SmartAttribute;
+
+use serde_json::{self, Map, Value};
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Samples older than this many seconds (180 days) are dropped.
+pub const MAX_AGE: u64 = 180 * 86400;
+
+/// Upper limit on the number of samples kept for an attribute.
+pub const MAX_SAMPLES: usize = 1000;
+
+/// Length of the window recent growth rate is computed over, in seconds (7 days).
+pub const RECENT: u64 = 7 * 86400;
+
+// attributes that count errors and remapped sectors
+const TRACKED: [u8; 9] = [
+	5,   // Reallocated_Sector_Ct
+	10,  // Spin_Retry_Count
+	184, // End-to-End_Error
+	187, // Reported_Uncorrect
+	188, // Command_Timeout
+	196, // Reallocated_Event_Count
+	197, // Current_Pending_Sector
+	198, // Offline_Uncorrectable
+	199, // UDMA_CRC_Error_Count
+];
+
+#[derive(Debug)]
+pub struct Trend {
+	pub id: u8,
+	pub name: Option<String>,
+	pub current: u64,
+	/// Growth since the previous run, and seconds passed since then
+	pub growth: Option<(u64, u64)>,
+	/// Average growth per day over the whole history
+	pub rate: Option<f64>,
+	/// Average growth per day over the last [`RECENT`](constant.RECENT.html) seconds
+	pub recent_rate: Option<f64>,
+	/// Whether the counter grows notably faster lately than it used to
+	pub accelerating: bool,
+}
+
+pub fn now() -> u64 {
+	// unwrap: the clock is not set before 1970, hopefully
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+// missing file is the same as an empty one: there's no history yet
+fn load(path: &str) -> Result<Map<String, Value>, String> {
+	let file = match File::open(path) {
+		Ok(file) => file,
+		Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Map::new()),
+		Err(err) => return Err(err.to_string()),
+	};
+	match serde_json::from_reader(BufReader::new(file)) {
+		Ok(Value::Object(state)) => Ok(state),
+		Ok(_) => Err("not a JSON object".to_string()),
+		Err(err) => Err(err.to_string()),
+	}
+}
+
+// writes the file next to the old one first, so that an interrupted run does not leave truncated state behind
+fn save(path: &str, state: &Map<String, Value>) -> Result<(), String> {
+	let tmp = format!("{}.tmp", path);
+	{
+		let file = File::create(&tmp).map_err(|err| err.to_string())?;
+		serde_json::to_writer(&mut BufWriter::new(file), state).map_err(|err| err.to_string())?;
+	}
+	fs::rename(&tmp, path).map_err(|err| err.to_string())
+}
+
+fn parse_samples(value: &Value) -> Vec<(u64, u64)> {
+	value.as_array().map(|samples| samples.iter().filter_map(|sample| {
+		let sample = sample.as_array()?;
+		match (sample.first().and_then(|t| t.as_u64()), sample.get(1).and_then(|v| v.as_u64())) {
+			(Some(t), Some(v)) => Some((t, v)),
+			_ => None,
+		}
+	}).collect()).unwrap_or_default()
+}
+
+fn retain(mut samples: Vec<(u64, u64)>, now: u64) -> Vec<(u64, u64)> {
+	samples.retain(|&(t, _)| t + MAX_AGE >= now);
+	while samples.len() > MAX_SAMPLES {
+		let half = samples.len() / 2;
+		// the most recent samples matter the most, so thin the older half out
+		let mut i = 0;
+		samples.retain(|_| { i += 1; i > half || i % 2 == 1 });
+	}
+	samples
+}
+
+fn per_day(from: (u64, u64), to: (u64, u64)) -> Option<f64> {
+	let (t0, v0) = from;
+	let (t1, v1) = to;
+	// rates over intervals shorter than a day are nothing but noise
+	if t1 < t0 + 86400 {
+		return None;
+	}
+	Some(v1.saturating_sub(v0) as f64 * 86400. / (t1 - t0) as f64)
+}
+
+// `samples` are ordered by time, and end with the current one
+fn evaluate(attr: &SmartAttribute, samples: &[(u64, u64)]) -> Option<Trend> {
+	let &current = samples.last()?;
+	let oldest = samples[0];
+
+	let growth = if samples.len() >= 2 {
+		let (t, v) = samples[samples.len() - 2];
+		Some((current.1.saturating_sub(v), current.0.saturating_sub(t)))
+	} else { None };
+
+	// the most recent sample that is at least RECENT seconds old, so that the window is fully covered
+	let window_start = samples.iter().rev()
+		.find(|&&(t, _)| t + RECENT <= current.0)
+		.cloned();
+
+	let rate = per_day(oldest, current);
+	let recent_rate = window_start.and_then(|start| per_day(start, current));
+
+	// only makes sense if the history goes farther back than the recent window
+	let accelerating = match (rate, recent_rate, window_start) {
+		(Some(rate), Some(recent), Some(start)) => start != oldest && recent > 0. && recent > rate * 2.,
+		_ => false,
+	};
+
+	Some(Trend {
+		id: attr.id,
+		name: attr.name.clone(),
+		current: current.1,
+		growth,
+		rate,
+		recent_rate,
+		accelerating,
+	})
+}
+
+/**
+Records current attribute values of the drive with the `serial` number in the state file at `path`, and returns trends of tracked error counters.
+
+If the state file cannot be read (e.g. it is not JSON), it is left as is, so that a typo in the path would not wipe earlier history out.
+*/
+pub fn update(path: &str, serial: &str, attrs: &[SmartAttribute], now: u64) -> Result<Vec<Trend>, String> {
+	let mut state = load(path)?;
+
+	let mut drives = match state.remove("drives") {
+		Some(Value::Object(drives)) => drives,
+		_ => Map::new(),
+	};
+	let mut drive = match drives.remove(serial) {
+		Some(Value::Object(drive)) => drive,
+		_ => Map::new(),
+	};
+	let mut history = match drive.remove("attributes") {
+		Some(Value::Object(history)) => history,
+		_ => Map::new(),
+	};
+
+	let mut trends = vec![];
+	for attr in attrs {
+		let raw = match attr.raw.count() {
+			Some(raw) => raw,
+			None => continue,
+		};
+		let key = attr.id.to_string();
+
+		let mut samples = history.get(&key).map(parse_samples).unwrap_or_default();
+		// clock went backwards? samples from the future would only confuse rates
+		samples.retain(|&(t, _)| t < now);
+		samples.push((now, raw));
+		let samples = retain(samples, now);
+
+		if TRACKED.contains(&attr.id) {
+			trends.extend(evaluate(attr, &samples));
+		}
+
+		history.insert(key, Value::Array(samples.into_iter()
+			.map(|(t, v)| Value::Array(vec![Value::from(t), Value::from(v)]))
+			.collect()));
+	}
+
+	drive.insert("updated".to_string(), Value::from(now));
+	drive.insert("attributes".to_string(), Value::Object(history));
+	drives.insert(serial.to_string(), Value::Object(drive));
+
+	// forget drives that were not seen for too long
+	let drives = drives.into_iter()
+		.filter(|(_, drive)| drive.get("updated").and_then(|t| t.as_u64()).map(|t| t + MAX_AGE >= now).unwrap_or(false))
+		.collect();
+	state.insert("drives".to_string(), Value::Object(drives));
+
+	save(path, &state)?;
+	Ok(trends)
+}
+
+/// Message to warn about the trend with, if there's anything to warn about.
+pub fn alert(trend: &Trend) -> Option<String> {
+	let name = trend.name.as_deref().unwrap_or("?");
+	if trend.accelerating {
+		// unwraps: acceleration is only reported if both rates are known
+		Some(format!("attribute {} ({}) grows faster than before: {:.2}/day over the last {} days, {:.2}/day overall",
+			trend.id, name, trend.recent_rate.unwrap(), RECENT / 86400, trend.rate.unwrap()))
+	} else {
+		match trend.growth {
+			Some((growth, _)) if growth > 0 =>
+				Some(format!("attribute {} ({}) grew by {} since the previous run, now at {}", trend.id, name, growth, trend.current)),
+			_ => None,
+		}
+	}
+}
@@ -0,0 +1,59 @@
+/*!
+Graceful handling of Ctrl-C (SIGINT) and SIGTERM in long-running subcommands (`scan-surface`, `self-test --wait`).
+
+Once [`install()`](fn.install.html) is called, the first signal only raises a flag that these subcommands check between commands, so that the command in flight completes, and whatever was found so far is still reported:
+- `scan-surface` stops after the current chunk of sectors, and lists unreadable sectors found before that, along with the range that was actually scanned;
+- `self-test --wait` aborts the self-test if it was started by the same invocation (one started elsewhere is left running), and shows the self-test log.
+
+Either way, the exit status has the "command failed" bit set, as the operation was not completed. The second signal terminates the tool right away, as usual.
+
+Other subcommands do not install the handler, as they finish quickly enough, and have nothing to clean up.
+*/
+
+use libc;
+
+use std::cmp;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[allow(unsafe_code)]
+extern "C" fn handle(signal: libc::c_int) {
+	INTERRUPTED.store(true, Ordering::SeqCst);
+	// let the next one through, in case the command we wait for never returns
+	unsafe { libc::signal(signal, libc::SIG_DFL); }
+}
+
+/// Makes SIGINT and SIGTERM set the flag that [`interrupted()`](fn.interrupted.html) returns.
+#[allow(unsafe_code)]
+pub fn install() {
+	let handler: extern "C" fn(libc::c_int) = handle;
+	let handler = handler as libc::sighandler_t;
+	unsafe {
+		libc::signal(libc::SIGINT, handler);
+		libc::signal(libc::SIGTERM, handler);
+	}
+}
+
+/// Whether the user asked the tool to stop.
+pub fn interrupted() -> bool {
+	INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Same as `thread::sleep()`, but wakes up early if interrupted; returns `false` if so.
+pub fn sleep(duration: Duration) -> bool {
+	// `thread::sleep()` itself resumes sleeping after signals, hence the steps
+	let step = Duration::from_secs(1);
+	let mut left = duration;
+	while left > Duration::from_secs(0) {
+		if interrupted() {
+			return false;
+		}
+		let now = cmp::min(left, step);
+		thread::sleep(now);
+		left -= now;
+	}
+	!interrupted()
+}
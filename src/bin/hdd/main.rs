@@ -17,9 +17,11 @@ extern crate hdd;
 
 use hdd::{device, Device};
 use hdd::scsi::SCSIDevice;
-use hdd::ata::ATADevice;
+use hdd::ata::{ATADevice, ErrorBits, RegistersRead};
+use hdd::ata::dump::Dump;
 
 use hdd::ata::data::id;
+use hdd::ata::data::power_mode::PowerMode;
 use hdd::drivedb;
 use hdd::ata::misc::{self, Misc};
 use hdd::scsi::ATAError;
@@ -30,32 +32,118 @@ use clap::{
 	App,
 	AppSettings,
 	Arg,
+	ArgMatches,
 	Values,
 };
 
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 extern crate serde_json;
 extern crate separator;
 extern crate number_prefix;
 extern crate prettytable;
 
+#[macro_use]
 extern crate log;
 extern crate env_logger;
 use log::LevelFilter;
 use env_logger::Builder as LogBuilder;
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[macro_use]
 extern crate lazy_static;
+extern crate libc;
 mod subcommands;
-use subcommands::SUBCOMMANDS;
+use subcommands::{Subcommand, SUBCOMMANDS};
+use status::{Outcome, Failure};
+mod status;
+mod tolerance;
+mod history;
+mod interrupt;
+#[cfg(test)]
+mod mock;
+
+/**
+Runs `action` if S.M.A.R.T. is enabled, complaining to stderr otherwise.
+
+With `json`, the reason is also printed to stdout as `{"error": "smart_unsupported"}` or `{"error": "smart_disabled"}`, so that consumers don't end up with no output at all.
+*/
+pub fn when_smart_enabled<F>(status: &id::Ternary, action_name: &str, json: bool, action: F) -> Result<Outcome, Failure> where F: FnOnce() -> Result<Outcome, Failure> {
+	let error = match status {
+		id::Ternary::Unsupported => {
+			eprint!("S.M.A.R.T. is not supported, cannot show {}\n", action_name);
+			"smart_unsupported"
+		},
+		id::Ternary::Disabled => {
+			eprint!("S.M.A.R.T. is disabled, cannot show {}\n", action_name);
+			"smart_disabled"
+		},
+		id::Ternary::Enabled => return action(),
+	};
+	if json {
+		print_json_error(error);
+	}
+	Err(Failure::reported(status::SMART_UNAVAILABLE))
+}
+
+/**
+Serializes `value` into a JSON string.
 
-pub fn when_smart_enabled<F>(status: &id::Ternary, action_name: &str, mut action: F) where F: FnMut() -> () {
-	match status {
-		id::Ternary::Unsupported => eprint!("S.M.A.R.T. is not supported, cannot show {}\n", action_name),
-		id::Ternary::Disabled => eprint!("S.M.A.R.T. is disabled, cannot show {}\n", action_name),
-		id::Ternary::Enabled => action(),
+This goes through `serde_json::Value` instead of serializing `value` directly, so that keys of every object come out sorted, regardless of the order the fields are declared in; that's how the output always looked like, and there might be consumers that rely on that.
+*/
+pub fn to_json_string<T: serde::Serialize>(value: &T) -> String {
+	// unwraps: nothing we serialize has non-string map keys
+	serde_json::to_string(&serde_json::to_value(value).unwrap()).unwrap()
+}
+
+/// Prints `{"error": error}`, for when JSON output is requested but there's nothing else to show.
+pub fn print_json_error(error: &str) {
+	let mut obj = serde_json::Map::new();
+	obj.insert("error".to_string(), serde_json::Value::String(error.to_string()));
+	print!("{}\n", serde_json::to_string(&obj).unwrap());
+}
+
+/**
+Registers returned by a device that reported an error, along with decoded error bits, e.g. `{"status": 81, "error": 4, "error_bits": ["ABRT"], "count": 0, "lba": 0, "device": 0}`.
+
+These look the same whether the device is talked to directly or through SAT (which returns registers in the sense data), so that failures are equally diagnosable either way.
+*/
+pub fn ata_registers_json(regs: &RegistersRead) -> serde_json::Value {
+	let mut obj = serde_json::Map::new();
+	obj.insert("status".to_string(), serde_json::to_value(regs.status).unwrap());
+	obj.insert("error".to_string(), serde_json::to_value(regs.error).unwrap());
+	obj.insert("error_bits".to_string(), serde_json::to_value(ErrorBits(regs.error).names()).unwrap());
+	obj.insert("count".to_string(), serde_json::to_value(regs.count()).unwrap());
+	obj.insert("lba".to_string(), serde_json::to_value(regs.lba()).unwrap());
+	obj.insert("device".to_string(), serde_json::to_value(regs.device).unwrap());
+	serde_json::Value::Object(obj)
+}
+
+/// Same as [`print_json_error()`](fn.print_json_error.html), but for failed commands: also includes the message, and registers if it's the device that reported the error (see [`ata_registers_json()`](fn.ata_registers_json.html)).
+pub fn print_json_command_error(error: &str, err: &misc::Error) {
+	let mut obj = serde_json::Map::new();
+	obj.insert("error".to_string(), serde_json::Value::String(error.to_string()));
+	obj.insert("message".to_string(), serde_json::Value::String(err.to_string()));
+	if let Some(regs) = err.registers() {
+		obj.insert("registers".to_string(), ata_registers_json(regs));
 	}
+	print!("{}\n", serde_json::to_string(&obj).unwrap());
+}
+
+/// Drivedb warning for the drive, if any, as `{"severity": "critical", "text": "…"}` (see [`classify_warning()`](../hdd/drivedb/fn.classify_warning.html) for severities).
+pub fn drivedb_warning_json(meta: &drivedb::DriveMeta) -> Option<serde_json::Value> {
+	let (warning, severity) = (meta.warning?, meta.warning_severity()?);
+	let mut obj = serde_json::Map::new();
+	obj.insert("severity".to_string(), serde_json::Value::String(format!("{:?}", severity).to_lowercase()));
+	obj.insert("text".to_string(), serde_json::Value::String(warning.clone()));
+	Some(serde_json::Value::Object(obj))
 }
 
 #[allow(non_upper_case_globals)]
@@ -69,8 +157,22 @@ static drivedb_additional_default: [&'static str; 1] = [
 	"/etc/smart_drivedb.h",
 ];
 
-/// Returns concatenated list of entries from main and additional drivedb files, falling back to built-in paths if none were provided.
-pub fn open_drivedb(options: Option<Values>) -> Option<drivedb::DriveDB> {
+#[cfg(feature = "embedded-drivedb")]
+fn load_embedded_drivedb(loader: &mut drivedb::Loader) {
+	match loader.load_embedded() {
+		Ok(()) => eprint!("Using built-in drivedb ({})\n", drivedb::embedded_version().unwrap_or("unknown version")),
+		Err(e) => eprint!("Cannot load built-in drivedb: {}\n", e),
+	}
+}
+#[cfg(not(feature = "embedded-drivedb"))]
+fn load_embedded_drivedb(_: &mut drivedb::Loader) {}
+
+/**
+Splits `-B` values into lists of main and additional drivedb files, falling back to built-in paths if none were provided.
+
+Also returns whether failures to load additional files are worth a warning (they are not for the default additional file).
+*/
+pub fn drivedb_paths<'a>(options: Option<Values<'a>>) -> (Vec<&'a str>, Vec<&'a str>, bool) {
 	let options = options
 		.map(|vals| vals.collect())
 		.unwrap_or_else(|| vec![]);
@@ -99,6 +201,13 @@ pub fn open_drivedb(options: Option<Values>) -> Option<drivedb::DriveDB> {
 		(paths_main, paths_add)
 	};
 
+	(paths_main, paths_add, show_warn_add)
+}
+
+/// Returns concatenated list of entries from main and additional drivedb files, falling back to built-in paths if none were provided.
+pub fn open_drivedb(options: Option<Values>) -> Option<drivedb::DriveDB> {
+	let (paths_main, paths_add, show_warn_add) = drivedb_paths(options);
+
 	let mut loader = drivedb::Loader::new();
 
 	for f in paths_add {
@@ -110,13 +219,17 @@ pub fn open_drivedb(options: Option<Values>) -> Option<drivedb::DriveDB> {
 		}
 	}
 
-	for f in paths_main {
-		match loader.load(f) {
-			Ok(()) => {
-				break; // we only need one 'main' file, the first valid one
-			},
-			Err(e) => eprint!("Cannot open drivedb file {}: {}\n", f, e),
-		}
+	let main_loaded = paths_main.iter().any(|f| match loader.load(f) {
+		Ok(()) => true, // we only need one 'main' file, the first valid one
+		Err(e) => {
+			eprint!("Cannot open drivedb file {}: {}\n", f, e);
+			false
+		},
+	});
+
+	// on-disk files always take precedence, so that users could update their drivedb
+	if !main_loaded {
+		load_embedded_drivedb(&mut loader);
 	}
 
 	// TODO? show regex error to the world
@@ -127,12 +240,12 @@ pub fn open_drivedb(options: Option<Values>) -> Option<drivedb::DriveDB> {
 
 #[cfg(target_os = "linux")]
 arg_enum! {
-	enum Type { Auto, SAT, SCSI }
+	enum Type { Auto, SAT, SAT12, SCSI }
 }
 
 #[cfg(target_os = "freebsd")]
 arg_enum! {
-	enum Type { Auto, ATA, SAT, SCSI }
+	enum Type { Auto, ATA, SAT, SAT12, SCSI }
 }
 
 #[derive(Debug)]
@@ -141,6 +254,356 @@ pub enum DeviceArgument {
 	ATA(ATADevice<Device>, id::Id),
 	SAT(ATADevice<SCSIDevice>, id::Id),
 	SCSI(SCSIDevice),
+	/// Replies saved earlier with the `dump` subcommand (see `--from-dump`)
+	Dump(Dump, id::Id),
+}
+
+impl DeviceArgument {
+	/// Returns ATA interface of the device along with its id, or `None` for pure SCSI devices.
+	///
+	/// This is what subcommands should use to issue ATA commands, so that they don't need to care whether the device is talked to directly or through SAT.
+	pub fn ata(&self) -> Option<(&Misc, &id::Id)> {
+		match self {
+			#[cfg(not(target_os = "linux"))]
+			DeviceArgument::ATA(dev, id) => Some((dev, id)),
+			DeviceArgument::SAT(dev, id) => Some((dev, id)),
+			DeviceArgument::SCSI(_) => None,
+			DeviceArgument::Dump(dev, id) => Some((dev, id)),
+		}
+	}
+
+	fn id_mut(&mut self) -> Option<&mut id::Id> {
+		match self {
+			#[cfg(not(target_os = "linux"))]
+			DeviceArgument::ATA(_, id) => Some(id),
+			DeviceArgument::SAT(_, id) => Some(id),
+			DeviceArgument::SCSI(_) => None,
+			DeviceArgument::Dump(_, id) => Some(id),
+		}
+	}
+}
+
+/**
+Refuses to continue if the device is not going to execute `operation` commands in its current security state.
+
+Devices just abort such commands, without telling why; subcommands call this beforehand to tell users what's wrong instead.
+*/
+pub fn check_security(id: &id::Id, operation: id::SecurityOperation) -> Result<(), Failure> {
+	id.security_state.check(operation)
+		.map_err(|msg| status::fail(format!("Refusing to continue: {}", msg), status::COMMAND_FAILED))
+}
+
+// parses the value of --logical-sector-size or --physical-sector-size, if any
+fn sector_size_arg(args: &ArgMatches, name: &str) -> Option<u32> {
+	args.value_of(name).map(|size| size.parse().unwrap_or_else(|_| {
+		eprint!("Invalid --{}: {}\n", name, size);
+		status::exit(status::USAGE);
+	}))
+}
+
+/**
+Parses `PATH=TYPE` values of `--device-type` into a map of per-device types.
+
+Paths are canonicalized (hence `/dev/disk/by-id/…` symlinks are matched against the device they point to), and must exist.
+*/
+fn parse_device_types(options: Option<Values>) -> Result<HashMap<PathBuf, Type>, String> {
+	let mut types = HashMap::new();
+
+	for option in options.into_iter().flatten() {
+		// split at the last '=', paths are more likely to contain one than type names
+		let (path, dtype) = match option.rfind('=') {
+			Some(pos) => (&option[..pos], &option[pos + 1 ..]),
+			None => return Err(format!("{}: expected PATH=TYPE", option)),
+		};
+
+		let dtype = dtype.parse::<Type>()
+			.map_err(|_| format!("{}: unknown device type {}", option, dtype))?;
+		let path = fs::canonicalize(path)
+			.map_err(|e| format!("{}: {}", path, e))?;
+
+		types.insert(path, dtype);
+	}
+
+	Ok(types)
+}
+
+/// Whether, and what for, [`open_device()`](fn.open_device.html) issues CHECK POWER MODE to ATA devices
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PowerCheck {
+	Off,
+	/// Only to tell the user
+	Report,
+	/// To leave devices in standby alone
+	SkipStandby,
+}
+
+// unpacked right after `open_device()` returns, so there is no point in boxing the device
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+enum Opened {
+	/// Device, along with its power mode at the time it was opened, if checked (and if the device can tell)
+	Device(DeviceArgument, Option<PowerMode>),
+	/// Device in standby that was left alone, as requested with [`PowerCheck::SkipStandby`](enum.PowerCheck.html)
+	Standby(PowerMode),
+}
+
+/**
+Issues CHECK POWER MODE which, unlike pretty much any command subcommands issue, does not wake the device up.
+
+Returns `Err` with the mode if the device is in standby and `check` asks to leave such devices alone, and `Ok(None)` if the power mode was not checked, or the device cannot tell (e.g. SATLs that reject the command).
+*/
+fn check_power_mode(path: &Path, dev: &Misc, check: PowerCheck) -> Result<Option<PowerMode>, PowerMode> {
+	if check == PowerCheck::Off {
+		return Ok(None);
+	}
+	match dev.get_power_mode() {
+		Ok(mode) if check == PowerCheck::SkipStandby && mode.is_standby() => Err(mode),
+		Ok(mode) => Ok(Some(mode)),
+		Err(err) => {
+			info!("{}: cannot check power mode: {}", path.display(), err);
+			Ok(None)
+		},
+	}
+}
+
+/**
+Opens the device and figures out how to talk to it.
+
+Why do we issue ATA IDENTIFY DEVICE here?
+- Device id is what every subcommand uses for one reason or the other, but usually to check whether some feature is supported and enabled.
+- It allows us to distinguish between pure SCSI devices and ATA devices behind SAT by issuing ATA PASS-THROUGH and checking whether this command is supported.
+
+With `Type::Auto`, transports are tried in order (ATA where the OS tells us it's an ATA device; otherwise ATA PASS-THROUGH (16), then ATA PASS-THROUGH (12), then plain SCSI), and the first one that yields device id is used.
+
+IDENTIFY DEVICE spins some drives up, so CHECK POWER MODE (see [`check_power_mode()`](fn.check_power_mode.html)) goes first, over the same transport.
+
+Errors are returned as messages ready to be shown to the user.
+*/
+fn open_device(path: &Path, dtype: &Type, timeout: Option<Duration>, check: PowerCheck) -> Result<Opened, String> {
+	let mut dev = Device::open(path)
+		.map_err(|e| format!("cannot open device: {}", e))?;
+	if let Some(timeout) = timeout {
+		dev.set_timeout(timeout);
+	}
+
+	// evaluates to the power mode, unless the device is to be left alone
+	macro_rules! power_mode { ($dev:expr) => {
+		match check_power_mode(path, &$dev, check) {
+			Ok(mode) => mode,
+			Err(mode) => return Ok(Opened::Standby(mode)),
+		}
+	} }
+
+	Ok(match *dtype {
+		Type::Auto => {
+			match dev.get_type().map_err(|e| format!("cannot determine device type: {}", e))? {
+				device::Type::SCSI => {
+					// check whether devices replies to ATA PASS-THROUGH
+					let satdev = ATADevice::new(SCSIDevice::new(dev));
+					let mode = power_mode!(satdev);
+					match satdev.get_device_id() {
+						// this is really an ATA device
+						Ok(id) => {
+							info!("{}: using ATA PASS-THROUGH (16)", path.display());
+							Opened::Device(DeviceArgument::SAT(satdev, check_id(id)?), mode)
+						},
+						// nnnnope, plain SCSI
+						Err(misc::Error::SCSI(ATAError::NotSupported)) => {
+							info!("{}: ATA PASS-THROUGH is not supported, using SCSI", path.display());
+							Opened::Device(DeviceArgument::SCSI(satdev.unwrap()), None)
+						},
+						// huh? time to contact Houston
+						// TODO? or should we just keep treating devices that return random garbage (Err(ATAError::NoRegisters), weird sense codes &c) as SCSI?
+						/*
+						e => {
+							e.unwrap(); // TODO abort gracefully
+							unreachable!() // we already panicked
+						},
+						*/
+						Err(e) => {
+							info!("{}: ATA PASS-THROUGH (16) failed ({}), trying ATA PASS-THROUGH (12)", path.display(), e);
+							let satdev = ATADevice::new_12(satdev.unwrap());
+							let mode = match mode {
+								Some(mode) => Some(mode),
+								None => power_mode!(satdev),
+							};
+							match satdev.get_device_id() {
+								Ok(id) => {
+									info!("{}: using ATA PASS-THROUGH (12)", path.display());
+									Opened::Device(DeviceArgument::SAT(satdev, check_id(id)?), mode)
+								},
+								Err(e) => {
+									info!("{}: ATA PASS-THROUGH (12) failed ({}), using SCSI", path.display(), e);
+									Opened::Device(DeviceArgument::SCSI(satdev.unwrap()), None)
+								},
+							}
+						},
+					}
+				},
+				#[cfg(not(target_os = "linux"))]
+				device::Type::ATA => {
+					info!("{}: using ATA", path.display());
+					let atadev = ATADevice::new(dev);
+					let mode = power_mode!(atadev);
+					let id = identify(&atadev)?;
+					Opened::Device(DeviceArgument::ATA(atadev, id), mode)
+				},
+			}
+		},
+		#[cfg(target_os = "freebsd")]
+		Type::ATA => {
+			let dev = ATADevice::new(dev);
+			let mode = power_mode!(dev);
+			let id = identify(&dev)?;
+			Opened::Device(DeviceArgument::ATA(dev, id), mode)
+		},
+		Type::SAT => {
+			let dev = ATADevice::new(SCSIDevice::new(dev));
+			let mode = power_mode!(dev);
+			let id = identify(&dev)?;
+			Opened::Device(DeviceArgument::SAT(dev, id), mode)
+		},
+		Type::SAT12 => {
+			let dev = ATADevice::new_12(SCSIDevice::new(dev));
+			let mode = power_mode!(dev);
+			let id = identify(&dev)?;
+			Opened::Device(DeviceArgument::SAT(dev, id), mode)
+		},
+		Type::SCSI => Opened::Device(DeviceArgument::SCSI(SCSIDevice::new(dev)), None),
+	})
+}
+
+/// Opens a file saved with the `dump` subcommand in place of a device.
+fn open_dump(path: &Path) -> Result<DeviceArgument, String> {
+	let dump = Dump::open(path)
+		.map_err(|e| format!("cannot open dump: {}", e))?;
+	let id = identify(&dump)?;
+	Ok(DeviceArgument::Dump(dump, id))
+}
+
+fn id_error(e: misc::Error) -> String {
+	format!("cannot read device identification: {}", e)
+}
+
+/**
+Issues IDENTIFY DEVICE, and checks the reply with [`check_id()`](fn.check_id.html).
+
+With `-T permissive`, failure of the command is not fatal: we carry on with blank device id that claims S.M.A.R.T. is enabled, so that subcommands would at least try to query the device.
+*/
+fn identify(dev: &Misc) -> Result<id::Id, String> {
+	match dev.get_device_id() {
+		Ok(id) => check_id(id),
+		Err(e) => {
+			tolerance::check(&id_error(e), status::COMMAND_FAILED)?;
+			let mut id = id::parse_id(&vec![0; 512]);
+			id.smart = id::Ternary::Enabled;
+			Ok(id)
+		},
+	}
+}
+
+// with `-T permissive`, IDENTIFY DEVICE data that does not match its checksum is used anyway
+fn check_id(id: id::Id) -> Result<id::Id, String> {
+	if id.checksum_valid == Some(false) {
+		tolerance::check("IDENTIFY DEVICE data checksum mismatch", status::COMMAND_FAILED)?;
+	}
+	Ok(id)
+}
+
+// smartctl flags that have a subcommand of their own
+static SMARTCTL_FLAGS: [(&str, &str, &str); 4] = [
+	("-i", "--info", "info"),
+	("-H", "--health", "health"),
+	("-A", "--attributes", "attrs"),
+	("-a", "--all", "report"),
+];
+
+// whether the argument names a device node rather than, say, a value of some option
+fn is_device_node(arg: &OsString) -> bool {
+	use std::os::unix::fs::FileTypeExt;
+	fs::metadata(arg)
+		.map(|meta| meta.file_type().is_block_device() || meta.file_type().is_char_device())
+		.unwrap_or(false)
+}
+
+/**
+Turns smartctl-like invocations (`hdd -H /dev/sda`) into regular ones (`hdd /dev/sda health`), for those who have smartctl flags committed to muscle memory.
+
+As with smartctl, the device goes last, so arguments between the flag and the device belong to the subcommand: `hdd -A --json /dev/sda` becomes `hdd /dev/sda attrs --json`. Arguments before the flag are left where they are, for the options of the tool itself (e.g. `hdd -T permissive -A /dev/sda`). More devices might precede the last one, as long as these are device nodes (otherwise there's no telling them from values of subcommand options, like in `-f brief`).
+
+Only one such flag is recognized, and only if there's no subcommand in the command line already; otherwise arguments are left intact for clap to complain about.
+*/
+fn smartctl_flags(args: Vec<OsString>) -> Vec<OsString> {
+	let mut flag = None;
+	for (i, arg) in args.iter().enumerate().skip(1) {
+		let arg = match arg.to_str() {
+			Some(arg) => arg,
+			None => continue,
+		};
+		if SUBCOMMANDS.contains_key(arg) {
+			return args;
+		}
+		if let Some(&(_, _, subcommand)) = SMARTCTL_FLAGS.iter().find(|&&(short, long, _)| arg == short || arg == long) {
+			if flag.is_some() {
+				return args;
+			}
+			flag = Some((i, subcommand));
+		}
+	}
+
+	let (i, subcommand) = match flag {
+		Some(flag) => flag,
+		None => return args,
+	};
+
+	let is_option = |arg: &OsString| arg.to_str().map(|arg| arg.starts_with('-')).unwrap_or(false);
+	let mut args = args;
+	let subcommand_args = args.split_off(i + 1);
+	args.pop(); // the flag itself
+
+	// the last argument is the device; preceding device nodes are, too
+	let mut devices = subcommand_args.len();
+	if devices > 0 && !is_option(&subcommand_args[devices - 1]) {
+		devices -= 1;
+		while devices > 0 && is_device_node(&subcommand_args[devices - 1]) {
+			devices -= 1;
+		}
+	}
+	let (subcommand_args, devices) = subcommand_args.split_at(devices);
+
+	args.extend_from_slice(devices);
+	args.push(OsString::from(subcommand));
+	args.extend_from_slice(subcommand_args);
+	args
+}
+
+/**
+Runs the subcommand, and returns exit status bits of what it came to.
+
+Whatever happens only concerns the device at hand, panics included (these are reported by the panic hook on stderr as usual): with `json`, failures are printed as JSON error objects in place of the output, so that with multiple devices, the object of every other device is left intact.
+*/
+fn run_subcommand(subcommand: &Subcommand, path: &Option<&Path>, dev: &Option<&DeviceArgument>, args: &ArgMatches, json: bool) -> usize {
+	match panic::catch_unwind(panic::AssertUnwindSafe(|| subcommand.run(path, dev, args))) {
+		Ok(Ok(outcome)) => outcome.bits(),
+		Ok(Err(failure)) => {
+			match failure.message() {
+				Some(msg) if json => print_json_error(msg),
+				_ => (),
+			}
+			failure.bits()
+		},
+		Err(_) => {
+			if json {
+				print_json_error("internal error");
+			}
+			status::COMMAND_FAILED
+		},
+	}
+}
+
+// whether the subcommand prints a single JSON (as opposed to human-readable text or something like Prometheus exposition format)
+fn outputs_json(args: &ArgMatches) -> bool {
+	args.is_present("json") || args.value_of("format") == Some("json")
 }
 
 fn main() {
@@ -166,6 +629,7 @@ fn main() {
 	let args = App::new("hdd")
 		.about("yet another disk querying tool")
 		.version(crate_version!())
+		.after_help("smartctl-like '-i', '-H', '-A' and '-a' (or '--info', '--health', '--attributes' and '--all') can be used in place of 'info', 'health', 'attrs' and 'report' subcommands, e.g. 'hdd -H /dev/sda'")
 		.setting(AppSettings::SubcommandRequired)
 		.subcommands(SUBCOMMANDS.values().map(|&subcommand| subcommand.subcommand()))
 		.arg(Arg::with_name("type")
@@ -173,7 +637,49 @@ fn main() {
 			.long("type")
 			.takes_value(true)
 			.possible_values(type_variants.as_slice())
-			.help("device type")
+			.help("device type (default: auto, which tries ATA, SAT and SAT12 in order, falling back to SCSI)")
+		)
+		.arg(Arg::with_name("device-type")
+			.long("device-type")
+			.takes_value(true)
+			.multiple(true)
+			.number_of_values(1)
+			.value_name("PATH=TYPE")
+			.help("device type for a particular device, overrides '-t' for that device\ndevices without one use '-t' (or auto-detection)")
+		)
+		.arg(Arg::with_name("from-dump")
+			.long("from-dump")
+			.conflicts_with_all(&["type", "device-type"])
+			.help("treat <device> as a file saved with the 'dump' subcommand, and replay replies from it instead of querying actual device")
+		)
+		.arg(Arg::with_name("logical-sector-size")
+			.long("logical-sector-size")
+			.takes_value(true)
+			.value_name("BYTES")
+			.help("use this logical sector size instead of the one ATA device reports (e.g. if it is misreported by a USB bridge), and compute capacity accordingly")
+		)
+		.arg(Arg::with_name("physical-sector-size")
+			.long("physical-sector-size")
+			.takes_value(true)
+			.value_name("BYTES")
+			.help("use this physical sector size instead of the one ATA device reports")
+		)
+		.arg(Arg::with_name("tolerance")
+			.short("T")
+			.long("tolerance")
+			.takes_value(true)
+			.possible_values(&["strict", "permissive"])
+			.help("what to do if a mandatory command (IDENTIFY DEVICE, S.M.A.R.T. READ DATA) fails or its reply fails the checksum\n'strict' (default): give up\n'permissive': warn, and show whatever data is available anyway")
+		)
+		.arg(Arg::with_name("timeout")
+			.long("timeout")
+			.takes_value(true)
+			.value_name("SECONDS")
+			.help("give up on commands that take longer than this (default: 10 seconds)\nraise it for lengthy commands, like scanning large ranges of sectors at once")
+		)
+		.arg(Arg::with_name("wake-standby")
+			.long("wake-standby")
+			.help("with more than one device, probe devices that are in standby too, spinning them up\nby default, these are reported as 'standby (not probed)' and left alone")
 		)
 		.arg(Arg::with_name("debug")
 			.short("d")
@@ -190,11 +696,12 @@ fn main() {
 		  - my guess is you're just interested in disk attributes, in which case you should really be looking into your monitoring system (doesn't matter whether it's local or remote).
 		*/
 		.arg(Arg::with_name("device")
-			.help("Device to query")
+			.help("Device(s) to query\nwith more than one device, output is grouped per device (JSON is keyed by device path), and devices that cannot be opened are reported and skipped")
 			//.required(true) // optional for 'list' subcommand, required for anything else
+			.multiple(true)
 			.index(1)
 		)
-		.get_matches();
+		.get_matches_from(smartctl_flags(std::env::args_os().collect()));
 
 	if let Ok(var) = std::env::var("RUST_LOG") {
 		log.parse(&var);
@@ -210,69 +717,243 @@ fn main() {
 	});
 	log.init();
 
-	let path = args.value_of("device").map(|path| Path::new(path));
-	let dev = path.map(|p| Device::open(p).unwrap());
+	tolerance::set_permissive(args.value_of("tolerance") == Some("permissive"));
+
+	let paths: Vec<&Path> = args.values_of("device")
+		.map(|vals| vals.map(Path::new).collect())
+		.unwrap_or_else(|| vec![]);
 
 	let dtype = args.value_of("type")
 		.unwrap_or("auto")
 		.parse::<Type>().unwrap();
 
+	let dtypes = parse_device_types(args.values_of("device-type")).unwrap_or_else(|err| {
+		eprint!("Invalid --device-type: {}\n", err);
+		status::exit(status::USAGE);
+	});
+
 	let (subcommand, sargs) = args.subcommand();
 	// unwrap() ×2: clap should not allow subcommands that do not exist
 	let subcommand = SUBCOMMANDS.get(subcommand).unwrap();
 	let sargs = sargs.unwrap();
 
-	/*
-	Why do we issue ATA IDENTIFY DEVICE here?
-	- Device id is what every subcommand uses for one reason or the other, but usually to check whether some feature is supported and enabled.
-	- It allows us to distinguish between pure SCSI devices and ATA devices behind SAT by issuing ATA PASS-THROUGH and checking whether this command is supported.
-	*/
+	let from_dump = args.is_present("from-dump");
 
-	let dev = dev.map(|dev| match dtype {
-		Type::Auto => {
-			match dev.get_type().unwrap() {
-				device::Type::SCSI => {
-					// check whether devices replies to ATA PASS-THROUGH
-					let satdev = ATADevice::new(SCSIDevice::new(dev));
-					match satdev.get_device_id() {
-						// this is really an ATA device
-						Ok(id) =>
-							DeviceArgument::SAT(satdev, id),
-						// nnnnope, plain SCSI
-						Err(misc::Error::SCSI(ATAError::NotSupported)) =>
-							DeviceArgument::SCSI(satdev.unwrap()),
-						// huh? time to contact Houston
-						// TODO? or should we just keep treating devices that return random garbage (Err(ATAError::NoRegisters), weird sense codes &c) as SCSI?
-						/*
-						e => {
-							e.unwrap(); // TODO abort gracefully
-							unreachable!() // we already panicked
-						},
-						*/
-						_ => DeviceArgument::SCSI(satdev.unwrap()),
-					}
-				},
-				#[cfg(not(target_os = "linux"))]
-				device::Type::ATA => {
-					let atadev = ATADevice::new(dev);
-					let id = atadev.get_device_id().unwrap();
-					DeviceArgument::ATA(atadev, id)
-				},
-			}
+	let logical_sector_size = sector_size_arg(&args, "logical-sector-size");
+	let physical_sector_size = sector_size_arg(&args, "physical-sector-size");
+
+	let timeout = args.value_of("timeout").map(|secs| match secs.parse() {
+		Ok(secs) if secs > 0 => Duration::from_secs(secs),
+		_ => {
+			eprint!("Invalid --timeout: {}\n", secs);
+			status::exit(status::USAGE);
 		},
-		#[cfg(target_os = "freebsd")]
-		Type::ATA => {
-			let dev = ATADevice::new(dev);
-			let id = dev.get_device_id().unwrap();
-			DeviceArgument::ATA(dev, id)
+	});
+
+	let open = |path: &Path, check: PowerCheck| -> Result<Opened, String> {
+		let mut opened = if from_dump {
+			Opened::Device(open_dump(path)?, None)
+		} else {
+			let dtype = fs::canonicalize(path).ok()
+				.and_then(|p| dtypes.get(&p))
+				.unwrap_or(&dtype);
+			open_device(path, dtype, timeout, check)?
+		};
+
+		if logical_sector_size.is_some() || physical_sector_size.is_some() {
+			if let Opened::Device(ref mut dev, _) = opened {
+				match dev.id_mut() {
+					Some(id) => id.override_sector_size(logical_sector_size, physical_sector_size).unwrap_or_else(|err| {
+						eprint!("Cannot override sector size: {}\n", err);
+						status::exit(status::USAGE);
+					}),
+					None => warn!("{}: sector size can only be overridden for ATA devices", path.display()),
+				}
+			}
+		}
+
+		Ok(opened)
+	};
+
+	let json = outputs_json(sargs);
+
+	match paths.len() {
+		0 => status::set(run_subcommand(*subcommand, &None, &None, sargs, json)),
+		1 => {
+			let path = paths[0];
+			let dev = match open(path, PowerCheck::Off) {
+				Ok(Opened::Device(dev, _)) => dev,
+				Ok(Opened::Standby(_)) => unreachable!(), // power mode is not checked
+				Err(err) => {
+					eprint!("{}: {}\n", path.display(), err);
+					status::exit(status::OPEN_FAILED);
+				},
+			};
+			status::set(run_subcommand(*subcommand, &Some(path), &Some(&dev), sargs, json));
 		},
-		Type::SAT => {
-			let dev = ATADevice::new(SCSIDevice::new(dev));
-			let id = dev.get_device_id().unwrap();
-			DeviceArgument::SAT(dev, id)
+		_ => {
+			// Prometheus metrics already carry device labels, and comments would only get in the way
+			let headers = !json && sargs.value_of("format") != Some("prometheus");
+
+			// waking up every drive of a large array on each monitoring cycle does them no good
+			let check = match (from_dump, args.is_present("wake-standby")) {
+				(true, _) => PowerCheck::Off,
+				(false, true) => PowerCheck::Report,
+				(false, false) => PowerCheck::SkipStandby,
+			};
+
+			// every subcommand prints exactly one JSON value (and a newline) per device, so we only need to glue them together into an object
+			if json { print!("{{"); }
+			for (i, &path) in paths.iter().enumerate() {
+				let opened = open(path, check);
+
+				let (mode, skip) = match opened {
+					Ok(Opened::Device(_, mode)) => (mode, false),
+					Ok(Opened::Standby(mode)) => (Some(mode), true),
+					Err(_) => (None, false),
+				};
+
+				let key = path.to_str().unwrap_or("(invalid UTF-8)");
+				if json {
+					print!("{}{}:", if i > 0 { "," } else { "" }, serde_json::to_string(key).unwrap());
+				} else if headers {
+					print!("{}=== {}{} ===\n",
+						if i > 0 { "\n" } else { "" },
+						path.display(),
+						match mode {
+							Some(mode) if skip => format!(" ({}, not probed)", mode),
+							Some(mode) => format!(" ({})", mode),
+							None => "".to_string(),
+						},
+					);
+				}
+
+				match opened {
+					Ok(Opened::Standby(_)) => {
+						if json {
+							let mut obj = serde_json::Map::new();
+							obj.insert("error".to_string(), serde_json::Value::String("not_probed".to_string()));
+							obj.insert("power_mode".to_string(), serde_json::Value::String("standby".to_string()));
+							print!("{}\n", serde_json::to_string(&obj).unwrap());
+						} else if !headers {
+							eprint!("{}: standby (not probed)\n", path.display());
+						}
+					},
+					Ok(Opened::Device(dev, _)) => status::set(run_subcommand(*subcommand, &Some(path), &Some(&dev), sargs, json)),
+					Err(err) => {
+						eprint!("{}: {}\n", path.display(), err);
+						status::set(status::OPEN_FAILED);
+						if json {
+							print_json_error(&err);
+						}
+					},
+				}
+			}
+			if json { print!("}}\n"); }
 		},
-		Type::SCSI => DeviceArgument::SCSI(SCSIDevice::new(dev)),
-	});
+	}
+
+	::std::process::exit(status::code());
+}
 
-	subcommand.run(&path, &dev.as_ref(), sargs)
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Mock(fn() -> Result<Outcome, Failure>);
+
+	impl Subcommand for Mock {
+		fn subcommand(&self) -> App<'static, 'static> {
+			App::new("mock")
+		}
+		fn run(&self, _: &Option<&Path>, _: &Option<&DeviceArgument>, _: &ArgMatches) -> Result<Outcome, Failure> {
+			(self.0)()
+		}
+	}
+
+	fn run(mock: Mock) -> usize {
+		let args = mock.subcommand().get_matches_from(vec!["mock"]);
+		run_subcommand(&mock, &None, &None, &args, true)
+	}
+
+	#[test]
+	fn outcome_bits() {
+		assert_eq!(run(Mock(|| {
+			let mut outcome = Outcome::new();
+			outcome.health(Some(false));
+			Ok(outcome)
+		})), status::HEALTH_BAD);
+	}
+
+	#[test]
+	fn failure_bits() {
+		assert_eq!(run(Mock(|| Err(status::fail("SCT is not supported", status::COMMAND_FAILED)))), status::COMMAND_FAILED);
+		assert_eq!(run(Mock(|| Err(Failure::reported(status::SMART_UNAVAILABLE)))), status::SMART_UNAVAILABLE);
+	}
+
+	#[test]
+	fn panic_does_not_propagate() {
+		assert_eq!(run(Mock(|| panic!("oops"))), status::COMMAND_FAILED);
+	}
+
+	#[test]
+	fn golden_json() {
+		// `attrs --json` output is checked in tests/cli.rs, against the binary itself
+		let regs = serde_json::to_string(&ata_registers_json(&mock::aborted())).unwrap();
+		assert_eq!(regs, r#"{"count":0,"device":0,"error":4,"error_bits":["ABRT"],"lba":0,"status":81}"#);
+	}
+
+	fn power_mode_dump(count: u8) -> Dump {
+		let mut dump = Dump::new();
+		dump.record(&hdd::ata::RegistersWrite {
+			command: hdd::ata::Command::CheckPowerMode as u8,
+			features: 0,
+			sector_count: 0,
+			sector: 0,
+			cyl_low: 0,
+			cyl_high: 0,
+			device: 0,
+			ext: None,
+		}, &RegistersRead { sector_count: count, ..mock::ok() }, &[]);
+		dump
+	}
+
+	#[test]
+	fn standby_skip() {
+		let path = Path::new("/dev/mock");
+		// no IDENTIFY DEVICE reply: the decision is made without it
+		let standby = power_mode_dump(0x00);
+		assert_eq!(check_power_mode(path, &standby, PowerCheck::SkipStandby), Err(PowerMode::Standby));
+		assert_eq!(check_power_mode(path, &standby, PowerCheck::Report), Ok(Some(PowerMode::Standby)));
+		assert_eq!(check_power_mode(path, &standby, PowerCheck::Off), Ok(None));
+
+		let idle = power_mode_dump(0x80);
+		assert_eq!(check_power_mode(path, &idle, PowerCheck::SkipStandby), Ok(Some(PowerMode::Idle)));
+
+		// devices that cannot tell are probed
+		assert_eq!(check_power_mode(path, &Dump::new(), PowerCheck::SkipStandby), Ok(None));
+	}
+
+	fn rewrite(args: &[&str]) -> Vec<String> {
+		smartctl_flags(args.iter().map(OsString::from).collect()).into_iter()
+			.map(|arg| arg.into_string().unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn smartctl_flags_rewrite() {
+		assert_eq!(rewrite(&["hdd", "-H", "/dev/sda"]), ["hdd", "/dev/sda", "health"]);
+		// options after the flag go to the subcommand
+		assert_eq!(rewrite(&["hdd", "-A", "--json", "/dev/sda"]), ["hdd", "/dev/sda", "attrs", "--json"]);
+		assert_eq!(rewrite(&["hdd", "--all", "-v", "9,minutes", "/dev/sda"]), ["hdd", "/dev/sda", "report", "-v", "9,minutes"]);
+		// options before it, to the tool
+		assert_eq!(rewrite(&["hdd", "-T", "permissive", "-i", "/dev/sda"]), ["hdd", "-T", "permissive", "/dev/sda", "info"]);
+		// /dev/null is as much of a device node as any
+		assert_eq!(rewrite(&["hdd", "-H", "--json", "/dev/null", "/dev/sda"]), ["hdd", "/dev/null", "/dev/sda", "health", "--json"]);
+		assert_eq!(rewrite(&["hdd", "-H"]), ["hdd", "health"]);
+
+		// subcommand is already there, or there are too many flags
+		assert_eq!(rewrite(&["hdd", "-H", "/dev/sda", "health"]), ["hdd", "-H", "/dev/sda", "health"]);
+		assert_eq!(rewrite(&["hdd", "-H", "-A", "/dev/sda"]), ["hdd", "-H", "-A", "/dev/sda"]);
+	}
 }
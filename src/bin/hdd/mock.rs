@@ -0,0 +1,106 @@
+/*!
+Canned devices for subcommand tests.
+
+Devices are [`Dump`](../../hdd/ata/dump/struct.Dump.html)s filled with replies to whatever commands the test is interested in; commands without a reply are aborted, same as with a device that does not support them.
+*/
+
+use hdd::ata::{Command, RegistersRead, RegistersWrite};
+use hdd::ata::dump::Dump;
+use hdd::ata::misc::Misc;
+
+use clap::ArgMatches;
+
+use ::DeviceArgument;
+use status::{Outcome, Failure};
+use subcommands::Subcommand;
+
+use std::path::Path;
+
+/// IDENTIFY DEVICE data with `words` set, and everything else zeroed.
+pub fn identify(words: &[(usize, u16)]) -> Vec<u8> {
+	let mut data = vec![0; 512];
+	for &(word, value) in words {
+		data[word * 2] = value as u8;
+		data[word * 2 + 1] = (value >> 8) as u8;
+	}
+	data
+}
+
+/// IDENTIFY DEVICE words of a drive with S.M.A.R.T. supported and enabled, and 1,000,000 512-byte sectors.
+pub const SMART_ENABLED: &[(usize, u16)] = &[
+	(60, 0x4240), (61, 0x000f),
+	(82, 1 << 0), (85, 1 << 0),
+	(84, 1 << 14 | 1 << 1 | 1 << 0), (87, 1 << 14 | 1 << 1 | 1 << 0),
+];
+
+/// SMART READ DATA reply with `attrs` (id, flags, value, worst, raw bytes, least significant first) in its attribute table, and a valid checksum.
+pub fn smart_values(attrs: &[(u8, u16, u8, u8, [u8; 6])]) -> Vec<u8> {
+	let mut data = vec![0; 512];
+	data[0] = 0x10; // revision
+	for (i, &(id, flags, value, worst, raw)) in attrs.iter().enumerate() {
+		let entry = &mut data[2 + i * 12 .. 2 + (i + 1) * 12];
+		entry[..5].copy_from_slice(&[id, flags as u8, (flags >> 8) as u8, value, worst]);
+		entry[5..11].copy_from_slice(&raw);
+	}
+	let sum = data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+	data[511] = 0u8.wrapping_sub(sum);
+	data
+}
+
+/// What the device replies with if the command succeeds.
+pub fn ok() -> RegistersRead {
+	RegistersRead {
+		error: 0,
+		sector_count: 0,
+		sector: 0,
+		cyl_low: 0,
+		cyl_high: 0,
+		device: 0,
+		status: 0x50, // DRDY, DSC
+		ext: None,
+	}
+}
+
+/// What the device replies with if it aborts the command.
+pub fn aborted() -> RegistersRead {
+	RegistersRead {
+		error: 1 << 2,
+		status: 0x51, // DRDY, DSC, ERR
+		..ok()
+	}
+}
+
+pub fn identify_regs() -> RegistersWrite {
+	RegistersWrite {
+		command: Command::Identify as u8,
+		sector: 1,
+		features: 0,
+		sector_count: 1,
+		cyl_low: 0,
+		cyl_high: 0,
+		device: 0,
+		ext: None,
+	}
+}
+
+/// Dump that replies to IDENTIFY DEVICE with `words` (see [`identify()`](fn.identify.html)).
+pub fn dump(words: &[(usize, u16)]) -> Dump {
+	let mut dump = Dump::new();
+	dump.record(&identify_regs(), &ok(), &identify(words));
+	dump
+}
+
+/// Turns the dump into a device, as `--from-dump` does.
+pub fn device(dump: Dump) -> DeviceArgument {
+	// unwrap: every dump built here has IDENTIFY DEVICE reply
+	let id = dump.get_device_id().unwrap();
+	DeviceArgument::Dump(dump, id)
+}
+
+/// Runs the subcommand on the device (as if it is `/dev/mock`), with the command line `args` (not including the subcommand name itself).
+pub fn run(subcommand: &Subcommand, dev: &DeviceArgument, args: &[&str]) -> Result<Outcome, Failure> {
+	let mut argv = vec!["hdd"];
+	argv.extend_from_slice(args);
+	let args: ArgMatches = subcommand.subcommand().get_matches_from(argv);
+	subcommand.run(&Some(Path::new("/dev/mock")), &Some(dev), &args)
+}
@@ -0,0 +1,196 @@
+/*!
+Exit status of the tool.
+
+Much like `smartctl`'s, exit status is a bit mask, so that scripts can tell what went wrong with a simple `$(( $? & mask ))`:
+
+bit | value | meaning
+----|-------|--------
+0   | 1     | invalid command line (also used by clap itself)
+1   | 2     | device could not be opened
+2   | 4     | command failed or was rejected by the device, requested feature is not supported, or a long operation was interrupted (see [`interrupt`](../interrupt/index.html))
+3   | 8     | S.M.A.R.T. is not supported, or is disabled
+4   | 16    | S.M.A.R.T. health status is BAD, surface scan found unreadable sectors, awaited self-test failed, or drivedb warns about a critical bug (`attrs` only)
+5   | 32    | some attributes are at or below their thresholds
+6   | 64    | some attributes were at or below their thresholds in the past (worst value is), but are not now
+7   | 128   | temperature is over the limit (SCSI devices: above the reference temperature)
+
+Status 0 means that nothing of the above happened. With multiple devices, bits are accumulated over all of them: a device that cannot be opened, or that a subcommand fails on (or panics on), does not stop the rest from being queried.
+
+Subcommands do not touch the exit status themselves. They return an [`Outcome`](struct.Outcome.html) with the conditions they observed, or a [`Failure`](struct.Failure.html) if they had to give up, and `main()` accounts for either. Subcommands are not supposed to pick bits themselves for the things they observe, either: use methods like [`Outcome::health()`](struct.Outcome.html#method.health) or [`Outcome::attributes()`](struct.Outcome.html#method.attributes) so that these are interpreted the same way everywhere.
+*/
+
+use hdd::ata::data::attr::{SmartAttribute, Status};
+use hdd::ata::data::self_test::SelfTest;
+use hdd::drivedb::Severity;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const USAGE: usize = 1 << 0;
+pub const OPEN_FAILED: usize = 1 << 1;
+pub const COMMAND_FAILED: usize = 1 << 2;
+pub const SMART_UNAVAILABLE: usize = 1 << 3;
+pub const HEALTH_BAD: usize = 1 << 4;
+pub const FAILING_NOW: usize = 1 << 5;
+pub const FAILED_IN_PAST: usize = 1 << 6;
+pub const TEMPERATURE: usize = 1 << 7;
+
+static STATUS: AtomicUsize = AtomicUsize::new(0);
+
+/// Marks that the condition(s) described by `bits` occurred.
+pub fn set(bits: usize) {
+	STATUS.fetch_or(bits, Ordering::SeqCst);
+}
+
+/// Exit status accumulated so far.
+pub fn code() -> i32 {
+	STATUS.load(Ordering::SeqCst) as i32
+}
+
+/// Marks the condition(s) described by `bits`, and terminates with the accumulated exit status. Only meant for `main()` itself, before any device is touched; subcommands give up with [`fail()`](fn.fail.html) instead.
+pub fn exit(bits: usize) -> ! {
+	set(bits);
+	::std::process::exit(code())
+}
+
+/// Conditions a subcommand observed on the device while running to completion, as returned from `Subcommand::run()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Outcome {
+	bits: usize,
+}
+
+impl Outcome {
+	pub fn new() -> Self { Outcome::default() }
+
+	/// Exit status bits of the conditions observed so far.
+	pub fn bits(&self) -> usize { self.bits }
+
+	/// Marks that the condition(s) described by `bits` occurred.
+	pub fn set(&mut self, bits: usize) {
+		self.bits |= bits;
+	}
+
+	/// Gives up, keeping bits observed so far; see [`fail()`](fn.fail.html).
+	pub fn fail<S: Into<String>>(&self, message: S, bits: usize) -> Failure {
+		fail(message, self.bits | bits)
+	}
+
+	/// Accounts for S.M.A.R.T. health status (`None` means the device did not tell).
+	pub fn health(&mut self, status: Option<bool>) {
+		if status == Some(false) {
+			self.set(HEALTH_BAD);
+		}
+	}
+
+	/// Accounts for unreadable sectors found during the surface scan.
+	pub fn bad_sectors(&mut self, count: usize) {
+		if count > 0 {
+			self.set(HEALTH_BAD);
+		}
+	}
+
+	/// Accounts for the result of a self-test.
+	pub fn self_test(&mut self, test: &SelfTest) {
+		if test.failed() {
+			self.set(HEALTH_BAD);
+		}
+	}
+
+	/// Accounts for the drivedb warning about the drive; only critical ones (known bugs that cause data loss and such) count.
+	pub fn drivedb_warning(&mut self, severity: Severity) {
+		if severity == Severity::Critical {
+			self.set(HEALTH_BAD);
+		}
+	}
+
+	/// Accounts for attributes that are failing now, or failed in the past.
+	pub fn attributes(&mut self, attrs: &[SmartAttribute]) {
+		for attr in attrs {
+			// margin only affects `Degrading` attributes, and we're not interested in these here
+			match attr.status(0) {
+				Status::FailingNow => self.set(FAILING_NOW),
+				Status::FailedInPast => self.set(FAILED_IN_PAST),
+				_ => (),
+			}
+		}
+	}
+
+	/// Accounts for the current temperature and the highest allowed one, if both are known.
+	pub fn temperature<T: PartialOrd>(&mut self, current: Option<T>, limit: Option<T>) {
+		if let (Some(current), Some(limit)) = (current, limit) {
+			if current > limit {
+				self.set(TEMPERATURE);
+			}
+		}
+	}
+}
+
+/**
+Subcommand gave up on the device.
+
+The message is already shown on stderr by then; with JSON output, `main()` also prints it as `{"error": message}` in place of whatever the subcommand would have printed, unless the subcommand did that itself (see [`reported()`](#method.reported)).
+*/
+#[derive(Debug)]
+pub struct Failure {
+	bits: usize,
+	message: Option<String>,
+}
+
+impl Failure {
+	/// For failures the subcommand already explained, on stderr and in JSON alike.
+	pub fn reported(bits: usize) -> Self {
+		Failure { bits, message: None }
+	}
+
+	pub fn bits(&self) -> usize { self.bits }
+
+	/// What to put into the JSON error object, if anything.
+	pub fn message(&self) -> Option<&str> {
+		self.message.as_deref()
+	}
+}
+
+/// Shows `message` on stderr, and returns the failure to give up with, e.g. `return Err(status::fail("SCT is not supported", status::COMMAND_FAILED))`.
+pub fn fail<S: Into<String>>(message: S, bits: usize) -> Failure {
+	let message = message.into();
+	eprint!("{}\n", message);
+	Failure { bits, message: Some(message) }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn outcome_accumulates() {
+		let mut outcome = Outcome::new();
+		outcome.health(Some(true));
+		outcome.health(None);
+		assert_eq!(outcome.bits(), 0);
+
+		outcome.health(Some(false));
+		outcome.bad_sectors(0);
+		outcome.temperature(Some(40), Some(50));
+		outcome.temperature(None, Some(50));
+		assert_eq!(outcome.bits(), HEALTH_BAD);
+
+		outcome.temperature(Some(60), Some(50));
+		outcome.drivedb_warning(Severity::Critical);
+		assert_eq!(outcome.bits(), HEALTH_BAD | TEMPERATURE);
+	}
+
+	#[test]
+	fn failure_keeps_observed_bits() {
+		let mut outcome = Outcome::new();
+		outcome.set(FAILING_NOW);
+		let failure = outcome.fail("Cannot read SCT status", COMMAND_FAILED);
+		assert_eq!(failure.bits(), FAILING_NOW | COMMAND_FAILED);
+		assert_eq!(failure.message(), Some("Cannot read SCT status"));
+	}
+
+	#[test]
+	fn reported_failure_has_no_message() {
+		let failure = Failure::reported(SMART_UNAVAILABLE);
+		assert_eq!(failure.bits(), SMART_UNAVAILABLE);
+		assert_eq!(failure.message(), None);
+	}
+}
@@ -1,12 +1,12 @@
-use hdd::ata::misc::Misc;
-
-use hdd::ata::data::attr;
+use hdd::ata::data::{self, attr};
 use hdd::ata::data::attr::raw::Raw;
+use hdd::ata::data::id::Id;
 use hdd::drivedb;
 use hdd::drivedb::vendor_attribute;
+use hdd::ata::misc;
 
 use hdd::scsi::pages::{SCSIPages, ErrorCounter};
-use hdd::scsi::{SCSICommon, DefectList};
+use hdd::scsi::{SCSICommon, DefectList, ATAError};
 use hdd::scsi::data::inquiry;
 
 use clap::{
@@ -17,7 +17,6 @@ use clap::{
 };
 
 use serde_json;
-use serde_json::value::ToJson;
 
 use std::collections::HashMap;
 use std::string::ToString;
@@ -25,64 +24,149 @@ use std::path::Path;
 
 use std::f64::NAN;
 
-use number_prefix::{decimal_prefix, binary_prefix, Prefixed, Standalone};
+use number_prefix::{decimal_prefix, Prefixed, Standalone};
 
 use prettytable;
 use prettytable::Table;
 use prettytable::row::Row;
 use prettytable::cell::Cell;
 
-use ::{DeviceArgument, open_drivedb};
-use super::{Subcommand, arg_drivedb};
+use ::{DeviceArgument, history, open_drivedb, print_json_error, print_json_command_error, status, tolerance, to_json_string};
+use status::{Outcome, Failure};
+use super::{Subcommand, arg_drivedb, arg_vendorattribute, arg_config, arg_units, arg_precision, Units, units, format_bytes};
 
 fn bool_to_flag(b: bool, c: char) -> char {
 	if b { c } else { '-' }
 }
 
+// line of the `print_attributes()` table, `width` being the width of the name column
+fn attribute_row(val: &attr::SmartAttribute, width: usize) -> String {
+	format!("{:3} {:.<width$} {}{}{}{}{}{}{}    {}   {}    {} {} {}",
+		val.id,
+		val.name.as_ref().unwrap_or(&"?".to_string()),
+		bool_to_flag(val.pre_fail, 'P'),
+		bool_to_flag(val.online, 'O'),
+		bool_to_flag(val.performance, 'S'),
+		bool_to_flag(val.error_rate, 'R'),
+		bool_to_flag(val.event_count, 'C'),
+		bool_to_flag(val.self_preserving, 'K'),
+		if val.flags == 0 { "     ".to_string() }
+			else { format!("+{:04x}", val.flags) },
+		val.value.map(|v| format!("{:3}", v)).unwrap_or("---".to_string()),
+		val.worst.map(|v| format!("{:3}", v)).unwrap_or("---".to_string()),
+		val.thresh.map(|v| format!("{:3}", v)).unwrap_or("(?)".to_string()),
+		match (val.value, val.worst, val.thresh) {
+			(Some(v), _, Some(t)) if v <= t => "NOW ",
+			(_, Some(w), Some(t)) if w <= t => "past",
+			// either value/worst are part of the `val.row`,
+			// or threshold is not available,
+			// or value never was below the threshold
+			_ => "-   ",
+		},
+		val.raw,
+		width = width,
+	)
+}
+
+// explains the flags column of `print_attributes()`, `width` being the width of the name column
+fn print_legend(width: usize) {
+	// based on the output of 'smartctl -A -f brief' (part of 'smartctl -x')
+	// flags start right after the id and the name columns
+	let indent = " ".repeat(3 + 1 + width + 1);
+	print!("{}││││││\n", indent);
+	print!("{}│││││K auto-keep\n", indent);
+	print!("{}││││C event count\n", indent);
+	print!("{}│││R error rate\n", indent);
+	print!("{}││S speed/performance\n", indent);
+	print!("{}│O updated online (otherwise only during off-line data collection)\n", indent);
+	print!("{}P prefailure warning\n", indent);
+}
+
 // XXX only `pretty_attributes` clearly shows failing/failed attributes
-fn print_attributes(values: Vec<attr::SmartAttribute>) {
+pub fn print_attributes(values: Vec<attr::SmartAttribute>, margin: u8, legend: bool) {
 	if values.is_empty() {
 		print!("No S.M.A.R.T. attributes found.\n");
 		return;
 	}
 
+	// > The NAME … should not exceed 23 characters
+	// still, names from drivedb or `-v` might be longer than that, so instead of truncating them, widen the column
+	let width = values.iter()
+		.map(|val| val.name.as_ref().map(|name| name.chars().count()).unwrap_or(1))
+		.max()
+		.unwrap_or(0);
+	let width = ::std::cmp::max(width + 1, 24);
+
 	print!("S.M.A.R.T. attribute values:\n");
-	print!(" ID name                     flags        value worst thresh fail raw\n");
+	print!(" ID {:<width$} flags        value worst thresh fail raw\n", "name", width = width);
+	for val in &values {
+		print!("{}\n", attribute_row(val, width));
+	}
+	if legend {
+		print_legend(width);
+	}
+
+	let degrading: Vec<_> = values.iter()
+		.filter(|val| val.status(margin) == attr::Status::Degrading)
+		.collect();
+	if !degrading.is_empty() {
+		print!("\nDegrading attributes (value or worst value within {} of the threshold):\n", margin);
+		for val in degrading {
+			print!("{:3} {}: value {}, worst {}, threshold {}\n",
+				val.id,
+				val.name.as_ref().unwrap_or(&"?".to_string()),
+				val.value.map(|v| v.to_string()).unwrap_or("-".to_string()),
+				val.worst.map(|v| v.to_string()).unwrap_or("-".to_string()),
+				// degrading attributes always have a threshold
+				val.thresh.unwrap(),
+			);
+		}
+	}
+}
+
+// mimics `smartctl -A`, down to column widths, so that scripts written for smartctl could keep parsing this
+fn print_attributes_smartctl(values: Vec<attr::SmartAttribute>) {
+	print!("Vendor Specific SMART Attributes with Thresholds:\n");
+	print!("ID# ATTRIBUTE_NAME          FLAG     VALUE WORST THRESH TYPE      UPDATED  WHEN_FAILED RAW_VALUE\n");
+	for val in values {
+		print!("{:3} {:<24}0x{:04x}   {:<3}   {:<3}   {:<3}    {:<10}{:<9}{:<12}{}\n",
+			val.id,
+			val.name.as_deref().unwrap_or("Unknown_Attribute"),
+			val.raw_flags,
+			val.value.map(|v| format!("{:03}", v)).unwrap_or("---".to_string()),
+			val.worst.map(|v| format!("{:03}", v)).unwrap_or("---".to_string()),
+			val.thresh.map(|v| format!("{:03}", v)).unwrap_or("---".to_string()),
+			if val.pre_fail { "Pre-fail" } else { "Old_age" },
+			if val.online { "Always" } else { "Offline" },
+			match (val.value, val.worst, val.thresh) {
+				(Some(v), _, Some(t)) if v <= t => "FAILING_NOW",
+				(_, Some(w), Some(t)) if w <= t => "In_the_past",
+				_ => "    -",
+			},
+			val.raw,
+		);
+	}
+}
+
+// one line per attribute, for `cut`, `awk`, and spreadsheets; there's no legend to explain flags, so these are shown as a number
+fn print_attributes_tsv(values: Vec<attr::SmartAttribute>) {
+	print!("id\tname\tflags\tvalue\tworst\tthresh\tfail\traw\n");
 	for val in values {
-		// > The NAME … should not exceed 23 characters
-		print!("{:3} {:.<24} {}{}{}{}{}{}{}    {}   {}    {} {} {}\n",
+		print!("{}\t{}\t0x{:04x}\t{}\t{}\t{}\t{}\t{}\n",
 			val.id,
-			val.name.as_ref().unwrap_or(&"?".to_string()),
-			bool_to_flag(val.pre_fail, 'P'),
-			bool_to_flag(!val.online, 'O'),
-			bool_to_flag(val.performance, 'S'),
-			bool_to_flag(val.error_rate, 'R'),
-			bool_to_flag(val.event_count, 'C'),
-			bool_to_flag(val.self_preserving, 'K'),
-			if val.flags == 0 { "     ".to_string() }
-				else { format!("+{:04x}", val.flags) },
-			val.value.map(|v| format!("{:3}", v)).unwrap_or("---".to_string()),
-			val.worst.map(|v| format!("{:3}", v)).unwrap_or("---".to_string()),
-			val.thresh.map(|v| format!("{:3}", v)).unwrap_or("(?)".to_string()),
+			val.name.as_deref().unwrap_or("?"),
+			val.raw_flags,
+			val.value.map(|v| v.to_string()).unwrap_or("-".to_string()),
+			val.worst.map(|v| v.to_string()).unwrap_or("-".to_string()),
+			val.thresh.map(|v| v.to_string()).unwrap_or("-".to_string()),
 			match (val.value, val.worst, val.thresh) {
-				(Some(v), _, Some(t)) if v <= t => "NOW ",
+				(Some(v), _, Some(t)) if v <= t => "now",
 				(_, Some(w), Some(t)) if w <= t => "past",
-				// either value/worst are part of the `val.row`,
-				// or threshold is not available,
-				// or value never was below the threshold
-				_ => "-   ",
+				_ => "-",
 			},
 			val.raw,
 		);
 	}
-	// based on the output of 'smartctl -A -f brief' (part of 'smartctl -x')
-	print!("                             ││││││\n");
-	print!("                             │││││K auto-keep\n");
-	print!("                             ││││C event count\n");
-	print!("                             │││R error rate\n");
-	print!("                             ││S speed/performance\n");
-	print!("                             │O updated during off-line testing\n");
-	print!("                             P prefailure warning\n");
 }
 
 fn escape(s: &String) -> String {
@@ -111,6 +195,72 @@ fn format_prom<T: ToString>(key: &str, labels: &HashMap<&str, String>, value: T)
 	line
 }
 
+#[derive(PartialEq)]
+enum NameStyle { Raw, Pretty }
+
+// `Power_On_Hours` → `Power On Hours`
+fn pretty_name(name: &str) -> String {
+	name.split('_')
+		.filter(|word| !word.is_empty())
+		.map(|word| {
+			let mut chars = word.chars();
+			// unwrap: empty words are skipped above
+			let first = chars.next().unwrap();
+			first.to_uppercase().chain(chars).collect::<String>()
+		})
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+// how ATA attributes are presented
+struct Options {
+	margin: u8,
+	legend: bool,
+	names: NameStyle,
+	state_file: Option<String>,
+}
+
+fn print_trends(trends: &[history::Trend]) {
+	// nothing happened, nothing to show
+	let trends: Vec<_> = trends.iter()
+		.filter(|trend| trend.rate.map(|rate| rate > 0.).unwrap_or(false) || history::alert(trend).is_some())
+		.collect();
+	if trends.is_empty() {
+		return;
+	}
+
+	let rate = |rate: Option<f64>| rate.map(|rate| format!("{:.2}/day", rate)).unwrap_or("-".to_string());
+	print!("\nError counter trends:\n");
+	for trend in &trends {
+		print!("{:3} {}: {}, overall {}, last {} days {}\n",
+			trend.id,
+			trend.name.as_ref().unwrap_or(&"?".to_string()),
+			trend.current,
+			rate(trend.rate),
+			history::RECENT / 86400,
+			rate(trend.recent_rate),
+		);
+	}
+	for trend in &trends {
+		if let Some(alert) = history::alert(trend) {
+			print!("WARNING: {}\n", alert);
+		}
+	}
+}
+
+fn print_prometheus_trends(labels: &HashMap<&str, String>, trends: &[history::Trend]) {
+	for trend in trends {
+		let mut labels = labels.clone();
+		labels.insert("id", trend.id.to_string());
+		labels.insert("name", trend.name.clone().unwrap_or("?".to_string()));
+
+		labels.insert("window", "all".to_string());
+		print!("{}\n", format_prom("smart_raw_rate_per_day", &labels, trend.rate.unwrap_or(NAN)));
+		labels.insert("window", format!("{}d", history::RECENT / 86400));
+		print!("{}\n", format_prom("smart_raw_rate_per_day", &labels, trend.recent_rate.unwrap_or(NAN)));
+	}
+}
+
 fn print_prometheus_values(labels: &HashMap<&str, String>, values: Vec<attr::SmartAttribute>) {
 	for val in values {
 		let mut labels = labels.clone();
@@ -153,8 +303,8 @@ impl Subcommand for Attrs {
 			.arg(Arg::with_name("format")
 				.long("format")
 				.takes_value(true)
-				.possible_values(&["plain", "json", "prometheus"])
-				.help("format to export data in")
+				.possible_values(&["plain", "json", "prometheus", "smartctl", "tsv"])
+				.help("format to export data in\n'smartctl' mimics the attribute table of 'smartctl -A'\n'tsv' prints the attribute table as tab-separated values, without the flag legend")
 			)
 			.arg(Arg::with_name("full-path")
 				.long("full-path")
@@ -167,13 +317,31 @@ impl Subcommand for Attrs {
 				.overrides_with("format")
 			)
 			.arg(arg_drivedb())
-			.arg(Arg::with_name("vendorattribute")
-				.multiple(true)
-				.short("v") // smartctl-like
-				.long("vendorattribute") // smartctl-like
+			.arg(arg_units())
+			.arg(arg_precision())
+			.arg(Arg::with_name("no-legend")
+				.long("no-legend")
+				.help("do not explain attribute flags after the table (only affects plain format)")
+			)
+			.arg(Arg::with_name("name-style")
+				.long("name-style")
+				.takes_value(true)
+				.possible_values(&["raw", "pretty"])
+				.help("how to show attribute names\n'raw' (default): as in drivedb, e.g. Power_On_Hours\n'pretty': with spaces, e.g. Power On Hours (does not affect --format=smartctl)")
+			)
+			.arg(Arg::with_name("margin")
+				.long("margin")
 				.takes_value(true)
-				.value_name("id,format[:byteorder][,name]")
-				.help("set display option for vendor attribute 'id'")
+				.value_name("POINTS")
+				.help("list attributes whose value or worst value is within this many points above the threshold as degrading (default: 10)")
+			)
+			.arg(arg_vendorattribute())
+			.arg(arg_config())
+			.arg(Arg::with_name("state-file")
+				.long("state-file")
+				.takes_value(true)
+				.value_name("FILE")
+				.help("keep raw values of attributes in this file across runs (keyed by drive serial), and warn about error counters that grow, or grow faster than they used to\nsamples older than 180 days are dropped")
 			)
 	}
 
@@ -182,12 +350,9 @@ impl Subcommand for Attrs {
 		path: &Option<&Path>,
 		dev: &Option<&DeviceArgument>,
 		args: &ArgMatches,
-	) {
-		let dev = dev.unwrap_or_else(|| {
-			// TODO show usage and whatnot
-			eprint!("<device> is required\n");
-			::std::process::exit(1);
-		});
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
 		let path = path.unwrap(); // `path` and `dev` are both `Some()` or both `None`
 
 		let path = if args.is_present("full-path") {
@@ -200,47 +365,85 @@ impl Subcommand for Attrs {
 			Some("plain") => Plain,
 			Some("json") => JSON,
 			Some("prometheus") => Prometheus,
+			Some("smartctl") => Smartctl,
+			Some("tsv") => TSV,
 			None if args.is_present("json") => JSON,
 			None => Plain,
 			_ => unreachable!(),
 		};
 
-		let user_attributes = args.values_of("vendorattribute")
-			.map(|attrs| attrs.collect())
-			.unwrap_or(vec![])
-			.into_iter()
-			.map(|attr| vendor_attribute::parse(attr).ok()) // TODO Err(_)
-			.filter(|x| x.is_some())
-			.map(|x| x.unwrap())
-			.collect();
 		let drivedb = open_drivedb(args.values_of("drivedb"));
+		let user_attributes = user_attributes(args, dev.ata().map(|(_, id)| id), &drivedb)?;
+
+		let margin = match args.value_of("margin") {
+			Some(m) => m.parse().map_err(|_| status::fail(format!("invalid margin: {}", m), status::USAGE))?,
+			None => 10,
+		};
+
+		let options = Options {
+			margin,
+			legend: !args.is_present("no-legend"),
+			names: match args.value_of("name-style") {
+				Some("raw") | None => NameStyle::Raw,
+				Some("pretty") => NameStyle::Pretty,
+				_ => unreachable!(),
+			},
+			state_file: args.value_of("state-file").map(|file| file.to_string()),
+		};
 
 		use DeviceArgument::*;
 		match dev {
 			#[cfg(not(target_os = "linux"))]
-			dev @ ATA(_, _) => attrs_ata(path, dev, format, drivedb, user_attributes),
-			dev @ SAT(_, _) => attrs_ata(path, dev, format, drivedb, user_attributes),
-			dev @ SCSI(_) => attrs_scsi(path, dev, format),
-		};
+			dev @ ATA(_, _) => attrs_ata(path, dev, format, drivedb, user_attributes, &options),
+			dev @ SAT(_, _) => attrs_ata(path, dev, format, drivedb, user_attributes, &options),
+			dev @ SCSI(_) => attrs_scsi(path, dev, format, units(args)?),
+			dev @ Dump(_, _) => attrs_ata(path, dev, format, drivedb, user_attributes, &options),
+		}
 	}
 }
 
+/**
+Vendor attribute options of `--config` (see [`arg_config()`](../fn.arg_config.html)) and `-v` (see [`arg_vendorattribute()`](../fn.arg_vendorattribute.html)) for the drive, in that order, so that `-v` could override the former.
+
+Config entries can also be matched against drivedb family, hence `drivedb`.
+*/
+pub fn user_attributes(args: &ArgMatches, id: Option<&Id>, drivedb: &Option<drivedb::DriveDB>) -> Result<Vec<drivedb::Attribute>, Failure> {
+	let user_attributes = args.values_of("vendorattribute")
+		.map(|attrs| attrs.collect())
+		.unwrap_or(vec![])
+		.into_iter()
+		.map(|attr| vendor_attribute::parse(attr).map_err(|err| status::fail(format!("Invalid -v {}: {}", attr, err), status::USAGE)))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let config = match args.value_of("config") {
+		Some(file) => Some(drivedb::Config::load(file)
+			.map_err(|err| status::fail(format!("Cannot load config file {}: {}", file, err), status::USAGE))?),
+		None => None,
+	};
+	Ok(match (id, &config) {
+		(Some(id), Some(config)) => {
+			let family = drivedb.as_ref().and_then(|drivedb| drivedb.render_meta(id, &vec![]).family);
+			let mut attributes = config.attributes(id, family.map(|f| f.as_str()));
+			attributes.extend(user_attributes);
+			attributes
+		},
+		_ => user_attributes,
+	})
+}
+
 #[derive(PartialEq)]
-enum Format { Plain, JSON, Prometheus }
+enum Format { Plain, JSON, Prometheus, Smartctl, TSV }
 use self::Format::*;
 
-fn attrs_ata(path: &str, dev: &DeviceArgument, format: Format, drivedb: Option<drivedb::DriveDB>, user_attributes: Vec<drivedb::Attribute>) {
-	let id = match dev {
-		#[cfg(not(target_os = "linux"))]
-		DeviceArgument::ATA(_, id) => id,
-		DeviceArgument::SAT(_, id) => id,
-		DeviceArgument::SCSI(_) => unreachable!(),
-	};
+fn attrs_ata(path: &str, dev: &DeviceArgument, format: Format, drivedb: Option<drivedb::DriveDB>, user_attributes: Vec<drivedb::Attribute>, options: &Options) -> Result<Outcome, Failure> {
+	// unwrap: only called for devices that speak ATA
+	let (dev, id) = dev.ata().unwrap();
 
-	let dbentry = drivedb.as_ref().map(|drivedb| drivedb.render_meta(
-		&id,
-		&user_attributes,
-	));
+	// without drivedb, at least the most common attributes get their names (and -v still applies)
+	let dbentry = Some(match drivedb {
+		Some(ref drivedb) => drivedb.render_meta(id, &user_attributes),
+		None => drivedb::builtin_meta(id, &user_attributes),
+	});
 
 	// for --format=prometheus (TODO? don't compose if other format is used)
 	let mut labels = HashMap::new();
@@ -253,46 +456,124 @@ fn attrs_ata(path: &str, dev: &DeviceArgument, format: Format, drivedb: Option<d
 		}
 	};
 
-	if let Some(ref entry) = dbentry {
-		if let Some(_) = entry.warning {
+	let mut outcome = Outcome::new();
+
+	if let Some(severity) = dbentry.as_ref().and_then(|entry| entry.warning_severity()) {
+		outcome.drivedb_warning(severity);
+		if format == Prometheus {
+			let mut labels = labels.clone();
+			labels.insert("severity", format!("{:?}", severity).to_lowercase());
 			print!("{}\n", format_prom("smart_drivedb_warning", &labels, 1));
 		}
 	};
 
 	use id::Ternary::*;
+	if id.smart != Enabled {
+		outcome.set(status::SMART_UNAVAILABLE);
+	}
 	match (format, id.smart) {
-		(Plain, Unsupported) | (JSON, Unsupported) =>
+		(Plain, Unsupported) | (Smartctl, Unsupported) | (TSV, Unsupported) =>
 			eprint!("S.M.A.R.T. is not supported, cannot show attributes\n"),
+		(JSON, Unsupported) => {
+			eprint!("S.M.A.R.T. is not supported, cannot show attributes\n");
+			print_json_error("smart_unsupported");
+		},
 		(Prometheus, Unsupported) =>
 			print!("{}\n", format_prom("smart_enabled", &labels, NAN)),
 
-		(Plain, Disabled) | (JSON, Disabled) =>
+		(Plain, Disabled) | (Smartctl, Disabled) | (TSV, Disabled) =>
 			eprint!("S.M.A.R.T. is disabled, cannot show attributes\n"),
+		(JSON, Disabled) => {
+			eprint!("S.M.A.R.T. is disabled, cannot show attributes\n");
+			print_json_error("smart_disabled");
+		},
 		(Prometheus, Disabled) =>
 			print!("{}\n", format_prom("smart_enabled", &labels, 0)),
 
 		(format, Enabled) => {
-			let values = match dev {
-				#[cfg(not(target_os = "linux"))]
-				DeviceArgument::ATA(dev, _) => dev.get_smart_attributes(&dbentry).unwrap(),
-				DeviceArgument::SAT(dev, _) => dev.get_smart_attributes(&dbentry).unwrap(),
-				DeviceArgument::SCSI(_) => unreachable!(),
+			// drive claims S.M.A.R.T. is enabled, but it still might refuse to show us the values (because of some firmware bug, or because SATL is not up to the task)
+			let data = match dev.get_smart_values_raw() {
+				Ok(data) => data,
+				Err(err) => {
+					let msg = match err {
+						misc::Error::Aborted(_) =>
+							format!("Drive reports S.M.A.R.T. as enabled, but refused S.M.A.R.T. READ DATA ({})", err),
+						misc::Error::SCSI(ATAError::NotSupported) =>
+							"Drive reports S.M.A.R.T. as enabled, but S.M.A.R.T. READ DATA was rejected as unsupported (SATL might not pass it through)".to_string(),
+						ref err =>
+							format!("Cannot read S.M.A.R.T. attributes: {}", err),
+					};
+					// either way, there's nothing to show; registers are there for the failures device itself reports
+					if format == JSON {
+						print_json_command_error("smart_read_data_failed", &err);
+					}
+					tolerance::fail(&mut outcome, &msg, status::COMMAND_FAILED)
+						.map_err(|failure| if format == JSON { Failure::reported(failure.bits()) } else { failure })?;
+					return Ok(outcome);
+				},
+			};
+			if !data::checksum_valid(&data) {
+				tolerance::fail(&mut outcome, "S.M.A.R.T. READ DATA reply checksum mismatch", status::COMMAND_FAILED)?;
+			}
+			// values are still worth showing without thresholds (e.g. if S.M.A.R.T. was disabled in between, or if it's some transient error)
+			let thresh = dev.get_smart_thresholds_raw().unwrap_or_else(|err| {
+				eprint!("Cannot read S.M.A.R.T. thresholds, showing attributes without them: {}\n", err);
+				outcome.set(status::COMMAND_FAILED);
+				vec![]
+			});
+			let mut values = attr::parse_smart_values(&data, &thresh, &dbentry);
+			outcome.attributes(&values);
+
+			// scripts that parse smartctl output expect names without spaces
+			if options.names == NameStyle::Pretty && format != Smartctl {
+				for val in values.iter_mut() {
+					val.name = val.name.as_ref().map(|name| pretty_name(name));
+				}
+			}
+
+			let trends = match options.state_file {
+				Some(ref file) if id.serial.is_empty() => {
+					eprint!("Drive does not report its serial number, not recording its attributes in {}\n", file);
+					vec![]
+				},
+				Some(ref file) => history::update(file, &id.serial, &values, history::now()).unwrap_or_else(|err| {
+					eprint!("Cannot update state file {}: {}\n", file, err);
+					outcome.set(status::COMMAND_FAILED);
+					vec![]
+				}),
+				None => vec![],
 			};
+			// these would break machine-readable output
+			if format != Plain {
+				for alert in trends.iter().filter_map(history::alert) {
+					eprint!("WARNING: {}\n", alert);
+				}
+			}
 
 			match format {
-				Plain => print_attributes(values),
-				JSON => print!("{}\n",
-					serde_json::to_string(
-						&values.to_json().unwrap()
-					).unwrap()
-				),
+				Plain => {
+					print_attributes(values, options.margin, options.legend);
+					print_trends(&trends);
+				},
+				Smartctl => print_attributes_smartctl(values),
+				TSV => print_attributes_tsv(values),
+				JSON => {
+					// stdout only gets the array of attributes, as it always did; `info` and `report` carry the warning in their JSON
+					if let Some(warning) = dbentry.as_ref().and_then(|entry| entry.warning) {
+						eprint!("WARNING: {}\n", warning);
+					}
+					print!("{}\n", to_json_string(&values));
+				},
 				Prometheus => {
 					print!("{}\n", format_prom("smart_enabled", &labels, 1));
 					print_prometheus_values(&labels, values);
+					print_prometheus_trends(&labels, &trends);
 				},
 			}
 		},
 	}
+
+	Ok(outcome)
 }
 
 fn print_prom_scsi_error_counters(labels: &HashMap<&str, String>, counters: &HashMap<ErrorCounter, u64>, action: &str) {
@@ -343,12 +624,12 @@ fn print_prom_scsi_error_counters(labels: &HashMap<&str, String>, counters: &Has
 	}
 }
 
-fn scsi_error_counters_json(counters: &HashMap<ErrorCounter, u64>) -> serde_json::Value {
+pub fn scsi_error_counters_json(counters: &HashMap<ErrorCounter, u64>) -> serde_json::Value {
 	let mut json = serde_json::Map::new();
 
 	use self::ErrorCounter::*;
 	for (&k, &v) in counters {
-		let v = v.to_json().unwrap();
+		let v = serde_json::to_value(v).unwrap();
 		match k {
 			// TODO? submaps for CRC, totals
 
@@ -367,10 +648,17 @@ fn scsi_error_counters_json(counters: &HashMap<ErrorCounter, u64>) -> serde_json
 		};
 	}
 
-	json.to_json().unwrap()
+	serde_json::Value::Object(json)
 }
 
-fn print_human_scsi_error_counters(counters: &Vec<(&str, HashMap<ErrorCounter, u64>)>) {
+fn print_human_scsi_error_counters(counters: &Vec<(&str, HashMap<ErrorCounter, u64>)>, (units, precision): (Units, usize)) {
+	// there's no sector size to speak of here, and both prefixes would make the table too wide
+	let units = match units {
+		Units::Auto => Units::Binary,
+		Units::Sectors => Units::Bytes,
+		units => units,
+	};
+
 	use self::ErrorCounter::*;
 
 	// no columns to show?
@@ -441,20 +729,16 @@ fn print_human_scsi_error_counters(counters: &Vec<(&str, HashMap<ErrorCounter, u
 		row.push(Cell::new(&name));
 
 		for (_, values) in counters.iter() {
-			#[allow(trivial_casts)] // closures in .map_or()
 			row.push(Cell::new(&values.get(&key)
 				.map_or(
 					"-".to_string(),
-					if key == BytesProcessed {
-						(|&v| match binary_prefix(v as f32) {
-							Prefixed(p, x) => format!("{:.1} {}B", x, p),
-							Standalone(x)  => format!("{} B", x),
-						}) as fn(&u64) -> String
+					|&v| if key == BytesProcessed {
+						format_bytes(v, units, precision)
 					} else {
-						(|&v| match decimal_prefix(v as f32) {
+						match decimal_prefix(v as f32) {
 							Prefixed(p, x) => format!("{:.1}{}", x, p),
 							Standalone(x)  => format!("{}", x),
-						}) as fn(&u64) -> String
+						}
 					},
 				)
 			).style_spec("r"));
@@ -468,11 +752,12 @@ fn print_human_scsi_error_counters(counters: &Vec<(&str, HashMap<ErrorCounter, u
 
 // TODO other formats
 // TODO prometheus: device id labels, just like in attrs_ata
-fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
+fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format, units: (Units, usize)) -> Result<Outcome, Failure> {
 	let dev = match dev {
 		#[cfg(not(target_os = "linux"))]
 		DeviceArgument::ATA(_, _) => unreachable!(),
 		DeviceArgument::SAT(_, _) => unreachable!(),
+		DeviceArgument::Dump(_, _) => unreachable!(),
 		DeviceArgument::SCSI(dev) => dev,
 	};
 
@@ -482,6 +767,7 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 	}
 
 	let mut json = serde_json::Map::new();
+	let mut outcome = Outcome::new();
 
 	let mut labels = HashMap::new();
 	labels.insert("dev", path.to_string());
@@ -507,12 +793,13 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 					counters.map(|counters| print_prom_scsi_error_counters(&labels, &counters, name));
 				}
 			},
-			Plain => {
+			// `--format=smartctl` and `--format=tsv` only concern ATA attribute table
+			Plain | Smartctl | TSV => {
 				let mut table = vec![];
 				for (name, counters) in error_counters {
 					counters.map(|counters| table.push((name, counters)));
 				}
-				print_human_scsi_error_counters(&table);
+				print_human_scsi_error_counters(&table, units);
 			},
 			JSON => {
 				for (name, counters) in error_counters {
@@ -532,11 +819,11 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 			Prometheus => {
 				print!("{}\n", format_prom("scsi_non_medium_errors", &labels, x));
 			},
-			Plain => {
+			Plain | Smartctl | TSV => {
 				print!("\nNon-medium errors: {}\n", x);
 			},
 			JSON => {
-				json.insert("non-medium-errors".to_string(), x.to_json().unwrap());
+				json.insert("non-medium-errors".to_string(), serde_json::to_value(x).unwrap());
 			},
 		}
 	}
@@ -545,12 +832,13 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 
 	// also TODO Err()
 	if let Some(Ok((temp, ref_temp))) = pages.iter_mut().next().map(|p| p.temperature()) {
+		outcome.temperature(temp, ref_temp);
 		match format {
 			Prometheus => {
 				if let Some(t) = temp     { print!("{}\n", format_prom("scsi_temperature", &labels, t)) };
 				if let Some(t) = ref_temp { print!("{}\n", format_prom("scsi_reference_temperature", &labels, t)) };
 			},
-			Plain => {
+			Plain | Smartctl | TSV => {
 				if let Some(t) = temp {
 					print!("\nTemperature: {}°C", t);
 					if let Some(t) = ref_temp {
@@ -561,9 +849,9 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 			},
 			JSON => {
 				let mut tmp = serde_json::Map::new();
-				tmp.insert("current".to_string(), temp.to_json().unwrap());
-				tmp.insert("reference".to_string(), ref_temp.to_json().unwrap());
-				json.insert("temperature".to_string(), tmp.to_json().unwrap());
+				tmp.insert("current".to_string(), serde_json::to_value(temp).unwrap());
+				tmp.insert("reference".to_string(), serde_json::to_value(ref_temp).unwrap());
+				json.insert("temperature".to_string(), serde_json::Value::Object(tmp));
 			},
 		}
 	}
@@ -585,7 +873,7 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 				if let Some(t) = cycles.load_unload_cycles          { print!("{}\n", format_prom("scsi_cycles", &labels, t)) };
 				if let Some(t) = cycles.lifetime_load_unload_cycles { print!("{}\n", format_prom("scsi_lifetime_cycles", &labels, t)) };
 			},
-			Plain => {
+			Plain | Smartctl | TSV => {
 				print!("\n");
 				if let Some(x) = cycles.start_stop_cycles {
 					print!("Start-stop cycles: {}", x);
@@ -606,16 +894,16 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 				let mut tmp = serde_json::Map::new();
 
 				let mut values = serde_json::Map::new();
-				values.insert("current".to_string(), cycles.start_stop_cycles.to_json().unwrap());
-				values.insert("lifetime".to_string(), cycles.lifetime_start_stop_cycles.to_json().unwrap());
-				tmp.insert("start-stop".to_string(), values.to_json().unwrap());
+				values.insert("current".to_string(), serde_json::to_value(cycles.start_stop_cycles).unwrap());
+				values.insert("lifetime".to_string(), serde_json::to_value(cycles.lifetime_start_stop_cycles).unwrap());
+				tmp.insert("start-stop".to_string(), serde_json::Value::Object(values));
 
 				let mut values = serde_json::Map::new();
-				values.insert("current".to_string(), cycles.load_unload_cycles.to_json().unwrap());
-				values.insert("lifetime".to_string(), cycles.lifetime_load_unload_cycles.to_json().unwrap());
-				tmp.insert("load-unload".to_string(), values.to_json().unwrap());
+				values.insert("current".to_string(), serde_json::to_value(cycles.load_unload_cycles).unwrap());
+				values.insert("lifetime".to_string(), serde_json::to_value(cycles.lifetime_load_unload_cycles).unwrap());
+				tmp.insert("load-unload".to_string(), serde_json::Value::Object(values));
 
-				json.insert("cycles".to_string(), tmp.to_json().unwrap());
+				json.insert("cycles".to_string(), serde_json::Value::Object(tmp));
 			},
 		}
 	}
@@ -629,14 +917,14 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 				labels.insert("list", "grown".to_string());
 				print!("{}\n", format_prom("scsi_defects", &labels, defects));
 			},
-			Plain => {
+			Plain | Smartctl | TSV => {
 				print!("\n");
 				print!("Elements in grown defect list: {}\n", defects);
 			},
 			JSON => {
 				let mut tmp = serde_json::Map::new();
-				tmp.insert("grown".to_string(), defects.to_json().unwrap());
-				json.insert("defect-list".to_string(), tmp.to_json().unwrap());
+				tmp.insert("grown".to_string(), serde_json::to_value(defects).unwrap());
+				json.insert("defect-list".to_string(), serde_json::Value::Object(tmp));
 			},
 		}
 	}
@@ -644,4 +932,72 @@ fn attrs_scsi(path: &str, dev: &DeviceArgument, format: Format) {
 	if format == JSON {
 		print!("{}\n", serde_json::to_string(&json).unwrap());
 	}
+
+	Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hdd::ata::{RegistersWrite, SMARTFeature};
+	use mock;
+
+	const VALUES: &[(u8, u16, u8, u8, [u8; 6])] = &[
+		(5, 0x0033, 100, 100, [0; 6]),
+		(9, 0x0032, 95, 95, [0x10, 0x27, 0, 0, 0, 0]),
+	];
+
+	// SMART is enabled, but READ DATA is aborted
+	fn refusing_read_data() -> DeviceArgument {
+		let mut dump = mock::dump(mock::SMART_ENABLED);
+		dump.record(&RegistersWrite::smart(SMARTFeature::ReadValues, 0, 1), &mock::aborted(), &[]);
+		mock::device(dump)
+	}
+
+	#[test]
+	fn read_data_refused() {
+		let failure = mock::run(&Attrs {}, &refusing_read_data(), &[]).unwrap_err();
+		assert_eq!(failure.bits(), status::COMMAND_FAILED);
+		assert!(failure.message().unwrap().contains("refused S.M.A.R.T. READ DATA"));
+	}
+
+	#[test]
+	fn read_data_refused_json() {
+		// the error object is already printed, along with the registers
+		let failure = mock::run(&Attrs {}, &refusing_read_data(), &["--json"]).unwrap_err();
+		assert_eq!(failure.bits(), status::COMMAND_FAILED);
+		assert_eq!(failure.message(), None);
+	}
+
+	#[test]
+	fn read_thresholds_refused() {
+		let mut dump = mock::dump(mock::SMART_ENABLED);
+		dump.record(&RegistersWrite::smart(SMARTFeature::ReadValues, 0, 1), &mock::ok(), &mock::smart_values(VALUES));
+		dump.record(&RegistersWrite::smart(SMARTFeature::ReadThresholds, 0, 1), &mock::aborted(), &[]);
+		let dev = mock::device(dump);
+
+		// values are still shown
+		assert_eq!(mock::run(&Attrs {}, &dev, &[]).unwrap().bits(), status::COMMAND_FAILED);
+
+		let (dev, _) = dev.ata().unwrap();
+		let values = attr::parse_smart_values(&dev.get_smart_values_raw().unwrap(), &vec![], &None);
+		assert_eq!(values.len(), 2);
+		assert!(values.iter().all(|val| val.thresh.is_none()));
+		let row = attribute_row(&values[1], 24);
+		assert!(row.contains(" 95    (?) -    "), "{}", row);
+		assert!(row.ends_with(" 10000"), "{}", row);
+	}
+
+	#[test]
+	fn json_flags() {
+		let values = attr::parse_smart_values(&mock::smart_values(&[(5, 0x1033, 100, 100, [0; 6])]), &vec![], &None);
+		let json = serde_json::to_value(&values).unwrap();
+		let attr = &json[0];
+
+		assert_eq!(attr["raw_flags"].as_u64(), Some(0x1033));
+		assert_eq!(attr["flags"].as_u64(), Some(0x1000));
+		for &(flag, set) in &[("pre_fail", true), ("online", true), ("performance", false), ("error_rate", false), ("event_count", true), ("self_preserving", true)] {
+			assert_eq!(attr[flag].as_bool(), Some(set), "{}", flag);
+		}
+	}
 }
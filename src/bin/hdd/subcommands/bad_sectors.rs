@@ -0,0 +1,137 @@
+use hdd::ata::misc::{self, Misc};
+use hdd::ata::data::id::{Id, Ternary};
+use hdd::ata::data::self_test::SelfTest;
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use serde_json;
+
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
+use super::{Subcommand, arg_json};
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// logs that could not be read are reported, but whatever's found in other logs is still shown
+fn warn_on_error<T>(name: &str, result: Result<Option<T>, misc::Error>, outcome: &mut Outcome) -> Option<T> {
+	match result {
+		Ok(Some(x)) => Some(x),
+		Ok(None) => {
+			eprint!("{} reply is truncated\n", name);
+			outcome.set(status::COMMAND_FAILED);
+			None
+		},
+		Err(err) => {
+			eprint!("Cannot read {}: {}\n", name, err);
+			outcome.set(status::COMMAND_FAILED);
+			None
+		},
+	}
+}
+
+// `ext_pages` is the size of the Extended SMART self-test log, 0 if there's none
+fn self_tests(dev: &Misc, id: &Id, ext_pages: u16, outcome: &mut Outcome) -> Option<Vec<SelfTest>> {
+	// extended log has 48-bit LBAs, prefer it over the SMART one
+	if ext_pages != 0 {
+		return warn_on_error("Extended SMART self-test log", dev.get_ext_self_test_log(ext_pages), outcome);
+	}
+	if id.smart == Ternary::Enabled {
+		return warn_on_error("SMART self-test log", dev.get_self_test_log(), outcome);
+	}
+	None
+}
+
+pub struct BadSectors {}
+impl Subcommand for BadSectors {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("bad-sectors")
+			.about("Lists LBAs the device knows to be problematic (from the Pending Defects log, and from failed self-tests), one per line, e.g. to feed them to dd or ddrescue")
+			.arg(Arg::with_name("bytes")
+				.long("bytes")
+				.help("show byte offsets (LBA times logical sector size) instead of LBAs")
+			)
+			.arg(arg_json())
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Bad sectors are only listed for ATA devices", status::COMMAND_FAILED))?;
+
+		let mut outcome = Outcome::new();
+
+		// if the directory is not there, neither are the logs that are listed in it
+		let directory = if id.gp_logging_supported {
+			warn_on_error("General Purpose Log Directory", dev.get_gp_log_directory(), &mut outcome)
+		} else { None };
+		let gpl_pages = |address: u8| directory.as_ref().map(|dir| dir.pages(address)).unwrap_or(0);
+
+		// LBA → logs it is found in
+		let mut sectors: BTreeMap<u64, Vec<&'static str>> = BTreeMap::new();
+		let mut checked = vec![];
+
+		if gpl_pages(0x0c) != 0 {
+			checked.push("pending defects");
+			if let Some(defects) = warn_on_error("Pending Defects log", dev.get_pending_defects(id.commands_supported.read_write_dma_ext_gpl), &mut outcome) {
+				for defect in defects {
+					sectors.entry(defect.lba).or_default().push("pending");
+				}
+			}
+		}
+
+		if let Some(tests) = self_tests(dev, id, gpl_pages(0x07), &mut outcome) {
+			checked.push("self-test");
+			for lba in tests.iter().filter_map(|test| test.failing_lba) {
+				let sources = sectors.entry(lba).or_default();
+				// the same LBA might be reported by a number of consecutive tests
+				if !sources.contains(&"self-test") {
+					sources.push("self-test");
+				}
+			}
+		}
+
+		if checked.is_empty() {
+			return Err(outcome.fail("Device provides neither Pending Defects nor self-test logs", status::COMMAND_FAILED));
+		}
+
+		let sector_size = id.sector_size_log as u64;
+		let bytes = args.is_present("bytes");
+
+		if args.is_present("json") {
+			let sectors: Vec<_> = sectors.iter().map(|(&lba, sources)| {
+				let mut sector = serde_json::Map::new();
+				sector.insert("lba".to_string(), serde_json::to_value(lba).unwrap());
+				sector.insert("offset".to_string(), serde_json::to_value(lba * sector_size).unwrap());
+				sector.insert("sources".to_string(), serde_json::to_value(sources).unwrap());
+				serde_json::Value::Object(sector)
+			}).collect();
+
+			let mut out = serde_json::Map::new();
+			out.insert("logical_sector_size".to_string(), serde_json::to_value(sector_size).unwrap());
+			out.insert("logs".to_string(), serde_json::to_value(&checked).unwrap());
+			out.insert("sectors".to_string(), serde_json::Value::Array(sectors));
+			print!("{}\n", serde_json::to_string(&out).unwrap());
+		} else {
+			// stdout is for the list only, so that it could be piped elsewhere
+			eprint!("{} problematic sector(s) found in {} log(s)\n", sectors.len(), checked.join(" and "));
+			for &lba in sectors.keys() {
+				print!("{}\n", if bytes { lba * sector_size } else { lba });
+			}
+		}
+
+		Ok(outcome)
+	}
+}
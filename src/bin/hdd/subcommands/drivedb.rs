@@ -0,0 +1,93 @@
+use hdd::drivedb::{Attribute, Entry, FirmwareBug};
+
+use clap::{
+	App,
+	AppSettings,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, open_drivedb, status, to_json_string};
+use status::{Outcome, Failure};
+use super::{Subcommand, arg_drivedb};
+
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+struct DumpEntry<'a> {
+	family: &'a str,
+	model: &'a str,
+	firmware: &'a str,
+	warning: &'a str,
+	presets: &'a str,
+	/// `None` if presets cannot be parsed, which is also why the raw string is there
+	attributes: Option<Vec<Attribute>>,
+	firmware_bugs: Option<Vec<FirmwareBug>>,
+	default: bool,
+}
+
+fn dump_entry<'a>(entry: &'a Entry, default: bool) -> DumpEntry<'a> {
+	let presets = entry.parse_presets();
+	if presets.is_none() {
+		eprint!("Cannot parse presets of drivedb entry {:?}: {:?}\n", entry.family, entry.presets);
+	}
+	let (attributes, firmware_bugs) = match presets {
+		Some((attributes, bugs)) => (Some(attributes), Some(bugs)),
+		None => (None, None),
+	};
+	DumpEntry {
+		family: &entry.family,
+		model: &entry.model,
+		firmware: &entry.firmware,
+		warning: &entry.warning,
+		presets: &entry.presets,
+		attributes,
+		firmware_bugs,
+		default,
+	}
+}
+
+fn dump(args: &ArgMatches) -> Result<Outcome, Failure> {
+	let drivedb = open_drivedb(args.values_of("drivedb"))
+		.ok_or_else(|| status::fail("Cannot load drivedb", status::COMMAND_FAILED))?;
+
+	// entries are listed in the order they are matched in, one per line, so that the output could be grepped, and still be a valid JSON
+	let entries: Vec<_> = drivedb.default_entry().map(|entry| dump_entry(entry, true)).into_iter()
+		.chain(drivedb.entries().iter().map(|entry| dump_entry(entry, false)))
+		.map(|entry| to_json_string(&entry))
+		.collect();
+	print!("[\n{}\n]\n", entries.join(",\n"));
+
+	Ok(Outcome::new())
+}
+
+pub struct Drivedb {}
+impl Subcommand for Drivedb {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("drivedb")
+			.about("Inspects drivedb (does not need a device)")
+			.setting(AppSettings::SubcommandRequired)
+			.subcommand(SubCommand::with_name("dump")
+				.about("Prints parsed drivedb entries as a JSON array, one entry per line (default entry first, then the rest in the order they are matched in)")
+				.arg(arg_drivedb())
+			)
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		if dev.is_some() {
+			// TODO show usage and whatnot
+			return Err(status::fail("<device> is redundant", status::USAGE));
+		};
+
+		match args.subcommand() {
+			("dump", Some(args)) => dump(args),
+			// clap should not allow subcommands that do not exist
+			_ => unreachable!(),
+		}
+	}
+}
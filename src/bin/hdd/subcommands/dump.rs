@@ -0,0 +1,75 @@
+use hdd::ata::misc::Misc;
+use hdd::ata::data::id::Ternary;
+use hdd::ata::dump::Recorder;
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+
+use std::path::Path;
+
+pub struct Dump {}
+impl Subcommand for Dump {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("dump")
+			.about("Saves raw replies to the commands other subcommands issue into a file, to be replayed later with --from-dump")
+			.arg(Arg::with_name("file")
+				.long("file")
+				.takes_value(true)
+				.required(true)
+				.value_name("FILE")
+				.help("File to save replies to")
+			)
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Dumps are only available for ATA devices", status::COMMAND_FAILED))?;
+
+		let recorder = Recorder::new(dev);
+
+		// errors are not fatal here: whatever fails is simply missing from the dump, and will look unsupported when replayed
+		let run = |name: &str, result: Result<(), _>| if let Err(e) = result {
+			eprint!("Cannot save {}: {}\n", name, e);
+		};
+
+		run("device identification", recorder.get_device_id_raw().map(|_| ()));
+		if id.smart == Ternary::Enabled {
+			run("S.M.A.R.T. health status", recorder.get_smart_health().map(|_| ()));
+			run("S.M.A.R.T. attributes", recorder.get_smart_attributes(&None).map(|_| ()));
+			run("SMART Log Directory", recorder.get_smart_log_directory().map(|_| ()));
+			if id.sct_supported {
+				run("SCT status", recorder.get_sct_status().map(|_| ()));
+			}
+		}
+		if id.gp_logging_supported {
+			run("General Purpose Log Directory", recorder.get_gp_log_directory().map(|_| ()));
+			run("NCQ Command Error log", recorder.get_ncq_error_log().map(|_| ()));
+		}
+		if id.trusted_computing_supported {
+			run("TCG Level 0 Discovery", recorder.get_tcg_discovery().map(|_| ()));
+		}
+
+		// unwrap: clap makes sure this one is present
+		let file = args.value_of("file").unwrap();
+		recorder.into_dump().save(file)
+			.map_err(|e| status::fail(format!("Cannot save dump to {}: {}", file, e), status::COMMAND_FAILED))?;
+
+		Ok(Outcome::new())
+	}
+}
@@ -0,0 +1,233 @@
+use hdd::ata::DownloadMicrocodeMode;
+use hdd::ata::misc::Misc;
+use hdd::ata::data::id;
+use hdd::scsi::SCSICommon;
+use hdd::scsi::data::{inquiry, sense};
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+
+use std::cmp::min;
+use std::fs;
+use std::path::Path;
+
+// this is what ATA PASS-THROUGH can carry with EXTEND=0
+const SAT_MAX_BLOCKS: usize = 0xff;
+// WRITE BUFFER chunk size; SG_IO transfers of up to 64 KiB are fine with every HBA out there
+const SCSI_CHUNK: usize = 0x10000;
+
+fn failed<S: Into<String>>(msg: S) -> Failure {
+	status::fail(msg, status::COMMAND_FAILED)
+}
+
+/**
+Picks the size of DOWNLOAD MICROCODE segments, in 512-byte blocks, for an image of `blocks` blocks.
+
+Every segment, the last one included, must be within the limits the device reports (IDENTIFY words 234 and 235), and also fit into one ATA PASS-THROUGH command. Segments are kept multiples of the minimum size, so that offsets stay aligned to it.
+*/
+fn segment_size(blocks: usize, min_blocks: Option<u16>, max_blocks: Option<u16>) -> Result<usize, String> {
+	let max = min(max_blocks.map(|x| x as usize).unwrap_or(SAT_MAX_BLOCKS), SAT_MAX_BLOCKS);
+	let min_blocks = min_blocks.map(|x| x as usize).unwrap_or(1);
+
+	if max < min_blocks {
+		return Err(format!("Device requires segments of at least {} blocks, which is more than can be sent at once", min_blocks));
+	}
+	let chunk = max - max % min_blocks;
+
+	let last = match blocks % chunk {
+		0 => chunk,
+		x => x,
+	};
+	if last < min_blocks {
+		return Err(format!("Firmware image would end with a segment of {} blocks, while device requires segments of at least {} blocks", last, min_blocks));
+	}
+
+	Ok(chunk)
+}
+
+// returns the (reported) logical unit's firmware revision
+fn download_ata(dev: &Misc, id: &id::Id, image: &[u8]) -> Result<String, Failure> {
+	if !id.commands_supported.download_microcode {
+		return Err(failed("DOWNLOAD MICROCODE is not supported"));
+	}
+	if image.len() % 512 != 0 {
+		return Err(failed("Firmware image size must be a multiple of 512 bytes"));
+	}
+
+	let blocks = image.len() / 512;
+
+	// errors (ERR bit of the status register) come back as `misc::Error::Device`
+	if id.commands_supported.download_microcode_segmented {
+		let chunk = segment_size(blocks, id.download_microcode_min, id.download_microcode_max)
+			.map_err(failed)?;
+		if blocks > 0xffff {
+			return Err(failed("Firmware image is too big"));
+		}
+
+		print!("Downloading {} blocks in segments of {} blocks\n", blocks, chunk);
+
+		for (i, segment) in image.chunks(chunk * 512).enumerate() {
+			let offset = i * chunk;
+			dev.download_microcode(DownloadMicrocodeMode::Offsets, offset as u16, segment)
+				.map_err(|err| failed(format!("DOWNLOAD MICROCODE failed at block {}: {}", offset, err)))?;
+			print!("Sent {} of {} blocks\n", offset + segment.len() / 512, blocks);
+		}
+	} else {
+		if blocks > SAT_MAX_BLOCKS {
+			return Err(failed("Device does not support segmented download, and firmware image is too big to be sent at once"));
+		}
+
+		dev.download_microcode(DownloadMicrocodeMode::Full, 0, image)
+			.map_err(|err| failed(format!("DOWNLOAD MICROCODE failed: {}", err)))?;
+	}
+
+	dev.get_device_id()
+		.map(|id| id.firmware)
+		.map_err(|err| failed(format!("Firmware is downloaded, but device identification cannot be read: {}", err)))
+}
+
+fn check_sense(sense: &Vec<u8>, offset: usize) -> Result<(), Failure> {
+	if let Some((true, sense)) = sense::parse(sense) {
+		match sense.kcq() {
+			// No Sense, Recovered Error
+			Some((0x00, _, _)) | Some((0x01, _, _)) | None => (),
+			Some((key, asc, ascq)) => return Err(failed(format!("Device rejected firmware image at byte {}: {:?} ({})",
+				offset,
+				sense::key::SenseKey::from(key),
+				sense::key::decode_asc(asc, ascq)
+					.map(|x| x.to_string())
+					.unwrap_or_else(|| format!("unknown additional sense code: {:02x} {:02x}", asc, ascq)),
+			))),
+		}
+	}
+	Ok(())
+}
+
+fn download_scsi<T: SCSICommon>(dev: &T, image: &[u8]) -> Result<String, Failure> {
+	// BUFFER OFFSET is 24 bits wide
+	if image.len() > 0xff_ffff {
+		return Err(failed("Firmware image is too big"));
+	}
+
+	if image.len() <= SCSI_CHUNK {
+		// mode 05h: download microcode with save
+		let sense = dev.write_buffer(0x05, 0, 0, image)
+			.map_err(|err| failed(format!("WRITE BUFFER failed: {}", err)))?;
+		check_sense(&sense, 0)?;
+	} else {
+		print!("Downloading {} bytes in chunks of {} bytes\n", image.len(), SCSI_CHUNK);
+
+		// mode 07h: download microcode with offsets, save, and activate (once the last chunk is received)
+		for (i, chunk) in image.chunks(SCSI_CHUNK).enumerate() {
+			let offset = i * SCSI_CHUNK;
+			let sense = dev.write_buffer(0x07, 0, offset as u32, chunk)
+				.map_err(|err| failed(format!("WRITE BUFFER failed at byte {}: {}", offset, err)))?;
+			check_sense(&sense, offset)?;
+			print!("Sent {} of {} bytes\n", offset + chunk.len(), image.len());
+		}
+	}
+
+	dev.scsi_inquiry(false, 0)
+		.map(|(_sense, data)| inquiry::parse_inquiry(&data).product_rev)
+		.map_err(|err| failed(format!("Firmware is downloaded, but INQUIRY failed: {}", err)))
+}
+
+pub struct Firmware {}
+impl Subcommand for Firmware {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("firmware")
+			.about("Updates firmware of the device (DANGEROUS)")
+			.arg(Arg::with_name("file")
+				.long("file")
+				.takes_value(true)
+				.required(true)
+				.value_name("IMG")
+				.help("Firmware image to download to the device")
+			)
+			.arg(Arg::with_name("yes")
+				.long("yes")
+				.help("Confirm that you understand the consequences")
+			)
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		// unwrap: clap makes sure this one is present
+		let file = args.value_of("file").unwrap();
+		let image = fs::read(file)
+			.map_err(|err| failed(format!("Cannot read {}: {}", file, err)))?;
+		if image.is_empty() {
+			return Err(failed("Firmware image is empty"));
+		}
+
+		eprint!("\n══════ WARNING ══════\n");
+		eprint!("Firmware update can render the device (and all the data on it) permanently unusable\n");
+		eprint!("if the image is not meant for this exact model, or if the update is interrupted.\n");
+		eprint!("Make sure you have a backup, and that nothing else is using the device.\n");
+		eprint!("═════════════════════\n\n");
+
+		if !args.is_present("yes") {
+			return Err(status::fail("Refusing to continue without --yes", status::USAGE));
+		}
+
+		let (old, new) = match (dev.ata(), dev) {
+			(Some((ata, id)), _) => (id.firmware.clone(), download_ata(ata, id, &image)?),
+			(None, DeviceArgument::SCSI(dev)) => {
+				let (_sense, data) = dev.scsi_inquiry(false, 0)
+					.map_err(|err| failed(format!("INQUIRY failed: {}", err)))?;
+				let old = inquiry::parse_inquiry(&data).product_rev;
+				(old, download_scsi(dev, &image)?)
+			},
+			(None, _) => unreachable!(),
+		};
+
+		print!("Firmware revision: {} → {}\n", old, new);
+		if old == new {
+			print!("Revision is unchanged: device might need to be power-cycled to activate the new firmware\n");
+		}
+
+		Ok(Outcome::new())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn segments_fit_pass_through() {
+		assert_eq!(segment_size(1000, None, None), Ok(SAT_MAX_BLOCKS));
+		assert_eq!(segment_size(1000, None, Some(0x400)), Ok(SAT_MAX_BLOCKS));
+		assert_eq!(segment_size(1000, Some(1), Some(128)), Ok(128));
+	}
+
+	#[test]
+	fn segments_are_aligned_to_minimum() {
+		// 255 is not a multiple of 8
+		assert_eq!(segment_size(1024, Some(8), None), Ok(248));
+		assert!(segment_size(256, Some(0x100), Some(0x100)).is_err());
+	}
+
+	#[test]
+	fn last_segment_is_checked() {
+		// 1000 = 4 * 248 + 8 is fine, 1000 = 7 * 128 + 104 is not
+		assert_eq!(segment_size(1000, Some(8), Some(248)), Ok(248));
+		assert!(segment_size(1000, Some(128), Some(128)).is_err());
+		assert_eq!(segment_size(1024, Some(128), Some(128)), Ok(128));
+	}
+}
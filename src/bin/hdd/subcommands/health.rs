@@ -1,15 +1,12 @@
-use hdd::ata::misc::Misc;
-
 use clap::{
 	App,
 	ArgMatches,
 	SubCommand,
 };
 
-use serde_json;
-use serde_json::value::ToJson;
 
-use ::{DeviceArgument, when_smart_enabled};
+use ::{DeviceArgument, when_smart_enabled, status, to_json_string};
+use status::{Outcome, Failure};
 use super::{Subcommand, arg_json};
 
 use std::path::Path;
@@ -27,32 +24,23 @@ impl Subcommand for Health {
 		_: &Option<&Path>,
 		dev: &Option<&DeviceArgument>,
 		args: &ArgMatches,
-	) {
-		let dev = dev.unwrap_or_else(|| {
-			// TODO show usage and whatnot
-			eprint!("<device> is required\n");
-			::std::process::exit(1);
-		});
-
-		let id = match dev {
-			#[cfg(not(target_os = "linux"))]
-			DeviceArgument::ATA(_, id) => id,
-			DeviceArgument::SAT(_, id) => id,
-			DeviceArgument::SCSI(_) => unimplemented!(),
-		};
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Health status is only available for ATA devices", status::COMMAND_FAILED))?;
 
 		let use_json = args.is_present("json");
 
-		when_smart_enabled(&id.smart, "health status", || {
-			let status = match dev {
-				#[cfg(not(target_os = "linux"))]
-				DeviceArgument::ATA(dev, _) => dev.get_smart_health().unwrap(),
-				DeviceArgument::SAT(dev, _) => dev.get_smart_health().unwrap(),
-				DeviceArgument::SCSI(_) => unimplemented!(),
-			};
+		when_smart_enabled(&id.smart, "health status", use_json, || {
+			let status = dev.get_smart_health()
+				.map_err(|err| status::fail(format!("Cannot read S.M.A.R.T. health status: {}", err), status::COMMAND_FAILED))?;
+			let mut outcome = Outcome::new();
+			outcome.health(status);
 
 			if use_json {
-				print!("{}\n", serde_json::to_string(&status.to_json().unwrap()).unwrap());
+				print!("{}\n", to_json_string(&status));
 			} else {
 				print!("S.M.A.R.T. health status: {}\n", match status {
 					Some(true) => "good",
@@ -60,6 +48,49 @@ impl Subcommand for Health {
 					None => "(unknown)",
 				});
 			}
-		});
+
+			Ok(outcome)
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hdd::ata::{RegistersRead, RegistersWrite, SMARTFeature};
+	use mock;
+
+	fn health(reply: RegistersRead) -> Result<Outcome, Failure> {
+		let mut dump = mock::dump(mock::SMART_ENABLED);
+		dump.record(&RegistersWrite::smart(SMARTFeature::ReturnStatus, 0, 0), &reply, &[]);
+		mock::run(&Health {}, &mock::device(dump), &["--json"])
+	}
+
+	#[test]
+	fn good() {
+		let reply = RegistersRead { cyl_low: 0x4f, cyl_high: 0xc2, ..mock::ok() };
+		assert_eq!(health(reply).unwrap().bits(), 0);
+	}
+
+	#[test]
+	fn bad() {
+		let reply = RegistersRead { cyl_low: 0xf4, cyl_high: 0x2c, ..mock::ok() };
+		assert_eq!(health(reply).unwrap().bits(), status::HEALTH_BAD);
+	}
+
+	#[test]
+	fn command_fails() {
+		let failure = health(mock::aborted()).unwrap_err();
+		assert_eq!(failure.bits(), status::COMMAND_FAILED);
+		assert!(failure.message().unwrap().starts_with("Cannot read S.M.A.R.T. health status"));
+	}
+
+	#[test]
+	fn smart_disabled() {
+		let dev = mock::device(mock::dump(&[(82, 1 << 0)]));
+		let failure = mock::run(&Health {}, &dev, &["--json"]).unwrap_err();
+		assert_eq!(failure.bits(), status::SMART_UNAVAILABLE);
+		// JSON error is already printed
+		assert_eq!(failure.message(), None);
 	}
 }
@@ -1,37 +1,182 @@
+use hdd::ata::misc::Misc;
 use hdd::ata::data::id;
+use hdd::ata::data::smart_capabilities::SmartCapabilities;
 use hdd::drivedb;
 use hdd::scsi::SCSICommon;
 use hdd::scsi::data::inquiry;
+use hdd::tcg;
 
 use clap::{
 	ArgMatches,
 	App,
+	Arg,
 	SubCommand,
 };
 
 use serde_json;
-use serde_json::value::ToJson;
 
 use separator::Separatable;
-use number_prefix::{decimal_prefix, binary_prefix, Standalone, Prefixed};
-
-use ::{DeviceArgument, open_drivedb};
-use super::{Subcommand, arg_json, arg_drivedb};
+use ::{DeviceArgument, drivedb_warning_json, open_drivedb, status};
+use status::{Outcome, Failure};
+use super::{Subcommand, arg_json, arg_drivedb, arg_units, arg_precision, Units, units, format_bytes};
 
 use std::path::Path;
 
+/// How much of the device information to show
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Verbosity {
+	/// One line: model, serial, capacity, health (`--brief`)
+	Brief,
+	Normal,
+	/// Also feature bits that are hardly of any interest to most users (`--verbose-info`)
+	Verbose,
+	/// Also device type and supported commands (`--verbose-info` twice)
+	All,
+}
+
+fn verbosity(args: &ArgMatches) -> Verbosity {
+	if args.is_present("brief") {
+		return Verbosity::Brief;
+	}
+	match args.occurrences_of("verbose-info") {
+		0 => Verbosity::Normal,
+		1 => Verbosity::Verbose,
+		_ => Verbosity::All,
+	}
+}
+
 fn bool_to_sup(b: bool) -> &'static str {
 	if b { "supported" }
 	else { "not supported" }
 }
 
-fn print_ata_id(id: &id::Id, meta: &Option<drivedb::DriveMeta>) {
-	if id.incomplete { print!("WARNING: device reports information it provides is incomplete\n\n"); }
+fn print_capacity(id: &id::Id, units: Units, precision: usize) {
+	match units {
+		Units::Auto => {
+			print!("Capacity: {} bytes\n", id.capacity.separated_string());
+			print!("          ({}, {})\n",
+				format_bytes(id.capacity, Units::Decimal, precision),
+				format_bytes(id.capacity, Units::Binary, precision),
+			);
+		},
+		Units::Bytes => print!("Capacity: {} bytes\n", id.capacity.separated_string()),
+		Units::Sectors => match id.capacity.checked_div(id.sector_size_log as u64) {
+			Some(sectors) => print!("Capacity: {} sectors of {} bytes\n", sectors.separated_string(), id.sector_size_log),
+			// see `arg_units()`
+			None => print!("Capacity: {} bytes\n", id.capacity.separated_string()),
+		},
+		units => print!("Capacity: {}\n", format_bytes(id.capacity, units, precision)),
+	}
+}
 
-	// XXX id.is_ata is deemed redundant and is skipped
-	// XXX we're skipping id.commands_supported for now as it is hardly of any interest to users
+fn udma_mode(mode: u8) -> String {
+	match id::udma_rate(mode) {
+		Some(rate) => format!("UDMA/{}", rate),
+		None => format!("UDMA mode {}", mode),
+	}
+}
+
+fn print_transfer_modes(modes: &id::TransferModes) {
+	// a drive stuck in a lower mode than it supports (bad cable, controller limits, errors on the link) is slower than it should be
+	let current = |selected: Option<String>| match selected {
+		Some(mode) => format!(" (current: {})", mode),
+		None => " (current: none)".to_string(),
+	};
+
+	print!("Transfer modes:\n");
+	if let Some(pio) = modes.pio_max {
+		print!("  PIO:           0-{}\n", pio);
+	}
+	match modes.mwdma_max {
+		Some(max) => print!("  Multiword DMA: 0-{}{}\n", max, current(modes.mwdma_selected.map(|m| m.to_string()))),
+		None => print!("  Multiword DMA: not supported\n"),
+	}
+	match modes.udma_max {
+		Some(max) => print!("  Ultra DMA:     {}{}\n", udma_mode(max), current(modes.udma_selected.map(udma_mode))),
+		None => print!("  Ultra DMA:     not supported\n"),
+	}
+}
+
+fn print_commands(commands: &id::IdCommands) {
+	let names: Vec<_> = vec![
+		(commands.device_reset, "DEVICE RESET"),
+		(commands.nop, "NOP"),
+		(commands.read_buffer, "READ BUFFER"),
+		(commands.write_buffer, "WRITE BUFFER"),
+		(commands.download_microcode, "DOWNLOAD MICROCODE"),
+		(commands.download_microcode_segmented, "DOWNLOAD MICROCODE with offsets"),
+		(commands.flush_cache, "FLUSH CACHE"),
+		(commands.flush_cache_ext, "FLUSH CACHE EXT"),
+		(commands.read_write_dma_queued, "READ/WRITE DMA QUEUED"),
+		(commands.write_dma_fua_ext, "WRITE DMA FUA EXT"),
+		(commands.write_dma_queued_fua_ext, "WRITE DMA QUEUED FUA EXT"),
+		(commands.write_uncorrectable, "WRITE UNCORRECTABLE EXT"),
+		(commands.read_write_dma_ext_gpl, "READ/WRITE LOG DMA EXT"),
+	].into_iter()
+		.filter(|&(sup, _)| sup)
+		.map(|(_, name)| name)
+		.collect();
+
+	print!("Commands supported:\n");
+	if names.is_empty() {
+		print!("  none of the optional ones\n");
+	}
+	for name in names {
+		print!("  {}\n", name);
+	}
+}
+
+// feature bits that are only shown with -v
+// fields only shown with `--verbose-info`, as (label, value) pairs
+fn verbose_features(id: &id::Id) -> Vec<(&'static str, String)> {
+	let mut fields = vec![
+		("World wide name", bool_to_sup(id.wwn_supported).to_string()),
+		("SCT Command Transport", bool_to_sup(id.sct_supported).to_string()),
+	];
+	if let Some(sectors) = id.multiple_sectors {
+		fields.push(("READ/WRITE MULTIPLE", format!("{} sector(s) per block", sectors)));
+	}
+	match (id.download_microcode_min, id.download_microcode_max) {
+		(Some(min), Some(max)) => fields.push(("DOWNLOAD MICROCODE blocks", format!("{}-{} per transfer", min, max))),
+		(Some(min), None) => fields.push(("DOWNLOAD MICROCODE blocks", format!("at least {} per transfer", min))),
+		(None, Some(max)) => fields.push(("DOWNLOAD MICROCODE blocks", format!("at most {} per transfer", max))),
+		(None, None) => (),
+	}
+	if id.security_state.supported {
+		fields.push(("Security password", if id.security_state.enabled { "set" } else { "not set" }.to_string()));
+		fields.push(("Master password capability", if id.security_state.master_password_maximum { "maximum" } else { "high" }.to_string()));
+	}
+	if id.sanitize.supported {
+		fields.push(("Sanitize antifreeze lock", bool_to_sup(id.sanitize.antifreeze_lock).to_string()));
+	}
+	fields.push(("IDENTIFY DEVICE checksum", match id.checksum_valid {
+		Some(true) => "valid",
+		Some(false) => "INVALID",
+		None => "not provided",
+	}.to_string()));
+	fields
+}
+
+fn print_verbose_features(id: &id::Id) {
+	for (label, value) in verbose_features(id) {
+		print!("{:<31}{}\n", format!("{}:", label), value);
+	}
+}
 
+pub fn print_ata_id(id: &id::Id, meta: &Option<drivedb::DriveMeta>, caps: &Option<SmartCapabilities>, units: Units, precision: usize, verbosity: Verbosity) {
+	if id.incomplete { print!("WARNING: device reports information it provides is incomplete\n\n"); }
+
+	if verbosity >= Verbosity::All {
+		print!("Type:     {}\n", if id.is_ata { "ATA" } else { "ATAPI" });
+	}
 	print!("Model:    {}\n", id.model);
+	if !id.is_ata {
+		// IDENTIFY PACKET DEVICE reply: words describing capacity, rotation, features and such mean something else (or nothing at all) here
+		print!("Firmware: {}\n", id.firmware);
+		print!("Serial:   {}\n", id.serial);
+		print!("\nThis is an ATAPI (packet) device; capacity and ATA features are not reported\n");
+		return;
+	}
 	match id.rpm {
 		id::RPM::Unknown => (),
 		id::RPM::NonRotating => print!("RPM:      N/A (SSD or other non-rotating media)\n"),
@@ -47,6 +192,10 @@ fn print_ata_id(id: &id::Id, meta: &Option<drivedb::DriveMeta>) {
 		} else {
 			print!("This drive is not in the drive database\n");
 		}
+		if !meta.firmware_bugs.is_empty() {
+			let bugs: Vec<_> = meta.firmware_bugs.iter().map(|bug| bug.name()).collect();
+			print!("Firmware bug workarounds: {}\n", bugs.join(", "));
+		}
 		if let Some(warning) = meta.warning {
 			print!("\n══════ WARNING ══════\n{}\n═════════════════════\n", warning);
 		}
@@ -54,19 +203,29 @@ fn print_ata_id(id: &id::Id, meta: &Option<drivedb::DriveMeta>) {
 
 	print!("\n");
 
-	print!("Capacity: {} bytes\n", id.capacity.separated_string());
-	print!("          ({}, {})\n",
-		match decimal_prefix(id.capacity as f32) {
-			Prefixed(p, x) => format!("{:.1} {}B", x, p),
-			Standalone(x)  => format!("{} bytes", x),
-		},
-		match binary_prefix(id.capacity as f32) {
-			Prefixed(p, x) => format!("{:.1} {}B", x, p),
-			Standalone(x)  => format!("{} bytes", x),
-		},
-	);
+	print_capacity(id, units, precision);
 	print!("Sector size (logical):  {}\n", id.sector_size_log);
 	print!("Sector size (physical): {}\n", id.sector_size_phy);
+	if id.zoned != id::Zoned::NotReported {
+		print!("Zoned device:           {}\n", id.zoned);
+	}
+	if id.sector_size_overridden {
+		print!("(sector sizes and capacity are overridden from the command line, not reported by the device)\n");
+	}
+	if id.sector_size_phy > id.sector_size_log {
+		// checked_div: sector size might not be reported, or come from a garbled IDENTIFY
+		match (id.sector_alignment, id.sector_size_phy.checked_div(id.sector_size_log)) {
+			(Some(offset), Some(per_physical)) if offset != 0 => {
+				print!("\nNote: this is a drive with {}-byte logical sectors emulated on top of {}-byte physical ones,\n",
+					id.sector_size_log, id.sector_size_phy);
+				print!("and its LBA 0 starts {} logical sector(s) into a physical sector.\n", offset);
+				print!("Partitions are aligned when (starting LBA + {}) is a multiple of {};\n",
+					offset, per_physical);
+				print!("misaligned partitions cause read-modify-write cycles and slow writes.\n");
+			},
+			_ => (),
+		}
+	}
 
 	print!("\n");
 
@@ -74,6 +233,10 @@ fn print_ata_id(id: &id::Id, meta: &Option<drivedb::DriveMeta>) {
 
 	print!("\n");
 
+	print_transfer_modes(&id.transfer_modes);
+
+	print!("\n");
+
 	// The following guide, when printed, is exactly 80 characters
 	// ... "..............................................................supported disabled\n"
 	print!("Host protected area:           {}\n", id.hpa);
@@ -84,17 +247,255 @@ fn print_ata_id(id: &id::Id, meta: &Option<drivedb::DriveMeta>) {
 	print!("Power management:              {}\n", bool_to_sup(id.power_mgmt_supported));
 	print!("General purpose logging:       {}\n", bool_to_sup(id.gp_logging_supported));
 	print!("Trusted computing:             {}\n", bool_to_sup(id.trusted_computing_supported));
+	print!("Encryption of all user data:   {}\n", bool_to_sup(id.encrypts_all_user_data));
 	print!("ATA security:                  {}\n", id.security);
+	if id.security_state.supported {
+		let state = &id.security_state;
+		let flags: Vec<_> = vec![
+			(state.locked, "LOCKED"),
+			(state.frozen, "FROZEN"),
+			(state.count_expired, "unlock attempts exceeded"),
+			(state.enhanced_erase_supported, "enhanced erase supported"),
+		].into_iter()
+			.filter(|&(set, _)| set)
+			.map(|(_, name)| name)
+			.collect();
+		if !flags.is_empty() {
+			print!("  state: {}\n", flags.join(", "));
+		}
+	}
+	print!("Sanitize:                      {}\n", bool_to_sup(id.sanitize.supported));
+	if id.sanitize.supported {
+		let methods: Vec<_> = vec![
+			(id.sanitize.block_erase, "block erase"),
+			(id.sanitize.overwrite, "overwrite"),
+			(id.sanitize.crypto_scramble, "crypto scramble"),
+		].into_iter()
+			.filter(|&(sup, _)| sup)
+			.map(|(_, name)| name)
+			.collect();
+		print!("  methods: {}\n", if methods.is_empty() { "none".to_string() } else { methods.join(", ") });
+	}
+
+	// non-SATA devices have none of these
+	let sata: Vec<_> = id::SataFeature::all().iter()
+		.map(|&feature| (feature, id.sata_features.get(feature)))
+		.filter(|&(_, state)| state != id::Ternary::Unsupported)
+		.collect();
+	if !sata.is_empty() {
+		print!("Serial ATA features:\n");
+		for (feature, state) in sata {
+			print!("  {:<50} {}\n", format!("{} ({}):", feature.description(), feature.name()), state);
+		}
+	}
+	if verbosity >= Verbosity::Verbose {
+		print_verbose_features(id);
+	}
 
 	print!("\n");
 
+	if verbosity >= Verbosity::All {
+		print_commands(&id.commands_supported);
+		print!("\n");
+	}
+
 	print!("S.M.A.R.T.:    {}\n", id.smart);
 	print!("Error logging: {}\n", bool_to_sup(id.smart_error_logging_supported));
 	print!("Self-test:     {}\n", bool_to_sup(id.smart_self_test_supported));
+	// these decide how often attribute values are updated
+	if let Some(caps) = caps {
+		print!("Auto off-line: {}\n", match (caps.auto_offline_supported, caps.auto_offline_enabled) {
+			(_, true) => "enabled",
+			(true, false) => "disabled",
+			(false, false) => "not supported",
+		});
+		print!("Off-line data: collection {}; takes ~{}s\n", caps.offline_collection_status, caps.offline_collection_time);
+		print!("Autosave:      {}\n", if caps.attribute_autosave_supported {
+			"supported (devices do not report whether it is enabled)"
+		} else { "not supported" });
+	}
+
+	print!("\n");
+}
+
+// one line for `--brief`: model, serial, capacity, health
+fn brief(dev: &Misc, id: &id::Id, units: Units, precision: usize, outcome: &mut Outcome) -> String {
+	let capacity = match units {
+		// there's no room for both
+		Units::Auto => format_bytes(id.capacity, Units::Decimal, precision),
+		Units::Bytes => format!("{} bytes", id.capacity.separated_string()),
+		// sector size might not be reported, or come from a garbled IDENTIFY
+		Units::Sectors => match id.capacity.checked_div(id.sector_size_log as u64) {
+			Some(sectors) => format!("{} sectors", sectors.separated_string()),
+			None => format!("{} bytes", id.capacity.separated_string()),
+		},
+		units => format_bytes(id.capacity, units, precision),
+	};
+
+	let health = if id.smart == id::Ternary::Enabled {
+		match dev.get_smart_health() {
+			Ok(health) => {
+				outcome.health(health);
+				match health {
+					Some(true) => "good",
+					Some(false) => "BAD",
+					None => "unknown",
+				}
+			},
+			Err(err) => {
+				eprint!("Cannot query S.M.A.R.T. health status: {}\n", err);
+				outcome.set(status::COMMAND_FAILED);
+				"unknown"
+			},
+		}
+	} else { "S.M.A.R.T. disabled" };
+
+	if id.is_ata {
+		format!("{}, serial {}, {}, health: {}", id.model, id.serial, capacity, health)
+	} else {
+		format!("{}, serial {} (ATAPI)", id.model, id.serial)
+	}
+}
+
+fn print_tcg(discovery: &Option<tcg::Discovery>) {
+	let discovery = match discovery {
+		Some(discovery) => discovery,
+		None => {
+			print!("TCG Level 0 Discovery: no data\n\n");
+			return;
+		},
+	};
+
+	print!("TCG Level 0 Discovery (revision {}):\n", discovery.revision);
+
+	if discovery.ssc.is_empty() {
+		print!("Security subsystem class: none\n");
+	}
+	for ssc in &discovery.ssc {
+		print!("Security subsystem class: {} (feature {:04x}h, version {}), base ComID {:04x}h, {} ComID(s)\n",
+			ssc.name, ssc.feature_code, ssc.version, ssc.base_comid, ssc.comids);
+	}
+
+	match &discovery.locking {
+		None => print!("Locking: not supported\n"),
+		Some(l) => {
+			print!("Locking:          {}\n", match (l.supported, l.enabled) {
+				(false, _) => "not supported",
+				(true, false) => "supported, disabled",
+				(true, true) => "enabled",
+			});
+			print!("Locked:           {}\n", if l.locked { "yes" } else { "no" });
+			print!("Media encryption: {}\n", bool_to_sup(l.media_encryption));
+			print!("Shadow MBR:       {}{}\n",
+				if l.mbr_enabled { "enabled" } else { "disabled" },
+				if l.mbr_enabled && l.mbr_done { ", done" } else { "" },
+			);
+		},
+	}
 
 	print!("\n");
 }
 
+// what IDENTIFY DEVICE words are, for --identify-raw --annotate
+// see ACS-3, Table 45 IDENTIFY DEVICE data
+static ID_FIELDS: [(usize, usize, &str); 60] = [
+	(0, 0, "general configuration"),
+	(1, 1, "obsolete (number of logical cylinders)"),
+	(2, 2, "specific configuration"),
+	(3, 3, "obsolete (number of logical heads)"),
+	(4, 6, "retired, obsolete"),
+	(7, 8, "reserved for CFA"),
+	(9, 9, "retired"),
+	(10, 19, "serial number"),
+	(20, 22, "retired, obsolete"),
+	(23, 26, "firmware revision"),
+	(27, 46, "model number"),
+	(47, 47, "READ/WRITE MULTIPLE: max sectors per transfer"),
+	(48, 48, "trusted computing feature set options"),
+	(49, 50, "capabilities"),
+	(51, 52, "obsolete"),
+	(53, 53, "field validity, free-fall control sensitivity"),
+	(54, 58, "obsolete (CHS)"),
+	(59, 59, "multiple sector setting, sanitize features"),
+	(60, 61, "total number of user addressable sectors (28-bit)"),
+	(62, 62, "obsolete"),
+	(63, 63, "Multiword DMA transfer modes"),
+	(64, 64, "PIO transfer modes"),
+	(65, 68, "DMA and PIO transfer cycle times"),
+	(69, 69, "additional supported features"),
+	(70, 74, "reserved"),
+	(75, 75, "queue depth"),
+	(76, 79, "Serial ATA capabilities and features"),
+	(80, 80, "major version number"),
+	(81, 81, "minor version number"),
+	(82, 84, "commands and feature sets supported"),
+	(85, 87, "commands and feature sets supported or enabled"),
+	(88, 88, "Ultra DMA modes"),
+	(89, 90, "time required for (enhanced) security erase"),
+	(91, 91, "current APM level"),
+	(92, 92, "master password identifier"),
+	(93, 93, "hardware reset results"),
+	(94, 94, "obsolete (AAM)"),
+	(95, 99, "streaming"),
+	(100, 103, "total number of user addressable sectors (48-bit)"),
+	(104, 104, "streaming transfer time (PIO)"),
+	(105, 105, "DATA SET MANAGEMENT: max 512-byte blocks"),
+	(106, 106, "physical sector size / logical sector size"),
+	(107, 107, "inter-seek delay for ISO 7779 acoustic testing"),
+	(108, 111, "world wide name"),
+	(112, 116, "reserved"),
+	(117, 118, "logical sector size"),
+	(119, 120, "commands and feature sets supported or enabled (continued)"),
+	(121, 127, "reserved"),
+	(128, 128, "security status"),
+	(129, 159, "vendor specific"),
+	(160, 167, "reserved for CFA"),
+	(168, 168, "device nominal form factor"),
+	(169, 169, "DATA SET MANAGEMENT command support"),
+	(176, 205, "current media serial number"),
+	(206, 206, "SCT Command Transport"),
+	(209, 209, "alignment of logical blocks within a physical block"),
+	(210, 213, "Write-Read-Verify sector counts"),
+	(217, 217, "nominal media rotation rate"),
+	(222, 223, "transport major and minor version number"),
+	(230, 235, "extended number of user addressable sectors, DOWNLOAD MICROCODE limits"),
+];
+
+fn id_field(word: usize) -> &'static str {
+	match word {
+		255 => "integrity word",
+		w => ID_FIELDS.iter()
+			.find(|&&(start, end, _)| start <= w && w <= end)
+			.map(|&(_, _, name)| name)
+			.unwrap_or("reserved or obsolete"),
+	}
+}
+
+fn id_words(data: &[u8]) -> Vec<u16> {
+	// ATA words are little-endian
+	data.chunks(2)
+		.map(|w| ((w[1] as u16) << 8) + (w[0] as u16))
+		.collect()
+}
+
+fn print_ata_id_raw(words: &[u16], annotate: bool) {
+	print!("IDENTIFY DEVICE data:\n");
+	if annotate {
+		for (i, w) in words.iter().enumerate() {
+			print!("{:3}: {:04x}  {}\n", i, w, id_field(i));
+		}
+	} else {
+		for (i, row) in words.chunks(8).enumerate() {
+			print!("{:3}:", i * 8);
+			for w in row {
+				print!(" {:04x}", w);
+			}
+			print!("\n");
+		}
+	}
+	print!("\n");
+}
+
 fn print_scsi_id(inquiry: &inquiry::Inquiry) {
 	print!("Vendor:   {}\n", inquiry.vendor_id);
 	print!("Model:    {}\n", inquiry.product_id);
@@ -110,6 +511,32 @@ impl Subcommand for Info {
 			.about("Prints a basic information about the device")
 			.arg(arg_json())
 			.arg(arg_drivedb())
+			.arg(arg_units())
+			.arg(arg_precision())
+			.arg(Arg::with_name("identify-raw")
+				.long("identify-raw")
+				.help("Also dump all 256 words of IDENTIFY DEVICE data (like 'hdparm --Istdout')")
+			)
+			.arg(Arg::with_name("annotate")
+				.long("annotate")
+				.requires("identify-raw")
+				.help("Annotate --identify-raw dump with the fields words belong to")
+			)
+			.arg(Arg::with_name("tcg")
+				.long("tcg")
+				.help("Also issue TCG Level 0 Discovery to show self-encrypting drive (Opal &c) details")
+			)
+			// no `-v`: that one is a vendor attribute elsewhere (see `attrs`), as in smartctl
+			.arg(Arg::with_name("verbose-info")
+				.long("verbose-info")
+				.multiple(true)
+				.help("Also show feature bits omitted by default; repeat it to also list device type and supported commands")
+			)
+			.arg(Arg::with_name("brief")
+				.long("brief")
+				.conflicts_with_all(&["verbose-info", "json", "identify-raw", "tcg"])
+				.help("Print a single line: model, serial number, capacity and health status")
+			)
 	}
 
 	fn run(
@@ -117,35 +544,79 @@ impl Subcommand for Info {
 		_: &Option<&Path>,
 		dev: &Option<&DeviceArgument>,
 		args: &ArgMatches,
-	) {
-		let dev = dev.unwrap_or_else(|| {
-			// TODO show usage and whatnot
-			eprint!("<device> is required\n");
-			::std::process::exit(1);
-		});
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
 
-		let ata_id = match dev {
-			#[cfg(not(target_os = "linux"))]
-			DeviceArgument::ATA(_, id) => Some(id),
-			DeviceArgument::SAT(_, id) => Some(id),
-			DeviceArgument::SCSI(_) => None,
-		};
+		let ata = dev.ata();
 
 		let use_json = args.is_present("json");
+		let verbosity = verbosity(args);
 
 		if let DeviceArgument::SCSI(dev) = dev {
-			let (_sense, data) = dev.scsi_inquiry(false, 0).unwrap();
+			let (_sense, data) = dev.scsi_inquiry(false, 0)
+				.map_err(|err| status::fail(format!("Cannot query device: {}", err), status::COMMAND_FAILED))?;
 			let inquiry = inquiry::parse_inquiry(&data);
 
-			if use_json {
-				let info = inquiry.to_json().unwrap();
+			if verbosity == Verbosity::Brief {
+				// TODO serial number (VPD page 80h), capacity
+				print!("{} {}, firmware {}\n", inquiry.vendor_id, inquiry.product_id, inquiry.product_rev);
+			} else if use_json {
+				let info = serde_json::to_value(&inquiry).unwrap();
 				print!("{}\n", serde_json::to_string(&info).unwrap());
 			} else {
 				print_scsi_id(&inquiry);
 			}
 		}
 
-		if let Some(id) = ata_id {
+		let mut outcome = Outcome::new();
+
+		if verbosity == Verbosity::Brief {
+			if let Some((dev, id)) = ata {
+				let (units, precision) = units(args)?;
+				print!("{}\n", brief(dev, id, units, precision, &mut outcome));
+			}
+			return Ok(outcome);
+		}
+
+		// TODO SECURITY PROTOCOL IN for SCSI devices
+		let tcg = if args.is_present("tcg") {
+			match ata {
+				Some((dev, id)) if id.trusted_computing_supported => Some(dev.get_tcg_discovery()
+					.map_err(|err| status::fail(format!("Cannot read TCG details: {}", err), status::COMMAND_FAILED))?),
+				_ => {
+					eprint!("Trusted computing is not supported, cannot show TCG details\n");
+					None
+				},
+			}
+		} else { None };
+
+		// IDENTIFY (PACKET) DEVICE data is already parsed by now, so we're asking for it once again
+		let id_raw = if args.is_present("identify-raw") {
+			match ata {
+				Some((dev, id)) => Some(id_words(&if id.is_ata {
+					dev.get_device_id_raw()
+				} else {
+					dev.get_packet_device_id_raw()
+				}
+					.map_err(|err| status::fail(format!("Cannot read device identification: {}", err), status::COMMAND_FAILED))?)),
+				None => {
+					eprint!("--identify-raw is only available for ATA devices\n");
+					None
+				},
+			}
+		} else { None };
+
+		// capabilities are only reported in SMART READ DATA reply
+		let caps = match ata {
+			Some((dev, id)) if id.smart == id::Ternary::Enabled => dev.get_smart_capabilities().unwrap_or_else(|err| {
+				eprint!("Cannot read S.M.A.R.T. capabilities: {}\n", err);
+				None
+			}),
+			_ => None,
+		};
+
+		if let Some((_, id)) = ata {
 			let drivedb = open_drivedb(args.values_of("drivedb"));
 			let meta = drivedb.as_ref().map(|drivedb| drivedb.render_meta(
 				&id,
@@ -155,21 +626,138 @@ impl Subcommand for Info {
 			));
 
 			if use_json {
-				let mut info = id.to_json().unwrap();
+				let mut info = serde_json::to_value(id).unwrap();
+				info.as_object_mut().unwrap().insert("sectors_per_physical".to_string(),
+					serde_json::to_value(id.sector_size_phy.checked_div(id.sector_size_log)).unwrap());
 
 				if let Some(meta) = &meta {
 					if let Some(family) = meta.family {
-						info.as_object_mut().unwrap().insert("family".to_string(), family.to_json().unwrap());
+						info.as_object_mut().unwrap().insert("family".to_string(), serde_json::to_value(family).unwrap());
 					}
-					if let Some(warning) = meta.warning {
-						info.as_object_mut().unwrap().insert("warning".to_string(), warning.to_json().unwrap());
+					if !meta.firmware_bugs.is_empty() {
+						let bugs: Vec<_> = meta.firmware_bugs.iter().map(|bug| bug.name()).collect();
+						info.as_object_mut().unwrap().insert("firmware_bugs".to_string(), serde_json::to_value(&bugs).unwrap());
+					}
+					if let Some(warning) = drivedb_warning_json(meta) {
+						info.as_object_mut().unwrap().insert("warning".to_string(), warning);
 					}
 				}
 
+				if let Some(tcg) = &tcg {
+					info.as_object_mut().unwrap().insert("tcg".to_string(), serde_json::to_value(tcg).unwrap());
+				} else if args.is_present("tcg") {
+					let mut error = serde_json::Map::new();
+					error.insert("error".to_string(), serde_json::to_value("trusted_computing_unsupported").unwrap());
+					info.as_object_mut().unwrap().insert("tcg".to_string(), serde_json::Value::Object(error));
+				}
+				if let Some(words) = &id_raw {
+					info.as_object_mut().unwrap().insert("identify_raw".to_string(), serde_json::to_value(words).unwrap());
+				}
+				if let Some(caps) = &caps {
+					info.as_object_mut().unwrap().insert("smart_capabilities".to_string(), serde_json::to_value(caps).unwrap());
+				}
+
 				print!("{}\n", serde_json::to_string(&info).unwrap());
 			} else {
-				print_ata_id(&id, &meta);
+				let (units, precision) = units(args)?;
+				print_ata_id(id, &meta, &caps, units, precision, verbosity);
+				if let Some(tcg) = &tcg {
+					print_tcg(tcg);
+				}
+				if let Some(words) = &id_raw {
+					print_ata_id_raw(words, args.is_present("annotate"));
+				}
 			}
 		}
+
+		Ok(outcome)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hdd::ata::data::id;
+	use hdd::ata::{RegistersRead, RegistersWrite, SMARTFeature};
+	use serde_json;
+	use mock;
+
+	fn json(words: &[(usize, u16)]) -> serde_json::Value {
+		serde_json::to_value(id::parse_id(&mock::identify(words))).unwrap()
+	}
+
+	#[test]
+	fn json_types() {
+		// 1,000,000 sectors, 4K physical sectors, 7200 RPM
+		let json = json(&[(60, 0x4240), (61, 0x000f), (106, 1 << 14 | 1 << 13 | 3), (217, 7200), (82, 1), (85, 1)]);
+		assert_eq!(json["capacity"].as_u64(), Some(512_000_000));
+		assert_eq!(json["sector_size_log"].as_u64(), Some(512));
+		assert_eq!(json["sector_size_phy"].as_u64(), Some(4096));
+		assert_eq!(json["rpm"].as_u64(), Some(7200));
+		assert_eq!(json["smart"].as_str(), Some("Enabled"));
+		assert_eq!(json["apm"].as_str(), Some("Unsupported"));
+		assert_eq!(json["zoned"].as_str(), Some("none"));
+	}
+
+	#[test]
+	fn json_rpm() {
+		assert_eq!(json(&[(217, 1)])["rpm"].as_u64(), Some(0));
+		assert!(json(&[])["rpm"].is_null());
+	}
+
+	#[test]
+	fn json_ternary() {
+		assert_eq!(json(&[(82, 1)])["smart"].as_str(), Some("Disabled"));
+	}
+
+	fn brief_line(words: &[(usize, u16)], units: Units) -> String {
+		let mut dump = mock::dump(words);
+		dump.record(&RegistersWrite::smart(SMARTFeature::ReturnStatus, 0, 0), &RegistersRead { cyl_low: 0x4f, cyl_high: 0xc2, ..mock::ok() }, &[]);
+		let mut id = id::parse_id(&mock::identify(words));
+		id.model = "MOCK".to_string();
+		id.serial = "1234".to_string();
+		brief(&dump, &id, units, 1, &mut Outcome::new())
+	}
+
+	#[test]
+	fn brief_fields() {
+		assert_eq!(brief_line(mock::SMART_ENABLED, Units::Bytes), "MOCK, serial 1234, 512,000,000 bytes, health: good");
+		assert_eq!(brief_line(mock::SMART_ENABLED, Units::Sectors), "MOCK, serial 1234, 1,000,000 sectors, health: good");
+		// no S.M.A.R.T., no health check
+		assert_eq!(brief_line(&mock::SMART_ENABLED[..2], Units::Bytes), "MOCK, serial 1234, 512,000,000 bytes, health: S.M.A.R.T. disabled");
+
+		let mut dump = mock::dump(mock::SMART_ENABLED);
+		dump.record(&RegistersWrite::smart(SMARTFeature::ReturnStatus, 0, 0), &mock::ok(), &[]);
+		let mut id = id::parse_id(&mock::identify(mock::SMART_ENABLED));
+		// a garbled IDENTIFY DEVICE reply
+		id.sector_size_log = 0;
+		assert!(brief(&dump, &id, Units::Sectors, 1, &mut Outcome::new()).contains(", 512,000,000 bytes, "));
+	}
+
+	#[test]
+	fn verbose_fields() {
+		let labels = |words: &[(usize, u16)]| -> Vec<&'static str> {
+			verbose_features(&id::parse_id(&mock::identify(words))).into_iter().map(|(label, _)| label).collect()
+		};
+		assert_eq!(labels(&[]), ["World wide name", "SCT Command Transport", "IDENTIFY DEVICE checksum"]);
+		// 16 sectors per block, ATA security and sanitize supported
+		assert_eq!(labels(&[(59, 0b1001_0001_0001_0000), (128, 1)]), [
+			"World wide name", "SCT Command Transport", "READ/WRITE MULTIPLE",
+			"Security password", "Master password capability", "Sanitize antifreeze lock",
+			"IDENTIFY DEVICE checksum",
+		]);
+	}
+
+	#[test]
+	fn verbosity_flags() {
+		let verbosity_of = |args: &[&str]| {
+			let mut argv = vec!["info"];
+			argv.extend_from_slice(args);
+			verbosity(&Info {}.subcommand().get_matches_from(argv))
+		};
+		assert_eq!(verbosity_of(&[]), Verbosity::Normal);
+		assert_eq!(verbosity_of(&["--brief"]), Verbosity::Brief);
+		assert_eq!(verbosity_of(&["--verbose-info"]), Verbosity::Verbose);
+		assert_eq!(verbosity_of(&["--verbose-info", "--verbose-info"]), Verbosity::All);
 	}
 }
@@ -0,0 +1,72 @@
+use hdd::ata::misc;
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+pub struct InternalStatus {}
+impl Subcommand for InternalStatus {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("internal-status")
+			.about("Saves Current Device Internal Status Data log (vendor-specific data for failure analysis) into a file")
+			.arg(Arg::with_name("output")
+				.long("output")
+				.short("o")
+				.takes_value(true)
+				.required(true)
+				.value_name("FILE")
+				.help("File to save the log to")
+			)
+			.arg(Arg::with_name("capture")
+				.long("capture")
+				.help("Ask the device to collect fresh data first\n(by default, whatever data device collected earlier is saved)")
+			)
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Device internal status is only available for ATA devices", status::COMMAND_FAILED))?;
+
+		if !id.gp_logging_supported {
+			return Err(status::fail("General purpose logging is not supported", status::COMMAND_FAILED));
+		}
+
+		let (header, data) = match dev.get_internal_status_log(args.is_present("capture"), id.commands_supported.read_write_dma_ext_gpl) {
+			Ok(Some(log)) => log,
+			Ok(None) => return Err(status::fail("Current Device Internal Status Data log is malformed or truncated", status::COMMAND_FAILED)),
+			Err(misc::Error::Aborted(_)) => return Err(status::fail("Current Device Internal Status Data log is not supported", status::COMMAND_FAILED)),
+			Err(err) => return Err(status::fail(format!("Cannot read Current Device Internal Status Data log: {}", err), status::COMMAND_FAILED)),
+		};
+
+		// unwrap: clap makes sure this one is present
+		let file = args.value_of("output").unwrap();
+		File::create(file).and_then(|mut f| f.write_all(&data))
+			.map_err(|e| status::fail(format!("Cannot save log to {}: {}", file, e), status::COMMAND_FAILED))?;
+
+		print!("Saved {} log pages (organization ID {:06x}) to {}\n", header.pages(), header.organization_id, file);
+		if header.saved_data_available {
+			print!("Device also has saved internal status data (generation {})\n", header.saved_data_generation);
+		}
+
+		Ok(Outcome::new())
+	}
+}
@@ -8,7 +8,8 @@ use clap::{
 
 use serde_json;
 
-use ::DeviceArgument;
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
 use super::{Subcommand, arg_json};
 
 use std::path::Path;
@@ -26,17 +27,14 @@ impl Subcommand for List {
 		_: &Option<&Path>,
 		dev: &Option<&DeviceArgument>,
 		args: &ArgMatches,
-	) {
+	) -> Result<Outcome, Failure> {
 		if dev.is_some() {
 			// TODO show usage and whatnot
-			eprint!("<device> is redundant\n");
-			::std::process::exit(1);
+			return Err(status::fail("<device> is redundant", status::USAGE));
 		};
 
-		let devs = list_devices().unwrap_or_else(|err| {
-			eprint!("Cannot list devices: {}\n", err);
-			::std::process::exit(1);
-		});
+		let devs = list_devices()
+			.map_err(|err| status::fail(format!("Cannot list devices: {}", err), status::COMMAND_FAILED))?;
 
 		if args.is_present("json") {
 			print!("{}\n", serde_json::to_string(&devs).unwrap());
@@ -45,5 +43,7 @@ impl Subcommand for List {
 				print!("{}\n", dev.into_os_string().to_str().unwrap());
 			}
 		}
+
+		Ok(Outcome::new())
 	}
 }
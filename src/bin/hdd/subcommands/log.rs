@@ -0,0 +1,618 @@
+use hdd::ata::ErrorBits;
+use hdd::ata::misc::{self, Misc};
+use hdd::ata::data::id::{Id, Ternary};
+use hdd::ata::data::log_directory::{self, LogDirectory};
+use hdd::ata::data::ncq::{NcqErrorLog, NcqCommandError};
+use hdd::ata::data::farm::FarmLog;
+use hdd::ata::data::error_log::{self, ErrorLog};
+use hdd::drivedb;
+use hdd::scsi::SCSIDevice;
+use hdd::scsi::pages::{self, SCSIPages, ErrorCounter};
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use serde_json;
+
+use ::{DeviceArgument, open_drivedb, status, to_json_string};
+use status::{Outcome, Failure};
+use super::{Subcommand, arg_json, arg_drivedb};
+use super::log_decoders::{self, LogDecoder};
+use super::attrs::scsi_error_counters_json;
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+// 0xNN or decimal
+fn parse_address(s: &str) -> Result<u8, String> {
+	let res = if s.starts_with("0x") || s.starts_with("0X") {
+		u8::from_str_radix(&s[2..], 16)
+	} else {
+		s.parse()
+	};
+	res.map_err(|e| format!("{}: {}", s, e))
+}
+
+// ADDRESS=NAME
+fn parse_log_name(s: &str) -> Result<(u8, String), String> {
+	let pos = s.find('=').ok_or_else(|| format!("{}: expected ADDRESS=NAME", s))?;
+	Ok((parse_address(&s[..pos])?, s[pos + 1 ..].to_string()))
+}
+
+pub fn print_ncq_error(err: &NcqCommandError) {
+	match err.tag {
+		Some(tag) => print!("Failed NCQ command tag: {}\n", tag),
+		None => print!("Failed command is not a queued one\n"),
+	}
+	if err.unload {
+		print!("(device received IDLE IMMEDIATE with UNLOAD feature)\n");
+	}
+	print!("Status: 0x{:02x}\n", err.status);
+	print!("Error:  {}\n", ErrorBits(err.error));
+	print!("Device: 0x{:02x}\n", err.device);
+	print!("LBA:    {}\n", err.lba);
+	print!("Count:  {}\n", err.count);
+	if err.sense_key != 0 || err.asc != 0 || err.ascq != 0 {
+		print!("Sense key/ASC/ASCQ: {:02x}/{:02x}/{:02x}\n", err.sense_key, err.asc, err.ascq);
+	}
+}
+
+// FARM fields are only there if the drive says so
+fn opt<T: Display>(value: Option<T>) -> String {
+	value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+pub fn print_farm(log: &FarmLog) {
+	print!("FARM log version {}.{}, {} page(s)\n", log.header.major_version, log.header.minor_version, log.header.pages);
+
+	if let Some(ref d) = log.drive {
+		print!("\nDrive information:\n");
+		print!("  Capacity:               {} sectors\n", opt(d.capacity));
+		print!("  Sector size:            {} logical, {} physical\n", opt(d.sector_size_log), opt(d.sector_size_phy));
+		print!("  Heads:                  {}\n", opt(d.heads));
+		print!("  RPM:                    {}\n", opt(d.rpm));
+		print!("  Power-on hours:         {}\n", opt(d.power_on_hours));
+		print!("  Spindle power-on hours: {}\n", opt(d.spindle_power_on_hours));
+		print!("  Head flight hours:      {}\n", opt(d.head_flight_hours));
+		print!("  Head load events:       {}\n", opt(d.head_load_events));
+		print!("  Power cycles:           {}\n", opt(d.power_cycles));
+		print!("  Resets:                 {}\n", opt(d.resets));
+		print!("  Spin-up time:           {} ms\n", opt(d.spin_up_time));
+	}
+
+	if let Some(ref w) = log.workload {
+		print!("\nWorkload:\n");
+		print!("  Read commands:   {}\n", opt(w.read_commands));
+		print!("  Write commands:  {}\n", opt(w.write_commands));
+		print!("  Random reads:    {}\n", opt(w.random_reads));
+		print!("  Random writes:   {}\n", opt(w.random_writes));
+		print!("  Other commands:  {}\n", opt(w.other_commands));
+		print!("  Sectors written: {}\n", opt(w.sectors_written));
+		print!("  Sectors read:    {}\n", opt(w.sectors_read));
+	}
+
+	if let Some(ref e) = log.errors {
+		print!("\nErrors:\n");
+		print!("  Unrecoverable read errors:  {}\n", opt(e.unrecoverable_read_errors));
+		print!("  Unrecoverable write errors: {}\n", opt(e.unrecoverable_write_errors));
+		print!("  Reallocated sectors:        {}\n", opt(e.reallocated_sectors));
+		print!("  Reallocation candidates:    {}\n", opt(e.reallocation_candidates));
+		print!("  Read recovery attempts:     {}\n", opt(e.read_recovery_attempts));
+		print!("  Mechanical start failures:  {}\n", opt(e.mechanical_start_failures));
+		print!("  ASR events:                 {}\n", opt(e.asr_events));
+		print!("  Interface CRC errors:       {}\n", opt(e.interface_crc_errors));
+		print!("  Spin retries:               {}\n", opt(e.spin_retries));
+	}
+
+	if let Some(ref e) = log.environment {
+		print!("\nEnvironment (°C):\n");
+		print!("  Temperature:        {}\n", opt(e.temperature));
+		print!("  Highest, lowest:    {}, {}\n", opt(e.highest_temperature), opt(e.lowest_temperature));
+		print!("  Specified range:    {} to {}\n", opt(e.min_temperature), opt(e.max_temperature));
+		print!("  Time over, under:   {}, {} min\n", opt(e.over_temperature_time), opt(e.under_temperature_time));
+	}
+}
+
+// milliseconds since power-on, e.g. '26:03:15.042'
+fn format_timestamp(ms: u32) -> String {
+	let s = ms / 1000;
+	format!("{:02}:{:02}:{:02}.{:03}", s / 3600, s / 60 % 60, s % 60, ms % 1000)
+}
+
+pub fn print_error_log(log: &ErrorLog) {
+	if log.errors.is_empty() {
+		print!("No errors logged");
+		if log.error_count != 0 {
+			print!(" (but device reports {} error(s) over its lifetime)", log.error_count);
+		}
+		print!("\n");
+		return;
+	}
+	print!("{} error(s) reported over the device lifetime, {} most recent are logged\n", log.error_count, log.errors.len());
+
+	// errors are numbered from the first one the device ever reported, like in smartctl
+	let last = ::std::cmp::max(log.error_count as usize, log.errors.len());
+	for (i, err) in log.errors.iter().enumerate() {
+		print!("\nError {} at {} power-on hours, device was {}:\n", last - i, err.lifetime, error_log::state_description(err.state));
+		print!("  Error: {}, status: 0x{:02x}, count: {}, LBA: {}, device: 0x{:02x}\n", ErrorBits(err.error), err.status, err.count, err.lba, err.device);
+		if err.commands.is_empty() {
+			continue;
+		}
+		print!("  Commands leading to the error, the failed one first:\n");
+		print!("    CMD  FEATURES  COUNT  LBA              DEVICE  DC  powered up for\n");
+		for cmd in &err.commands {
+			print!("    {:02x}h  {:04x}h     {:04x}h  {:<15}  {:02x}h     {:02x}h {}\n",
+				cmd.command, cmd.features, cmd.count, cmd.lba, cmd.device, cmd.device_control, format_timestamp(cmd.timestamp));
+		}
+	}
+}
+
+// `ext_only` is for `-l xerror`; `-l error` picks the extended log if it is there, and the summary one otherwise; `meta` tells which firmware bugs to work around
+fn show_error_log(dev: &Misc, id: &Id, meta: &Option<drivedb::DriveMeta>, ext_only: bool, json: bool) -> Result<Outcome, Failure> {
+	let ext_pages = if id.gp_logging_supported { log_pages(dev, 0x03) } else { Ok(0) };
+	// extended log has 48-bit LBAs, and usually more room for errors, prefer it over the summary one
+	let (extended, log) = match ext_pages {
+		Ok(pages) if pages != 0 => (true, dev.get_ext_error_log(pages, id.commands_supported.read_write_dma_ext_gpl, meta)),
+		Err(ref err) if ext_only => return Err(status::fail(format!("Cannot read General Purpose Log Directory: {}", err), status::COMMAND_FAILED)),
+		_ if ext_only => return Err(status::fail("Extended Comprehensive SMART error log is not supported", status::COMMAND_FAILED)),
+		_ => {
+			if id.smart != Ternary::Enabled {
+				return Err(status::fail(format!("S.M.A.R.T. is {}, and Extended Comprehensive SMART error log is not supported", if id.smart == Ternary::Disabled { "disabled" } else { "not supported" }), status::SMART_UNAVAILABLE));
+			}
+			if !id.smart_error_logging_supported {
+				return Err(status::fail("SMART error logging is not supported", status::COMMAND_FAILED));
+			}
+			(false, dev.get_summary_error_log(meta))
+		},
+	};
+	let name = if extended { "Extended Comprehensive SMART error log" } else { "Summary SMART error log" };
+
+	let log = match log {
+		Ok(Some(log)) => log,
+		Ok(None) => return Err(status::fail(format!("{} reply is truncated", name), status::COMMAND_FAILED)),
+		Err(misc::Error::Aborted(_)) => return Err(status::fail(format!("{} is not supported", name), status::COMMAND_FAILED)),
+		Err(err) => return Err(status::fail(format!("Cannot read {}: {}", name, err), status::COMMAND_FAILED)),
+	};
+
+	if json {
+		let mut out = serde_json::to_value(&log).unwrap();
+		out.as_object_mut().unwrap().insert("extended".to_string(), serde_json::to_value(extended).unwrap());
+		print!("{}\n", serde_json::to_string(&out).unwrap());
+	} else {
+		print!("{}:\n", name);
+		print_error_log(&log);
+	}
+
+	Ok(Outcome::new())
+}
+
+// the log is vendor-specific, and log address A6h might mean anything on other drives
+fn is_seagate(id: &Id) -> bool {
+	id.model.starts_with("ST") || id.model.starts_with("Seagate")
+}
+
+fn show_farm(dev: &Misc, id: &Id, json: bool) -> Result<Outcome, Failure> {
+	if !is_seagate(id) {
+		return Err(status::fail("FARM log is only available on Seagate drives", status::COMMAND_FAILED));
+	}
+	check_gp_log(dev, 0xa6, "FARM log")?;
+
+	let log = match dev.get_farm_log(id.commands_supported.read_write_dma_ext_gpl) {
+		Ok(Some(log)) => log,
+		Ok(None) => return Err(status::fail("FARM log is malformed or truncated", status::COMMAND_FAILED)),
+		Err(misc::Error::Aborted(_)) => return Err(status::fail("FARM log is not supported", status::COMMAND_FAILED)),
+		Err(err) => return Err(status::fail(format!("Cannot read FARM log: {}", err), status::COMMAND_FAILED)),
+	};
+
+	if json {
+		print!("{}\n", to_json_string(&log));
+	} else {
+		print_farm(&log);
+	}
+
+	Ok(Outcome::new())
+}
+
+fn error_counter_name(counter: ErrorCounter) -> String {
+	use self::ErrorCounter::*;
+	match counter {
+		CorrectedNoDelay => "Errors corrected without substantial delay".to_string(),
+		CorrectedDelay => "Errors corrected with possible delays".to_string(),
+		Total => "Total rewrites or rereads".to_string(),
+		ErrorsCorrected => "Total errors corrected".to_string(),
+		CRCProcessed => "Correction algorithm invocations".to_string(),
+		BytesProcessed => "Bytes processed".to_string(),
+		Uncorrected => "Total uncorrected errors".to_string(),
+		VendorSpecific(n) => format!("Vendor-specific parameter {:04x}h", n),
+		Reserved(n) => format!("Reserved parameter {:04x}h", n),
+	}
+}
+
+fn error_counter_code(counter: ErrorCounter) -> u16 {
+	use self::ErrorCounter::*;
+	match counter {
+		CorrectedNoDelay => 0,
+		CorrectedDelay => 1,
+		Total => 2,
+		ErrorsCorrected => 3,
+		CRCProcessed => 4,
+		BytesProcessed => 5,
+		Uncorrected => 6,
+		VendorSpecific(n) | Reserved(n) => n,
+	}
+}
+
+// Write, Read, Verify Error Counter and Non-Medium Error log pages
+fn show_scsi_errors(dev: &SCSIDevice, json: bool) -> Result<Outcome, Failure> {
+	let mut log_pages = SCSIPages::new(dev)
+		.map_err(|err| status::fail(format!("Cannot access SCSI log pages: {}", err), status::COMMAND_FAILED))?;
+	let mut outcome = Outcome::new();
+
+	let mut counters = vec![];
+	for &(page, name) in [(0x02, "write"), (0x03, "read"), (0x05, "verify")].iter() {
+		if !log_pages.supported_pages().contains(&page) { continue }
+
+		match log_pages.error_counters(page) {
+			Ok(values) => counters.push((page, name, values)),
+			Err(err) => {
+				eprint!("Cannot read {} log page: {}\n", pages::page_name(page), err);
+				outcome.set(status::COMMAND_FAILED);
+			},
+		}
+	}
+
+	let non_medium = if log_pages.supported_pages().contains(&0x06) {
+		match log_pages.non_medium_error_count() {
+			Ok(count) => Some(count),
+			Err(err) => {
+				eprint!("Cannot read {} log page: {}\n", pages::page_name(0x06), err);
+				outcome.set(status::COMMAND_FAILED);
+				None
+			},
+		}
+	} else { None };
+
+	if counters.is_empty() && non_medium.is_none() {
+		return Err(outcome.fail("Device does not provide error counter log pages", status::COMMAND_FAILED));
+	}
+
+	if json {
+		// same keys as in `attrs --json` for SCSI devices
+		let mut out = serde_json::Map::new();
+		for &(_, name, ref values) in counters.iter() {
+			out.insert(name.to_string(), scsi_error_counters_json(values));
+		}
+		if let Some(count) = non_medium {
+			out.insert("non-medium-errors".to_string(), serde_json::to_value(count).unwrap());
+		}
+		print!("{}\n", serde_json::to_string(&serde_json::Value::Object(out)).unwrap());
+	} else {
+		for (i, &(page, _, ref values)) in counters.iter().enumerate() {
+			print!("{}{} log page ({:02x}h):\n", if i > 0 { "\n" } else { "" }, pages::page_name(page), page);
+
+			let mut values: Vec<_> = values.iter().collect();
+			values.sort_by_key(|&(&counter, _)| error_counter_code(counter));
+			for (&counter, value) in values {
+				print!("  {:<44} {}\n", format!("{}:", error_counter_name(counter)), value);
+			}
+		}
+		if let Some(count) = non_medium {
+			print!("{}Non-medium errors: {}\n", if counters.is_empty() { "" } else { "\n" }, count);
+		}
+	}
+
+	Ok(outcome)
+}
+
+// Format Status log page
+fn show_format_status(dev: &SCSIDevice, json: bool) -> Result<Outcome, Failure> {
+	let format = pages::format_status(dev).map_err(|err| status::fail(match err {
+		pages::Error::NotSupported => format!("Device does not provide {} log page", pages::page_name(0x08)),
+		err => format!("Cannot read {} log page: {}", pages::page_name(0x08), err),
+	}, status::COMMAND_FAILED))?;
+
+	if json {
+		print!("{}\n", to_json_string(&format));
+		return Ok(Outcome::new());
+	}
+
+	if format.in_progress {
+		match format.progress {
+			Some(progress) => print!("Format in progress: {:.1}% done\n", progress as f64 * 100. / 65536.),
+			None => print!("Format in progress\n"),
+		}
+		return Ok(Outcome::new());
+	}
+
+	let opt = |value: Option<u64>| value.map(|x| x.to_string()).unwrap_or_else(|| "not available".to_string());
+	print!("{} log page (08h):\n", pages::page_name(0x08));
+	match format.format_data_out {
+		Some(ref data) => {
+			let hex: Vec<_> = data.iter().map(|b| format!("{:02x}", b)).collect();
+			print!("  {:<44} {}\n", "Most recent FORMAT UNIT parameter list:", hex.join(" "));
+		},
+		None => print!("  {:<44} not available (format failed, or never issued)\n", "Most recent FORMAT UNIT parameter list:"),
+	}
+	print!("  {:<44} {}\n", "Grown defects during certification:", opt(format.grown_defects_during_certification));
+	print!("  {:<44} {}\n", "Blocks reassigned during format:", opt(format.total_blocks_reassigned_during_format));
+	print!("  {:<44} {}\n", "Blocks reassigned since format:", opt(format.total_new_blocks_reassigned));
+	print!("  {:<44} {}\n", "Power-on minutes since format:", opt(format.power_on_minutes_since_format));
+
+	Ok(Outcome::new())
+}
+
+pub struct Log {}
+impl Subcommand for Log {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("log")
+			.about("Prints the content of various device logs")
+			.arg(Arg::with_name("log")
+				.short("l") // smartctl-like
+				.long("log") // smartctl-like
+				.takes_value(true)
+				.required_unless("address")
+				.possible_values(&["directory", "error", "xerror", "ncqerror", "farm", "scsierror", "format"])
+				.help("log to show\n'directory': logs the device supports, along with their sizes (from both General Purpose and SMART log directories)\n'error': errors the device reported, along with commands that led to them (from Extended Comprehensive SMART error log if the device has one, or Summary SMART error log otherwise)\n'xerror': same, but only from Extended Comprehensive SMART error log, which has 48-bit LBAs\n'ncqerror': NCQ Command Error log, the last failed queued command\n'farm': Seagate Field Accessible Reliability Metrics (Seagate drives only)\n'scsierror': write, read and verify error counters, and the number of non-medium errors (SCSI devices only)\n'format': Format Status log page, whether the device is being formatted, and defects found during the most recent format (SCSI devices only)")
+			)
+			.arg(Arg::with_name("address")
+				.long("address")
+				.takes_value(true)
+				.conflicts_with("log")
+				.value_name("ADDRESS")
+				.help("read General Purpose log at this address (e.g. 0xa6), decoding it if its format is known, or showing a hex dump otherwise")
+			)
+			.arg(Arg::with_name("pages")
+				.long("pages")
+				.takes_value(true)
+				.requires("address")
+				.value_name("N")
+				.help("number of log pages to read with --address (default: as many as the log directory says)")
+			)
+			.arg(Arg::with_name("log-name")
+				.long("log-name")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.value_name("ADDRESS=NAME")
+				.help("label the log at ADDRESS as NAME in --address output (e.g. 0xa6=seagate_farm); can be repeated")
+			)
+			.arg(arg_drivedb())
+			.arg(arg_json())
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		if args.value_of("log") == Some("scsierror") {
+			return match *dev {
+				DeviceArgument::SCSI(ref dev) => show_scsi_errors(dev, args.is_present("json")),
+				_ => Err(status::fail("Error counter log pages are only available for SCSI devices", status::COMMAND_FAILED)),
+			};
+		}
+
+		if args.value_of("log") == Some("format") {
+			return match *dev {
+				DeviceArgument::SCSI(ref dev) => show_format_status(dev, args.is_present("json")),
+				_ => Err(status::fail("Format Status log page is only available for SCSI devices", status::COMMAND_FAILED)),
+			};
+		}
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Logs are only available for ATA devices", status::COMMAND_FAILED))?;
+
+		// SMART logs do not need general purpose logging
+		if args.value_of("log") == Some("directory") {
+			return show_directory(dev, id, args.is_present("json"));
+		}
+
+		// summary log does not need general purpose logging either
+		if args.value_of("log") == Some("error") || args.value_of("log") == Some("xerror") {
+			// only for firmware bugs (-F) of the drive
+			let drivedb = open_drivedb(args.values_of("drivedb"));
+			let meta = drivedb.as_ref().map(|drivedb| drivedb.render_meta(id, &vec![]));
+			return show_error_log(dev, id, &meta, args.value_of("log") == Some("xerror"), args.is_present("json"));
+		}
+
+		if !id.gp_logging_supported {
+			return Err(status::fail("General purpose logging is not supported", status::COMMAND_FAILED));
+		}
+
+		if let Some(address) = args.value_of("address") {
+			let address = parse_address(address)
+				.map_err(|err| status::fail(format!("Invalid --address: {}", err), status::USAGE))?;
+			let names = match args.values_of("log-name") {
+				Some(names) => names.map(parse_log_name).collect::<Result<_, _>>()
+					.map_err(|err| status::fail(format!("Invalid --log-name: {}", err), status::USAGE))?,
+				None => HashMap::new(),
+			};
+			let pages = match args.value_of("pages") {
+				Some(p) => Some(p.parse()
+					.map_err(|_| status::fail(format!("Invalid --pages: {}", p), status::USAGE))?),
+				None => None,
+			};
+			return print_log(dev, id, address, pages, &names, args.is_present("json"));
+		}
+
+		if args.value_of("log") == Some("farm") {
+			return show_farm(dev, id, args.is_present("json"));
+		}
+
+		// ncqerror
+		check_gp_log(dev, 0x10, "NCQ Command Error log")?;
+		let log = match dev.get_ncq_error_log() {
+			Ok(Some(log)) => log,
+			Ok(None) => return Err(status::fail("NCQ Command Error log reply is truncated", status::COMMAND_FAILED)),
+			Err(misc::Error::Aborted(_)) => return Err(status::fail("NCQ Command Error log is not supported", status::COMMAND_FAILED)),
+			Err(err) => return Err(status::fail(format!("Cannot read NCQ Command Error log: {}", err), status::COMMAND_FAILED)),
+		};
+
+		if args.is_present("json") {
+			print!("{}\n", to_json_string(&log));
+		} else {
+			match log {
+				NcqErrorLog::Empty => print!("No NCQ errors logged\n"),
+				NcqErrorLog::Error(ref err) => print_ncq_error(err),
+			}
+		}
+
+		Ok(Outcome::new())
+	}
+}
+
+// number of pages the log at `address` has, according to the General Purpose Log Directory
+fn log_pages(dev: &Misc, address: u8) -> Result<u16, String> {
+	match dev.get_gp_log_directory() {
+		Ok(Some(dir)) => Ok(dir.pages(address)),
+		Ok(None) => Err("reply is truncated".to_string()),
+		Err(err) => Err(err.to_string()),
+	}
+}
+
+// fails early if the General Purpose Log Directory says that the log is not there; if the directory itself cannot be read, it's up to the device to reject the log read
+fn check_gp_log(dev: &Misc, address: u8, name: &str) -> Result<(), Failure> {
+	match log_pages(dev, address) {
+		Ok(0) => Err(status::fail(format!("{} is not supported", name), status::COMMAND_FAILED)),
+		_ => Ok(()),
+	}
+}
+
+fn print_log(dev: &Misc, id: &Id, address: u8, pages: Option<u16>, names: &HashMap<u8, String>, json: bool) -> Result<Outcome, Failure> {
+	let supported = log_pages(dev, address);
+	let pages = match (pages, supported) {
+		(_, Ok(0)) => return Err(status::fail(format!("Log 0x{:02x} is not supported", address), status::COMMAND_FAILED)),
+		(Some(pages), Ok(supported)) if pages > supported => return Err(status::fail(format!("Log 0x{:02x} only has {} page(s)", address, supported), status::USAGE)),
+		(Some(pages), _) => pages,
+		(None, Ok(supported)) => supported,
+		(None, Err(err)) => return Err(status::fail(format!("Cannot read General Purpose Log Directory: {}", err), status::COMMAND_FAILED)),
+	};
+	if pages == 0 {
+		return Err(status::fail("Nothing to read, as --pages is 0", status::USAGE));
+	}
+
+	// read in chunks to keep transfers reasonably small; multi-page logs are faster to read with DMA
+	let mut dma = pages > 1 && id.commands_supported.read_write_dma_ext_gpl;
+	let mut data = vec![];
+	let mut page = 0;
+	while page < pages {
+		let count = ::std::cmp::min(pages - page, 128);
+		let chunk = match dev.read_log_pages(&mut dma, address, page, count) {
+			Ok(chunk) => chunk,
+			Err(misc::Error::Aborted(_)) => return Err(status::fail(format!("Log 0x{:02x} is not supported", address), status::COMMAND_FAILED)),
+			Err(err) => return Err(status::fail(format!("Cannot read log 0x{:02x}: {}", address, err), status::COMMAND_FAILED)),
+		};
+		data.extend_from_slice(&chunk[.. ::std::cmp::min(chunk.len(), count as usize * 512)]);
+		page += count;
+	}
+
+	let decoder = log_decoders::decoder(address);
+	let name = names.get(&address).map(|name| name.as_str()).unwrap_or(decoder.name());
+
+	if json {
+		let mut log = serde_json::Map::new();
+		log.insert("address".to_string(), serde_json::to_value(address).unwrap());
+		log.insert("name".to_string(), serde_json::to_value(name).unwrap());
+		log.insert("pages".to_string(), serde_json::to_value(pages).unwrap());
+		// fall back to raw bytes, much like the hex dump below
+		let content = decoder.to_json(&data).unwrap_or_else(|| serde_json::to_value(&data).unwrap());
+		log.insert("content".to_string(), content);
+		print!("{}\n", serde_json::to_string(&serde_json::Value::Object(log)).unwrap());
+	} else {
+		print!("Log 0x{:02x} ({}), {} page(s):\n", address, name, pages);
+		if !decoder.print(&data) {
+			print!("(cannot decode, showing raw content)\n");
+			log_decoders::Raw {}.print(&data);
+		}
+	}
+
+	Ok(Outcome::new())
+}
+
+// either directory might be unavailable, in which case the other one is still useful, hence no failure here
+fn directory(name: &str, dir: Result<Option<LogDirectory>, misc::Error>, outcome: &mut Outcome) -> Option<LogDirectory> {
+	match dir {
+		Ok(Some(dir)) => Some(dir),
+		Ok(None) => {
+			eprint!("{} reply is truncated\n", name);
+			outcome.set(status::COMMAND_FAILED);
+			None
+		},
+		Err(err) => {
+			eprint!("Cannot read {}: {}\n", name, err);
+			outcome.set(status::COMMAND_FAILED);
+			None
+		},
+	}
+}
+
+fn show_directory(dev: &Misc, id: &Id, json: bool) -> Result<Outcome, Failure> {
+	let mut outcome = Outcome::new();
+	let gpl = if id.gp_logging_supported {
+		directory("General Purpose Log Directory", dev.get_gp_log_directory(), &mut outcome)
+	} else { None };
+	let smart = if id.smart == Ternary::Enabled {
+		directory("SMART Log Directory", dev.get_smart_log_directory(), &mut outcome)
+	} else { None };
+
+	if gpl.is_none() && smart.is_none() {
+		return Err(outcome.fail("Neither General Purpose nor SMART logs are available", status::COMMAND_FAILED));
+	}
+
+	let pages = |dir: &Option<LogDirectory>, address: u8| dir.as_ref().map(|dir| dir.pages(address)).unwrap_or(0);
+	let addresses: Vec<u8> = (0..256)
+		.map(|address| address as u8)
+		.filter(|&address| pages(&gpl, address) != 0 || pages(&smart, address) != 0)
+		.collect();
+
+	if json {
+		let logs: Vec<_> = addresses.iter().map(|&address| {
+			let mut log = serde_json::Map::new();
+			log.insert("address".to_string(), serde_json::to_value(address).unwrap());
+			log.insert("name".to_string(), serde_json::to_value(log_directory::log_name(address)).unwrap());
+			// null if the log cannot be read that way
+			let sizes = [("gpl_pages", &gpl), ("smart_pages", &smart)];
+			for &(key, dir) in sizes.iter() {
+				let n = pages(dir, address);
+				log.insert(key.to_string(), serde_json::to_value(if n != 0 { Some(n) } else { None }).unwrap());
+			}
+			serde_json::Value::Object(log)
+		}).collect();
+
+		let mut out = serde_json::Map::new();
+		out.insert("gpl_version".to_string(), serde_json::to_value(gpl.as_ref().map(|dir| dir.version)).unwrap());
+		out.insert("smart_version".to_string(), serde_json::to_value(smart.as_ref().map(|dir| dir.version)).unwrap());
+		out.insert("logs".to_string(), serde_json::to_value(&logs).unwrap());
+		print!("{}\n", serde_json::to_string(&serde_json::Value::Object(out)).unwrap());
+	} else {
+		let show = |dir: &Option<LogDirectory>, address: u8| match (dir, pages(dir, address)) {
+			(&None, _) => "?".to_string(),
+			(_, 0) => "-".to_string(),
+			(_, n) => n.to_string(),
+		};
+
+		print!("Address  GPL pages  SMART pages  Name\n");
+		for address in addresses {
+			print!("0x{:02x}     {:>9}  {:>11}  {}\n",
+				address,
+				show(&gpl, address),
+				show(&smart, address),
+				log_directory::log_name(address).unwrap_or("?"),
+			);
+		}
+		if gpl.is_none() || smart.is_none() {
+			print!("\n(?: log directory is not available)\n");
+		}
+	}
+
+	Ok(outcome)
+}
@@ -0,0 +1,213 @@
+/*!
+Decoders for General Purpose logs read with `log --address`, looked up by log address.
+
+Logs without a decoder (most of the vendor-specific ones, 80h–9Fh and A0h–DFh) are shown as a hex dump. To add a decoder, implement [`LogDecoder`](trait.LogDecoder.html) and register it in `DECODERS`.
+*/
+
+use hdd::ata::data::devstat::{self, parse_device_statistics};
+use hdd::ata::data::internal_status::parse_internal_status_header;
+use hdd::ata::data::ncq::{self, NcqErrorLog};
+use hdd::ata::data::farm::parse_farm_log;
+use hdd::ata::data::error_log::parse_ext_error_log;
+
+use serde_json;
+
+use super::log::{print_ncq_error, print_farm, print_error_log};
+
+use std::collections::HashMap;
+
+pub trait LogDecoder: Sync {
+	/// Name to label the log with, unless user provides their own with `--log-name`
+	fn name(&self) -> &'static str;
+	/// Prints decoded content of the log (`data` holds every page read, starting with page 0). Returns `false` if the data cannot be decoded, so that it is shown as a hex dump instead.
+	fn print(&self, data: &[u8]) -> bool;
+	/// Same as `print()`, but for `--json`.
+	fn to_json(&self, data: &[u8]) -> Option<serde_json::Value>;
+}
+
+/// The default: no idea what's inside
+pub struct Raw {}
+impl LogDecoder for Raw {
+	fn name(&self) -> &'static str { "unknown" }
+
+	fn print(&self, data: &[u8]) -> bool {
+		for (i, line) in data.chunks(16).enumerate() {
+			let hex: Vec<_> = line.iter().map(|b| format!("{:02x}", b)).collect();
+			let ascii: String = line.iter()
+				.map(|&b| if (0x20..0x7f).contains(&b) { b as char } else { '.' })
+				.collect();
+			print!("{:06x}  {:<47}  {}\n", i * 16, hex.join(" "), ascii);
+		}
+		true
+	}
+
+	fn to_json(&self, data: &[u8]) -> Option<serde_json::Value> {
+		serde_json::to_value(data).ok()
+	}
+}
+
+// shown as the device reports it: working around firmware bugs takes drivedb, which `--address` does not consult
+struct ExtErrors {}
+impl LogDecoder for ExtErrors {
+	fn name(&self) -> &'static str { "Extended Comprehensive SMART error log" }
+
+	fn print(&self, data: &[u8]) -> bool {
+		match parse_ext_error_log(data, &None) {
+			Some(log) => { print_error_log(&log); true },
+			None => false,
+		}
+	}
+
+	fn to_json(&self, data: &[u8]) -> Option<serde_json::Value> {
+		parse_ext_error_log(data, &None).and_then(|log| serde_json::to_value(&log).ok())
+	}
+}
+
+struct NcqError {}
+impl LogDecoder for NcqError {
+	fn name(&self) -> &'static str { "NCQ Command Error log" }
+
+	fn print(&self, data: &[u8]) -> bool {
+		match ncq::parse_ncq_error_log(data) {
+			Some(NcqErrorLog::Empty) => print!("No NCQ errors logged\n"),
+			Some(NcqErrorLog::Error(ref err)) => print_ncq_error(err),
+			None => return false,
+		}
+		true
+	}
+
+	fn to_json(&self, data: &[u8]) -> Option<serde_json::Value> {
+		ncq::parse_ncq_error_log(data).and_then(|log| serde_json::to_value(&log).ok())
+	}
+}
+
+struct InternalStatus {}
+impl LogDecoder for InternalStatus {
+	fn name(&self) -> &'static str { "Current Device Internal Status Data log" }
+
+	fn print(&self, data: &[u8]) -> bool {
+		match parse_internal_status_header(data) {
+			Some(header) => {
+				print!("Organization ID: {:06x}\n", header.organization_id);
+				print!("Last page of data areas 1, 2, 3: {}, {}, {}\n", header.area1_last_page, header.area2_last_page, header.area3_last_page);
+				if header.saved_data_available {
+					print!("Device also has saved internal status data (generation {})\n", header.saved_data_generation);
+				}
+				// the rest is vendor-specific
+				true
+			},
+			None => false,
+		}
+	}
+
+	fn to_json(&self, data: &[u8]) -> Option<serde_json::Value> {
+		parse_internal_status_header(data).and_then(|header| serde_json::to_value(&header).ok())
+	}
+}
+
+struct Farm {}
+impl LogDecoder for Farm {
+	fn name(&self) -> &'static str { "Seagate FARM log" }
+
+	fn print(&self, data: &[u8]) -> bool {
+		match parse_farm_log(data) {
+			Some(log) => { print_farm(&log); true },
+			None => false,
+		}
+	}
+
+	fn to_json(&self, data: &[u8]) -> Option<serde_json::Value> {
+		parse_farm_log(data).and_then(|log| serde_json::to_value(&log).ok())
+	}
+}
+
+fn devstat_page_name(page: u8) -> &'static str {
+	match page {
+		devstat::GENERAL => "General Statistics",
+		0x02 => "Free-Fall Statistics",
+		devstat::ROTATING_MEDIA => "Rotating Media Statistics",
+		0x04 => "General Errors Statistics",
+		0x05 => "Temperature Statistics",
+		0x06 => "Transport Statistics",
+		0x07 => "Solid State Device Statistics",
+		0xff => "Vendor Specific Statistics",
+		_ => "Unknown",
+	}
+}
+
+// see ACS-4, Table 246 and the following ones; only these two pages are decoded for now
+fn devstat_name(page: u8, offset: u16) -> Option<&'static str> {
+	match (page, offset) {
+		(devstat::GENERAL, 0x08) => Some("Lifetime power-on resets"),
+		(devstat::GENERAL, 0x10) => Some("Power-on hours"),
+		(devstat::GENERAL, 0x18) => Some("Logical sectors written"),
+		(devstat::GENERAL, 0x20) => Some("Number of write commands"),
+		(devstat::GENERAL, 0x28) => Some("Logical sectors read"),
+		(devstat::GENERAL, 0x30) => Some("Number of read commands"),
+		(devstat::GENERAL, 0x38) => Some("Date and time timestamp"),
+		(devstat::GENERAL, 0x40) => Some("Pending error count"),
+		(devstat::GENERAL, 0x48) => Some("Workload utilization"),
+		(devstat::GENERAL, 0x50) => Some("Utilization usage rate"),
+		(devstat::ROTATING_MEDIA, 0x08) => Some("Spindle motor power-on hours"),
+		(devstat::ROTATING_MEDIA, 0x10) => Some("Head flying hours"),
+		(devstat::ROTATING_MEDIA, 0x18) => Some("Head load events"),
+		(devstat::ROTATING_MEDIA, 0x20) => Some("Number of reallocated logical sectors"),
+		(devstat::ROTATING_MEDIA, 0x28) => Some("Read recovery attempts"),
+		(devstat::ROTATING_MEDIA, 0x30) => Some("Number of mechanical start failures"),
+		(devstat::ROTATING_MEDIA, 0x38) => Some("Number of reallocation candidate logical sectors"),
+		(devstat::ROTATING_MEDIA, 0x40) => Some("Number of high priority unload events"),
+		_ => None,
+	}
+}
+
+struct DeviceStatistics {}
+impl LogDecoder for DeviceStatistics {
+	fn name(&self) -> &'static str { "Device Statistics log" }
+
+	fn print(&self, data: &[u8]) -> bool {
+		let log = match parse_device_statistics(data) {
+			Some(log) => log,
+			None => return false,
+		};
+		if log.pages.is_empty() {
+			print!("No statistics pages read (device supports {} page(s))\n", log.supported_pages.len());
+		}
+		for page in &log.pages {
+			print!("Page {:02x}h ({}), revision {}:\n", page.number, devstat_page_name(page.number), page.revision);
+			for stat in &page.statistics {
+				let name = devstat_name(page.number, stat.offset).map(|name| name.to_string())
+					.unwrap_or_else(|| format!("Statistic at {:03x}h", stat.offset));
+				let value = match stat.value {
+					Some(value) => value.to_string(),
+					None => "-".to_string(),
+				};
+				print!("  {:<48} {}{}{}\n", name, value,
+					if stat.normalized { " (normalized)" } else { "" },
+					if stat.monitored_condition_met { " (threshold reached)" } else { "" },
+				);
+			}
+		}
+		true
+	}
+
+	fn to_json(&self, data: &[u8]) -> Option<serde_json::Value> {
+		parse_device_statistics(data).and_then(|log| serde_json::to_value(&log).ok())
+	}
+}
+
+lazy_static! {
+	static ref DECODERS: HashMap<u8, &'static LogDecoder> = {
+		let mut m: HashMap<u8, &'static LogDecoder> = HashMap::new();
+		m.insert(0x03, &ExtErrors {});
+		m.insert(0x04, &DeviceStatistics {});
+		m.insert(0x10, &NcqError {});
+		m.insert(0x24, &InternalStatus {});
+		m.insert(0xa6, &Farm {});
+		m
+	};
+}
+
+/// Finds decoder for the log at `address`, falling back to [`Raw`](struct.Raw.html).
+pub fn decoder(address: u8) -> &'static LogDecoder {
+	DECODERS.get(&address).cloned().unwrap_or(&Raw {})
+}
@@ -2,12 +2,31 @@ mod info;
 mod health;
 mod attrs;
 mod list;
+mod firmware;
+mod standby;
+mod sct;
+mod log;
+mod log_decoders;
+mod version;
+mod dump;
+mod internal_status;
+mod scan;
+mod bad_sectors;
+mod raw_cmd;
+mod smart_config;
+mod drivedb;
+mod sata_feature;
+mod report;
+mod self_test;
 
 use std::collections::HashMap;
 use clap::{self, App, ArgMatches};
-use ::DeviceArgument;
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
 use std::path::Path;
 
+use number_prefix::{decimal_prefix, binary_prefix, Prefixed, Standalone};
+
 type Arg = clap::Arg<'static, 'static>;
 
 pub fn arg_json() -> Arg {
@@ -33,9 +52,78 @@ pub fn arg_drivedb() -> Arg {
 			.help("paths to drivedb files to look for\nuse 'FILE' for main (system-wide) file, '+FILE' for additional entries\nentries are looked up in every additional file in order of their appearance, then in the first valid main file, stopping at the first match\n(this option and its behavior is, to some extent, consistent with '-B' from smartctl)")
 }
 
+pub fn arg_vendorattribute() -> Arg {
+	Arg::with_name("vendorattribute")
+		.multiple(true)
+		.short("v") // smartctl-like
+		.long("vendorattribute") // smartctl-like
+		.takes_value(true)
+		.value_name("id,format[:byteorder][,name]")
+		.help("set display option for vendor attribute 'id'")
+}
+
+pub fn arg_config() -> Arg {
+	Arg::with_name("config")
+		.long("config")
+		.takes_value(true)
+		.value_name("FILE")
+		.help("read per-model vendor attribute options from this file\n(these take precedence over drivedb presets, while '-v' takes precedence over both)")
+}
+
+pub fn arg_units() -> Arg {
+	Arg::with_name("units")
+		.long("units")
+		.takes_value(true)
+		.possible_values(&["auto", "decimal", "binary", "bytes", "sectors"])
+		.help("units to show capacities and byte counters in\n'auto' shows both decimal and binary prefixes where possible; 'sectors' falls back to bytes where sector size is not known")
+}
+
+pub fn arg_precision() -> Arg {
+	Arg::with_name("precision")
+		.long("precision")
+		.takes_value(true)
+		.value_name("DIGITS")
+		.help("number of decimal places for values with decimal and binary prefixes (default: 1)")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Units { Auto, Decimal, Binary, Bytes, Sectors }
+
+/// Reads `--units` and `--precision` (see `arg_units()`, `arg_precision()`).
+pub fn units(args: &ArgMatches) -> Result<(Units, usize), Failure> {
+	let units = match args.value_of("units") {
+		Some("auto") | None => Units::Auto,
+		Some("decimal") => Units::Decimal,
+		Some("binary") => Units::Binary,
+		Some("bytes") => Units::Bytes,
+		Some("sectors") => Units::Sectors,
+		_ => unreachable!(),
+	};
+
+	let precision = match args.value_of("precision") {
+		Some(p) => p.parse().map_err(|_| status::fail(format!("invalid precision: {}", p), status::USAGE))?,
+		None => 1,
+	};
+
+	Ok((units, precision))
+}
+
+/// Formats byte count with either decimal or binary prefix (`Units::Decimal` and `Units::Binary`, respectively), or as is otherwise.
+pub fn format_bytes(bytes: u64, units: Units, precision: usize) -> String {
+	let prefixed = match units {
+		Units::Decimal => decimal_prefix(bytes as f32),
+		Units::Binary => binary_prefix(bytes as f32),
+		_ => return format!("{} bytes", bytes),
+	};
+	match prefixed {
+		Prefixed(p, x) => format!("{:.*} {}B", precision, x, p),
+		Standalone(x)  => format!("{} bytes", x),
+	}
+}
+
 pub trait Subcommand: Sync {
 	fn subcommand(&self) -> App<'static, 'static>;
-	fn run(&self, path: &Option<&Path>, dev: &Option<&DeviceArgument>, args: &ArgMatches);
+	fn run(&self, path: &Option<&Path>, dev: &Option<&DeviceArgument>, args: &ArgMatches) -> Result<Outcome, Failure>;
 }
 
 lazy_static! {
@@ -45,6 +133,21 @@ lazy_static! {
 		m.insert("list",   &list::List {});
 		m.insert("info",   &info::Info {});
 		m.insert("attrs",  &attrs::Attrs {});
+		m.insert("sct",    &sct::Sct {});
+		m.insert("firmware", &firmware::Firmware {});
+		m.insert("standby-timer", &standby::Standby {});
+		m.insert("log",    &log::Log {});
+		m.insert("version", &version::Version {});
+		m.insert("dump",   &dump::Dump {});
+		m.insert("internal-status", &internal_status::InternalStatus {});
+		m.insert("scan-surface", &scan::Scan {});
+		m.insert("bad-sectors", &bad_sectors::BadSectors {});
+		m.insert("raw-cmd", &raw_cmd::RawCmd {});
+		m.insert("smart-config", &smart_config::SmartConfig {});
+		m.insert("drivedb", &drivedb::Drivedb {});
+		m.insert("sata-feature", &sata_feature::Sata {});
+		m.insert("report", &report::Report {});
+		m.insert("self-test", &self_test::SelfTest {});
 		m
 	};
 }
@@ -0,0 +1,217 @@
+use hdd::Direction;
+use hdd::ata::{RegistersRead, RegistersWrite, RegistersWriteExt, ErrorBits};
+use hdd::ata::data::id::SecurityOperation;
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, check_security, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+use super::log_decoders::{LogDecoder, Raw};
+
+use std::path::Path;
+
+// see ACS-3, 6.2 STATUS field; bits 5, 4, 2 and 1 mean different things for different commands, so these are the names ATA/ATAPI-5 gave them
+const STATUS_BITS: [(u8, &str); 8] = [
+	(1 << 7, "BSY"),
+	(1 << 6, "DRDY"),
+	(1 << 5, "DF"),
+	(1 << 4, "DSC"),
+	(1 << 3, "DRQ"),
+	(1 << 2, "CORR"),
+	(1 << 1, "IDX"),
+	(1 << 0, "ERR"),
+];
+
+fn format_status(status: u8) -> String {
+	let names: Vec<_> = STATUS_BITS.iter()
+		.filter(|&&(bit, _)| status & bit != 0)
+		.map(|&(_, name)| name)
+		.collect();
+	if names.is_empty() {
+		format!("0x{:02x}", status)
+	} else {
+		format!("0x{:02x} ({})", status, names.join(", "))
+	}
+}
+
+// accepts both `0x1f` and `1f`
+fn hex_arg(args: &ArgMatches, name: &str, max: u64) -> Result<u64, Failure> {
+	let value = match args.value_of(name) {
+		Some(value) => value,
+		None => return Ok(0),
+	};
+	let digits = if value.starts_with("0x") || value.starts_with("0X") { &value[2..] } else { value };
+	match u64::from_str_radix(digits, 16) {
+		Ok(x) if x <= max => Ok(x),
+		Ok(_) => Err(status::fail(format!("Invalid --{}: {} is too big (max 0x{:x})", name, value, max), status::USAGE)),
+		Err(_) => Err(status::fail(format!("Invalid --{}: {} is not a hex number", name, value), status::USAGE)),
+	}
+}
+
+fn print_registers(regs: &RegistersRead) {
+	print!("Status: {}\n", format_status(regs.status));
+	print!("Error:  {}\n", ErrorBits(regs.error));
+	print!("Count:  0x{:04x}\n", regs.count());
+	print!("LBA:    0x{:012x}\n", regs.lba());
+	print!("Device: 0x{:02x}\n", regs.device);
+}
+
+pub struct RawCmd {}
+impl Subcommand for RawCmd {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("raw-cmd")
+			.about("Issues arbitrary ATA command, and shows returned registers and data (DANGEROUS)")
+			.arg(Arg::with_name("command")
+				.long("command")
+				.takes_value(true)
+				.required(true)
+				.value_name("HEX")
+				.help("command code, e.g. ec for IDENTIFY DEVICE")
+			)
+			.arg(Arg::with_name("features")
+				.long("features")
+				.takes_value(true)
+				.value_name("HEX")
+				.help("FEATURES field (default: 0)")
+			)
+			.arg(Arg::with_name("count")
+				.long("count")
+				.takes_value(true)
+				.value_name("HEX")
+				.help("COUNT field (default: 0); with --data-in, this is also the number of 512-byte blocks to read, and cannot be 0")
+			)
+			.arg(Arg::with_name("lba")
+				.long("lba")
+				.takes_value(true)
+				.value_name("HEX")
+				.help("LBA field (default: 0); for 28-bit commands, bits 27:24 go into the DEVICE field")
+			)
+			.arg(Arg::with_name("device")
+				.long("device")
+				.takes_value(true)
+				.value_name("HEX")
+				.help("DEVICE field (default: 0), e.g. 40 to set the LBA bit")
+			)
+			.arg(Arg::with_name("ext")
+				.long("ext")
+				.help("issue 48-bit command (required for 16-bit features and count, and for LBAs over 28 bits)")
+			)
+			.arg(Arg::with_name("data-in")
+				.long("data-in")
+				.help("command transfers data from the device (PIO Data-In); otherwise it is treated as a non-data command")
+			)
+			.arg(Arg::with_name("i-know-what-im-doing")
+				.long("i-know-what-im-doing")
+				.help("Confirm that you understand that some commands destroy data or render the device unusable")
+			)
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		if !args.is_present("i-know-what-im-doing") {
+			eprint!("Arbitrary commands can erase data, or render the device unusable.\n");
+			return Err(status::fail("Refusing to continue without --i-know-what-im-doing", status::USAGE));
+		}
+
+		let ext = args.is_present("ext");
+		let (max16, max_lba) = if ext { (0xffff, 0xffff_ffff_ffff) } else { (0xff, 0x0fff_ffff) };
+
+		let command = hex_arg(args, "command", 0xff)? as u8;
+		let features = hex_arg(args, "features", max16)?;
+		let count = hex_arg(args, "count", max16)?;
+		let lba = hex_arg(args, "lba", max_lba)?;
+		let device = hex_arg(args, "device", 0xff)? as u8;
+
+		// ATA reads 0 as 256 blocks (65536 for 48-bit commands), which is more than the transfer we set up would take
+		if count == 0 && args.is_present("data-in") {
+			return Err(status::fail("Invalid --count: 0 blocks cannot be read with --data-in", status::USAGE));
+		}
+
+		let regs = RegistersWrite {
+			command,
+			features: features as u8,
+			sector_count: count as u8,
+			sector: lba as u8,
+			cyl_low: (lba >> 8) as u8,
+			cyl_high: (lba >> 16) as u8,
+			device: if ext { device } else { device | ((lba >> 24) as u8 & 0x0f) },
+			ext: if ext {
+				Some(RegistersWriteExt {
+					features: (features >> 8) as u8,
+					sector_count: (count >> 8) as u8,
+					sector: (lba >> 24) as u8,
+					cyl_low: (lba >> 32) as u8,
+					cyl_high: (lba >> 40) as u8,
+				})
+			} else { None },
+		};
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Raw commands can only be issued to ATA devices", status::COMMAND_FAILED))?;
+
+		// these are refused by locked or frozen devices anyway, and the reply does not tell why
+		match command {
+			// SECURITY SET PASSWORD, SECURITY UNLOCK, SECURITY ERASE PREPARE, SECURITY ERASE UNIT, SECURITY DISABLE PASSWORD
+			0xf1 | 0xf2 | 0xf3 | 0xf4 | 0xf6 => check_security(id, SecurityOperation::SecurityChange)?,
+			// SANITIZE DEVICE, READ VERIFY SECTOR(S) (EXT), and SECURITY FREEZE LOCK, which, unlike other SECURITY commands, is aborted by locked devices rather than frozen ones
+			0xb4 | 0x40 | 0x42 | 0xf5 => check_security(id, SecurityOperation::MediaAccess)?,
+			_ => (),
+		}
+
+		let dir = if args.is_present("data-in") { Direction::From } else { Direction::None };
+		let (regs, data) = dev.ata_do(dir, &regs).map_err(|err| {
+			let failure = status::fail(format!("Command failed: {}", err), status::COMMAND_FAILED);
+			// device errors are as interesting as successful replies here
+			if let Some(regs) = err.registers() {
+				print_registers(regs);
+			}
+			failure
+		})?;
+
+		print_registers(&regs);
+
+		if let Direction::From = dir {
+			print!("\nData, {} byte(s):\n", data.len());
+			Raw {}.print(&data);
+		}
+
+		Ok(Outcome::new())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use mock;
+
+	#[test]
+	fn zero_count_data_in() {
+		let dev = mock::device(mock::dump(mock::SMART_ENABLED));
+		let failure = mock::run(&RawCmd {}, &dev, &["--command", "ec", "--data-in", "--i-know-what-im-doing"]).unwrap_err();
+		assert_eq!(failure.bits(), status::USAGE);
+		assert!(failure.message().unwrap().starts_with("Invalid --count"));
+	}
+
+	#[test]
+	fn frozen_refuses_unlock() {
+		let mut words = mock::SMART_ENABLED.to_vec();
+		words.push((128, 0b0_1011)); // supported, enabled, frozen
+		let dev = mock::device(mock::dump(&words));
+		let failure = mock::run(&RawCmd {}, &dev, &["--command", "f2", "--i-know-what-im-doing"]).unwrap_err();
+		assert_eq!(failure.bits(), status::COMMAND_FAILED);
+		assert!(failure.message().unwrap().contains("SECURITY FROZEN"));
+	}
+}
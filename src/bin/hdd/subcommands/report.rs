@@ -0,0 +1,279 @@
+use hdd::ata::data::{self, attr};
+use hdd::ata::data::id::{Id, Ternary};
+use hdd::ata::data::self_test::{self, SelfTest};
+use hdd::ata::RegistersRead;
+use hdd::ata::misc::{self, Misc};
+use hdd::drivedb;
+
+use clap::{
+	App,
+	ArgMatches,
+	SubCommand,
+};
+
+use serde;
+use serde_json;
+
+use ::{DeviceArgument, ata_registers_json, drivedb_warning_json, open_drivedb, status, tolerance};
+use status::{Outcome, Failure};
+use super::{Subcommand, arg_json, arg_drivedb, arg_vendorattribute, arg_config, arg_units, arg_precision, units};
+use super::attrs::{print_attributes, user_attributes};
+use super::info::{Verbosity, print_ata_id};
+
+use std::path::Path;
+
+/*
+Every section is produced on its own, and a section that fails only records its error (and accounts for it in the outcome), so that whatever else the drive is willing to tell is still reported.
+Partially broken drives are exactly the ones people want the full picture of.
+*/
+
+#[derive(Debug)]
+struct SectionError {
+	message: String,
+	/// Registers returned along with the error, if it is the device that reported one
+	registers: Option<RegistersRead>,
+}
+
+impl SectionError {
+	fn new(message: &str) -> Self {
+		SectionError { message: message.to_string(), registers: None }
+	}
+	fn command(what: &str, err: misc::Error, outcome: &mut Outcome) -> Self {
+		outcome.set(status::COMMAND_FAILED);
+		SectionError {
+			message: format!("{}: {}", what, err),
+			registers: err.registers().cloned(),
+		}
+	}
+}
+
+fn smart_enabled(id: &Id, outcome: &mut Outcome) -> Result<(), SectionError> {
+	match id.smart {
+		Ternary::Enabled => Ok(()),
+		Ternary::Disabled => {
+			outcome.set(status::SMART_UNAVAILABLE);
+			Err(SectionError::new("S.M.A.R.T. is disabled"))
+		},
+		Ternary::Unsupported => {
+			outcome.set(status::SMART_UNAVAILABLE);
+			Err(SectionError::new("S.M.A.R.T. is not supported"))
+		},
+	}
+}
+
+fn health(dev: &Misc, id: &Id, outcome: &mut Outcome) -> Result<Option<bool>, SectionError> {
+	smart_enabled(id, outcome)?;
+	let health = dev.get_smart_health().map_err(|err| SectionError::command("cannot query S.M.A.R.T. health status", err, outcome))?;
+	outcome.health(health);
+	Ok(health)
+}
+
+fn attributes(dev: &Misc, id: &Id, meta: &Option<drivedb::DriveMeta>, outcome: &mut Outcome) -> Result<Vec<attr::SmartAttribute>, SectionError> {
+	smart_enabled(id, outcome)?;
+	let data = dev.get_smart_values_raw().map_err(|err| SectionError::command("cannot read S.M.A.R.T. attributes", err, outcome))?;
+	if !data::checksum_valid(&data) {
+		tolerance::check_in(outcome, "S.M.A.R.T. READ DATA reply checksum mismatch", status::COMMAND_FAILED).map_err(|err| {
+			outcome.set(status::COMMAND_FAILED);
+			SectionError::new(&err)
+		})?;
+	}
+	// values are still worth showing without thresholds
+	let thresh = dev.get_smart_thresholds_raw().unwrap_or_else(|err| {
+		eprint!("Cannot read S.M.A.R.T. thresholds, showing attributes without them: {}\n", err);
+		outcome.set(status::COMMAND_FAILED);
+		vec![]
+	});
+	let values = attr::parse_smart_values(&data, &thresh, meta);
+	outcome.attributes(&values);
+	Ok(values)
+}
+
+fn self_tests(dev: &Misc, id: &Id, outcome: &mut Outcome) -> Result<Vec<SelfTest>, SectionError> {
+	smart_enabled(id, outcome)?;
+	if !id.smart_self_test_supported {
+		return Err(SectionError::new("self-tests are not supported"));
+	}
+	match dev.get_self_test_log() {
+		Ok(Some(tests)) => Ok(tests),
+		Ok(None) => {
+			outcome.set(status::COMMAND_FAILED);
+			Err(SectionError::new("SMART self-test log reply is truncated"))
+		},
+		Err(err) => Err(SectionError::command("cannot read SMART self-test log", err, outcome)),
+	}
+}
+
+pub fn print_self_tests(tests: &[SelfTest]) {
+	if tests.is_empty() {
+		print!("No self-tests have been logged.\n");
+		return;
+	}
+	print!("S.M.A.R.T. self-test log:\n");
+	print!("  # test status                          remaining hours  LBA of first error\n");
+	for (i, test) in tests.iter().enumerate() {
+		print!("{:3} {:4} {:<32} {:>8}% {:5}  {}\n",
+			i + 1,
+			format!("{:02x}h", test.test),
+			self_test::status_description(test.status),
+			test.remaining as u32 * 10,
+			test.lifetime,
+			test.failing_lba.map(|lba| lba.to_string()).unwrap_or_else(|| "-".to_string()),
+		);
+	}
+}
+
+// in JSON, every section is either `{"error": …, "registers": …}`, or whatever the section has to say in place of it
+fn section_json<T: serde::Serialize>(section: &Result<T, SectionError>) -> serde_json::Value {
+	match section {
+		Ok(value) => serde_json::to_value(value).unwrap(),
+		Err(err) => {
+			let mut obj = serde_json::Map::new();
+			obj.insert("error".to_string(), serde_json::Value::String(err.message.clone()));
+			if let Some(ref regs) = err.registers {
+				obj.insert("registers".to_string(), ata_registers_json(regs));
+			}
+			serde_json::Value::Object(obj)
+		},
+	}
+}
+
+fn report_json(id: &Id, meta: &Option<drivedb::DriveMeta>, health: &Result<Option<bool>, SectionError>, attributes: &Result<Vec<attr::SmartAttribute>, SectionError>, self_tests: &Result<Vec<SelfTest>, SectionError>) -> serde_json::Value {
+	let mut info = serde_json::to_value(id).unwrap();
+	if let Some(family) = meta.as_ref().and_then(|meta| meta.family) {
+		info.as_object_mut().unwrap().insert("family".to_string(), serde_json::to_value(family).unwrap());
+	}
+	if let Some(warning) = meta.as_ref().and_then(drivedb_warning_json) {
+		info.as_object_mut().unwrap().insert("warning".to_string(), warning);
+	}
+
+	let mut out = serde_json::Map::new();
+	out.insert("info".to_string(), info);
+	out.insert("health".to_string(), section_json(health));
+	out.insert("attributes".to_string(), section_json(attributes));
+	out.insert("self_tests".to_string(), section_json(self_tests));
+	serde_json::Value::Object(out)
+}
+
+pub struct Report {}
+impl Subcommand for Report {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("report")
+			.about("Prints device information, health status, attributes and self-test log at once; sections that fail are reported, and do not prevent others from being shown")
+			.arg(arg_json())
+			.arg(arg_drivedb())
+			.arg(arg_vendorattribute())
+			.arg(arg_config())
+			.arg(arg_units())
+			.arg(arg_precision())
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Reports are only available for ATA devices", status::COMMAND_FAILED))?;
+
+		// same attribute names as `attrs` would show
+		let drivedb = open_drivedb(args.values_of("drivedb"));
+		let user_attributes = user_attributes(args, Some(id), &drivedb)?;
+		let meta = Some(match drivedb {
+			Some(ref drivedb) => drivedb.render_meta(id, &user_attributes),
+			None => drivedb::builtin_meta(id, &user_attributes),
+		});
+		let mut outcome = Outcome::new();
+		if let Some(severity) = meta.as_ref().and_then(|meta| meta.warning_severity()) {
+			outcome.drivedb_warning(severity);
+		}
+
+		let health = health(dev, id, &mut outcome);
+		let attributes = attributes(dev, id, &meta, &mut outcome);
+		let self_tests = self_tests(dev, id, &mut outcome);
+
+		if args.is_present("json") {
+			print!("{}\n", serde_json::to_string(&report_json(id, &meta, &health, &attributes, &self_tests)).unwrap());
+			return Ok(outcome);
+		}
+
+		let (units, precision) = units(args)?;
+		// capabilities are for `info` to show; this is about the state of the drive
+		print_ata_id(id, &meta, &None, units, precision, Verbosity::Normal);
+
+		match health {
+			Ok(health) => print!("S.M.A.R.T. health status: {}\n", match health {
+				Some(true) => "good",
+				Some(false) => "BAD",
+				None => "(unknown)",
+			}),
+			Err(err) => print!("S.M.A.R.T. health status: not available ({})\n", err.message),
+		}
+		print!("\n");
+
+		match attributes {
+			Ok(values) => print_attributes(values, 10, false),
+			Err(err) => print!("S.M.A.R.T. attributes: not available ({})\n", err.message),
+		}
+		print!("\n");
+
+		match self_tests {
+			Ok(tests) => print_self_tests(&tests),
+			Err(err) => print!("S.M.A.R.T. self-test log: not available ({})\n", err.message),
+		}
+
+		Ok(outcome)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hdd::ata::{RegistersWrite, SMARTFeature};
+	use mock;
+
+	#[test]
+	fn health_fails() {
+		// SMART RETURN STATUS is aborted, everything else is in order
+		let mut dump = mock::dump(mock::SMART_ENABLED);
+		dump.record(&RegistersWrite::smart(SMARTFeature::ReadValues, 0, 1), &mock::ok(), &mock::smart_values(&[
+			(5, 0x0033, 100, 100, [0; 6]),
+			(9, 0x0032, 95, 95, [0x10, 0x27, 0, 0, 0, 0]),
+		]));
+		dump.record(&RegistersWrite::smart(SMARTFeature::ReadThresholds, 0, 1), &mock::ok(), &vec![0; 512]);
+		let dev = mock::device(dump);
+
+		// the report is still there, and the failure is accounted for
+		assert_eq!(mock::run(&Report {}, &dev, &["--json"]).unwrap().bits(), status::COMMAND_FAILED);
+		assert_eq!(mock::run(&Report {}, &dev, &[]).unwrap().bits(), status::COMMAND_FAILED);
+
+		let (dev, id) = dev.ata().unwrap();
+		let meta = Some(drivedb::builtin_meta(id, &[]));
+		let mut outcome = Outcome::new();
+		let health = health(dev, id, &mut outcome);
+		let attributes = attributes(dev, id, &meta, &mut outcome);
+		let self_tests = self_tests(dev, id, &mut outcome);
+		let json = report_json(id, &meta, &health, &attributes, &self_tests);
+
+		assert!(json["health"]["error"].as_str().unwrap().starts_with("cannot query S.M.A.R.T. health status"));
+		assert_eq!(json["health"]["registers"]["error_bits"][0].as_str(), Some("ABRT"));
+		assert_eq!(json["info"]["capacity"].as_u64(), Some(512_000_000));
+		let attributes = json["attributes"].as_array().unwrap();
+		assert_eq!(attributes.len(), 2);
+		assert_eq!(attributes[1]["name"].as_str(), Some("Power_On_Hours"));
+	}
+
+	#[test]
+	fn vendor_attributes() {
+		// same as with `attrs -v`
+		let args = Report {}.subcommand().get_matches_from(vec!["report", "-v", "9,raw48,Power_On_Time"]);
+		let id = data::id::parse_id(&mock::identify(mock::SMART_ENABLED));
+		let attributes = user_attributes(&args, Some(&id), &None).unwrap();
+		let meta = Some(drivedb::builtin_meta(&id, &attributes));
+		let values = attr::parse_smart_values(&mock::smart_values(&[(9, 0x0032, 95, 95, [0; 6])]), &vec![], &meta);
+		assert_eq!(values[0].name, Some("Power_On_Time".to_string()));
+	}
+}
@@ -0,0 +1,99 @@
+use hdd::ata::data::id::{SataFeature, SataFeatures, Ternary};
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+	Values,
+};
+
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+
+use std::path::Path;
+
+fn arg_feature(name: &'static str, help: &'static str) -> Arg<'static, 'static> {
+	Arg::with_name(name)
+		.long(name)
+		.takes_value(true)
+		.multiple(true)
+		.number_of_values(1)
+		.value_name("FEATURE")
+		.help(help)
+}
+
+fn parse_features(values: Option<Values>) -> Result<Vec<SataFeature>, Failure> {
+	values.into_iter().flatten().map(|name| SataFeature::parse(name).ok_or_else(|| {
+		let names: Vec<_> = SataFeature::all().iter().map(|f| f.name()).collect();
+		status::fail(format!("Unknown SATA feature {}, expected one of: {}", name, names.join(", ")), status::USAGE)
+	})).collect()
+}
+
+fn print_features(features: &SataFeatures) {
+	for &feature in SataFeature::all().iter() {
+		print!("{:<18} {:<50} {}\n", feature.name(), feature.description(), features.get(feature));
+	}
+}
+
+pub struct Sata {}
+impl Subcommand for Sata {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("sata-feature")
+			.about("Shows Serial ATA features (DIPM, DevSleep and such), and turns them on or off")
+			.arg(arg_feature("enable", "turn the feature on (by its short name, as listed by this subcommand, or by its SET FEATURES code)"))
+			.arg(arg_feature("disable", "turn the feature off"))
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Serial ATA features are only available for ATA devices", status::COMMAND_FAILED))?;
+
+		let changes: Vec<_> = parse_features(args.values_of("enable"))?.into_iter().map(|f| (f, true))
+			.chain(parse_features(args.values_of("disable"))?.into_iter().map(|f| (f, false)))
+			.collect();
+
+		if changes.is_empty() {
+			print_features(&id.sata_features);
+			return Ok(Outcome::new());
+		}
+
+		for &(feature, _) in &changes {
+			if id.sata_features.get(feature) == Ternary::Unsupported {
+				return Err(status::fail(format!("{} is not supported", feature.description()), status::COMMAND_FAILED));
+			}
+		}
+
+		let mut outcome = Outcome::new();
+		for &(feature, enable) in &changes {
+			if let Err(err) = dev.set_sata_feature(feature, enable) {
+				eprint!("Cannot {} {}: {}\n", if enable { "enable" } else { "disable" }, feature.name(), err);
+				outcome.set(status::COMMAND_FAILED);
+			}
+		}
+
+		// the only way to tell whether the device actually did what it's been told to
+		let id = dev.get_device_id()
+			.map_err(|err| outcome.fail(format!("Cannot re-read device identification: {}", err), status::COMMAND_FAILED))?;
+		for &(feature, enable) in &changes {
+			let expected = if enable { Ternary::Enabled } else { Ternary::Disabled };
+			if id.sata_features.get(feature) != expected {
+				eprint!("{} is still {} after SET FEATURES\n", feature.name(), if enable { "disabled" } else { "enabled" });
+				outcome.set(status::COMMAND_FAILED);
+			}
+		}
+
+		print_features(&id.sata_features);
+
+		Ok(outcome)
+	}
+}
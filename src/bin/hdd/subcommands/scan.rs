@@ -0,0 +1,241 @@
+use hdd::ata::ErrorBits;
+use hdd::ata::misc::{self, Misc};
+use hdd::ata::data::id::SecurityOperation;
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, check_security, interrupt, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+
+use std::path::Path;
+
+// Sectors verified at once. Verification does not transfer any data to the host, so it's cheap, but a command should still finish well before it times out, even if the device retries reading some sectors.
+const CHUNK: u64 = 2048;
+
+// parses START-END (inclusive LBAs)
+fn parse_range(s: &str) -> Result<(u64, u64), String> {
+	let pos = s.find('-').ok_or_else(|| format!("{}: expected START-END", s))?;
+	let start = s[..pos].parse().map_err(|e| format!("{}: {}", &s[..pos], e))?;
+	let end = s[pos + 1 ..].parse().map_err(|e| format!("{}: {}", &s[pos + 1 ..], e))?;
+	if start > end {
+		return Err(format!("{}: START is past END", s));
+	}
+	Ok((start, end))
+}
+
+// verifies `count` (at most CHUNK) sectors starting at `lba`, splitting the range into commands the device can handle
+fn verify(dev: &Misc, lba48: bool, lba: u64, count: u64) -> Result<(), misc::Error> {
+	if lba48 {
+		return dev.read_verify_sectors_ext(lba, count as u16);
+	}
+	let mut i = 0;
+	while i < count {
+		let n = ::std::cmp::min(count - i, 256);
+		// 256 sectors are encoded as 0
+		dev.read_verify_sectors((lba + i) as u32, n as u8)?;
+		i += n;
+	}
+	Ok(())
+}
+
+pub struct Scan {}
+impl Subcommand for Scan {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("scan-surface")
+			.about("Looks for unreadable sectors using READ VERIFY SECTOR(S), which does not transfer any data to the host")
+			.after_help("Ctrl-C stops the scan after the chunk of sectors being verified, and lists unreadable sectors found up to that point")
+			.arg(Arg::with_name("range")
+				.long("range")
+				.takes_value(true)
+				.value_name("START-END")
+				.help("scan this range of LBAs only (inclusive; default: the whole device)")
+			)
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Surface scan is only available for ATA devices", status::COMMAND_FAILED))?;
+		check_security(id, SecurityOperation::MediaAccess)?;
+
+		let sectors = id.capacity.checked_div(id.sector_size_log as u64).unwrap_or(0);
+		if sectors == 0 {
+			return Err(status::fail("Device reports no sectors to scan", status::COMMAND_FAILED));
+		}
+		if !id.lba48_supported && sectors > 1 << 28 {
+			return Err(status::fail("Device does not support 48-bit commands, but is too large for 28-bit ones", status::COMMAND_FAILED));
+		}
+
+		let (start, end) = match args.value_of("range") {
+			Some(range) => parse_range(range)
+				.map_err(|err| status::fail(format!("Invalid --range: {}", err), status::USAGE))?,
+			None => (0, sectors - 1),
+		};
+		if end >= sectors {
+			return Err(status::fail(format!("Invalid --range: device only has {} sectors", sectors), status::USAGE));
+		}
+
+		let total = end - start + 1;
+		let mut bad = vec![];
+		let mut progress = None;
+
+		interrupt::install();
+
+		let mut lba = start;
+		// transport errors and timeouts tell nothing about the media, and the next command is not likely to fare any better
+		let mut stopped_by = None;
+		while lba <= end {
+			if interrupt::interrupted() {
+				break;
+			}
+			let count = ::std::cmp::min(end - lba + 1, CHUNK);
+
+			match verify(dev, id.lba48_supported, lba, count) {
+				Ok(()) => (),
+				// the command might have been cut short by the signal itself, which tells nothing about sectors
+				Err(_) if interrupt::interrupted() => break,
+				Err(misc::Error::Aborted(regs)) => return Err(status::fail(format!("\nDevice rejected READ VERIFY SECTOR(S) at LBA {}: {}", lba, ErrorBits(regs.error)), status::COMMAND_FAILED)),
+				// something in this chunk is unreadable; let's find out what exactly
+				Err(misc::Error::Device(regs)) => {
+					// sectors before the one the device failed at were verified just fine
+					let failed = regs.lba();
+					let first = if failed >= lba && failed < lba + count { failed } else { lba };
+					for i in first .. lba + count {
+						let res = verify(dev, id.lba48_supported, i, 1);
+						if interrupt::interrupted() {
+							// this sector does not count as scanned, whatever the outcome
+							lba = i;
+							break;
+						}
+						match res {
+							Ok(()) => (),
+							Err(misc::Error::Device(_)) | Err(misc::Error::Aborted(_)) => {
+								eprint!("\rLBA {}: {}\n", i, res.unwrap_err());
+								bad.push(i);
+							},
+							Err(err) => {
+								lba = i;
+								stopped_by = Some(err);
+								break;
+							},
+						}
+					}
+					if interrupt::interrupted() || stopped_by.is_some() {
+						break;
+					}
+				},
+				Err(err) => {
+					stopped_by = Some(err);
+					break;
+				},
+			}
+
+			lba += count;
+
+			// in tenths of a percent, so that we don't flood the terminal, yet still show some movement for large drives
+			let done = (lba - start) * 1000 / total;
+			if progress != Some(done) {
+				progress = Some(done);
+				eprint!("\rScanned {:.1}% (LBA {} of {})", done as f64 / 10., lba - 1, end);
+			}
+		}
+		eprint!("\n");
+
+		let mut outcome = Outcome::new();
+		outcome.bad_sectors(bad.len());
+
+		// `lba` is the first sector that was not scanned
+		let end = if lba <= end {
+			outcome.set(status::COMMAND_FAILED);
+			let why = match stopped_by {
+				Some(err) => format!("stopped at LBA {}: {}", lba, err),
+				None => format!("interrupted at LBA {}", lba),
+			};
+			if lba == start {
+				print!("Scan {}, before any sectors were verified\n", why);
+				return Ok(outcome);
+			}
+			print!("Scan {}\n", why);
+			lba - 1
+		} else { end };
+
+		if bad.is_empty() {
+			print!("No unreadable sectors found in LBAs {}-{}\n", start, end);
+		} else {
+			print!("{} unreadable sector(s) found in LBAs {}-{}:\n", bad.len(), start, end);
+			for lba in bad {
+				print!("{}\n", lba);
+			}
+		}
+
+		Ok(outcome)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hdd::ata::{Command, RegistersRead, RegistersWrite};
+	use mock;
+
+	fn verify_regs(lba: u8, count: u8) -> RegistersWrite {
+		RegistersWrite {
+			command: Command::ReadVerifySectors as u8,
+			features: 0,
+			sector_count: count,
+			sector: lba,
+			cyl_low: 0,
+			cyl_high: 0,
+			device: 0x40,
+			ext: None,
+		}
+	}
+
+	// uncorrectable error at `lba`
+	fn unc(lba: u8) -> RegistersRead {
+		RegistersRead { error: 1 << 6, status: 0x51, sector: lba, ..mock::ok() }
+	}
+
+	// 10-sector drive that fails to verify them all at once, reporting LBA `failed`; sectors without a reply are aborted
+	fn scan(failed: u8, replies: &[(u8, RegistersRead)]) -> Result<Outcome, Failure> {
+		let mut dump = mock::dump(&[(60, 10)]);
+		dump.record(&verify_regs(0, 10), &unc(failed), &[]);
+		for &(lba, ref reply) in replies {
+			dump.record(&verify_regs(lba, 1), reply, &[]);
+		}
+		mock::run(&Scan {}, &mock::device(dump), &[])
+	}
+
+	#[test]
+	fn probes_from_reported_lba() {
+		// had any sector before LBA 7 been probed, it would have been aborted, and counted as unreadable
+		let replies = [(7, mock::ok()), (8, mock::ok()), (9, mock::ok())];
+		assert_eq!(scan(7, &replies).unwrap().bits(), 0);
+	}
+
+	#[test]
+	fn unreadable_sector() {
+		let replies = [(7, unc(7)), (8, mock::ok()), (9, mock::ok())];
+		assert_eq!(scan(7, &replies).unwrap().bits(), status::HEALTH_BAD);
+	}
+
+	#[test]
+	fn no_sectors() {
+		// logical sector size is said to be in words 117..118, which are zero
+		let failure = mock::run(&Scan {}, &mock::device(mock::dump(&[(60, 10), (106, 1 << 14 | 1 << 12)])), &[]).unwrap_err();
+		assert_eq!(failure.bits(), status::COMMAND_FAILED);
+	}
+}
@@ -0,0 +1,71 @@
+use hdd::ata::data::sct::Temperatures;
+
+use clap::{
+	App,
+	ArgMatches,
+	SubCommand,
+};
+
+
+use ::{DeviceArgument, when_smart_enabled, status, to_json_string};
+use status::{Outcome, Failure};
+use super::{Subcommand, arg_json};
+
+use std::path::Path;
+
+fn temp(t: &Option<i8>) -> String {
+	match *t {
+		Some(t) => format!("{}°C", t),
+		None => "-".to_string(),
+	}
+}
+
+fn print_temperatures(t: &Temperatures) {
+	print!("Current temperature: {}\n", temp(&t.current));
+	print!("Power cycle min/max temperature: {}/{}\n", temp(&t.power_cycle_min), temp(&t.power_cycle_max));
+	print!("Lifetime min/max temperature: {}/{}\n", temp(&t.lifetime_min), temp(&t.lifetime_max));
+}
+
+pub struct Sct {}
+impl Subcommand for Sct {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("sct")
+			.about("Prints SCT status of the device, including min/max temperatures")
+			.arg(arg_json())
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("SCT is only available for ATA devices", status::COMMAND_FAILED))?;
+
+		if !id.sct_supported {
+			return Err(status::fail("SCT is not supported", status::COMMAND_FAILED));
+		}
+
+		let use_json = args.is_present("json");
+
+		when_smart_enabled(&id.smart, "SCT status", use_json, || {
+			let status = dev.get_sct_status()
+				.map_err(|err| status::fail(format!("Cannot read SCT status: {}", err), status::COMMAND_FAILED))?
+				.ok_or_else(|| status::fail("SCT status reply is truncated", status::COMMAND_FAILED))?;
+
+			if use_json {
+				print!("{}\n", to_json_string(&status));
+			} else {
+				print!("SCT format version: {}, SCT version: {}, SCT spec: {}\n", status.format_version, status.sct_version, status.sct_spec);
+				print_temperatures(&status.temperature);
+				print!("Intervals over/under temperature limit: {}/{}\n", status.over_limit_count, status.under_limit_count);
+			}
+
+			Ok(Outcome::new())
+		})
+	}
+}
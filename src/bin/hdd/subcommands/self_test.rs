@@ -0,0 +1,221 @@
+use hdd::ata::SelfTestKind;
+use hdd::ata::misc::Misc;
+use hdd::ata::data::id::{SecurityOperation, Ternary};
+use hdd::ata::data::self_test;
+use hdd::ata::data::smart_capabilities::SmartCapabilities;
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, check_security, interrupt, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+use super::report::print_self_tests;
+
+use std::path::Path;
+use std::time::Duration;
+
+// devices only update the progress in 10% steps anyway
+const POLL_INTERVAL: u64 = 30;
+
+fn kind_name(kind: SelfTestKind) -> &'static str {
+	match kind {
+		SelfTestKind::Short => "short",
+		SelfTestKind::Extended => "extended",
+		SelfTestKind::Conveyance => "conveyance",
+	}
+}
+
+fn capabilities(dev: &Misc) -> Result<SmartCapabilities, Failure> {
+	match dev.get_smart_capabilities() {
+		Ok(Some(caps)) => Ok(caps),
+		Ok(None) => Err(status::fail("S.M.A.R.T. READ DATA reply is truncated", status::COMMAND_FAILED)),
+		Err(err) => Err(status::fail(format!("Cannot read self-test status: {}", err), status::COMMAND_FAILED)),
+	}
+}
+
+// the device does not tell which test is running, but some log it as one in progress right away
+fn running_kind(dev: &Misc) -> Option<SelfTestKind> {
+	let tests = dev.get_self_test_log().ok()??;
+	let test = tests.first()?;
+	if test.status != 15 {
+		return None;
+	}
+	// captive mode tests (81h..83h) would not let us ask, but let's not be picky
+	match test.test & 0x7f {
+		1 => Some(SelfTestKind::Short),
+		2 => Some(SelfTestKind::Extended),
+		3 => Some(SelfTestKind::Conveyance),
+		_ => None,
+	}
+}
+
+// e.g. 'extended self-test in progress, 40% remaining, ~38 minutes left'
+fn progress(caps: &SmartCapabilities, kind: Option<SelfTestKind>) -> String {
+	// only called for tests in progress
+	let remaining = caps.self_test_remaining().unwrap_or(0);
+	match kind {
+		Some(kind) => {
+			let total = caps.self_test_time(kind) as u32;
+			// round up, so that it never says 0 minutes for a running test
+			let left = (total * remaining as u32 + 99) / 100;
+			format!("{} self-test in progress, {}% remaining, ~{} minute(s) left", kind_name(kind), remaining, left)
+		},
+		None => format!("self-test in progress, {}% remaining", remaining),
+	}
+}
+
+// prints the outcome of the most recent self-test
+fn print_result(dev: &Misc, caps: &SmartCapabilities, outcome: &mut Outcome) {
+	print!("Self-test {}\n", self_test::status_description(caps.self_test_status >> 4));
+	match dev.get_self_test_log() {
+		Ok(Some(tests)) => if let Some(test) = tests.first() {
+			outcome.self_test(test);
+			print!("\n");
+			print_self_tests(&tests[..1]);
+		},
+		Ok(None) => {
+			eprint!("SMART self-test log reply is truncated\n");
+			outcome.set(status::COMMAND_FAILED);
+		},
+		Err(err) => {
+			eprint!("Cannot read SMART self-test log: {}\n", err);
+			outcome.set(status::COMMAND_FAILED);
+		},
+	}
+}
+
+pub struct SelfTest {}
+impl Subcommand for SelfTest {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("self-test")
+			.about("Starts, aborts, or shows the progress of S.M.A.R.T. self-tests")
+			.arg(Arg::with_name("start")
+				.long("start")
+				.takes_value(true)
+				.possible_values(&["short", "extended", "conveyance"])
+				.help("self-test to start; it runs in the background, and the device keeps serving other commands in the meantime")
+			)
+			.arg(Arg::with_name("abort")
+				.long("abort")
+				.conflicts_with("start")
+				.help("abort the self-test that is running")
+			)
+			.arg(Arg::with_name("status")
+				.long("status")
+				.help("show progress of the running self-test, with an estimate of the time left (this is the default)")
+			)
+			.arg(Arg::with_name("wait")
+				.long("wait")
+				.conflicts_with("abort")
+				.help("wait until the self-test completes, polling its progress, and show its result from the self-test log\nCtrl-C aborts the self-test if it was started with --start, and leaves it running otherwise")
+			)
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Self-tests are only available for ATA devices", status::COMMAND_FAILED))?;
+
+		if id.smart != Ternary::Enabled {
+			return Err(status::fail(format!("S.M.A.R.T. is {}, cannot run self-tests", if id.smart == Ternary::Disabled { "disabled" } else { "not supported" }), status::SMART_UNAVAILABLE));
+		}
+
+		let mut caps = capabilities(dev)?;
+		let mut outcome = Outcome::new();
+
+		if args.is_present("abort") {
+			if !caps.self_test_in_progress() {
+				return Err(status::fail("No self-test is running", status::COMMAND_FAILED));
+			}
+			dev.abort_self_test()
+				.map_err(|err| status::fail(format!("Cannot abort self-test: {}", err), status::COMMAND_FAILED))?;
+			print!("Self-test aborted\n");
+			return Ok(outcome);
+		}
+
+		let mut kind = None;
+		if let Some(start) = args.value_of("start") {
+			let start = match start {
+				"short" => SelfTestKind::Short,
+				"extended" => SelfTestKind::Extended,
+				"conveyance" => SelfTestKind::Conveyance,
+				_ => unreachable!(),
+			};
+			let supported = match start {
+				SelfTestKind::Conveyance => caps.conveyance_self_test_supported,
+				_ => caps.self_test_supported,
+			};
+			if !supported {
+				return Err(status::fail(format!("Device does not support {} self-test", kind_name(start)), status::COMMAND_FAILED));
+			}
+			if caps.self_test_in_progress() {
+				return Err(status::fail(format!("Another {}; abort it with --abort first", progress(&caps, running_kind(dev))), status::COMMAND_FAILED));
+			}
+			check_security(id, SecurityOperation::MediaAccess)?;
+
+			dev.start_self_test(start)
+				.map_err(|err| status::fail(format!("Cannot start {} self-test: {}", kind_name(start), err), status::COMMAND_FAILED))?;
+			print!("Started {} self-test, it takes about {} minute(s) to complete\n", kind_name(start), caps.self_test_time(start));
+			kind = Some(start);
+
+			caps = capabilities(dev)?;
+		}
+
+		if !caps.self_test_in_progress() {
+			if kind.is_some() {
+				// completed before we asked, or rejected the test right away
+				print_result(dev, &caps, &mut outcome);
+			} else {
+				print!("No self-test is running; the most recent one: {}\n", self_test::status_description(caps.self_test_status >> 4));
+			}
+			return Ok(outcome);
+		}
+
+		let kind = kind.or_else(|| running_kind(dev));
+
+		if !args.is_present("wait") {
+			print!("{}\n", progress(&caps, kind));
+			return Ok(outcome);
+		}
+
+		// only the test this very command has started is ours to abort
+		let started = args.is_present("start");
+
+		interrupt::install();
+		while caps.self_test_in_progress() {
+			// TODO? space-padded \r-updates like in scan-surface if this ever gets any faster
+			eprint!("{}\n", progress(&caps, kind));
+			if !interrupt::sleep(Duration::from_secs(POLL_INTERVAL)) {
+				outcome.set(status::COMMAND_FAILED);
+				if !started {
+					eprint!("Interrupted, leaving the self-test running\n");
+					print!("{}\n", progress(&capabilities(dev)?, kind));
+					return Ok(outcome);
+				}
+				dev.abort_self_test()
+					.map_err(|err| status::fail(format!("Interrupted, but cannot abort self-test: {}", err), status::COMMAND_FAILED))?;
+				eprint!("Interrupted, self-test aborted\n");
+				caps = capabilities(dev)?;
+				break;
+			}
+			caps = capabilities(dev)?;
+		}
+
+		// aborted by another host, interrupted by reset, or failed: the device does not care which test it was
+		print_result(dev, &caps, &mut outcome);
+
+		Ok(outcome)
+	}
+}
@@ -0,0 +1,87 @@
+use hdd::ata::misc::{self, Misc};
+use hdd::ata::data::id;
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+
+use std::path::Path;
+
+fn arg_on_off(name: &'static str, help: &'static str) -> Arg<'static, 'static> {
+	Arg::with_name(name)
+		.long(name)
+		.takes_value(true)
+		.possible_values(&["on", "off"])
+		.help(help)
+}
+
+fn set(dev: &Misc, what: &str, supported: bool, enable: bool, cmd: fn(&Misc, bool) -> Result<(), misc::Error>, outcome: &mut Outcome) {
+	if !supported {
+		eprint!("{} is not supported\n", what);
+		outcome.set(status::COMMAND_FAILED);
+		return;
+	}
+	match cmd(dev, enable) {
+		Ok(()) => print!("{}: {}\n", what, if enable { "enabled" } else { "disabled" }),
+		Err(err) => {
+			eprint!("Cannot {} {}: {}\n", if enable { "enable" } else { "disable" }, what, err);
+			outcome.set(status::COMMAND_FAILED);
+		},
+	}
+}
+
+pub struct SmartConfig {}
+impl Subcommand for SmartConfig {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("smart-config")
+			.about("Turns S.M.A.R.T. background features on or off (like 'smartctl -o' and 'smartctl -S')")
+			.arg(arg_on_off("auto-offline", "automatic off-line data collection, which updates off-line attributes every few hours"))
+			.arg(arg_on_off("autosave", "attribute autosave, which makes the device save attribute values to the media on its own"))
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		let auto_offline = args.value_of("auto-offline").map(|v| v == "on");
+		let autosave = args.value_of("autosave").map(|v| v == "on");
+		if auto_offline.is_none() && autosave.is_none() {
+			return Err(status::fail("Nothing to do, use --auto-offline and/or --autosave", status::USAGE));
+		}
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("S.M.A.R.T. features are only available for ATA devices", status::COMMAND_FAILED))?;
+
+		if id.smart != id::Ternary::Enabled {
+			return Err(status::fail(format!("S.M.A.R.T. is {}, cannot change its features", id.smart), status::SMART_UNAVAILABLE));
+		}
+
+		let caps = match dev.get_smart_capabilities() {
+			Ok(Some(caps)) => caps,
+			Ok(None) => return Err(status::fail("S.M.A.R.T. READ DATA reply is truncated", status::COMMAND_FAILED)),
+			Err(err) => return Err(status::fail(format!("Cannot read S.M.A.R.T. capabilities: {}", err), status::COMMAND_FAILED)),
+		};
+
+		let mut outcome = Outcome::new();
+		if let Some(enable) = auto_offline {
+			set(dev, "Automatic off-line data collection", caps.auto_offline_supported, enable, |dev, enable| dev.set_auto_offline(enable), &mut outcome);
+		}
+		if let Some(enable) = autosave {
+			set(dev, "Attribute autosave", caps.attribute_autosave_supported, enable, |dev, enable| dev.set_attribute_autosave(enable), &mut outcome);
+		}
+
+		Ok(outcome)
+	}
+}
@@ -0,0 +1,119 @@
+use hdd::ata::misc::{self, Misc};
+use hdd::ata::data::id;
+
+use clap::{
+	App,
+	Arg,
+	ArgMatches,
+	SubCommand,
+};
+
+use ::{DeviceArgument, status};
+use status::{Outcome, Failure};
+use super::Subcommand;
+
+use std::path::Path;
+
+/// Parses things like `0`, `off`, `600`, `90s`, `10m`, `1h30m` into seconds.
+fn parse_duration(s: &str) -> Result<u32, String> {
+	if s == "off" {
+		return Ok(0);
+	}
+	if s.is_empty() {
+		return Err("empty duration".to_string());
+	}
+
+	let mut total = 0u32;
+	let mut rest = s;
+	while !rest.is_empty() {
+		let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+		if len == 0 {
+			return Err(format!("{}: number expected", s));
+		}
+		let n: u32 = rest[..len].parse().map_err(|_| format!("{}: number is too big", s))?;
+		rest = &rest[len..];
+
+		let len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+		let unit = match &rest[..len] {
+			"" | "s" => 1,
+			"m" | "min" => 60,
+			"h" => 3600,
+			unit => return Err(format!("{}: unknown unit '{}'", s, unit)),
+		};
+		rest = &rest[len..];
+
+		total = n.checked_mul(unit)
+			.and_then(|n| total.checked_add(n))
+			.ok_or_else(|| format!("{}: duration is too long", s))?;
+	}
+
+	Ok(total)
+}
+
+fn format_duration(s: u32) -> String {
+	match s {
+		0 => "disabled".to_string(),
+		s if s % 3600 == 0 => format!("{}h", s / 3600),
+		s if s > 3600 && s % 60 == 0 => format!("{}h{}m", s / 3600, s % 3600 / 60),
+		s if s % 60 == 0 => format!("{}m", s / 60),
+		s if s > 60 => format!("{}m{}s", s / 60, s % 60),
+		s => format!("{}s", s),
+	}
+}
+
+fn set_timer(dev: &Misc, id: &id::Id, timer: u8) -> Result<(), Failure> {
+	if !id.power_mgmt_supported {
+		return Err(status::fail("Power management is not supported, cannot set standby timer", status::COMMAND_FAILED));
+	}
+
+	// rejection (ERR bit of the status register) comes back as `misc::Error::Device`
+	dev.set_standby_timer(timer)
+		.map_err(|err| status::fail(format!("Cannot set standby timer: {}", err), status::COMMAND_FAILED))?;
+
+	if let id::Ternary::Enabled = id.apm {
+		eprint!("Note: Advanced Power Management is enabled, and device might enter standby on its own regardless of this timer\n");
+	}
+
+	Ok(())
+}
+
+pub struct Standby {}
+impl Subcommand for Standby {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("standby-timer")
+			.about("Sets the standby (spin-down) timer of the device (like 'hdparm -S')")
+			.arg(Arg::with_name("set")
+				.long("set")
+				.takes_value(true)
+				.required(true)
+				.value_name("DURATION")
+				.help("Period of inactivity after which device enters standby, e.g. '90s', '10m', '1h30m', or 'off'\nrepresentable values are: up to 20m in 5s steps, 30m to 5h30m in 30m steps, 21m, and 21m15s\nthis setting is volatile and is lost on power cycle")
+			)
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		// TODO show usage and whatnot
+		let dev = dev.ok_or_else(|| status::fail("<device> is required", status::USAGE))?;
+
+		// unwrap: clap makes sure this one is present
+		let duration = args.value_of("set").unwrap();
+		let seconds = parse_duration(duration)
+			.map_err(|err| status::fail(format!("Invalid duration: {}", err), status::USAGE))?;
+		let timer = misc::encode_standby_timer(seconds)
+			.ok_or_else(|| status::fail(format!("Standby timer cannot be set to {}, see --help for the list of supported values", format_duration(seconds)), status::USAGE))?;
+
+		let (dev, id) = dev.ata()
+			.ok_or_else(|| status::fail("Standby timer is only available for ATA devices", status::COMMAND_FAILED))?;
+		set_timer(dev, id, timer)?;
+
+		// unwrap: we've just encoded it from seconds
+		print!("Standby timer: {}\n", format_duration(misc::decode_standby_timer(timer).unwrap()));
+
+		Ok(Outcome::new())
+	}
+}
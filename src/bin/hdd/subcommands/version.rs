@@ -0,0 +1,132 @@
+use hdd::drivedb;
+
+use clap::{
+	ArgMatches,
+	App,
+	SubCommand,
+};
+
+use ::{DeviceArgument, Type, drivedb_paths, status, to_json_string};
+use status::{Outcome, Failure};
+use super::{Subcommand, arg_json, arg_drivedb};
+
+use std::path::Path;
+
+// only features that change what the binary can do are listed here; `bin` and `serializable` are always there
+fn features() -> Vec<&'static str> {
+	let mut features = vec![];
+	if cfg!(feature = "embedded-drivedb") {
+		features.push("embedded-drivedb");
+	}
+	features
+}
+
+#[cfg(feature = "embedded-drivedb")]
+fn embedded_drivedb_version() -> Option<&'static str> {
+	Some(drivedb::embedded_version().unwrap_or("unknown version"))
+}
+#[cfg(not(feature = "embedded-drivedb"))]
+fn embedded_drivedb_version() -> Option<&'static str> { None }
+
+#[derive(Debug, Serialize)]
+struct DrivedbFile<'a> {
+	path: &'a str,
+	version: &'a Option<String>,
+}
+
+// `(path, version)` pairs of drivedb files that can be read
+fn drivedb_file<'a>(&(path, ref version): &'a (&'a str, Option<String>)) -> DrivedbFile<'a> {
+	DrivedbFile { path, version }
+}
+
+#[derive(Debug, Serialize)]
+struct Drivedb<'a> {
+	main: Option<DrivedbFile<'a>>,
+	additional: Vec<DrivedbFile<'a>>,
+	embedded: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+	version: &'static str,
+	os: &'static str,
+	types: Vec<String>,
+	features: Vec<&'static str>,
+	drivedb: Drivedb<'a>,
+}
+
+pub struct Version {}
+impl Subcommand for Version {
+	fn subcommand(&self) -> App<'static, 'static> {
+		SubCommand::with_name("version")
+			.about("Prints version, supported platform and transports, and drivedb that would be used (handy for bug reports)")
+			.arg(arg_json())
+			.arg(arg_drivedb())
+	}
+
+	fn run(
+		&self,
+		_: &Option<&Path>,
+		dev: &Option<&DeviceArgument>,
+		args: &ArgMatches,
+	) -> Result<Outcome, Failure> {
+		if dev.is_some() {
+			// TODO show usage and whatnot
+			return Err(status::fail("<device> is redundant", status::USAGE));
+		};
+
+		let version = crate_version!();
+		let os = ::std::env::consts::OS;
+		let types: Vec<_> = Type::variants().iter()
+			.map(|t| t.to_lowercase())
+			.collect();
+		let features = features();
+
+		let (paths_main, paths_add, _) = drivedb_paths(args.values_of("drivedb"));
+		// this mimics what `open_drivedb()` does, except files are not parsed
+		let main = paths_main.iter()
+			.filter_map(|&path| drivedb::file_version(path).ok().map(|v| (path, v)))
+			.next();
+		let additional: Vec<_> = paths_add.iter()
+			.filter_map(|&path| drivedb::file_version(path).ok().map(|v| (path, v)))
+			.collect();
+		let embedded = embedded_drivedb_version();
+
+		if args.is_present("json") {
+			let report = Report {
+				version,
+				os,
+				types,
+				features,
+				drivedb: Drivedb {
+					main: main.as_ref().map(drivedb_file),
+					additional: additional.iter().map(drivedb_file).collect(),
+					embedded,
+				},
+			};
+
+			print!("{}\n", to_json_string(&report));
+		} else {
+			print!("hdd {}\n", version);
+			print!("Platform: {}\n", os);
+			print!("Device types: {}\n", types.join(", "));
+			print!("Features: {}\n", if features.is_empty() { "(none)".to_string() } else { features.join(", ") });
+
+			print!("\n");
+
+			match &main {
+				Some((path, version)) => print!("Drivedb: {} ({})\n", path, version.as_deref().unwrap_or("unknown version")),
+				None => print!("Drivedb: none of {} can be read\n", paths_main.join(", ")),
+			}
+			for (path, version) in additional {
+				print!("Additional drivedb: {} ({})\n", path, version.as_deref().unwrap_or("unknown version"));
+			}
+			if let Some(version) = embedded {
+				print!("Built-in drivedb: {}{}\n", version,
+					if main.is_some() { " (not used, drivedb file takes precedence)" } else { "" });
+			}
+		}
+
+		Ok(Outcome::new())
+	}
+}
@@ -0,0 +1,49 @@
+/*!
+How strictly failures of mandatory commands and suspect data are treated (`-T`/`--tolerance`).
+
+In strict mode (the default), it is fatal if a mandatory command (IDENTIFY DEVICE, S.M.A.R.T. READ DATA) fails, or if its reply does not match its own checksum. In permissive mode, these are only reported as warnings, and whatever data is available is shown anyway: this is meant for coaxing data out of marginal or failing drives, and such data should be taken with a grain of salt.
+
+Either way, the failure is accounted for in the exit status.
+*/
+
+use status::{self, Outcome, Failure};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PERMISSIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_permissive(permissive: bool) {
+	PERMISSIVE.store(permissive, Ordering::SeqCst);
+}
+
+pub fn permissive() -> bool {
+	PERMISSIVE.load(Ordering::SeqCst)
+}
+
+/**
+Checks whether we can carry on after a failed mandatory command or suspect data that `msg` describes.
+
+In strict mode, returns `msg` along with a hint on `-T` as an error. In permissive mode, only warns, and sets `bits` in the exit status.
+*/
+pub fn check(msg: &str, bits: usize) -> Result<(), String> {
+	let mut outcome = Outcome::new();
+	let result = check_in(&mut outcome, msg, bits);
+	status::set(outcome.bits());
+	result
+}
+
+/// Same as [`check()`](fn.check.html), but for subcommands: in permissive mode, `bits` are accounted for in `outcome` instead.
+pub fn check_in(outcome: &mut Outcome, msg: &str, bits: usize) -> Result<(), String> {
+	if permissive() {
+		eprint!("WARNING: {} (continuing anyway, as requested with -T permissive)\n", msg);
+		outcome.set(bits);
+		Ok(())
+	} else {
+		Err(format!("{} (use -T permissive to continue anyway)", msg))
+	}
+}
+
+/// Same as [`check_in()`](fn.check_in.html), but gives up with `bits` (and whatever `outcome` observed so far) in strict mode.
+pub fn fail(outcome: &mut Outcome, msg: &str, bits: usize) -> Result<(), Failure> {
+	check_in(outcome, msg, bits).map_err(|msg| outcome.fail(msg, bits))
+}
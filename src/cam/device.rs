@@ -3,7 +3,7 @@ use cam::ccb::CCB;
 use cam::error;
 use std::io;
 
-use std::ffi::{OsStr, CString};
+use std::ffi::{OsStr, CStr, CString};
 use std::os::unix::ffi::OsStrExt;
 
 use libc;
@@ -20,7 +20,7 @@ impl CAMDevice {
 		let path = CString::new(path.as_bytes()).unwrap();
 		let dev = unsafe { bindings::cam_open_device(path.as_ptr(), libc::O_RDWR) };
 		if dev.is_null() {
-			Err(error::current())
+			Err(error::current(path.to_string_lossy().as_ref()))
 		} else {
 			Ok(CAMDevice(dev))
 		}
@@ -28,9 +28,33 @@ impl CAMDevice {
 
 	pub fn send_ccb(&self, ccb: &CCB) -> Result<(), io::Error> {
 		if unsafe { bindings::cam_send_ccb(self.0, ccb.0) } < 0 {
-			Err(io::Error::last_os_error())
+			Err(error::on(&self.name(), io::Error::last_os_error()))
 		} else { Ok(()) }
 	}
+
+	/**
+	Renders the device name for error messages, e.g. `/dev/ada0`.
+
+	This is the device as it was opened; if CAM resolved it to some other peripheral (e.g. `/dev/pass0` to `ada0`), the latter is appended in parentheses.
+	*/
+	pub fn name(&self) -> String {
+		// `unsafe {}` is safe: libcam fills these with NUL-terminated strings upon opening the device, and the struct outlives the borrow
+		let (given, given_unit, periph, periph_unit) = unsafe {
+			let dev = &*self.0;
+			(
+				CStr::from_ptr(dev.given_dev_name.as_ptr()).to_string_lossy().into_owned(),
+				dev.given_unit_number,
+				CStr::from_ptr(dev.device_name.as_ptr()).to_string_lossy().into_owned(),
+				dev.dev_unit_num,
+			)
+		};
+
+		if given == periph && given_unit == periph_unit {
+			format!("/dev/{}{}", given, given_unit)
+		} else {
+			format!("/dev/{}{} ({}{})", given, given_unit, periph, periph_unit)
+		}
+	}
 }
 
 impl Drop for CAMDevice {
@@ -23,16 +23,23 @@ Use this function if the following ones returned -1:
 
 - cam_get_device
 
+`path` is the device the function was called for; it is mentioned in the error message.
+
 **Note:** cam_send_ccb is a simple `return ioctl(…)` function and thus its errors are *not* rendered in `cam_errbuf`.
 */
-pub fn current() -> io::Error { io::Error::new(io::ErrorKind::Other,
+pub fn current(path: &str) -> io::Error { on(path, io::Error::new(io::ErrorKind::Other,
 	unsafe {
 		CStr::from_ptr(
 			// strdup() to avoid implicit deallocation of external static variable
 			libc::strdup(bindings::cam_errbuf.as_ptr())
 		).to_string_lossy().into_owned()
 	}
-) }
+)) }
+
+/// Prefixes the error message with the name of the device (see `CAMDevice::name()`), keeping the kind of the error intact, so that it is clear which device failed when there are several of them.
+pub fn on(name: &str, err: io::Error) -> io::Error {
+	io::Error::new(err.kind(), format!("CAM error on {}: {}", name, err))
+}
 
 /// Returns errors indicated with `ccb.ccb.h.status & CAM_STATUS_MASK`.
 pub fn from_status(dev: &CAMDevice, ccb: &CCB) -> io::Error {
@@ -47,8 +54,14 @@ pub fn from_status(dev: &CAMDevice, ccb: &CCB) -> io::Error {
 			bindings::cam_error_proto_flags_CAM_EPF_ALL,
 		);
 
-		io::Error::new(io::ErrorKind::Other,
+		let kind = if ccb.get_status() == bindings::cam_status_CAM_CMD_TIMEOUT {
+			io::ErrorKind::TimedOut
+		} else {
+			io::ErrorKind::Other
+		};
+
+		on(&dev.name(), io::Error::new(kind,
 			CStr::from_ptr(err).to_string_lossy().into_owned()
-		)
+		))
 	}
 }
@@ -2,11 +2,16 @@ use cam::{CAMDevice, CCB, error};
 use cam::bindings::*;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default command timeout, in seconds: most commands complete in a fraction of a second, and the ones that don't (e.g. READ VERIFY over a large range) should have a timeout set explicitly
+pub const DEFAULT_TIMEOUT: u64 = 10;
 
 /// See [parent module docs](../index.html)
 #[derive(Debug)]
 pub struct Device {
 	pub(crate) dev: CAMDevice,
+	pub(crate) timeout: Duration,
 }
 
 #[derive(Debug)]
@@ -16,9 +21,29 @@ impl Device {
 	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
 		Ok(Device {
 			dev: CAMDevice::open(path.as_ref().as_os_str())?,
+			timeout: Duration::from_secs(DEFAULT_TIMEOUT),
 		})
 	}
 
+	/**
+	Sets the deadline for every command issued to the device from now on.
+
+	The timeout is set in every CCB sent to the device, so CAM aborts commands that take longer than that; these fail with an error of kind `io::ErrorKind::TimedOut`.
+	*/
+	pub fn set_timeout(&mut self, timeout: Duration) {
+		self.timeout = timeout;
+	}
+
+	pub fn timeout(&self) -> Duration {
+		self.timeout
+	}
+
+	// CCB timeouts are in milliseconds
+	pub(crate) fn timeout_ms(&self) -> u32 {
+		let ms = self.timeout.as_secs() * 1000 + u64::from(self.timeout.subsec_nanos() / 1_000_000);
+		::std::cmp::min(ms, u64::from(u32::max_value())) as u32
+	}
+
 	pub fn get_type(&self) -> Result<Type, io::Error> {
 		unsafe {
 			let ccb: CCB = CCB::new(&self.dev);
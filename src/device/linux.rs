@@ -1,16 +1,57 @@
 use std::fs::OpenOptions;
 use std::fs::{self, File};
 use std::io;
-use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, OpenOptionsExt};
 
 use std::collections::HashSet;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default command timeout, in seconds: most commands complete in a fraction of a second, and the ones that don't (e.g. READ VERIFY over a large range) should have a timeout set explicitly
+pub const DEFAULT_TIMEOUT: u64 = 10;
 
 /// See [parent module docs](../index.html)
 #[derive(Debug)]
 pub struct Device {
     pub(crate) file: File,
+    pub(crate) timeout: Duration,
+    pub(crate) interface: Interface,
+}
+
+/// Flavour of SG_IO the device node accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interface {
+    /// SG_IO v3 (`struct sg_io_hdr`), accepted by block devices (`/dev/sdX`) and SCSI generic devices (`/dev/sgN`)
+    SG,
+    /// SG_IO v4 (`struct sg_io_v4`), the only one bsg devices (`/dev/bsg/…`) accept; these are sometimes all there is for devices in SAS topologies
+    BSG,
+}
+
+/*
+bsg devices have a dynamically allocated major number, so the only reliable way to recognize one is to ask sysfs what class the device node belongs to,
+e.g. `readlink /sys/dev/char/248:0` → `../../devices/…/0:0:0:0/bsg/0:0:0:0`
+*/
+fn detect_interface(file: &File) -> Interface {
+    let meta = match file.metadata() {
+        Ok(meta) => meta,
+        Err(_) => return Interface::SG,
+    };
+    // block devices are never bsg ones
+    if !meta.file_type().is_char_device() {
+        return Interface::SG;
+    }
+
+    let dev = meta.rdev();
+    let (major, minor) = (libc::major(dev), libc::minor(dev));
+
+    match fs::read_link(format!("/sys/dev/char/{}:{}", major, minor)) {
+        Ok(ref path) if path.components().any(|c| c.as_os_str() == "bsg") => {
+            info!("{}:{} is a bsg device, using SG_IO v4", major, minor);
+            Interface::BSG
+        }
+        _ => Interface::SG,
+    }
 }
 
 #[derive(Debug)]
@@ -19,9 +60,9 @@ pub enum Type {
 }
 
 impl Device {
+    /// Opens the device, and figures out which [`Interface`](enum.Interface.html) it speaks.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
-        Ok(Device {
-            file: OpenOptions::new()
+        let file = OpenOptions::new()
                 .read(true)
                 // > Under Linux, the O_NONBLOCK flag indicates that one wants to open but does not necessarily have the intention to read or write.
                 // > This is typically used to open devices in order to get a file descriptor for use with ioctl(2).
@@ -29,17 +70,100 @@ impl Device {
                 // this fixes access to optical drives and other ejectable media
                 // (https://github.com/vthriller/hdd-rs/issues/1)
                 .custom_flags(libc::O_NONBLOCK)
-                .open(path)?,
+                .open(path)?;
+        let interface = detect_interface(&file);
+        Ok(Device {
+            file,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+            interface,
         })
     }
 
+    pub fn interface(&self) -> Interface {
+        self.interface
+    }
+
+    /**
+    Sets the deadline for every command issued to the device from now on.
+
+    The timeout is passed along with each command to SG_IO, so the kernel aborts commands that take longer than that; these fail with an error of kind `io::ErrorKind::TimedOut`. This is also why there is no need to run ioctls on a separate thread.
+    */
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    // SG_IO timeouts are in milliseconds
+    pub(crate) fn timeout_ms(&self) -> u32 {
+        let ms = self.timeout.as_secs() * 1000 + u64::from(self.timeout.subsec_millis());
+        // u32::MAX means no timeout at all for SG_IO
+        ::std::cmp::min(ms, u64::from(u32::MAX - 1)) as u32
+    }
+
     pub fn get_type(&self) -> Result<Type, io::Error> {
         Ok(Type::SCSI)
     }
 }
 
 /// Lists paths to devices currently presented in the system.
+///
+/// Paths are sorted the way the kernel numbers devices (`sdz` goes before `sdaa`, `sg2` goes before `sg10`), and each device is only listed once: SCSI generic devices (`/dev/sgN`) that belong to a listed block device are skipped.
 pub fn list_devices() -> Result<Vec<PathBuf>, io::Error> {
+    list_devices_in(Path::new("/sys"))
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum NameChunk {
+    Number(u64),
+    Text(String),
+}
+
+// disks of these kinds are numbered with letters rather than digits: sda, …, sdz, sdaa, …
+const LETTER_INDEXED: [&str; 4] = ["sd", "hd", "vd", "xvd"];
+
+// splits device name into runs of digits and everything else, so that numbers are compared as such
+fn sort_key(name: &str) -> Vec<NameChunk> {
+    use self::NameChunk::*;
+
+    let mut key = vec![];
+    let mut rest = name;
+
+    if let Some(prefix) = LETTER_INDEXED.iter().find(|prefix| name.starts_with(*prefix)) {
+        let letters = name[prefix.len()..].bytes().take_while(|b| b.is_ascii_lowercase()).count();
+        // bijective base-26: a is 1, z is 26, aa is 27
+        let index = name[prefix.len() .. prefix.len() + letters].bytes()
+            .try_fold(0u64, |n, b| n.checked_mul(26)?.checked_add(u64::from(b - b'a' + 1)));
+        if let (true, Some(index)) = (letters > 0, index) {
+            key.push(Text(prefix.to_string()));
+            key.push(Number(index));
+            rest = &name[prefix.len() + letters..];
+        }
+    }
+
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(|b| b.is_ascii_digit()).count();
+        let len = if digits > 0 {
+            digits
+        } else {
+            rest.bytes().take_while(|b| !b.is_ascii_digit()).count()
+        };
+        let (chunk, tail) = rest.split_at(len);
+        key.push(match chunk.parse() {
+            Ok(n) if digits > 0 => Number(n),
+            // non-digits, or a number too long to fit
+            _ => Text(chunk.to_string()),
+        });
+        rest = tail;
+    }
+
+    key
+}
+
+// `sys` is where sysfs is mounted
+fn list_devices_in(sys: &Path) -> Result<Vec<PathBuf>, io::Error> {
     /*
     Various software enumerates block devices in a variety of ways:
     - smartd: probes for /dev/hd[a-t], /dev/sd[a-z], /dev/sd[a-c][a-z], /dev/nvme[0-99]
@@ -60,13 +184,21 @@ pub fn list_devices() -> Result<Vec<PathBuf>, io::Error> {
 
     let mut devices = vec![];
     let mut skip_generics = HashSet::new();
+    // `device` directories of block devices, for generics that lack a `device/generic` symlink on the block side
+    let mut skip_generic_devices = HashSet::new();
+
+    // symlinks under class/ are resolved into devices/, so this should be canonical as well
+    let sys = sys.canonicalize()?;
+    let virtual_devices = sys.join("devices/virtual");
+    let floppy_devices = sys.join("devices/platform/floppy");
 
     // XXX do not return Err() if /sys/class/block does not exist but /sys/class/scsi_generic does, or vice versa
 
     // N.B. log entries are indented relative to each other
 
-    info!("inspecting /sys/class/block");
-    for d in fs::read_dir("/sys/class/block")? {
+    let class_block = sys.join("class/block");
+    info!("inspecting {:?}", class_block);
+    for d in fs::read_dir(class_block)? {
         let d = if let Ok(d) = d { d } else { continue };
 
         // XXX this assumes that dir name equals to whatever `DEVNAME` is set to in the uevent file
@@ -84,7 +216,7 @@ pub fn list_devices() -> Result<Vec<PathBuf>, io::Error> {
         debug!("  {:?} → {:?}", name, path);
 
         // skip devices like /dev/{loop,ram,zram,md,fd}*
-        if path.starts_with("/sys/devices/virtual/") {
+        if path.starts_with(&virtual_devices) {
             debug!("    virtual device, skipping");
             continue;
         }
@@ -94,7 +226,7 @@ pub fn list_devices() -> Result<Vec<PathBuf>, io::Error> {
             .as_path()
             .to_str()
             .unwrap()
-            .starts_with("/sys/devices/platform/floppy")
+            .starts_with(floppy_devices.to_str().unwrap())
         {
             debug!("    floppy device, skipping");
             continue;
@@ -149,6 +281,10 @@ pub fn list_devices() -> Result<Vec<PathBuf>, io::Error> {
 
         devices.push(name);
 
+        if let Ok(device) = path.join("device").canonicalize() {
+            skip_generic_devices.insert(device);
+        }
+
         // e.g. `readlink /sys/class/block/sda/device/generic` → `scsi_generic/sg0`
         if let Ok(generic_path) = path.join("device/generic").read_link() {
             if let Some(generic_name) = generic_path.file_name() {
@@ -166,22 +302,93 @@ pub fn list_devices() -> Result<Vec<PathBuf>, io::Error> {
     these devices can be used to query SMART or SCSI logs from disks that are not represented with corresponding block devices
     */
 
-    info!("inspecting /sys/class/scsi_generic");
-    for d in fs::read_dir("/sys/class/scsi_generic")? {
+    let class_generic = sys.join("class/scsi_generic");
+    info!("inspecting {:?}", class_generic);
+    for d in fs::read_dir(class_generic)? {
         let d = if let Ok(d) = d { d } else { continue };
 
         let name = d.file_name();
         debug!("  {:?}", name);
 
-        if !skip_generics.contains(&name) {
+        // e.g. `readlink -f /sys/class/scsi_generic/sg0/device` → `/sys/devices/…/0:0:0:0`, same as `readlink -f /sys/class/block/sda/device`
+        let same_device = d.path().join("device").canonicalize()
+            .map(|device| skip_generic_devices.contains(&device))
+            .unwrap_or(false);
+
+        if !skip_generics.contains(&name) && !same_device {
             devices.push(name);
         } else {
             debug!("    already covered by corresponding block device, skipping");
         }
     }
 
+    let mut devices: Vec<String> = devices
+        .into_iter()
+        .map(|dev| dev.into_string().unwrap())
+        .collect();
+
+    // read_dir() order is up to the filesystem and might change from one run to another
+    devices.sort_by(|a, b| sort_key(a).cmp(&sort_key(b)).then_with(|| a.cmp(b)));
+    devices.dedup();
+
     Ok(devices
         .into_iter()
-        .map(|dev| PathBuf::from(format!("/dev/{}", dev.into_string().unwrap())))
+        .map(|dev| PathBuf::from(format!("/dev/{}", dev)))
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::os::unix::fs::symlink;
+    use std::process;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["sg10", "sdaa", "nvme10n1", "sdb", "sg2", "nvme2n1", "sda", "sdz"];
+        names.sort_by_key(|name| sort_key(name));
+        assert_eq!(names, ["nvme2n1", "nvme10n1", "sda", "sdb", "sdz", "sdaa", "sg2", "sg10"]);
+    }
+
+    // sysfs tree with just enough in it for `list_devices_in()`
+    fn sysfs(root: &Path) {
+        let disk = |dir: &str| {
+            let dir = root.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("uevent"), "MAJOR=8\nDEVNAME=x\nDEVTYPE=disk\n").unwrap();
+        };
+        fs::create_dir_all(root.join("class/block")).unwrap();
+        fs::create_dir_all(root.join("class/scsi_generic")).unwrap();
+
+        for name in &["sdb", "sdaa", "sda"] {
+            disk(&format!("devices/pci0/host0/{}/block/{}", name, name));
+            symlink(root.join(format!("devices/pci0/host0/{}/block/{}", name, name)), root.join("class/block").join(name)).unwrap();
+        }
+        // sda and sg0 are the same device
+        fs::create_dir_all(root.join("devices/pci0/host0/target0")).unwrap();
+        symlink(root.join("devices/pci0/host0/target0"), root.join("devices/pci0/host0/sda/block/sda/device")).unwrap();
+        fs::create_dir_all(root.join("devices/pci0/host0/target0/scsi_generic/sg0")).unwrap();
+        symlink(root.join("devices/pci0/host0/target0"), root.join("devices/pci0/host0/target0/scsi_generic/sg0/device")).unwrap();
+        symlink(root.join("devices/pci0/host0/target0/scsi_generic/sg0"), root.join("class/scsi_generic/sg0")).unwrap();
+
+        for name in &["sg10", "sg2"] {
+            fs::create_dir_all(root.join("devices/raid0").join(name)).unwrap();
+            symlink(root.join("devices/raid0").join(name), root.join("class/scsi_generic").join(name)).unwrap();
+        }
+
+        disk("devices/virtual/block/loop0");
+        symlink(root.join("devices/virtual/block/loop0"), root.join("class/block/loop0")).unwrap();
+    }
+
+    #[test]
+    fn list_devices() {
+        let root = env::temp_dir().join(format!("hdd-test-{}-sysfs", process::id()));
+        sysfs(&root);
+        let devices = list_devices_in(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        let devices: Vec<_> = devices.unwrap().into_iter().map(|dev| dev.to_str().unwrap().to_string()).collect();
+        assert_eq!(devices, ["/dev/sda", "/dev/sdb", "/dev/sdaa", "/dev/sg2", "/dev/sg10"]);
+    }
+}
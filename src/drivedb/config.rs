@@ -0,0 +1,332 @@
+/*!
+Per-model vendor attribute overrides from a user-provided config file.
+
+This is meant for users with a bunch of identical drives, who'd rather not repeat long `-v` options, nor maintain their own drivedb file. The format is a simple one of its own, even though it borrows its syntax from TOML:
+
+```text
+# applied to every drive with matching model (and, optionally, firmware)
+[[drive]]
+model = 'ST4000DM000-.*'
+attributes = ["9,minutes", "194,tempminmax"]
+
+# same, but matched against drivedb family; drivedb-style presets are also accepted
+[[drive]]
+family = 'Seagate Barracuda 7200\.14 .*'
+presets = "-v 1,raw24/raw32 -v 7,raw24/raw32"
+```
+
+Patterns are regular expressions that should match the whole string, just like in drivedb. Entries that specify more than one pattern only match if all of them do. The file is not TOML, and is not parsed as such: it only consists of `[[drive]]` tables with `key = value` lines, where values are single-line strings (both 'literal' and "basic") or arrays of strings, and `#` comments. Anything else TOML has, e.g. inline tables, dotted keys, or multi-line strings, is rejected.
+
+Precedence, from lowest to highest: drivedb presets, matching config entries (in order of appearance), attributes given by user on the command line.
+*/
+
+use super::{presets, vendor_attribute, Attribute};
+
+use ata::data::id;
+
+use regex::Regex;
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+
+quick_error! {
+	#[derive(Debug)]
+	pub enum Error {
+		IO(err: io::Error) {
+			from()
+			display("IO error: {}", err)
+			description(err.description())
+			cause(err)
+		}
+		Parse(line: usize, msg: String) {
+			display("line {}: {}", line, msg)
+			description("malformed config")
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Drive {
+	model: Option<Regex>,
+	firmware: Option<Regex>,
+	family: Option<Regex>,
+	attributes: Vec<Attribute>,
+}
+
+impl Drive {
+	fn matches(&self, id: &id::Id, family: Option<&str>) -> bool {
+		// unspecified patterns match anything, but patterns for unknown family match nothing
+		let matches = |re: &Option<Regex>, s: Option<&str>| match (re, s) {
+			(None, _) => true,
+			(Some(re), Some(s)) => re.is_match(s),
+			(Some(_), None) => false,
+		};
+
+		matches(&self.model, Some(&id.model))
+			&& matches(&self.firmware, Some(&id.firmware))
+			&& matches(&self.family, family)
+	}
+}
+
+/// Vendor attribute overrides, see [module docs](index.html)
+#[derive(Debug)]
+pub struct Config {
+	drives: Vec<Drive>,
+}
+
+enum Value {
+	Str(String),
+	Array(Vec<String>),
+}
+
+// skips whitespace and comments, newlines included if `newlines` is set
+fn skip(s: &str, newlines: bool) -> &str {
+	let mut s = s;
+	loop {
+		let start = s.find(|c| !(c == ' ' || c == '\t' || c == '\r' || (newlines && c == '\n'))).unwrap_or(s.len());
+		let trimmed = &s[start..];
+		if trimmed.starts_with('#') {
+			s = &trimmed[trimmed.find('\n').unwrap_or(trimmed.len())..];
+		} else {
+			return trimmed;
+		}
+	}
+}
+
+fn string(s: &str) -> Result<(String, &str), String> {
+	if let Some(s) = s.strip_prefix('\'') {
+		// literal string: no escapes whatsoever, which is handy for regexes
+		let end = s.find(['\'', '\n']).ok_or("unterminated string")?;
+		if &s[end .. end + 1] != "'" {
+			return Err("unterminated string".to_string());
+		}
+		return Ok((s[..end].to_string(), &s[end + 1 ..]));
+	}
+
+	if !s.starts_with('"') {
+		return Err("expected a string".to_string());
+	}
+
+	let mut out = String::new();
+	let mut chars = s.char_indices().skip(1);
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'"' => return Ok((out, &s[i + 1 ..])),
+			'\n' => break,
+			'\\' => match chars.next() {
+				Some((_, '\\')) => out.push('\\'),
+				Some((_, '"')) => out.push('"'),
+				Some((_, 't')) => out.push('\t'),
+				Some((_, 'n')) => out.push('\n'),
+				Some((_, c)) => return Err(format!("unsupported escape sequence: \\{}", c)),
+				None => break,
+			},
+			c => out.push(c),
+		}
+	}
+	Err("unterminated string".to_string())
+}
+
+fn value(s: &str) -> Result<(Value, &str), String> {
+	if !s.starts_with('[') {
+		let (v, s) = string(s)?;
+		return Ok((Value::Str(v), s));
+	}
+
+	// arrays can span multiple lines, and can have a trailing comma
+	let mut items = vec![];
+	let mut s = skip(&s[1..], true);
+	while !s.starts_with(']') {
+		let (item, rest) = string(s)?;
+		items.push(item);
+		s = skip(rest, true);
+		if s.starts_with(',') {
+			s = skip(&s[1..], true);
+		} else if !s.starts_with(']') {
+			return Err("expected ',' or ']'".to_string());
+		}
+	}
+	Ok((Value::Array(items), &s[1..]))
+}
+
+fn pattern(p: &str) -> Result<Regex, String> {
+	Regex::new(&format!("^{}$", p)).map_err(|e| format!("invalid pattern {:?}: {}", p, e))
+}
+
+fn compile(drive: Vec<(String, Value)>) -> Result<Drive, String> {
+	let mut d = Drive {
+		model: None,
+		firmware: None,
+		family: None,
+		attributes: vec![],
+	};
+
+	for (key, value) in drive {
+		match (key.as_str(), value) {
+			("model", Value::Str(p)) => d.model = Some(pattern(&p)?),
+			("firmware", Value::Str(p)) => d.firmware = Some(pattern(&p)?),
+			("family", Value::Str(p)) => d.family = Some(pattern(&p)?),
+			("attributes", Value::Array(attrs)) => for attr in attrs {
+				d.attributes.push(vendor_attribute::parse(&attr).map_err(|_| format!("invalid attribute: {:?}", attr))?);
+			},
+			("presets", Value::Str(p)) => {
+				d.attributes.extend(presets::parse(&p).ok_or_else(|| format!("invalid presets: {:?}", p))?);
+			},
+			("model", _) | ("firmware", _) | ("family", _) | ("presets", _) => return Err(format!("{} should be a string", key)),
+			("attributes", _) => return Err("attributes should be an array of strings".to_string()),
+			(key, _) => return Err(format!("unknown key: {}", key)),
+		}
+	}
+
+	if d.model.is_none() && d.family.is_none() {
+		return Err("entry should have either model or family".to_string());
+	}
+
+	Ok(d)
+}
+
+impl Config {
+	pub fn load(file: &str) -> Result<Self, Error> {
+		let mut s = String::new();
+		File::open(file)?.read_to_string(&mut s)?;
+
+		Config::parse(&s)
+	}
+
+	pub fn parse(s: &str) -> Result<Self, Error> {
+		let line = |rest: &str| s[.. s.len() - rest.len()].matches('\n').count() + 1;
+		let err = |rest: &str, msg: String| Error::Parse(line(rest), msg);
+
+		// every [[drive]] table with its keys, along with the position of the table for error messages
+		let mut tables: Vec<(&str, Vec<(String, Value)>)> = vec![];
+
+		let mut rest = skip(s, true);
+		while !rest.is_empty() {
+			if rest.starts_with("[[") {
+				let end = rest.find("]]").ok_or_else(|| err(rest, "unterminated table header".to_string()))?;
+				let name = rest[2..end].trim();
+				if name != "drive" {
+					return Err(err(rest, format!("unknown table: {}", name)));
+				}
+				tables.push((rest, vec![]));
+				rest = &rest[end + 2 ..];
+			} else if rest.starts_with('[') {
+				return Err(err(rest, "only [[drive]] tables are supported".to_string()));
+			} else {
+				let key_len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-')).unwrap_or(rest.len());
+				if key_len == 0 {
+					return Err(err(rest, "expected a key".to_string()));
+				}
+				let key = &rest[..key_len];
+
+				let after_key = skip(&rest[key_len..], false);
+				if !after_key.starts_with('=') {
+					return Err(err(rest, "expected '='".to_string()));
+				}
+				let (v, after_value) = value(skip(&after_key[1..], false)).map_err(|e| err(rest, e))?;
+
+				match tables.last_mut() {
+					Some(&mut (_, ref mut keys)) => keys.push((key.to_string(), v)),
+					None => return Err(err(rest, "keys should be inside of a [[drive]] table".to_string())),
+				}
+				rest = after_value;
+			}
+
+			let after = skip(rest, false);
+			if !after.is_empty() && !after.starts_with('\n') {
+				return Err(err(after, "expected a newline".to_string()));
+			}
+			rest = skip(after, true);
+		}
+
+		let mut drives = vec![];
+		for (pos, keys) in tables {
+			drives.push(compile(keys).map_err(|e| err(pos, e))?);
+		}
+
+		Ok(Config { drives })
+	}
+
+	/// Returns attribute overrides from every entry that matches the drive, in order of appearance; `family` is the one found in drivedb for the drive, if any.
+	///
+	/// Pass these to [`DriveDB::render_meta()`](../struct.DriveDB.html#method.render_meta), followed by the attributes user gave on the command line, if any.
+	pub fn attributes(&self, id: &id::Id, family: Option<&str>) -> Vec<Attribute> {
+		self.drives.iter()
+			.filter(|d| d.matches(id, family))
+			.flat_map(|d| d.attributes.iter().cloned())
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const CONFIG: &str = r#"
+# comment
+[[drive]]
+model = 'ST4000DM000-.*'
+attributes = [
+	"9,minutes",
+	"194,tempminmax", # trailing comma and comments are fine
+]
+
+[[drive]]
+family = 'Seagate Barracuda 7200\.14 .*'
+firmware = "CC4[0-9]"
+presets = "-v 1,raw24/raw32"
+"#;
+
+	fn id(model: &str, firmware: &str) -> id::Id {
+		let mut id = id::parse_id(&vec![0; 512]);
+		id.model = model.to_string();
+		id.firmware = firmware.to_string();
+		id
+	}
+
+	fn formats(attrs: Vec<Attribute>) -> Vec<(Option<u8>, String)> {
+		attrs.into_iter().map(|a| (a.id, a.format)).collect()
+	}
+
+	#[test]
+	fn overrides_apply_to_matching_drives() {
+		let config = Config::parse(CONFIG).unwrap();
+
+		assert_eq!(formats(config.attributes(&id("ST4000DM000-1F2168", "CC54"), None)), vec![
+			// `-v 9,minutes` is how smartctl spells it
+			(Some(9), "min2hour".to_string()),
+			(Some(194), "tempminmax".to_string()),
+		]);
+		// model does not match the whole pattern
+		assert!(config.attributes(&id("XST4000DM000-1F2168", "CC54"), None).is_empty());
+
+		// family is only known from drivedb, and all the patterns of an entry should match
+		let family = Some("Seagate Barracuda 7200.14 (AF)");
+		assert_eq!(formats(config.attributes(&id("ST1000DM003-1CH162", "CC47"), family)), vec![
+			(Some(1), "raw24/raw32".to_string()),
+		]);
+		assert!(config.attributes(&id("ST1000DM003-1CH162", "CC47"), None).is_empty());
+		assert!(config.attributes(&id("ST1000DM003-1CH162", "CC52"), family).is_empty());
+	}
+
+	fn error_line(config: &str) -> usize {
+		match Config::parse(config) {
+			Err(Error::Parse(line, _)) => line,
+			other => panic!("{:?}", other),
+		}
+	}
+
+	#[test]
+	fn rejects_what_it_does_not_understand() {
+		assert_eq!(error_line("[[drive]]\nmodel = 'X'\nattributes = ['1,raw48'\n"), 3);
+		// actual TOML, but not supported
+		assert_eq!(error_line("[[drive]]\nmodel = { pattern = 'X' }\n"), 2);
+		assert_eq!(error_line("[[drive]]\nmodel.pattern = 'X'\n"), 2);
+		assert_eq!(error_line("[[drive]]\nmodel = '''X'''\n"), 2);
+		assert_eq!(error_line("[drive]\nmodel = 'X'\n"), 1);
+		// valid syntax, but meaningless entries
+		assert_eq!(error_line("[[drive]]\nfirmware = 'X'\n\n[[drive]]\nmodel = 'X'\nattributes = ['1,bogus']\n"), 1);
+		assert_eq!(error_line("model = 'X'\n"), 1);
+	}
+}
@@ -1,4 +1,5 @@
 use super::{presets, Attribute};
+use super::presets::FirmwareBug;
 use super::parser::Entry;
 use regex;
 use regex::bytes::{RegexSet, RegexSetBuilder};
@@ -46,6 +47,8 @@ impl DriveDB {
 		).unicode(false).build()?;
 		let firmware_regexes = RegexSetBuilder::new(entries.iter()
 			.map(|e|
+				// empty firmware pattern means "any firmware", not "empty firmware" (which "^$" would be);
+				// unanchored empty regex matches anything
 				if e.firmware.is_empty() {
 					"".to_string()
 				} else {
@@ -62,6 +65,16 @@ impl DriveDB {
 		})
 	}
 
+	/// Entries the database was built from, in the order they are matched in (entries from additional files first); the default entry and USB entries are not included.
+	pub fn entries(&self) -> &[Entry] {
+		&self.entries
+	}
+
+	/// Entry of the `DEFAULT` family, which every drive gets its presets from before the matching entry is applied.
+	pub fn default_entry(&self) -> Option<&Entry> {
+		self.default.as_ref()
+	}
+
 	fn find(&self, model: &str, firmware: &str) -> Option<&Entry> {
 		let models: HashSet<_> = self.model_regexes.matches(model.as_bytes()).iter().collect();
 		let firmwares: HashSet<_> = self.firmware_regexes.matches(firmware.as_bytes()).iter().collect();
@@ -75,28 +88,40 @@ impl DriveDB {
 	/**
 	Matches given ATA IDENTIFY DEVICE response `id` against drive database `db`.
 
-	Return value is a merge between the default entry and the first match; if multiple entries match the `id`, the first one is used (this is consistent with smartmontools' `lookup_drive` function).
+	Return value is a merge between the [built-in attribute names](fn.builtin_meta.html), the default entry and the first match; if multiple entries match the `id`, the first one is used (this is consistent with smartmontools' `lookup_drive` function).
 	`extra_attributes` are also appended to the list of presets afterwards.
 	*/
 	pub fn render_meta(&self, id: &id::Id, extra_attributes: &Vec<Attribute>) -> DriveMeta {
 		let mut m = DriveMeta {
 			family: None,
 			warning: None,
-			presets: Vec::<Attribute>::new(),
+			presets: builtin_presets(),
+			firmware_bugs: vec![],
 		};
 
 		// TODO show somehow whether default entry was found or not, or ask caller for the default entry
 		if let Some(default) = &self.default {
-			// TODO show somehow whether preset is valid or not
-			if let Some(presets) = presets::parse(&default.presets) {
-				m.presets.extend(presets);
+			match presets::parse_all(&default.presets) {
+				Some((presets, bugs)) => {
+					m.presets.extend(presets);
+					m.firmware_bugs.extend(bugs);
+				},
+				None => warn!("cannot parse presets of the default drivedb entry: {:?}", default.presets),
 			}
 		}
 
 		if let Some(entry) = self.find(&id.model, &id.firmware) {
-			// TODO show somehow whether preset is valid or not
-			if let Some(presets) = presets::parse(&entry.presets) {
-				m.presets.extend(presets);
+			info!("drivedb entry found: family={:?} presets={:?}", entry.family, entry.presets);
+			match presets::parse_all(&entry.presets) {
+				Some((presets, bugs)) => {
+					m.presets.extend(presets);
+					for bug in bugs {
+						if !m.firmware_bugs.contains(&bug) {
+							m.firmware_bugs.push(bug);
+						}
+					}
+				},
+				None => warn!("cannot parse presets of drivedb entry {:?}: {:?}", entry.family, entry.presets),
 			}
 
 			m.family = Some(&entry.family);
@@ -109,6 +134,49 @@ impl DriveDB {
 	}
 }
 
+// A few attributes from the default entry of drivedb.h that pretty much every vendor agrees upon, so that their names are still shown if no drivedb is available.
+// Any drivedb entry, including the default one, overrides these.
+const BUILTIN_PRESETS: &str = "\
+	-v 1,raw48,Raw_Read_Error_Rate \
+	-v 3,raw16(avg16),Spin_Up_Time \
+	-v 4,raw48,Start_Stop_Count \
+	-v 5,raw16(raw16),Reallocated_Sector_Ct \
+	-v 7,raw48,Seek_Error_Rate \
+	-v 9,raw24(raw8),Power_On_Hours \
+	-v 10,raw48,Spin_Retry_Count \
+	-v 12,raw48,Power_Cycle_Count \
+	-v 187,raw48,Reported_Uncorrect \
+	-v 188,raw48,Command_Timeout \
+	-v 190,tempminmax,Airflow_Temperature_Cel \
+	-v 194,tempminmax,Temperature_Celsius \
+	-v 196,raw16(raw16),Reallocated_Event_Count \
+	-v 197,raw48,Current_Pending_Sector \
+	-v 198,raw48,Offline_Uncorrectable \
+	-v 199,raw48,UDMA_CRC_Error_Count\
+";
+
+fn builtin_presets() -> Vec<Attribute> {
+	// unwrap: the string above is known to be valid
+	presets::parse(BUILTIN_PRESETS).unwrap()
+}
+
+/**
+Renders drive metadata without any drive database, for when none could be loaded: only a small built-in table of the most common attribute names, followed by `extra_attributes`.
+
+With drivedb at hand, use [`DriveDB::render_meta()`](struct.DriveDB.html#method.render_meta) instead: these names are included there as well, and are overridden by drivedb entries.
+*/
+pub fn builtin_meta(id: &id::Id, extra_attributes: &[Attribute]) -> DriveMeta<'static> {
+	let mut presets = builtin_presets();
+	presets.extend(extra_attributes.iter().cloned());
+
+	DriveMeta {
+		family: None,
+		warning: None,
+		presets: filter_presets(id, presets),
+		firmware_bugs: vec![],
+	}
+}
+
 fn filter_presets(id: &id::Id, preset: Vec<Attribute>) -> Vec<Attribute> {
 	let drivetype = {
 		use self::id::RPM::*;
@@ -133,6 +201,39 @@ fn filter_presets(id: &id::Id, preset: Vec<Attribute>) -> Vec<Attribute> {
 	}).collect()
 }
 
+/**
+How serious the drivedb warning is.
+
+drivedb itself does not tell, so this is a guess based on the wording of the warning; see [`classify_warning()`](fn.classify_warning.html).
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub enum Severity {
+	/// Drive is known to report bogus or misleading values, but otherwise works fine.
+	Info,
+	/// Anything that does not look like the other two, e.g. a firmware update is available.
+	Warning,
+	/// Known bug that causes data loss or corruption, or renders the drive inaccessible.
+	Critical,
+}
+
+// lowercase substrings, looked up in the lowercased warning text
+// (a bare "fail" would also catch warnings about e.g. self-tests that fail to run, or attributes that fail to update)
+const CRITICAL_WORDING: [&str; 10] = ["data loss", "lose data", "loss of data", "corrupt", "inaccessible", "brick", "unusable", "drive failure", "drive may fail", "drive will fail"];
+const INFO_WORDING: [&str; 4] = ["bogus", "raw value", "incorrect value", "wrong value"];
+
+/// Guesses how serious the drivedb warning `text` is. Critical wording always wins, so that a warning that mentions both bogus values and data loss is not downplayed.
+pub fn classify_warning(text: &str) -> Severity {
+	let text = text.to_lowercase();
+	if CRITICAL_WORDING.iter().any(|w| text.contains(w)) {
+		Severity::Critical
+	} else if INFO_WORDING.iter().any(|w| text.contains(w)) {
+		Severity::Info
+	} else {
+		Severity::Warning
+	}
+}
+
 /// Drive-related data that cannot be queried from the drive itself (model family, attribute presets etc.)
 #[derive(Debug)]
 pub struct DriveMeta<'a> {
@@ -145,9 +246,22 @@ pub struct DriveMeta<'a> {
 
 	/// SMART attribute descriptions
 	presets: Vec<Attribute>,
+
+	/// Firmware bug workarounds (`-F` presets) of the matching entry, along with those of the default entry
+	pub firmware_bugs: Vec<FirmwareBug>,
 }
 
 impl<'a> DriveMeta<'a> {
+	/// Whether drivedb says the drive needs the `bug` worked around.
+	pub fn has_firmware_bug(&self, bug: FirmwareBug) -> bool {
+		self.firmware_bugs.contains(&bug)
+	}
+
+	/// Severity of the `warning`, if there is one.
+	pub fn warning_severity(&self) -> Option<Severity> {
+		self.warning.map(|w| classify_warning(w))
+	}
+
 	/*
 	Attributes are never looked up; they must be rendered for a number of reasons:
 	- description might match all attributes at once (`-v N,…`, represented with `attr.id` of `None`),
@@ -181,3 +295,159 @@ impl<'a> DriveMeta<'a> {
 		out
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use drivedb::vendor_attribute;
+	use ata::data::attr;
+
+	fn entry(family: &str, model: &str, firmware: &str, warning: &str, presets: &str) -> Entry {
+		Entry {
+			family: family.to_string(),
+			model: model.to_string(),
+			firmware: firmware.to_string(),
+			warning: warning.to_string(),
+			presets: presets.to_string(),
+		}
+	}
+
+	fn id(model: &str, firmware: &str) -> id::Id {
+		let mut id = id::parse_id(&vec![0; 512]);
+		id.model = model.to_string();
+		id.firmware = firmware.to_string();
+		id
+	}
+
+	// SMART READ DATA reply with attribute 1, whose raw bytes are 05 00 00 00 01 00 (least significant first)
+	fn values() -> Vec<u8> {
+		let mut data = vec![0; 512];
+		data[2..14].copy_from_slice(&[1, 0x0b, 0x00, 100, 100, 0x05, 0, 0, 0, 0x01, 0, 0]);
+		data
+	}
+
+	fn db() -> DriveDB {
+		DriveDB::new(vec![
+			entry("Test family", "TEST MODEL .*", "", "", "-v 1,raw16(raw16),Renamed_Read_Error_Rate"),
+		]).unwrap()
+	}
+
+	#[test]
+	fn presets_apply() {
+		let db = db();
+		let meta = Some(db.render_meta(&id("TEST MODEL 123", "FW1"), &vec![]));
+		let attrs = attr::parse_smart_values(&values(), &vec![], &meta);
+
+		assert_eq!(meta.as_ref().unwrap().family.map(|f| f.as_str()), Some("Test family"));
+		assert_eq!(attrs[0].name.as_deref(), Some("Renamed_Read_Error_Rate"));
+		// built-in raw48 would show 4294967301 instead
+		assert_eq!(attrs[0].raw.to_string(), "5 (1 0)");
+	}
+
+	#[test]
+	fn user_attributes_override_presets() {
+		let db = db();
+		let user = vec![vendor_attribute::parse("1,raw48,User_Name").unwrap()];
+		let meta = Some(db.render_meta(&id("TEST MODEL 123", "FW1"), &user));
+		let attrs = attr::parse_smart_values(&values(), &vec![], &meta);
+
+		assert_eq!(attrs[0].name.as_deref(), Some("User_Name"));
+		assert_eq!(attrs[0].raw.to_string(), "4294967301");
+	}
+
+	#[test]
+	fn presets_of_other_entries_do_not_apply() {
+		let db = db();
+		let meta = Some(db.render_meta(&id("OTHER MODEL", "FW1"), &vec![]));
+		let attrs = attr::parse_smart_values(&values(), &vec![], &meta);
+
+		assert_eq!(meta.as_ref().unwrap().family, None);
+		// built-in name and format
+		assert_eq!(attrs[0].name.as_deref(), Some("Raw_Read_Error_Rate"));
+		assert_eq!(attrs[0].raw.to_string(), "4294967301");
+	}
+
+	#[test]
+	fn empty_firmware_matches_any_firmware() {
+		let db = DriveDB::new(vec![
+			entry("Pinned firmware", "TEST MODEL .*", "FW1", "", ""),
+			entry("Any firmware", "TEST MODEL .*", "", "", ""),
+		]).unwrap();
+
+		assert_eq!(db.find("TEST MODEL 123", "FW1").map(|e| e.family.as_str()), Some("Pinned firmware"));
+		assert_eq!(db.find("TEST MODEL 123", "FW2").map(|e| e.family.as_str()), Some("Any firmware"));
+		assert_eq!(db.find("TEST MODEL 123", "").map(|e| e.family.as_str()), Some("Any firmware"));
+		assert!(db.find("OTHER MODEL", "FW1").is_none());
+	}
+
+	#[test]
+	fn empty_presets_override_nothing() {
+		let db = DriveDB::new(vec![
+			entry("Test family", "TEST MODEL .*", "", "", ""),
+			entry("Blank family", "BLANK MODEL", "", "", " "),
+		]).unwrap();
+
+		for model in &["TEST MODEL 123", "BLANK MODEL"] {
+			let meta = Some(db.render_meta(&id(model, "FW1"), &vec![]));
+			let attrs = attr::parse_smart_values(&values(), &vec![], &meta);
+
+			// the entry still matches
+			assert!(meta.as_ref().unwrap().family.is_some());
+			assert!(meta.as_ref().unwrap().firmware_bugs.is_empty());
+			// built-in name and format
+			assert_eq!(attrs[0].name.as_deref(), Some("Raw_Read_Error_Rate"));
+			assert_eq!(attrs[0].raw.to_string(), "4294967301");
+		}
+	}
+
+	#[test]
+	fn warning_severity() {
+		assert_eq!(classify_warning("A firmware update can prevent data loss"), Severity::Critical);
+		assert_eq!(classify_warning("Drive may become INACCESSIBLE after a power cycle"), Severity::Critical);
+		assert_eq!(classify_warning("This drive may fail without warning"), Severity::Critical);
+		assert_eq!(classify_warning("Bogus raw values of attribute 194"), Severity::Info);
+		// critical wording wins
+		assert_eq!(classify_warning("Bogus raw values, and a risk of data corruption"), Severity::Critical);
+		// mentions failure, but nothing critical
+		assert_eq!(classify_warning("Extended self-tests fail to complete, a firmware update is available"), Severity::Warning);
+		assert_eq!(classify_warning("A firmware update is available"), Severity::Warning);
+	}
+
+	// SMART READ DATA reply with attribute 9 (10000 hours) and some vendor-specific attribute 240
+	fn power_on_values() -> Vec<u8> {
+		let mut data = vec![0; 512];
+		data[2..14].copy_from_slice(&[9, 0x32, 0x00, 95, 95, 0x10, 0x27, 0, 0, 0, 0, 0]);
+		data[14..26].copy_from_slice(&[240, 0x00, 0x00, 100, 100, 0x01, 0, 0, 0, 0, 0, 0]);
+		data
+	}
+
+	#[test]
+	fn builtin_names_without_drivedb() {
+		let meta = Some(builtin_meta(&id("TEST MODEL 123", "FW1"), &[]));
+		let attrs = attr::parse_smart_values(&power_on_values(), &vec![], &meta);
+
+		assert_eq!(attrs[0].name.as_deref(), Some("Power_On_Hours"));
+		assert_eq!(attrs[0].raw.to_string(), "10000");
+		// not in the built-in table
+		assert_eq!(attrs[1].name, None);
+	}
+
+	#[test]
+	fn builtin_names_are_overridden() {
+		let user = vec![vendor_attribute::parse("9,raw48,User_Hours").unwrap()];
+		let meta = Some(builtin_meta(&id("TEST MODEL 123", "FW1"), &user));
+		let attrs = attr::parse_smart_values(&power_on_values(), &vec![], &meta);
+		assert_eq!(attrs[0].name.as_deref(), Some("User_Hours"));
+
+		let db = DriveDB::new(vec![
+			entry("Test family", "TEST MODEL .*", "", "", "-v 9,msec24hour32,Drivedb_Hours"),
+		]).unwrap();
+		let meta = Some(db.render_meta(&id("TEST MODEL 123", "FW1"), &vec![]));
+		let attrs = attr::parse_smart_values(&power_on_values(), &vec![], &meta);
+		assert_eq!(attrs[0].name.as_deref(), Some("Drivedb_Hours"));
+		// built-in names are still there for drives drivedb does not know
+		let meta = Some(db.render_meta(&id("OTHER MODEL", "FW1"), &vec![]));
+		let attrs = attr::parse_smart_values(&power_on_values(), &vec![], &meta);
+		assert_eq!(attrs[0].name.as_deref(), Some("Power_On_Hours"));
+	}
+}
@@ -30,7 +30,11 @@ fn load(file: &str) -> Result<Vec<Entry>, Error> {
     let mut db = Vec::new();
     File::open(&file)?.read_to_end(&mut db)?;
 
-    match parser::database(&db) {
+    parse(&db)
+}
+
+fn parse(db: &[u8]) -> Result<Vec<Entry>, Error> {
+    match parser::database(db) {
         Ok((_, entries)) => Ok(entries),
         Err(nom::Err::Error(_)) => Err(Error::Parse),
         Err(nom::Err::Incomplete(_)) => unreachable!(), // XXX is it true?
@@ -38,6 +42,42 @@ fn load(file: &str) -> Result<Vec<Entry>, Error> {
     }
 }
 
+// version entry is the one with the family of `"$Id: … $"`, which parser skips
+fn version(db: &[u8]) -> Option<&str> {
+    let start = db.windows(5).position(|w| w == b"\"$Id:")? + 5;
+    let len = db[start..].iter().position(|&c| c == b'$' || c == b'"')?;
+
+    ::std::str::from_utf8(&db[start..start + len])
+        .ok()
+        .map(|v| v.trim())
+}
+
+/**
+Returns version of the drivedb file, as recorded in its `$Id$` entry (e.g. `drivedb.h 5040 2020-04-04 14:34:19Z chrfranke`).
+
+Returns `Ok(None)` if the file carries no version information. The file is not parsed, so this does not tell whether the file is valid.
+*/
+pub fn file_version(file: &str) -> Result<Option<String>, Error> {
+    let mut db = Vec::new();
+    File::open(&file)?.read_to_end(&mut db)?;
+
+    Ok(version(&db).map(|v| v.to_string()))
+}
+
+/// Copy of `drivedb.h` taken at build time (see `embedded-drivedb` feature).
+#[cfg(feature = "embedded-drivedb")]
+static EMBEDDED: &'static [u8] = include_bytes!(concat!(env!("OUT_DIR"), "/drivedb.h"));
+
+/**
+Returns version of the drivedb embedded into the crate, as recorded in its `$Id$` entry (e.g. `drivedb.h 5040 2020-04-04 14:34:19Z chrfranke`).
+
+Returns `None` if embedded drivedb carries no version information.
+*/
+#[cfg(feature = "embedded-drivedb")]
+pub fn embedded_version() -> Option<&'static str> {
+    version(EMBEDDED)
+}
+
 /**
 Use this helper to load entries from `drivedb.h`.
 
@@ -72,6 +112,20 @@ impl Loader {
         Ok(())
     }
     /**
+    Loads entries from the copy of `drivedb.h` that was embedded into the crate at build time, as if it was the main drivedb file.
+
+    This is meant to be the last resort for those who could not find drivedb on disk (e.g. with static binaries deployed on systems without smartmontools).
+
+    ## Errors
+
+    Returns [enum Error](enum.Error.html) if embedded drive database is malformed.
+    */
+    #[cfg(feature = "embedded-drivedb")]
+    pub fn load_embedded(&mut self) -> Result<(), Error> {
+        self.entries = parse(EMBEDDED)?;
+        Ok(())
+    }
+    /**
     Loads more entries from additional drivedb file. Additional entries always take precedence over the ones from the main file.
 
     ## Errors
@@ -82,7 +136,7 @@ impl Loader {
     - drive database is malformed.
     */
     pub fn load_additional(&mut self, file: &str) -> Result<(), Error> {
-        self.entries = load(file)?;
+        self.additional.extend(load(file)?);
         Ok(())
     }
     /// Returns actual drive database with all entries loaded beforehand.
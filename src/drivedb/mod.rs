@@ -3,16 +3,21 @@ Use this module to match hard drive and SMART values it returns against smartmon
 
 ## Example
 
-```
+```no_run
+use hdd::Device;
+use hdd::ata::ATADevice;
+use hdd::ata::misc::Misc;
 use hdd::drivedb::{Loader, vendor_attribute};
+use hdd::scsi::SCSIDevice;
 
-let loader = Loader::new();
+let mut loader = Loader::new();
 // look for version updated with `update-smart-drivedb(8)` first
-loader.load("/var/lib/smartmontools/drivedb/drivedb.h")
-	.or_else(|| loader.load("/usr/share/smartmontools/drivedb.h"))?;
-// `?` is optional though: if nothing can be loaded, loader will still provide dummy db for us
+let _ = loader.load("/var/lib/smartmontools/drivedb/drivedb.h")
+	.or_else(|_| loader.load("/usr/share/smartmontools/drivedb.h"));
+// errors are not fatal though: if nothing can be loaded, loader will still provide dummy db for us
+// (with `embedded-drivedb` feature there's also `loader.load_embedded()` to fall back to)
 
-let db = loader.db();
+let db = loader.db().unwrap();
 
 // extra attribute definitions that user might give
 let user_attributes = vec!["9,minutes"]
@@ -20,8 +25,8 @@ let user_attributes = vec!["9,minutes"]
 	.map(|attr| vendor_attribute::parse(attr).unwrap())
 	.collect();
 
-// TODO: issue ATA IDENTIFY DEVICE cmd and parse the answer here
-let id = unimplemented!();
+let dev = ATADevice::new(SCSIDevice::new(Device::open("/dev/sda").unwrap()));
+let id = dev.get_device_id().unwrap();
 
 let meta = db.render_meta(&id, &user_attributes);
 
@@ -43,5 +48,11 @@ mod drivedb;
 mod loader;
 pub mod vendor_attribute;
 pub use self::vendor_attribute::Attribute;
-pub use self::drivedb::{DriveDB, DriveMeta};
-pub use self::loader::{Loader, Error};
+pub mod config;
+pub use self::config::Config;
+pub use self::drivedb::{DriveDB, DriveMeta, Severity, classify_warning, builtin_meta};
+pub use self::presets::FirmwareBug;
+pub use self::parser::Entry;
+pub use self::loader::{Loader, Error, file_version};
+#[cfg(feature = "embedded-drivedb")]
+pub use self::loader::embedded_version;
@@ -1,13 +1,16 @@
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_until},
-    character::complete::{char, multispace0, none_of, one_of},
+    character::complete::{char, multispace1, none_of, one_of},
     combinator::{eof, map, value},
     multi::{many0, many1},
     sequence::tuple,
     IResult,
 };
 
+use super::presets::{self, FirmwareBug};
+use super::Attribute;
+
 fn comment_block(input: &[u8]) -> IResult<&[u8], ()> {
     value(
         (), // output is thrown away
@@ -25,7 +28,7 @@ fn comment(input: &[u8]) -> IResult<&[u8], ()> {
 fn whitespace(input: &[u8]) -> IResult<&[u8], ()> {
     value(
         (), // output is thrown away
-        many0(alt((value((), multispace0), comment, comment_block))),
+        many0(alt((value((), multispace1), comment, comment_block))),
     )(input)
 }
 
@@ -73,6 +76,7 @@ fn string(i: &[u8]) -> IResult<&[u8], String> {
 
 /// drivedb.h entry
 #[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
 pub struct Entry {
     /// > Informal string about the model family/series of a device.
     pub family: String,
@@ -95,6 +99,13 @@ pub struct Entry {
     pub presets: String,
 }
 
+impl Entry {
+    /// Parses `presets` into attribute descriptions and firmware bug workarounds; returns `None` if these are malformed.
+    pub fn parse_presets(&self) -> Option<(Vec<Attribute>, Vec<FirmwareBug>)> {
+        presets::parse_all(&self.presets)
+    }
+}
+
 fn comma(i: &[u8]) -> IResult<&[u8], ()> {
     value(
         (), // output is thrown away
@@ -1,13 +1,70 @@
 use super::vendor_attribute;
 use super::vendor_attribute::Attribute;
 
+/**
+Firmware bug workarounds, as listed with `-F` presets of drivedb entries (see `smartctl(8)`, option `-F`, for details).
+
+Of these, the crate itself applies `Samsung` (to the flags of SMART attributes, see [`parse_smart_values()`](../ata/data/attr/fn.parse_smart_values.html)), `Samsung2` and `XErrorLBA` (to SMART error logs, see [`error_log`](../ata/data/error_log/index.html)); others are here for the sake of users that parse affected structures themselves.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub enum FirmwareBug {
+	/// `-F nologdir`: device does not support General Purpose Log Directory and SMART Log Directory properly
+	NoLogDir,
+	/// `-F samsung`: some two- and four-byte fields of SMART data structures are byte-swapped (e.g. attribute flags, self-test log index)
+	Samsung,
+	/// `-F samsung2`: number of ATA errors in the SMART error log is byte-swapped
+	Samsung2,
+	/// `-F samsung3`: self-test execution status is reported as 'in progress' (with 0% remaining) after the test is completed
+	Samsung3,
+	/// `-F xerrorlba`: LBA bytes in Extended Comprehensive SMART error log are in the wrong order
+	XErrorLBA,
+	/// `-F swapid`: bytes of identification strings (model, serial, firmware) are swapped
+	SwapId,
+}
+
+impl FirmwareBug {
+	pub fn parse(s: &str) -> Option<Self> {
+		use self::FirmwareBug::*;
+		match s {
+			"nologdir" => Some(NoLogDir),
+			"samsung" => Some(Samsung),
+			"samsung2" => Some(Samsung2),
+			"samsung3" => Some(Samsung3),
+			"xerrorlba" => Some(XErrorLBA),
+			"swapid" => Some(SwapId),
+			_ => None,
+		}
+	}
+
+	/// Value of the `-F` option, e.g. `samsung`
+	pub fn name(&self) -> &'static str {
+		use self::FirmwareBug::*;
+		match *self {
+			NoLogDir => "nologdir",
+			Samsung => "samsung",
+			Samsung2 => "samsung2",
+			Samsung3 => "samsung3",
+			XErrorLBA => "xerrorlba",
+			SwapId => "swapid",
+		}
+	}
+}
+
+// empty `line` is perfectly valid, and means there's nothing to override
 pub fn parse(line: &str) -> Option<Vec<Attribute>> {
+	parse_all(line).map(|(attrs, _)| attrs)
+}
+
+// same as `parse()`, but also returns firmware bugs (`-F`)
+pub fn parse_all(line: &str) -> Option<(Vec<Attribute>, Vec<FirmwareBug>)> {
 	// using clap here would be an overkill
 	let mut args = line.split_whitespace().into_iter();
 	let mut output = Vec::<Attribute>::new();
+	let mut bugs = Vec::<FirmwareBug>::new();
 	loop {
 		match args.next() {
-			None => return Some(output),
+			None => return Some((output, bugs)),
 			Some(key) => match args.next() {
 				None => return None, // we always expect an argument for the option
 				Some(value) => {
@@ -16,6 +73,12 @@ pub fn parse(line: &str) -> Option<Vec<Attribute>> {
 							Ok(attr) => output.push(attr),
 							Err(_) => (), // TODO
 						} },
+						// `-F none` resets whatever was set before it
+						"-F" if value == "none" => bugs.clear(),
+						"-F" => match FirmwareBug::parse(value) {
+							Some(bug) => if !bugs.contains(&bug) { bugs.push(bug) },
+							None => warn!("unknown firmware bug preset: {:?}", value),
+						},
 						_ => continue, // TODO other options
 					}
 				},
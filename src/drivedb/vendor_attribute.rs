@@ -31,6 +31,7 @@ quick_error! {
 
 /// HDD or SSD
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
 pub enum Type {
     HDD,
     SSD,
@@ -38,6 +39,7 @@ pub enum Type {
 
 /// SMART attribute description
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
 pub struct Attribute {
     /// id of described attribute
     pub id: Option<u8>,
@@ -51,6 +53,16 @@ pub struct Attribute {
     pub drivetype: Option<Type>,
 }
 
+/**
+Characters allowed in byte orders:
+
+* `0` to `5`: raw bytes of the attribute, from the least significant one,
+* `v`, `w`: current and worst values,
+* `r`: reserved byte that follows the raw value,
+* `6`, `7`, `s`, `m`: positions no attribute data backs; these yield zero bytes.
+*/
+pub const BYTE_ORDER_TOKENS: &str = "012345vwr67sm";
+
 fn not_comma(c: u8) -> bool {
     c == b','
 }
@@ -61,9 +73,13 @@ fn not_comma_nor_colon(c: u8) -> bool {
 // `opt!()` is used with `complete!()` here because the former returns `Incomplete` untouched, thus making attributes not ending with otherwise optional ',(HDD|SSD)' `Incomplete` as well.
 fn parse_standard(i: &[u8]) -> IResult<&[u8], Attribute> {
     let (i, id) = alt((
-        // XXX map_res!()?
-        map(digit1, |x: &[u8]| {
-            str::from_utf8(x).unwrap().parse::<u8>().ok()
+        // ids that do not fit into u8 must not fall through to `None`, which means "every attribute";
+        // 0 is not valid either, it marks unused entries of the attribute table
+        map_res(digit1, |x: &[u8]| {
+            match str::from_utf8(x).unwrap().parse::<u8>() {
+                Ok(0) | Err(_) => Err(Error::Parse),
+                Ok(id) => Ok(Some(id)),
+            }
         }),
         value(None, char('N')),
     ))(i)?;
@@ -73,8 +89,14 @@ fn parse_standard(i: &[u8]) -> IResult<&[u8], Attribute> {
     // TODO '+' for ATTRFLAG_INCREASING
     let (i, byte_order) = opt(complete(preceded(
         char(':'),
-        // TODO len()<6 should be invalid
-        map_res(take_till1(not_comma), str::from_utf8),
+        // every character has to have a meaning for the raw value decoder (see `BYTE_ORDER_TOKENS`), and the result has to fit into u64
+        map_res(take_till1(not_comma), |x: &[u8]| {
+            let byte_order = str::from_utf8(x).map_err(|_| Error::Parse)?;
+            if byte_order.len() > 8 || !byte_order.chars().all(|c| BYTE_ORDER_TOKENS.contains(c)) {
+                return Err(Error::Parse);
+            }
+            Ok(byte_order)
+        }),
     )))(i)?;
 
     fn parse_name_drive_type(i: &[u8]) -> IResult<&[u8], (&str, Option<Type>)> {
@@ -121,7 +143,7 @@ Parses single attribute description (`-v` option argument).
 
 The following formats are supported:
 
-* `ID,FORMAT[:BYTEORDER][,NAME[,(HDD|SSD)]]`
+* `ID,FORMAT[:BYTEORDER][,NAME[,(HDD|SSD)]]`, where `ID` is either 1 to 255, or `N` for every attribute, and `BYTEORDER` is up to 8 of [`BYTE_ORDER_TOKENS`](constant.BYTE_ORDER_TOKENS.html)
 * legacy `-v` arguments, like `9,halfminutes`
 */
 pub fn parse(s: &str) -> Result<Attribute, Error> {
@@ -150,3 +172,32 @@ pub fn parse(s: &str) -> Result<Attribute, Error> {
         Err(nom::Err::Failure(_)) => todo!(),         // TODO?
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_range() {
+        assert_eq!(parse("1,raw48").unwrap().id, Some(1));
+        assert_eq!(parse("254,raw48,Free_Fall_Sensor").unwrap().id, Some(254));
+        assert_eq!(parse("255,raw48").unwrap().id, Some(255));
+        assert_eq!(parse("N,raw48").unwrap().id, None);
+
+        assert!(parse("0,raw48").is_err());
+        assert!(parse("256,raw48").is_err());
+    }
+    #[test]
+    fn byte_order_tokens() {
+        for token in BYTE_ORDER_TOKENS.chars() {
+            let attr = parse(&format!("241,raw48:{}", token)).unwrap();
+            assert_eq!(attr.byte_order, token.to_string());
+        }
+        assert_eq!(parse("241,raw48:r543210v").unwrap().byte_order, "r543210v");
+
+        assert!(parse("241,raw48:x").is_err());
+        assert!(parse("241,raw48:V").is_err());
+        // result has to fit into u64
+        assert!(parse("241,raw48:r543210vw").is_err());
+    }
+}
@@ -3,12 +3,13 @@ This crate allows you to send various commands to storage devices, and to interp
 
 ## Example
 
-```
+```no_run
 use hdd::Device;
 use hdd::scsi::{SCSIDevice, SCSICommon};
 
-let dev = Device::open("/dev/da0").unwrap();
-let (sense, data) = dev.scsi_inquiry(vpd, page).unwrap();
+let dev = SCSIDevice::new(Device::open("/dev/da0").unwrap());
+// standard INQUIRY data; pass `true` and the page code to get a Vital Product Data page instead
+let (sense, data) = dev.scsi_inquiry(false, 0).unwrap();
 ```
 
 TODO show how to send hand-crafted commands, or how to use porcelain interfaces.
@@ -55,6 +56,8 @@ If `Foo<X>::foo()` is gone for some reason (usually during refactoring), `Whatev
 #[cfg(feature = "serializable")]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(feature = "serializable")]
+extern crate serde;
 
 #[macro_use]
 extern crate quick_error;
@@ -86,6 +89,8 @@ mod cam;
 pub mod ata;
 pub mod scsi;
 
+pub mod tcg;
+
 pub mod drivedb;
 
 mod utils;
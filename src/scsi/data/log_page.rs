@@ -5,16 +5,19 @@ For more, see SPC-4, 7.3 Log parameters.
 
 ## Example
 
-```
+```no_run
+use hdd::Device;
+use hdd::scsi::{SCSIDevice, SCSICommon};
 use hdd::scsi::data::log_page;
 
-let (_sense, data) = dev.log_sense(...)?;
+let dev = SCSIDevice::new(Device::open("/dev/da0").unwrap());
+// Temperature page, current cumulative values
+let (_sense, data) = dev.log_sense(false, false, false, false, 0x0d, 0x00, 0).unwrap();
 
 let page = log_page::parse(&data).unwrap();
 println!("{:#?}", page);
 
-let params = page.parse_params();
-for param in params {
+for param in page.parse_params().unwrap() {
 	println!("{:#?}", param);
 }
 ```
@@ -12,10 +12,27 @@ impl SCSIDevice {
 	pub(crate) fn do_platform_cmd(&self, cmd: &[u8], dir: Direction, sense_len: usize, data_len: usize)-> Result<(Vec<u8>, Vec<u8>), io::Error> {
 		// might've used Vec::with_capacity(), but this requires rebuilding with Vec::from_raw_parts() later on to hint actual size of data in buffer vecs,
 		// and we're not expecting this function to be someone's bottleneck
-		let mut sense = vec![0; sense_len];
 		let mut data = vec![0; data_len];
 
-		let timeout = 10; // in seconds; TODO configurable
+		let (sense, data_len) = self.do_platform_cmd_buf(cmd, dir, sense_len, &mut data)?;
+		data.truncate(data_len);
+
+		Ok((sense, data))
+	}
+
+	/// Executes `cmd`, sending `data` to the device, and returns sense.
+	pub(crate) fn do_platform_cmd_out(&self, cmd: &[u8], sense_len: usize, data: &[u8]) -> Result<Vec<u8>, io::Error> {
+		// csio.data_ptr is *mut, so we need our own copy
+		let mut data = data.to_vec();
+
+		let (sense, _) = self.do_platform_cmd_buf(cmd, Direction::To, sense_len, &mut data)?;
+
+		Ok(sense)
+	}
+
+	/// Executes `cmd`, returning sense and the amount of data transferred.
+	fn do_platform_cmd_buf(&self, cmd: &[u8], dir: Direction, sense_len: usize, data: &mut [u8]) -> Result<(Vec<u8>, usize), io::Error> {
+		let mut sense = vec![0; sense_len];
 
 		let dev = &self.device.dev;
 
@@ -30,18 +47,17 @@ impl SCSIDevice {
 			csio.ccb_h.flags = {
 				use self::Direction::*;
 				match dir {
-					// TODO &[u8] arg → data → csio.data_ptr for Direction::{To,Both}
 					From => ccb_flags_CAM_DIR_IN,
-					To => unimplemented!(), //CAM_DIR_OUT,
+					To => ccb_flags_CAM_DIR_OUT,
 					Both => unimplemented!(), //CAM_DIR_BOTH,
 					None => ccb_flags_CAM_DIR_NONE,
 				}
 			};
 			csio.ccb_h.xflags = 0;
 			csio.ccb_h.retry_count = 1;
-			csio.ccb_h.timeout = timeout*1000;
+			csio.ccb_h.timeout = self.device.timeout_ms();
 			csio.data_ptr = data.as_mut_ptr();
-			csio.dxfer_len = data.capacity() as u32;
+			csio.dxfer_len = data.len() as u32;
 			csio.sense_len = sense.capacity() as u8;
 			csio.tag_action = MSG_SIMPLE_Q_TAG as u8;
 
@@ -93,7 +109,7 @@ impl SCSIDevice {
 
 		Ok((
 			sense[ .. sense_len as usize].to_vec(),
-			data[ .. data_len as usize].to_vec(),
+			data_len as usize,
 		))
 	}
 }
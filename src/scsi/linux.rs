@@ -10,6 +10,7 @@ use std::os::unix::io::AsRawFd;
 use std::io;
 
 use Direction;
+use device::Interface;
 use scsi::SCSIDevice;
 
 use std::cmp::max;
@@ -22,6 +23,11 @@ const SG_IO: c_ulong = 0x2285;
 #[cfg(any(target_env = "musl"))]
 const SG_IO: c_int = 0x2285;
 
+// see scsi/scsi.h (host_status) and scsi/sg.h (driver_status)
+const DID_TIME_OUT: c_ushort = 0x03;
+const DRIVER_TIMEOUT: c_ushort = 0x06;
+const SG_ERR_DRIVER_MASK: c_ushort = 0x0f;
+
 #[repr(C)]
 #[derive(Debug)]
 struct sg_io_hdr {
@@ -49,26 +55,93 @@ struct sg_io_hdr {
 	info:	c_uint,	// [o] auxiliary information
 }
 
+// see linux/bsg.h
+const BSG_PROTOCOL_SCSI: c_uint = 0;
+const BSG_SUB_PROTOCOL_SCSI_CMD: c_uint = 0;
+
+// pointers are passed as u64 regardless of the platform, so the layout is the same for 32- and 64-bit processes (160 bytes)
+#[repr(C)]
+#[derive(Debug)]
+struct sg_io_v4 {
+	guard:	c_int,	// [i] 'Q' to differentiate from v3
+	protocol:	c_uint,	// [i] 0 -> SCSI , ....
+	subprotocol:	c_uint,	// [i] 0 -> SCSI command, 1 -> SCSI task management function, ....
+	request_len:	c_uint,	// [i] in bytes
+	request:	u64,	// [i], [*i] {SCSI: cdb}
+	request_tag:	u64,	// [i] {SCSI: task tag (only if flagged)}
+	request_attr:	c_uint,	// [i] {SCSI: task attribute}
+	request_priority:	c_uint,	// [i] {SCSI: task priority}
+	request_extra:	c_uint,	// [i] {spare, for padding}
+	max_response_len:	c_uint,	// [i] in bytes
+	response:	u64,	// [i], [*o] {SCSI: (auto)sense data}
+	dout_iovec_count:	c_uint,	// [i] 0 -> "flat" dout transfer else dout_xfer points to array of iovec
+	dout_xfer_len:	c_uint,	// [i] bytes to be transferred to device
+	din_iovec_count:	c_uint,	// [i] 0 -> "flat" din transfer
+	din_xfer_len:	c_uint,	// [i] bytes to be transferred from device
+	dout_xferp:	u64,	// [i], [*i]
+	din_xferp:	u64,	// [i], [*o]
+	timeout:	c_uint,	// [i] units: millisecond
+	flags:	c_uint,	// [i] bit mask
+	usr_ptr:	u64,	// [i->o] unused internally
+	spare_in:	c_uint,	// [i]
+	driver_status:	c_uint,	// [o] 0 -> ok
+	transport_status:	c_uint,	// [o] 0 -> ok
+	device_status:	c_uint,	// [o] {SCSI: command completion status}
+	retry_delay:	c_uint,	// [o] {SCSI: status auxiliary information}
+	info:	c_uint,	// [o] additional information
+	duration:	c_uint,	// [o] time to complete, in milliseconds
+	response_len:	c_uint,	// [o] bytes of response actually written
+	din_resid:	c_int,	// [o] din_xfer_len - actual_din_xfer_len
+	dout_resid:	c_int,	// [o] dout_xfer_len - actual_dout_xfer_len
+	generated_tag:	u64,	// [o] {SCSI: transport generated task tag}
+	spare_out:	c_uint,	// [o]
+	padding:	c_uint,
+}
+
 impl SCSIDevice {
 	pub(crate) fn do_platform_cmd(&self, cmd: &[u8], dir: Direction, sense_len: usize, data_len: usize) -> Result<(Vec<u8>, Vec<u8>), io::Error> {
 		// might've used Vec::with_capacity(), but this requires rebuilding with Vec::from_raw_parts() later on to hint actual size of data in buffer vecs,
 		// and we're not expecting this function to be someone's bottleneck
-		let mut sense = vec![0; sense_len];
 		let mut data = vec![0; data_len];
 
+		let (sense, data_len) = self.do_platform_cmd_buf(cmd, dir, sense_len, &mut data)?;
+		data.truncate(data_len);
+
+		Ok((sense, data))
+	}
+
+	pub(crate) fn do_platform_cmd_out(&self, cmd: &[u8], sense_len: usize, data: &[u8]) -> Result<Vec<u8>, io::Error> {
+		// sg_io_hdr.dxferp is *mut, so we need our own copy
+		let mut data = data.to_vec();
+
+		let (sense, _) = self.do_platform_cmd_buf(cmd, Direction::To, sense_len, &mut data)?;
+
+		Ok(sense)
+	}
+
+	/// Executes `cmd`, returning sense and the amount of data transferred.
+	fn do_platform_cmd_buf(&self, cmd: &[u8], dir: Direction, sense_len: usize, data: &mut [u8]) -> Result<(Vec<u8>, usize), io::Error> {
+		match self.device.interface {
+			Interface::SG => self.do_sg_io_v3(cmd, dir, sense_len, data),
+			Interface::BSG => self.do_sg_io_v4(cmd, dir, sense_len, data),
+		}
+	}
+
+	fn do_sg_io_v3(&self, cmd: &[u8], dir: Direction, sense_len: usize, data: &mut [u8]) -> Result<(Vec<u8>, usize), io::Error> {
+		let mut sense = vec![0; sense_len];
+
 		let hdr = sg_io_hdr {
 			interface_id:	'S' as c_int,
 
 			dxfer_direction: match dir {
 				// see scsi/sg.h, constants SG_DXFER_{NONE,{TO,FROM,TO_FROM}_DEV}
-				// TODO &[u8] arg → data → sg_io_hdr.dxferp for Direction::{To,Both}
 				Direction::None => -1,
-				Direction::To => unimplemented!(), //-2,
+				Direction::To => -2,
 				Direction::From => -3,
 				Direction::Both => unimplemented!(), //-4,
 			},
 			dxferp:	data.as_mut_ptr() as *mut c_void,
-			dxfer_len:	data.capacity() as c_uint,
+			dxfer_len:	data.len() as c_uint,
 			resid:	0,
 
 			sbp:	sense.as_mut_ptr(),
@@ -82,7 +155,7 @@ impl SCSIDevice {
 			host_status:	0,
 			driver_status:	0,
 
-			timeout:	10000,	// TODO configurable
+			timeout:	self.device.timeout_ms(),
 			duration:	0,
 
 			iovec_count:	0,
@@ -100,6 +173,11 @@ impl SCSIDevice {
 			}
 		}
 
+		// the ioctl itself succeeds even if the command was aborted due to timeout
+		if hdr.host_status == DID_TIME_OUT || hdr.driver_status & SG_ERR_DRIVER_MASK == DRIVER_TIMEOUT {
+			return Err(io::Error::new(io::ErrorKind::TimedOut, format!("command timed out after {} ms", hdr.timeout)));
+		}
+
 		// > In practice [resid] only reports underruns (i.e. positive number) as data overruns should never happen
 		// but I'd still not cast i32 to u32 blindly, just to be sure
 		// TODO? return overrun flag
@@ -108,7 +186,118 @@ impl SCSIDevice {
 
 		Ok((
 			sense[ .. hdr.sb_len_wr as usize].to_vec(),
-			data[ .. data_len as usize].to_vec(),
+			data_len as usize,
 		))
 	}
+
+	fn do_sg_io_v4(&self, cmd: &[u8], dir: Direction, sense_len: usize, data: &mut [u8]) -> Result<(Vec<u8>, usize), io::Error> {
+		let mut sense = vec![0; sense_len];
+
+		// unlike v3, there are separate buffers for each direction
+		let (dout, din) = match dir {
+			Direction::None => ((0, 0), (0, 0)),
+			Direction::To => ((data.as_mut_ptr() as u64, data.len() as c_uint), (0, 0)),
+			Direction::From => ((0, 0), (data.as_mut_ptr() as u64, data.len() as c_uint)),
+			Direction::Both => unimplemented!(),
+		};
+
+		let hdr = sg_io_v4 {
+			guard:	'Q' as c_int,
+			protocol:	BSG_PROTOCOL_SCSI,
+			subprotocol:	BSG_SUB_PROTOCOL_SCSI_CMD,
+
+			request:	cmd.as_ptr() as u64,
+			request_len:	cmd.len() as c_uint,
+
+			response:	sense.as_mut_ptr() as u64,
+			max_response_len:	sense.capacity() as c_uint,
+			response_len:	0,
+
+			dout_xferp:	dout.0,
+			dout_xfer_len:	dout.1,
+			dout_resid:	0,
+			din_xferp:	din.0,
+			din_xfer_len:	din.1,
+			din_resid:	0,
+
+			device_status:	0,
+			transport_status:	0,
+			driver_status:	0,
+
+			timeout:	self.device.timeout_ms(),
+			duration:	0,
+
+			request_tag:	0,
+			request_attr:	0,
+			request_priority:	0,
+			request_extra:	0,
+			dout_iovec_count:	0,
+			din_iovec_count:	0,
+			flags:	0,
+			usr_ptr:	0,
+			spare_in:	0,
+			retry_delay:	0,
+			info:	0,
+			generated_tag:	0,
+			spare_out:	0,
+			padding:	0,
+		};
+
+		unsafe {
+			if ioctl(self.device.file.as_raw_fd(), SG_IO, &hdr) == -1 {
+				return Err(io::Error::last_os_error());
+			}
+		}
+
+		// transport_status is what v3 calls host_status
+		if hdr.transport_status == DID_TIME_OUT as c_uint || hdr.driver_status & SG_ERR_DRIVER_MASK as c_uint == DRIVER_TIMEOUT as c_uint {
+			return Err(io::Error::new(io::ErrorKind::TimedOut, format!("command timed out after {} ms", hdr.timeout)));
+		}
+
+		let data_len = match dir {
+			Direction::From => hdr.din_xfer_len - max(hdr.din_resid, 0) as u32,
+			Direction::To => hdr.dout_xfer_len - max(hdr.dout_resid, 0) as u32,
+			_ => 0,
+		};
+
+		Ok((
+			sense[ .. hdr.response_len as usize].to_vec(),
+			data_len as usize,
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::mem::{self, size_of};
+
+	#[test]
+	fn sg_io_v4_layout() {
+		assert_eq!(size_of::<sg_io_v4>(), 160);
+
+		// see linux/bsg.h
+		let hdr: sg_io_v4 = unsafe { mem::zeroed() };
+		let base = ptr::addr_of!(hdr) as usize;
+		macro_rules! offset {
+			($field:ident) => { ptr::addr_of!(hdr.$field) as usize - base };
+		}
+		assert_eq!(offset!(guard), 0);
+		assert_eq!(offset!(request_len), 12);
+		assert_eq!(offset!(request), 16);
+		assert_eq!(offset!(max_response_len), 44);
+		assert_eq!(offset!(response), 48);
+		assert_eq!(offset!(dout_xfer_len), 60);
+		assert_eq!(offset!(din_xfer_len), 68);
+		assert_eq!(offset!(dout_xferp), 72);
+		assert_eq!(offset!(din_xferp), 80);
+		assert_eq!(offset!(timeout), 88);
+		assert_eq!(offset!(usr_ptr), 96);
+		assert_eq!(offset!(driver_status), 108);
+		assert_eq!(offset!(device_status), 116);
+		assert_eq!(offset!(response_len), 132);
+		assert_eq!(offset!(din_resid), 136);
+		assert_eq!(offset!(generated_tag), 144);
+		assert_eq!(offset!(padding), 156);
+	}
 }
@@ -30,11 +30,14 @@ quick_error! {
     #[derive(Debug)]
     pub enum Error {
         IO(err: io::Error) {
-            from()
             display("IO error: {}", err)
             description(err.description())
             cause(err)
         }
+        /// Command did not complete within the device timeout (see [`Device::set_timeout()`](../struct.Device.html#method.set_timeout))
+        Timeout {
+            display("command timed out")
+        }
         // XXX make sure only non-deferred senses are used here
         // XXX it makes no sense (sorry!) to put informational senses here (i.e. sense::SenseKey::{Ok, Recovered, Completed})
         Sense(key: sense::key::SenseKey, asc: u8, ascq: u8) { // XXX do we need additional sense data? descriptors? flags? probably not
@@ -52,6 +55,15 @@ quick_error! {
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::IO(err),
+        }
+    }
+}
+
 impl Error {
     fn from_sense(sense: &sense::Sense) -> Self {
         match sense.kcq() {
@@ -67,13 +79,17 @@ quick_error! {
     pub enum ATAError {
         SCSI(err: Error) {
             from()
-            from(err: io::Error) -> (Error::IO(err))
+            from(err: io::Error) -> (Error::from(err))
             display("{}", err)
         }
         /// Device does not support ATA PASS-THROUGH command
         NotSupported {}
         // no non-deferred sense is available, or there's no descriptors for ATA registers to be found
         NoRegisters {}
+        /// 48-bit commands cannot be issued with ATA PASS-THROUGH (12)
+        No48Bit {
+            display("48-bit commands cannot be issued with ATA PASS-THROUGH (12)")
+        }
     }
 }
 
@@ -111,6 +127,29 @@ impl SCSIDevice {
         }
         ret
     }
+
+    /// Executes `cmd`, sending `data` to the device, and returns sense.
+    pub fn do_cmd_out(
+        &self,
+        cmd: &[u8],
+        sense_len: usize,
+        data: &[u8],
+    ) -> Result<Vec<u8>, io::Error> {
+        info!("SCSI cmd: dir={:?} cmd={:02x?}", Direction::To, cmd);
+        debug!("SCSI data: {}", hexdump_8(data));
+
+        // this one is implemented in `mod {linux,freebsd}`
+        let ret = Self::do_platform_cmd_out(self, cmd, sense_len, data);
+        match &ret {
+            Ok(sense) => {
+                debug!("SCSI autosense: {}", hexdump_8(sense));
+            }
+            err => {
+                debug!("SCSI err: {:?}", err);
+            }
+        }
+        ret
+    }
 }
 
 // TODO pub? see read_defect_data_*()
@@ -144,6 +183,8 @@ pub trait SCSICommon: Sized {
         data_len: usize,
     ) -> Result<(Vec<u8>, Vec<u8>), io::Error>;
 
+    fn do_cmd_out(&self, cmd: &[u8], sense_len: usize, data: &[u8]) -> Result<Vec<u8>, io::Error>;
+
     fn scsi_inquiry(&self, vital: bool, code: u8) -> Result<(Vec<u8>, Vec<u8>), Error> {
         info!("issuing INQUIRY: code={:?} vital={:?}", code, vital);
 
@@ -297,6 +338,112 @@ pub trait SCSICommon: Sized {
         Ok(self.do_cmd(&cmd, Direction::From, 32, ALLOC)?)
     }
 
+    /**
+    Executes SECURITY PROTOCOL IN command, returning tuple of `(sense, data)`.
+
+    `protocol` and `sp_specific` are what the security protocol in question (e.g. TCG, see [`tcg` module](../tcg/index.html)) make of them; `alloc` is the number of bytes to receive.
+    */
+    fn security_protocol_in(
+        &self,
+        protocol: u8,
+        sp_specific: u16,
+        alloc: u32,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        info!(
+            "issuing SECURITY PROTOCOL IN: protocol={:?} sp_specific={:?} alloc={:?}",
+            protocol, sp_specific, alloc
+        );
+
+        let cmd: [u8; 12] = [
+            0xa2, // opcode
+            protocol,
+            (sp_specific >> 8) as u8,
+            (sp_specific & 0xff) as u8,
+            0, // INC_512 << 7; allocation length is in bytes
+            0, // reserved
+            (alloc >> 24) as u8,
+            (alloc >> 16) as u8,
+            (alloc >> 8) as u8,
+            (alloc & 0xff) as u8,
+            0, // reserved
+            0, // control (XXX what's that?!)
+        ];
+
+        Ok(self.do_cmd(&cmd, Direction::From, 32, alloc as usize)?)
+    }
+
+    /// Executes SECURITY PROTOCOL OUT command, sending `data` to the device and returning sense. See also [`security_protocol_in()`](#method.security_protocol_in).
+    fn security_protocol_out(
+        &self,
+        protocol: u8,
+        sp_specific: u16,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        info!(
+            "issuing SECURITY PROTOCOL OUT: protocol={:?} sp_specific={:?} len={:?}",
+            protocol,
+            sp_specific,
+            data.len()
+        );
+
+        let len = data.len() as u32;
+
+        let cmd: [u8; 12] = [
+            0xb5, // opcode
+            protocol,
+            (sp_specific >> 8) as u8,
+            (sp_specific & 0xff) as u8,
+            0, // INC_512 << 7; transfer length is in bytes
+            0, // reserved
+            (len >> 24) as u8,
+            (len >> 16) as u8,
+            (len >> 8) as u8,
+            (len & 0xff) as u8,
+            0, // reserved
+            0, // control (XXX what's that?!)
+        ];
+
+        Ok(self.do_cmd_out(&cmd, 32, data)?)
+    }
+
+    /**
+    Executes WRITE BUFFER command, sending `data` to the device and returning sense.
+
+    `mode` is, e.g., 05h for microcode download (with save), or 07h for microcode download with offsets (with save and activation). `offset` is in bytes.
+    */
+    fn write_buffer(
+        &self,
+        mode: u8,
+        buffer_id: u8,
+        offset: u32,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        info!(
+            "issuing WRITE BUFFER: mode={:?} buffer_id={:?} offset={:?} len={:?}",
+            mode,
+            buffer_id,
+            offset,
+            data.len()
+        );
+
+        let len = data.len() as u32;
+
+        let cmd: [u8; 10] = [
+            0x3b, // opcode
+            mode, // mode specific << 5 + mode
+            buffer_id,
+            (offset >> 16) as u8,
+            (offset >> 8) as u8,
+            (offset & 0xff) as u8,
+            (len >> 16) as u8,
+            (len >> 8) as u8,
+            (len & 0xff) as u8,
+            0, // control (XXX what's that?!)
+        ];
+
+        Ok(self.do_cmd_out(&cmd, 32, data)?)
+    }
+
     fn ata_pass_through_16(
         &self,
         dir: Direction,
@@ -307,115 +454,296 @@ pub trait SCSICommon: Sized {
             dir, regs
         );
 
-        // see T10/04-262r8a ATA Command Pass-Through, 3.2.3
-        let extend = 0; // TODO
-        let protocol = match dir {
-            Direction::None => 3,              // Non-data
-            Direction::From => 4,              // PIO Data-In
-            Direction::To => unimplemented!(), //5, // PIO Data-Out
-            _ => unimplemented!(),
+        let ata_cmd = ata_pass_through_16_cmd(dir, regs);
+
+        // sector_count is the number of 512-byte blocks to transfer (see T_LENGTH below), but non-data commands use that register for other purposes
+        let data_len = match dir {
+            Direction::From => 512 * ::std::cmp::max(regs.blocks(), 1),
+            _ => 512,
         };
-        let multiple_count = 0; // TODO
-        let ata_cmd: [u8; 16] = [
-            0x85, // opcode: ATA PASS-THROUGH (16)
-            (multiple_count << 5) + (protocol << 1) + extend,
-            // 0b00: wait up to 2^(OFF_LINE+1)-2 seconds for valid ATA status register
-            // 0b1: CK_COND, return ATA register info in the sense data
-            // 0b0: reserved
-            // 0b1: T_DIR; transfer from ATA device
-            // 0b1: BYT_BLOK; T_LENGTH is in blocks, not in bytes
-            // 0b01: T_LENGTH itself
-            0b0010_1101,
-            0,
-            regs.features,
-            0,
-            regs.sector_count,
-            0,
-            regs.sector,
-            0,
-            regs.cyl_low,
-            0,
-            regs.cyl_high,
-            regs.device,
-            regs.command,
-            0, // control (XXX what's that?!)
-        ];
 
-        let (sense, data) = self.do_cmd(&ata_cmd, Direction::From, 32, 512)?;
+        let (sense, data) = self.do_cmd(&ata_cmd, Direction::From, 32, data_len)?;
 
-        let sense = match sense::parse(&sense) {
-            Some((true, sense)) => sense,
-            Some((false, _)) | None => {
-                // no (current) sense
-                return Err(ATAError::NoRegisters);
-            }
-        };
+        Ok((parse_ata_pass_through_sense(&sense)?, data))
+    }
 
-        let descriptors = match sense {
-            // current sense in the descriptor format
-            sense::Sense::Descriptor(sense::DescriptorData {
-                descriptors,
-                // Recovered Error / ATA PASS THROUGH INFORMATION AVAILABLE
-                key: 0x01,
-                asc: 0x00,
-                ascq: 0x1D,
-                ..
-            }) => descriptors,
-
-            sense::Sense::Descriptor(sense::DescriptorData {
-                descriptors,
-                // some devices/drivers return (Ok, 0, 0) as a sense;
-                // will validate its contents below
-                key: 0x00,
-                asc: 0x00,
-                ascq: 0x00,
-                ..
-            }) => descriptors,
-
-            sense::Sense::Fixed(sense::FixedData::Valid {
-                // Illegal Request / INVALID COMMAND OPERATION CODE
-                key: 0x05,
-                asc: 0x20,
-                ascq: 0x00,
-                ..
-            }) => {
-                return Err(ATAError::NotSupported);
-            }
+    /// Same as [`ata_pass_through_16()`](#method.ata_pass_through_16), but sends `data` to the device. `data` should be exactly `regs.sector_count` 512-byte blocks long.
+    fn ata_pass_through_16_out(
+        &self,
+        regs: &ata::RegistersWrite,
+        data: &[u8],
+    ) -> Result<ata::RegistersRead, ATAError> {
+        info!(
+            "issuing ATA PASS-THROUGH (16): dir={:?} regs={:?}",
+            Direction::To,
+            regs
+        );
+
+        let ata_cmd = ata_pass_through_16_cmd(Direction::To, regs);
+
+        let sense = self.do_cmd_out(&ata_cmd, 32, data)?;
+
+        parse_ata_pass_through_sense(&sense)
+    }
 
-            // unexpected sense
-            sense => return Err(Error::from_sense(&sense))?,
+    /// Same as [`ata_pass_through_16()`](#method.ata_pass_through_16), but uses 12-byte CDB, which is the only option for certain SAT implementations (e.g. some USB bridges). Note that it cannot carry 48-bit commands.
+    fn ata_pass_through_12(
+        &self,
+        dir: Direction,
+        regs: &ata::RegistersWrite,
+    ) -> Result<(ata::RegistersRead, Vec<u8>), ATAError> {
+        info!(
+            "issuing ATA PASS-THROUGH (12): dir={:?} regs={:?}",
+            dir, regs
+        );
+
+        if regs.ext.is_some() {
+            return Err(ATAError::No48Bit);
+        }
+
+        let ata_cmd = ata_pass_through_12_cmd(dir, regs);
+
+        let data_len = match dir {
+            Direction::From => 512 * ::std::cmp::max(regs.blocks(), 1),
+            _ => 512,
         };
 
-        for desc in descriptors {
-            if desc.code != 0x09 {
-                continue;
-            }
-            if desc.data.len() != 12 {
-                continue;
-            }
+        let (sense, data) = self.do_cmd(&ata_cmd, Direction::From, 32, data_len)?;
 
-            let d = desc.data;
+        Ok((parse_ata_pass_through_sense(&sense)?, data))
+    }
 
-            // TODO? EXTEND bit, ATA PASS-THROUGH 12 vs 16
-            return Ok((
-                ata::RegistersRead {
-                    error: d[1],
+    /// Same as [`ata_pass_through_12()`](#method.ata_pass_through_12), but sends `data` to the device.
+    fn ata_pass_through_12_out(
+        &self,
+        regs: &ata::RegistersWrite,
+        data: &[u8],
+    ) -> Result<ata::RegistersRead, ATAError> {
+        info!(
+            "issuing ATA PASS-THROUGH (12): dir={:?} regs={:?}",
+            Direction::To,
+            regs
+        );
+
+        if regs.ext.is_some() {
+            return Err(ATAError::No48Bit);
+        }
 
-                    sector_count: d[3],
+        let ata_cmd = ata_pass_through_12_cmd(Direction::To, regs);
 
-                    sector: d[5],
-                    cyl_low: d[7],
-                    cyl_high: d[9],
-                    device: d[10],
+        let sense = self.do_cmd_out(&ata_cmd, 32, data)?;
 
-                    status: d[11],
-                },
-                data,
-            ));
+        parse_ata_pass_through_sense(&sense)
+    }
+}
+
+// returns (protocol, flags) bytes of ATA PASS-THROUGH CDB
+fn ata_pass_through_proto_flags(dir: Direction, regs: &ata::RegistersWrite) -> (u8, u8) {
+    // see T10/04-262r8a ATA Command Pass-Through, 3.2.3
+    let (protocol, t_dir) = match dir {
+        Direction::None => (3, 1), // Non-data
+        Direction::From if regs.is_dma() => (6, 1), // DMA
+        Direction::From => (4, 1), // PIO Data-In
+        Direction::To if regs.is_dma() => (6, 0),
+        Direction::To => (5, 0),   // PIO Data-Out
+        _ => unimplemented!(),
+    };
+    let multiple_count = 0; // TODO
+    (
+        (multiple_count << 5) + (protocol << 1),
+        // 0b00: wait up to 2^(OFF_LINE+1)-2 seconds for valid ATA status register
+        // 0b1: CK_COND, return ATA register info in the sense data
+        // 0b0: reserved
+        // T_DIR: transfer from (1) or to (0) ATA device
+        // 0b1: BYT_BLOK; T_LENGTH is in blocks, not in bytes
+        // 0b01: T_LENGTH itself
+        0b0010_0101 + (t_dir << 3),
+    )
+}
+
+fn ata_pass_through_16_cmd(dir: Direction, regs: &ata::RegistersWrite) -> [u8; 16] {
+    let (proto, flags) = ata_pass_through_proto_flags(dir, regs);
+    // EXTEND bit tells SATL to also pass previous content of the registers, thus issuing 48-bit command
+    let (extend, ext) = match regs.ext {
+        Some(ext) => (1, ext),
+        None => (
+            0,
+            ata::RegistersWriteExt {
+                features: 0,
+                sector_count: 0,
+                sector: 0,
+                cyl_low: 0,
+                cyl_high: 0,
+            },
+        ),
+    };
+    [
+        0x85, // opcode: ATA PASS-THROUGH (16)
+        proto + extend,
+        flags,
+        ext.features,
+        regs.features,
+        ext.sector_count,
+        regs.sector_count,
+        ext.sector,
+        regs.sector,
+        ext.cyl_low,
+        regs.cyl_low,
+        ext.cyl_high,
+        regs.cyl_high,
+        regs.device,
+        regs.command,
+        0, // control (XXX what's that?!)
+    ]
+}
+
+fn ata_pass_through_12_cmd(dir: Direction, regs: &ata::RegistersWrite) -> [u8; 12] {
+    let (proto, flags) = ata_pass_through_proto_flags(dir, regs);
+    [
+        0xa1, // opcode: ATA PASS-THROUGH (12)
+        proto,
+        flags,
+        regs.features,
+        regs.sector_count,
+        regs.sector,
+        regs.cyl_low,
+        regs.cyl_high,
+        regs.device,
+        regs.command,
+        0, // reserved
+        0, // control (XXX what's that?!)
+    ]
+}
+
+// see SAT-3, 12.2.2.7 Fixed format sense data
+fn parse_fixed_ata_return(info: &[u8; 4], cmd_info: &[u8; 4]) -> ata::RegistersRead {
+    // unlike the descriptor, fixed format only tells whether the upper bytes of 48-bit registers are non-zero (bits 6 and 5 of cmd_info[0]),
+    // so previous content of the registers is not available
+    if cmd_info[0] & 0b0110_0000 != 0 {
+        debug!("fixed format ATA PASS-THROUGH sense: upper bytes of 48-bit registers are not zero, but are not available");
+    }
+
+    ata::RegistersRead {
+        error: info[0],
+        status: info[1],
+        device: info[2],
+        sector_count: info[3],
+
+        sector: cmd_info[1],
+        cyl_low: cmd_info[2],
+        cyl_high: cmd_info[3],
+
+        ext: None,
+    }
+}
+
+fn parse_ata_pass_through_sense(sense: &Vec<u8>) -> Result<ata::RegistersRead, ATAError> {
+    let sense = match sense::parse(sense) {
+        Some((true, sense)) => sense,
+        Some((false, _)) | None => {
+            // no (current) sense
+            return Err(ATAError::NoRegisters);
         }
+    };
 
-        return Err(ATAError::NoRegisters);
+    let descriptors = match sense {
+        // current sense in the descriptor format
+        sense::Sense::Descriptor(sense::DescriptorData {
+            descriptors,
+            // Recovered Error / ATA PASS THROUGH INFORMATION AVAILABLE
+            key: 0x01,
+            asc: 0x00,
+            ascq: 0x1D,
+            ..
+        }) => descriptors,
+
+        sense::Sense::Descriptor(sense::DescriptorData {
+            descriptors,
+            // some devices/drivers return (Ok, 0, 0) as a sense;
+            // will validate its contents below
+            key: 0x00,
+            asc: 0x00,
+            ascq: 0x00,
+            ..
+        }) => descriptors,
+
+        sense::Sense::Descriptor(sense::DescriptorData {
+            descriptors,
+            // Aborted Command / NO ADDITIONAL SENSE INFORMATION: device reported an error;
+            // status and error registers in the descriptor say what went wrong
+            key: 0x0B,
+            asc: 0x00,
+            ascq: 0x00,
+            ..
+        }) => descriptors,
+
+        // SATLs that do not support descriptor format (or are told not to use it, see D_SENSE in the Control mode page) return registers in fixed format sense instead,
+        // with the same sense codes as above (Recovered Error / ATA PASS THROUGH INFORMATION AVAILABLE, or Aborted Command)
+        sense::Sense::Fixed(sense::FixedData::Valid {
+            key,
+            asc: 0x00,
+            ascq,
+            info,
+            cmd_info,
+            ..
+        }) if (key == 0x01 && ascq == 0x1D) || (key == 0x0B && ascq == 0x00) => {
+            return Ok(parse_fixed_ata_return(&info, &cmd_info));
+        }
+
+        sense::Sense::Fixed(sense::FixedData::Valid {
+            // Illegal Request / INVALID COMMAND OPERATION CODE
+            key: 0x05,
+            asc: 0x20,
+            ascq: 0x00,
+            ..
+        }) => {
+            return Err(ATAError::NotSupported);
+        }
+
+        // unexpected sense
+        sense => return Err(Error::from_sense(&sense))?,
+    };
+
+    for desc in descriptors {
+        if desc.code != 0x09 {
+            continue;
+        }
+        if desc.data.len() != 12 {
+            continue;
+        }
+
+        let d = desc.data;
+
+        // see SAT-3, 12.2.2.6 ATA Status Return sense data descriptor
+        // EXTEND bit indicates that previous content of the registers is valid
+        let extend = d[0] & 1 != 0;
+
+        return Ok(ata::RegistersRead {
+            error: d[1],
+
+            sector_count: d[3],
+
+            sector: d[5],
+            cyl_low: d[7],
+            cyl_high: d[9],
+            device: d[10],
+
+            status: d[11],
+
+            ext: if extend {
+                Some(ata::RegistersReadExt {
+                    sector_count: d[2],
+                    sector: d[4],
+                    cyl_low: d[6],
+                    cyl_high: d[8],
+                })
+            } else {
+                None
+            },
+        });
     }
+
+    return Err(ATAError::NoRegisters);
 }
 
 impl SCSICommon for SCSIDevice {
@@ -429,6 +757,10 @@ impl SCSICommon for SCSIDevice {
     ) -> Result<(Vec<u8>, Vec<u8>), io::Error> {
         Self::do_cmd(self, cmd, dir, sense_len, data_len)
     }
+
+    fn do_cmd_out(&self, cmd: &[u8], sense_len: usize, data: &[u8]) -> Result<Vec<u8>, io::Error> {
+        Self::do_cmd_out(self, cmd, sense_len, data)
+    }
 }
 
 fn read_defect_data<D: SCSICommon, C>(
@@ -598,3 +930,116 @@ fn parse_defect_data_12(data: &[u8]) -> Option<(u8, bool, bool, u32)> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // fixed format sense data, as returned by SATLs that do not do descriptors
+    fn fixed_sense(key: u8, ascq: u8, info: [u8; 4], cmd_info: [u8; 4]) -> Vec<u8> {
+        let mut sense = vec![0; 18];
+        sense[0] = 0x70; // current error
+        sense[2] = key;
+        sense[3..7].copy_from_slice(&info);
+        sense[7] = 10; // additional sense length
+        sense[8..12].copy_from_slice(&cmd_info);
+        sense[12] = 0x00;
+        sense[13] = ascq;
+        sense
+    }
+
+    #[test]
+    fn fixed_sense_registers() {
+        // Recovered Error / ATA PASS THROUGH INFORMATION AVAILABLE, which is what check condition always (CK_COND) gets for a command that succeeded
+        let sense = fixed_sense(0x01, 0x1d, [0x00, 0x50, 0x40, 0x01], [0x00, 0x12, 0x34, 0x56]);
+        let regs = parse_ata_pass_through_sense(&sense).unwrap();
+        assert_eq!((regs.error, regs.status, regs.device, regs.sector_count), (0x00, 0x50, 0x40, 0x01));
+        assert_eq!((regs.sector, regs.cyl_low, regs.cyl_high), (0x12, 0x34, 0x56));
+        assert!(regs.ext.is_none());
+        assert_eq!(regs.lba(), 0x0056_3412);
+    }
+
+    #[test]
+    fn fixed_sense_aborted_command() {
+        // Aborted Command: registers are still there, with ERR and ABRT set
+        let sense = fixed_sense(0x0b, 0x00, [0x04, 0x51, 0x00, 0x00], [0; 4]);
+        let regs = parse_ata_pass_through_sense(&sense).unwrap();
+        assert_eq!((regs.error, regs.status), (0x04, 0x51));
+    }
+
+    #[test]
+    fn descriptor_sense_aborted_command() {
+        // captured from a SATL for READ LOG EXT of a log the drive does not have:
+        // Aborted Command / NO ADDITIONAL SENSE INFORMATION, with ATA Status Return descriptor (EXTEND set)
+        let sense = vec![
+            0x72, 0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0e,
+            0x09, 0x0c, 0x01, 0x04, 0x00, 0x01, 0x00, 0xa0, 0x00, 0x00, 0x00, 0x00, 0x40, 0x51,
+        ];
+        let regs = parse_ata_pass_through_sense(&sense).unwrap();
+        assert_eq!((regs.error, regs.status, regs.device), (0x04, 0x51, 0x40));
+        assert_eq!((regs.count(), regs.lba()), (0x0001, 0xa0));
+        assert!(regs.ext.is_some());
+
+        // which ends up in the error, same as with the native ATA path
+        let err = ata::misc::check_status(regs).unwrap_err();
+        match err {
+            ata::misc::Error::Aborted(_) => (),
+            ref other => panic!("expected Aborted, got {:?}", other),
+        }
+        let regs = err.registers().unwrap();
+        assert_eq!(ata::ErrorBits(regs.error).names(), vec!["ABRT"]);
+        assert_eq!((regs.status, regs.count(), regs.lba()), (0x51, 0x0001, 0xa0));
+    }
+
+    #[test]
+    fn fixed_sense_not_supported() {
+        // Illegal Request / INVALID COMMAND OPERATION CODE: SATL does not do ATA PASS-THROUGH at all
+        let mut sense = fixed_sense(0x05, 0x00, [0; 4], [0; 4]);
+        sense[12] = 0x20;
+        match parse_ata_pass_through_sense(&sense) {
+            Err(ATAError::NotSupported) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    fn regs(command: u8) -> ata::RegistersWrite {
+        ata::RegistersWrite {
+            command,
+            features: 0,
+            sector_count: 1,
+            sector: 0,
+            cyl_low: 0,
+            cyl_high: 0,
+            device: 0,
+            ext: None,
+        }
+    }
+
+    // protocol field of ATA PASS-THROUGH CDB, and T_DIR bit
+    fn protocol(dir: Direction, command: u8) -> (u8, bool) {
+        let cdb = ata_pass_through_16_cmd(dir, &regs(command));
+        assert_eq!(cdb[1], ata_pass_through_12_cmd(dir, &regs(command))[1]);
+        (cdb[1] >> 1 & 0x0f, cdb[2] & 0b1000 != 0)
+    }
+
+    #[test]
+    fn pass_through_protocol() {
+        // SMART RETURN STATUS: non-data
+        assert_eq!(protocol(Direction::None, 0xb0), (3, true));
+        // READ LOG EXT vs READ LOG DMA EXT
+        assert_eq!(protocol(Direction::From, 0x2f), (4, true));
+        assert_eq!(protocol(Direction::From, 0x47), (6, true));
+        // DOWNLOAD MICROCODE vs DOWNLOAD MICROCODE DMA
+        assert_eq!(protocol(Direction::To, 0x92), (5, false));
+        assert_eq!(protocol(Direction::To, 0x93), (6, false));
+        // READ DMA, as one might issue with raw-cmd
+        assert_eq!(protocol(Direction::From, 0xc8), (6, true));
+    }
+
+    #[test]
+    fn pass_through_extend() {
+        let mut regs = regs(0x47);
+        regs.ext = Some(ata::RegistersWriteExt { features: 0, sector_count: 0, sector: 0, cyl_low: 0, cyl_high: 0 });
+        assert_eq!(ata_pass_through_16_cmd(Direction::From, &regs)[1], 6 << 1 | 1);
+    }
+}
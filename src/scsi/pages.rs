@@ -5,24 +5,24 @@ The reason why this is implemented as a wrapper type instead of a trait is becau
 
 ## Example
 
-```
+```no_run
 use hdd::Device;
 use hdd::scsi::SCSIDevice;
-use hdd::scsi::pages::{Pages, page_name};
-
-...
+use hdd::scsi::pages::{SCSIPages, page_name};
 
-let pages = dev.supported_pages().unwrap();
+let dev = SCSIDevice::new(Device::open("/dev/da0").unwrap());
+let mut pages = SCSIPages::new(&dev).unwrap();
 
-if pages.contains(0x03) {
+if pages.supported_pages().contains(&0x03) {
     println!("{}:", page_name(0x03));
-    println!("{:#?}\n", dev.read_error_counters()),
+    println!("{:#?}\n", pages.read_error_counters());
 }
 ```
 */
 
 use scsi;
 use scsi::data::log_page;
+use scsi::data::sense::{self, Sense, FixedData};
 use scsi::{SCSICommon, SCSIDevice};
 
 extern crate byteorder;
@@ -102,6 +102,110 @@ pub struct InformationalException {
     pub vendor_specific: Vec<u8>,
 }
 
+/**
+Format Status log page (SBC-3, 6.4.3): how the device was formatted most recently.
+
+Counters are `None` if the device does not report them, or if they are not available (e.g. because the most recent format was not completed successfully, in which case the device fills them with FFh).
+*/
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct FormatStatus {
+    /// Whether the device is being formatted at the moment; such devices report LOGICAL UNIT NOT READY, FORMAT IN PROGRESS instead of the page, hence all the counters are `None`
+    pub in_progress: bool,
+    /// Progress of the ongoing format, in 1/65536 units, if the device reports it
+    pub progress: Option<u16>,
+    /// Parameter list of the most recent FORMAT UNIT command (parameter list header, and either initialization pattern descriptor or defect list, if any)
+    pub format_data_out: Option<Vec<u8>>,
+    /// Defects found during the certification phase of the most recent format
+    pub grown_defects_during_certification: Option<u64>,
+    /// Logical blocks reassigned during the most recent format
+    pub total_blocks_reassigned_during_format: Option<u64>,
+    /// Logical blocks reassigned since the most recent format
+    pub total_new_blocks_reassigned: Option<u64>,
+    /// Power-on minutes passed since the most recent format
+    pub power_on_minutes_since_format: Option<u64>,
+}
+
+// all FFh means the value is not available
+fn format_status_counter(value: &[u8]) -> Option<u64> {
+    if value.is_empty() || value.len() > 8 || value.iter().all(|&b| b == 0xff) {
+        return None;
+    }
+    Some((&value[..]).read_uint::<BigEndian>(value.len()).unwrap())
+}
+
+/**
+Queries Format Status log page (08h).
+
+Unlike other log pages, this is not a method of [`SCSIPages`](struct.SCSIPages.html): devices that are being formatted refuse to list supported log pages, which is exactly when this page is the most interesting. Returns `Error::NotSupported` if the device rejects the page.
+*/
+pub fn format_status<D: SCSICommon>(device: &D) -> Result<FormatStatus, Error> {
+    info!("querying format status");
+
+    let (sense, data) = device.log_sense(
+        false, // changed
+        false, // save_params
+        false, // default
+        false, // threshold
+        0x08, 0, // page, subpage
+        0, // param_ptr
+    )?;
+
+    let mut status = FormatStatus {
+        in_progress: false,
+        progress: None,
+        format_data_out: None,
+        grown_defects_during_certification: None,
+        total_blocks_reassigned_during_format: None,
+        total_new_blocks_reassigned: None,
+        power_on_minutes_since_format: None,
+    };
+
+    if !sense.is_empty() {
+        // only current senses are expected here
+        if let Some((true, sense)) = sense::parse(&sense) {
+            match sense.kcq() {
+                // No Sense, Recovered Error
+                Some((0x00, _, _)) | Some((0x01, _, _)) | None => (),
+                // NOT READY: LOGICAL UNIT NOT READY, FORMAT IN PROGRESS
+                Some((0x02, 0x04, 0x04)) => {
+                    status.in_progress = true;
+                    // progress indication is in the sense key specific bytes, if the SKSV bit is set
+                    if let Sense::Fixed(FixedData::Valid { sks, .. }) = sense {
+                        if sks[0] & 0x80 != 0 {
+                            status.progress = Some((&sks[1..3]).read_u16::<BigEndian>().unwrap());
+                        }
+                    }
+                    return Ok(status);
+                },
+                // ILLEGAL REQUEST: INVALID FIELD IN CDB
+                Some((0x05, 0x24, 0x00)) => return Err(Error::NotSupported),
+                _ => return Err(Error::SCSI(scsi::Error::from_sense(&sense))),
+            }
+        }
+    }
+
+    let page = log_page::parse(&data).ok_or(Error::InvalidData("parse log page data"))?;
+    let params = page.parse_params().ok_or(Error::InvalidData("parse log page params"))?;
+
+    for param in params {
+        match param.code {
+            0x0000 if !param.value.is_empty() && !param.value.iter().all(|&b| b == 0xff) => {
+                status.format_data_out = Some(param.value);
+            },
+            0x0001 => status.grown_defects_during_certification = format_status_counter(&param.value),
+            0x0002 => status.total_blocks_reassigned_during_format = format_status_counter(&param.value),
+            0x0003 => status.total_new_blocks_reassigned = format_status_counter(&param.value),
+            0x0004 => status.power_on_minutes_since_format = format_status_counter(&param.value),
+            _ => {
+                // XXX tell about unexpected params?
+            }
+        }
+    }
+
+    Ok(status)
+}
+
 /// For a given page number `page`, return its name
 pub fn page_name(page: u8) -> &'static str {
     match page {
@@ -111,8 +215,10 @@ pub fn page_name(page: u8) -> &'static str {
         0x04 => "Read Reverse Error Counter",
         0x05 => "Verify Error Counter",
         0x06 => "Non-Medium Error",
+        0x08 => "Format Status",
         0x0d => "Temperature",
         0x0e => "Start-Stop Cycle Counter",
+        0x0f => "Application Client",
         0x10 => "Self-Test results",
         0x2f => "Informational Exceptions",
         0x30..=0x3e => "(Vendor-Specific)",
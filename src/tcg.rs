@@ -0,0 +1,130 @@
+/*!
+Functions to parse and structs to represent TCG (Trusted Computing Group) Storage replies, like the ones of self-encrypting drives that implement Opal, Enterprise, Pyrite and such.
+
+Replies are obtained with IF-RECV, which is TRUSTED RECEIVE for ATA devices.
+
+For more, see TCG Storage Architecture Core Specification, 3.3.6 Level 0 Discovery, and TCG Storage Security Subsystem Class: Opal, 3.1.1 Level 0 Discovery.
+*/
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+/// Security protocol for TCG Storage
+pub const PROTOCOL: u8 = 0x01;
+/// ComID to send Level 0 Discovery request at
+pub const DISCOVERY_COMID: u16 = 0x0001;
+
+/// Locking feature descriptor (feature code 0002h)
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct Locking {
+	pub supported: bool,
+	pub enabled: bool,
+	/// At least one of the locking ranges is locked
+	pub locked: bool,
+	pub media_encryption: bool,
+	pub mbr_enabled: bool,
+	pub mbr_done: bool,
+}
+
+/// Security Subsystem Class feature descriptor, e.g. Opal or Enterprise
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct SSC {
+	pub feature_code: u16,
+	pub name: &'static str,
+	pub version: u8,
+	pub base_comid: u16,
+	pub comids: u16,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serializable", derive(Serialize))]
+pub struct Discovery {
+	pub revision: u32,
+	pub locking: Option<Locking>,
+	pub ssc: Vec<SSC>,
+	/// Codes of all the feature descriptors found, including the unknown ones
+	pub features: Vec<u16>,
+}
+
+fn ssc_name(code: u16) -> Option<&'static str> {
+	match code {
+		0x0100 => Some("Enterprise"),
+		0x0200 => Some("Opal 1.0"),
+		0x0201 => Some("Single User Mode"),
+		0x0203 => Some("Opal 2.0"),
+		0x0301 => Some("Opalite"),
+		0x0302 => Some("Pyrite 1.0"),
+		0x0303 => Some("Pyrite 2.0"),
+		0x0304 => Some("Ruby"),
+		_ => None,
+	}
+}
+
+fn is_set(byte: u8, bit: usize) -> bool {
+	byte & (1 << bit) != 0
+}
+
+/**
+Parses Level 0 Discovery reply.
+
+Returns `None` if the reply is too short, or if its header claims there's no data, which is what devices with no TCG support usually do.
+*/
+pub fn parse_discovery(data: &[u8]) -> Option<Discovery> {
+	if data.len() < 48 {
+		return None;
+	}
+
+	// length of the parameter data, excluding the length field itself
+	let len = (&data[0..4]).read_u32::<BigEndian>().unwrap() as usize;
+	if len == 0 {
+		return None;
+	}
+	// truncated replies are not uncommon: devices are free to report more data than we've asked for
+	let end = ::std::cmp::min(len + 4, data.len());
+
+	let mut discovery = Discovery {
+		revision: (&data[4..8]).read_u32::<BigEndian>().unwrap(),
+		locking: None,
+		ssc: vec![],
+		features: vec![],
+	};
+
+	// 8..16: reserved, 16..48: vendor-specific
+	let mut pos = 48;
+	while pos + 4 <= end {
+		let code = (&data[pos .. pos + 2]).read_u16::<BigEndian>().unwrap();
+		let version = data[pos + 2] >> 4;
+		let desc_len = data[pos + 3] as usize;
+
+		let desc = &data[pos + 4 .. ::std::cmp::min(pos + 4 + desc_len, end)];
+		pos += 4 + desc_len;
+
+		discovery.features.push(code);
+
+		if code == 0x0002 && !desc.is_empty() {
+			discovery.locking = Some(Locking {
+				supported: is_set(desc[0], 0),
+				enabled: is_set(desc[0], 1),
+				locked: is_set(desc[0], 2),
+				media_encryption: is_set(desc[0], 3),
+				mbr_enabled: is_set(desc[0], 4),
+				mbr_done: is_set(desc[0], 5),
+			});
+		}
+
+		if let Some(name) = ssc_name(code) {
+			// all of the SSC descriptors begin with base ComID and the number of ComIDs
+			let (base_comid, comids) = if desc.len() >= 4 {
+				(
+					(&desc[0..2]).read_u16::<BigEndian>().unwrap(),
+					(&desc[2..4]).read_u16::<BigEndian>().unwrap(),
+				)
+			} else { (0, 0) };
+
+			discovery.ssc.push(SSC { feature_code: code, name, version, base_comid, comids });
+		}
+	}
+
+	Some(discovery)
+}
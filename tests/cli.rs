@@ -0,0 +1,127 @@
+/*!
+Runs the `hdd` binary against dumps (see `--from-dump`), checking what actually ends up on its stdout and stderr.
+*/
+
+extern crate hdd;
+
+use hdd::ata::{Command, RegistersRead, RegistersWrite, SMARTFeature};
+use hdd::ata::dump::Dump;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Output};
+
+fn ok() -> RegistersRead {
+	RegistersRead {
+		error: 0,
+		sector_count: 0,
+		sector: 0,
+		cyl_low: 0,
+		cyl_high: 0,
+		device: 0,
+		status: 0x50, // DRDY, DSC
+		ext: None,
+	}
+}
+
+// drive with S.M.A.R.T. supported and enabled, replying with `attrs` (id, flags, value, worst, raw) and `thresholds` (id, threshold)
+fn smart_dump(attrs: &[(u8, u16, u8, u8, [u8; 6])], thresholds: &[(u8, u8)]) -> Dump {
+	let mut id = vec![0; 512];
+	for &(word, value) in &[(82, 1 << 0), (85, 1 << 0), (84, 1 << 14 | 1 << 1 | 1 << 0), (87, 1 << 14 | 1 << 1 | 1 << 0)] {
+		id[word * 2] = value as u8;
+		id[word * 2 + 1] = (value >> 8) as u8;
+	}
+
+	let mut values = vec![0; 512];
+	values[0] = 0x10; // revision
+	for (i, &(id, flags, value, worst, raw)) in attrs.iter().enumerate() {
+		let entry = &mut values[2 + i * 12 .. 2 + (i + 1) * 12];
+		entry[..5].copy_from_slice(&[id, flags as u8, (flags >> 8) as u8, value, worst]);
+		entry[5..11].copy_from_slice(&raw);
+	}
+
+	let mut thresh = vec![0; 512];
+	for (i, &(id, value)) in thresholds.iter().enumerate() {
+		thresh[2 + i * 12 .. 4 + i * 12].copy_from_slice(&[id, value]);
+	}
+
+	for data in &mut [&mut values, &mut thresh] {
+		let sum = data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+		data[511] = 0u8.wrapping_sub(sum);
+	}
+
+	let mut dump = Dump::new();
+	dump.record(&RegistersWrite {
+		command: Command::Identify as u8,
+		sector: 1,
+		features: 0,
+		sector_count: 1,
+		cyl_low: 0,
+		cyl_high: 0,
+		device: 0,
+		ext: None,
+	}, &ok(), &id);
+	dump.record(&RegistersWrite::smart(SMARTFeature::ReadValues, 0, 1), &ok(), &values);
+	dump.record(&RegistersWrite::smart(SMARTFeature::ReadThresholds, 0, 1), &ok(), &thresh);
+	dump
+}
+
+// `name` keeps files of concurrent tests apart
+fn temp_path(name: &str) -> PathBuf {
+	env::temp_dir().join(format!("hdd-cli-{}-{}", process::id(), name))
+}
+
+// runs `hdd --from-dump DUMP <args>` with `drivedb` as the only drivedb file
+fn run(name: &str, dump: &Dump, drivedb: &str, args: &[&str]) -> Output {
+	let dump_path = temp_path(&format!("{}.dump", name));
+	let drivedb_path = temp_path(&format!("{}.h", name));
+	dump.save(&dump_path).unwrap();
+	fs::write(&drivedb_path, drivedb).unwrap();
+
+	let output = process::Command::new(env!("CARGO_BIN_EXE_hdd"))
+		.arg("--from-dump")
+		.arg(&dump_path)
+		.args(args)
+		.arg("-B")
+		.arg(&drivedb_path)
+		.output()
+		.unwrap();
+
+	fs::remove_file(&dump_path).unwrap();
+	fs::remove_file(&drivedb_path).unwrap();
+	output
+}
+
+#[test]
+fn attrs_golden_json() {
+	let dump = smart_dump(&[
+		(5, 0x0033, 100, 100, [0; 6]),
+		(194, 0x0022, 36, 53, [36, 0, 20, 0, 53, 0]),
+	], &[(5, 10)]);
+	let output = run("attrs-json", &dump, "", &["attrs", "--json"]);
+	assert!(output.status.success(), "{:?}", output);
+
+	// keys are sorted, and enums are externally tagged, same as it was with ToJson
+	assert_eq!(String::from_utf8(output.stdout).unwrap(), concat!(
+		r#"[{"error_rate":false,"event_count":true,"flags":0,"id":5,"name":"Reallocated_Sector_Ct","online":true,"performance":false,"pre_fail":true,"#,
+		r#""raw":{"Raw16opt16":[0,null]},"raw_flags":51,"self_preserving":true,"thresh":10,"value":100,"worst":100},"#,
+		r#"{"error_rate":false,"event_count":false,"flags":0,"id":194,"name":"Temperature_Celsius","online":true,"performance":false,"pre_fail":false,"#,
+		r#""raw":{"CelsiusMinMax":{"current":36,"max":53,"min":20}},"raw_flags":34,"self_preserving":true,"thresh":null,"value":36,"worst":53}]"#,
+		"\n",
+	));
+}
+
+#[test]
+fn attrs_json_drivedb_warning() {
+	let dump = smart_dump(&[(9, 0x0032, 100, 100, [0x10, 0x27, 0, 0, 0, 0])], &[]);
+	let drivedb = r#"{ "Test family", ".*", "", "Firmware bug may cause data loss", "" },"#;
+	let output = run("attrs-json-warning", &dump, drivedb, &["attrs", "--json"]);
+
+	// the warning must not turn the array into something else
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.starts_with(r#"[{"#), "{}", stdout);
+	assert!(!stdout.contains("Firmware bug"), "{}", stdout);
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("WARNING: Firmware bug may cause data loss\n"), "{}", stderr);
+}